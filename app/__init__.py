@@ -0,0 +1,3 @@
+"""CivicSentinel backend."""
+
+__version__ = "0.1.0"
@@ -0,0 +1,60 @@
+"""Agent heartbeat endpoints for fleet liveness."""
+
+import logging
+from datetime import datetime, timedelta
+from typing import Dict, List, Tuple
+from fastapi import APIRouter, Depends, status
+
+from app.dependencies import get_current_user
+from app.models.database import User
+from app.models.schemas import AgentHeartbeat, AgentStatus
+
+logger = logging.getLogger(__name__)
+router = APIRouter()
+
+# Agents that haven't reported for this long are shown as offline
+OFFLINE_AFTER = timedelta(minutes=5)
+
+# Latest heartbeat per (user id, agent id); liveness is transient so it is
+# kept in memory rather than the database
+_heartbeats: Dict[Tuple[int, str], Tuple[AgentHeartbeat, datetime]] = {}
+
+
+@router.post("/agents/heartbeat", status_code=status.HTTP_204_NO_CONTENT)
+async def agent_heartbeat(
+    heartbeat: AgentHeartbeat,
+    current_user: User = Depends(get_current_user),
+):
+    """
+    Record that an agent is alive.
+
+    Args:
+        heartbeat: Agent state at the time of the heartbeat
+        current_user: Authenticated user
+    """
+    _heartbeats[(current_user.id, heartbeat.agent_id)] = (heartbeat, datetime.utcnow())
+    logger.debug(f"Heartbeat from agent {heartbeat.agent_id} (user {current_user.id})")
+
+
+@router.get("/agents", response_model=List[AgentStatus])
+async def list_agents(current_user: User = Depends(get_current_user)):
+    """
+    List the agents that have reported for the current user.
+
+    Args:
+        current_user: Authenticated user
+
+    Returns:
+        Last heartbeat of each agent, most recently seen first
+    """
+    now = datetime.utcnow()
+    agents = [
+        AgentStatus(
+            **heartbeat.model_dump(),
+            last_seen=last_seen,
+            online=now - last_seen <= OFFLINE_AFTER,
+        )
+        for (user_id, _), (heartbeat, last_seen) in _heartbeats.items()
+        if user_id == current_user.id
+    ]
+    return sorted(agents, key=lambda agent: agent.last_seen, reverse=True)
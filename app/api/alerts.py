@@ -23,6 +23,7 @@ async def get_alerts(
     start_date: Optional[datetime] = Query(None, description="Filter by start date"),
     end_date: Optional[datetime] = Query(None, description="Filter by end date"),
     alert_type: Optional[str] = Query(None, description="Filter by alert type"),
+    since_id: Optional[int] = Query(None, ge=0, description="Only alerts with a greater ID, oldest first"),
     page: int = Query(1, ge=1, description="Page number"),
     page_size: int = Query(50, ge=1, le=100, description="Items per page"),
     current_user: User = Depends(get_current_user),
@@ -36,6 +37,7 @@ async def get_alerts(
         start_date: Optional start date filter
         end_date: Optional end date filter
         alert_type: Optional alert type filter
+        since_id: Optional cursor; only alerts with a greater ID are returned
         page: Page number (1-indexed)
         page_size: Number of items per page
         current_user: Authenticated user
@@ -76,6 +78,7 @@ async def get_alerts(
             start_date=start_date,
             end_date=end_date,
             alert_type=alert_type,
+            since_id=since_id,
             limit=page_size,
             offset=offset,
         )
@@ -2,6 +2,7 @@
 
 import json
 import logging
+import time
 from datetime import datetime
 from typing import Annotated
 from fastapi import APIRouter, Depends, File, Form, UploadFile, HTTPException, status
@@ -82,7 +83,9 @@ async def detect_objects(
             )
 
         # Run YOLO detection
+        inference_started = time.perf_counter()
         detections = yolo_service.detect_objects(pil_image)
+        inference_ms = (time.perf_counter() - inference_started) * 1000
         logger.info(f"Detected {len(detections)} persons in frame from camera {camera_id}")
 
         # Get active zones for this camera
@@ -113,6 +116,7 @@ async def detect_objects(
             timestamp=datetime.utcnow(),
             detections=detections,
             alerts=alerts,
+            inference_ms=inference_ms,
         )
 
         # Debug logging
@@ -6,6 +6,7 @@ from fastapi import APIRouter, Depends
 from sqlalchemy.ext.asyncio import AsyncSession
 from sqlalchemy import text
 
+from app import __version__
 from app.db.database import get_db
 from app.models.schemas import HealthResponse
 from app.services.yolo_service import yolo_service
@@ -47,6 +48,7 @@ async def health_check(db: AsyncSession = Depends(get_db)):
 
     return HealthResponse(
         status=status,
+        version=__version__,
         model_loaded=model_loaded,
         database_connected=database_connected,
         timestamp=datetime.utcnow(),
@@ -156,10 +156,15 @@ async def get_alerts(
     start_date: Optional[datetime] = None,
     end_date: Optional[datetime] = None,
     alert_type: Optional[str] = None,
+    since_id: Optional[int] = None,
     limit: int = 100,
     offset: int = 0,
 ) -> tuple[List[Alert], int]:
-    """Get alerts with optional filters."""
+    """Get alerts with optional filters.
+
+    With ``since_id`` only newer alerts are returned, oldest first, so the
+    largest ID in a page is a safe cursor for the next request.
+    """
     # Build query
     query = select(Alert)
     conditions = []
@@ -170,6 +175,8 @@ async def get_alerts(
         conditions.append(Alert.timestamp >= start_date)
     if end_date:
         conditions.append(Alert.timestamp <= end_date)
+    if since_id is not None:
+        conditions.append(Alert.id > since_id)
 
     if conditions:
         query = query.where(and_(*conditions))
@@ -180,7 +187,8 @@ async def get_alerts(
     total = len(list(total_result.scalars().all()))
 
     # Get paginated results
-    query = query.order_by(desc(Alert.timestamp)).limit(limit).offset(offset)
+    order = Alert.id if since_id is not None else desc(Alert.timestamp)
+    query = query.order_by(order).limit(limit).offset(offset)
     result = await db.execute(query)
     alerts = list(result.scalars().all())
 
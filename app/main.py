@@ -5,10 +5,11 @@ from contextlib import asynccontextmanager
 from fastapi import FastAPI
 from fastapi.middleware.cors import CORSMiddleware
 
+from app import __version__
 from app.config import settings
 from app.db.database import init_db
 from app.services.yolo_service import yolo_service
-from app.api import detect, zones, alerts, health
+from app.api import detect, zones, alerts, health, agents
 
 # Configure logging
 logging.basicConfig(
@@ -57,7 +58,7 @@ async def lifespan(app: FastAPI):
 app = FastAPI(
     title="CivicSentinel API",
     description="AI-powered CCTV surveillance system for detecting persons in restricted zones",
-    version="0.1.0",
+    version=__version__,
     lifespan=lifespan,
 )
 
@@ -89,6 +90,12 @@ app.include_router(
     tags=["Alerts"],
 )
 
+app.include_router(
+    agents.router,
+    prefix="/api/v1",
+    tags=["Agents"],
+)
+
 app.include_router(
     health.router,
     prefix="/api/v1",
@@ -101,7 +108,7 @@ async def root():
     """Root endpoint."""
     return {
         "name": "CivicSentinel API",
-        "version": "0.1.0",
+        "version": __version__,
         "status": "running",
         "docs": "/docs",
     }
@@ -41,6 +41,7 @@ class DetectionResponse(BaseModel):
     timestamp: datetime = Field(..., description="Detection timestamp")
     detections: List[Detection] = Field(default_factory=list, description="List of detections")
     alerts: List[ZoneAlert] = Field(default_factory=list, description="List of zone alerts")
+    inference_ms: Optional[float] = Field(None, description="Time spent running the model, in milliseconds")
 
 
 # Zone Schemas
@@ -103,11 +104,30 @@ class HealthResponse(BaseModel):
     """Health check response."""
 
     status: str = Field(..., description="Overall health status")
+    version: str = Field(..., description="Backend version")
     model_loaded: bool = Field(..., description="Whether YOLO model is loaded")
     database_connected: bool = Field(..., description="Whether database is accessible")
     timestamp: datetime = Field(..., description="Health check timestamp")
 
 
+# Agent Schemas
+class AgentHeartbeat(BaseModel):
+    """Liveness report sent periodically by a desktop agent."""
+
+    agent_id: str = Field(..., min_length=1, max_length=100, description="Stable agent install ID")
+    version: str = Field(..., description="Agent version")
+    cameras_connected: int = Field(..., ge=0, description="Cameras currently connected")
+    cameras_monitored: int = Field(..., ge=0, description="Cameras with periodic capture running")
+    cameras_paused: int = Field(..., ge=0, description="Cameras with monitoring paused")
+
+
+class AgentStatus(AgentHeartbeat):
+    """Last heartbeat received from an agent."""
+
+    last_seen: datetime = Field(..., description="When the last heartbeat arrived")
+    online: bool = Field(..., description="Whether the agent has reported recently")
+
+
 # Camera Schemas
 class CameraResponse(BaseModel):
     """Response schema for camera."""
@@ -1,5 +1,8 @@
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
 use reqwest::multipart;
+use crate::error::AgentError;
+use crate::settings::ImageFormat;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BoundingBox {
@@ -30,7 +33,38 @@ pub struct DetectionResponse {
     pub camera_id: String,
     pub timestamp: String,
     pub detections: Vec<Detection>,
+    #[serde(default)]
     pub alerts: Vec<ZoneAlert>,
+    /// Normalized (x, y, w, h) crop the frame was taken from; detection
+    /// coordinates are relative to this region. Filled in by the agent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roi: Option<[f64; 4]>,
+    /// URL of the backend that answered. Differs from the first configured
+    /// backend when the agent is running on a fallback. Filled in by the agent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub served_by: Option<String>,
+    /// Object storage URL of the annotated frame, when evidence upload is
+    /// enabled and the frame raised alerts. Filled in by the agent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evidence_url: Option<String>,
+    /// Local wall-clock time (RFC 3339) the frame was captured, unaffected by
+    /// the backend's clock or timezone. Filled in by the agent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_timestamp: Option<String>,
+    /// Round trip of the detection request in milliseconds. Filled in by the agent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// Time the backend spent running the model, in milliseconds, when it
+    /// reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inference_ms: Option<f64>,
+}
+
+/// One detection backend in the failover list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackendConfig {
+    pub url: String,
+    pub api_key: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,6 +77,9 @@ pub struct ZoneResponse {
     pub active: bool,
     pub active_hours: Option<String>,
     pub created_at: String,
+    /// Where this zone's alerts go; kept by the agent, not the backend
+    #[serde(default)]
+    pub notify: crate::zones::NotifyPolicy,
 }
 
 #[derive(Debug, Serialize)]
@@ -53,47 +90,385 @@ struct ZoneCreateRequest {
     active: bool,
 }
 
-/// Send frame to cloud API for detection
-pub async fn send_detection_request(
+/// Classify a failed request as a timeout or a network error
+fn request_error(e: reqwest::Error) -> AgentError {
+    let message = format!("Request failed: {}", e);
+    if e.is_timeout() {
+        AgentError::Timeout(message)
+    } else {
+        AgentError::Network(message)
+    }
+}
+
+/// Convert a non-success response into an error keyed on its status
+async fn status_error(response: reqwest::Response) -> AgentError {
+    let status = response.status();
+    let text = response.text().await.unwrap_or_default();
+    let message = format!("API error {}: {}", status, text);
+
+    match status.as_u16() {
+        401 | 403 => AgentError::Auth(message),
+        404 => AgentError::NotFound(message),
+        408 | 504 => AgentError::Timeout(message),
+        429 => AgentError::RateLimited(message),
+        _ => AgentError::Backend(message),
+    }
+}
+
+/// Wait requested by a `Retry-After` header: delta-seconds or an HTTP date
+pub fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&chrono::Utc) - now).to_std().unwrap_or_default())
+}
+
+/// How the API key is attached to backend requests
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "name", rename_all = "snake_case")]
+pub enum AuthScheme {
+    /// `Authorization: Bearer <key>`
+    #[default]
+    Bearer,
+    /// The key as the value of a named header, e.g. `X-API-Key`
+    ApiKeyHeader(String),
+    /// The key as a named query parameter
+    QueryParam(String),
+}
+
+impl AuthScheme {
+    /// Attach the API key to a request; an empty key sends no credential
+    pub fn apply(&self, request: reqwest::RequestBuilder, api_key: &str) -> reqwest::RequestBuilder {
+        if api_key.is_empty() {
+            return request;
+        }
+        match self {
+            AuthScheme::Bearer => request.header("Authorization", format!("Bearer {}", api_key)),
+            AuthScheme::ApiKeyHeader(name) => request.header(name.as_str(), api_key),
+            AuthScheme::QueryParam(name) => request.query(&[(name.as_str(), api_key)]),
+        }
+    }
+}
+
+/// JSON shape of the detect endpoint's response
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// `DetectionResponse`, as returned by the CivicSentinel backend
+    #[default]
+    CivicSentinel,
+    /// Array of `[class_index, x_center, y_center, width, height, confidence]`
+    /// rows with coordinates normalized to 0-1, as in YOLO label files
+    YoloRaw,
+    /// COCO results: array of `{category_id, bbox: [x, y, width, height], score}`
+    /// with `bbox` in pixels from the top-left corner
+    CocoJson,
+}
+
+/// Maps a backend's detect response onto `DetectionResponse`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResponseAdapter {
+    pub format: ResponseFormat,
+    /// Class names by index, for formats that report numeric classes;
+    /// indices without a label keep the number as the class name
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CocoDetection {
+    category_id: i64,
+    bbox: [f64; 4],
+    score: f64,
+}
+
+impl ResponseAdapter {
+    fn label(&self, index: i64) -> String {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| self.labels.get(index))
+            .cloned()
+            .unwrap_or_else(|| index.to_string())
+    }
+
+    /// Parse a detect response body for `camera_id`
+    ///
+    /// Raw formats carry no timestamp or alerts, so the response is stamped
+    /// with the current time and has no zone alerts. YOLO coordinates are
+    /// scaled to pixels using the size of the uploaded `frame_bytes`.
+    pub fn parse(&self, body: &str, camera_id: &str, frame_bytes: &[u8]) -> Result<DetectionResponse, AgentError> {
+        let detections = match self.format {
+            ResponseFormat::CivicSentinel => return parse_body(body),
+            ResponseFormat::YoloRaw => {
+                let rows: Vec<[f64; 6]> = parse_body(body)?;
+                let (width, height) = crate::frame::frame_dimensions(frame_bytes)?;
+                let (width, height) = (width as f64, height as f64);
+                rows.into_iter()
+                    .map(|[class, x, y, w, h, confidence]| Detection {
+                        class_name: self.label(class as i64),
+                        confidence,
+                        bbox: BoundingBox {
+                            x1: (x - w / 2.0) * width,
+                            y1: (y - h / 2.0) * height,
+                            x2: (x + w / 2.0) * width,
+                            y2: (y + h / 2.0) * height,
+                        },
+                    })
+                    .collect()
+            }
+            ResponseFormat::CocoJson => {
+                let results: Vec<CocoDetection> = parse_body(body)?;
+                results.into_iter()
+                    .map(|result| {
+                        let [x, y, w, h] = result.bbox;
+                        Detection {
+                            class_name: self.label(result.category_id),
+                            confidence: result.score,
+                            bbox: BoundingBox { x1: x, y1: y, x2: x + w, y2: y + h },
+                        }
+                    })
+                    .collect()
+            }
+        };
+
+        Ok(DetectionResponse {
+            camera_id: camera_id.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            detections,
+            alerts: Vec::new(),
+            roi: None,
+            served_by: None,
+            evidence_url: None,
+            local_timestamp: None,
+            latency_ms: None,
+            inference_ms: None,
+        })
+    }
+}
+
+/// Longest body excerpt quoted in parse errors
+const BODY_SNIPPET_CHARS: usize = 200;
+
+/// Parse a JSON body, quoting the start of it if it doesn't match `T`
+fn parse_body<T: serde::de::DeserializeOwned>(body: &str) -> Result<T, AgentError> {
+    serde_json::from_str(body).map_err(|e| {
+        let snippet: String = body.chars().take(BODY_SNIPPET_CHARS).collect();
+        let ellipsis = if body.chars().count() > BODY_SNIPPET_CHARS { "..." } else { "" };
+        AgentError::Decode(format!("Failed to parse response: {} (body: {}{})", e, snippet, ellipsis))
+    })
+}
+
+/// Describes a frame in v2 detect requests
+#[derive(Debug, Clone, Serialize)]
+pub struct FrameMetadata {
+    pub camera_id: String,
+    /// UTC capture time, RFC 3339
+    pub capture_timestamp: String,
+    /// Per-camera counter, so the backend can spot dropped or reordered frames
+    pub sequence: u64,
+}
+
+fn image_part(frame_bytes: &[u8], format: ImageFormat) -> Result<multipart::Part, AgentError> {
+    multipart::Part::bytes(frame_bytes.to_vec())
+        .file_name(format.file_name())
+        .mime_str(format.mime_type())
+        .map_err(|e| AgentError::Internal(format!("Failed to create multipart: {}", e)))
+}
+
+/// Add the camera's previous frame as `prev_image`, for temporal models
+fn with_previous_frame(
+    form: multipart::Form,
+    previous_frame: Option<&[u8]>,
+    format: ImageFormat,
+) -> Result<multipart::Form, AgentError> {
+    Ok(match previous_frame {
+        Some(previous) => form.part(
+            "prev_image",
+            image_part(previous, format)?.file_name(format!("prev_{}", format.file_name())),
+        ),
+        None => form,
+    })
+}
+
+/// Build the multipart body expected by the v1 detect endpoint
+fn detection_form(
+    camera_id: &str,
+    frame_bytes: &[u8],
+    format: ImageFormat,
+) -> Result<multipart::Form, AgentError> {
+    Ok(multipart::Form::new()
+        .part("image", image_part(frame_bytes, format)?)
+        .text("camera_id", camera_id.to_string()))
+}
+
+/// Build the v2 body: the image plus a JSON `metadata` part
+fn detection_form_v2(
+    metadata_json: &str,
+    frame_bytes: &[u8],
+    format: ImageFormat,
+) -> Result<multipart::Form, AgentError> {
+    let metadata_part = multipart::Part::text(metadata_json.to_string())
+        .mime_str("application/json")
+        .map_err(|e| AgentError::Internal(format!("Failed to create multipart: {}", e)))?;
+
+    Ok(multipart::Form::new()
+        .part("image", image_part(frame_bytes, format)?)
+        .part("metadata", metadata_part))
+}
+
+/// The parts of a detect request covered by its signature, in upload order
+fn signed_parts<'a>(
+    frame_bytes: &'a [u8],
+    metadata_json: Option<&'a str>,
+    previous_frame: Option<&'a [u8]>,
+) -> Vec<(&'static str, &'a [u8])> {
+    let mut parts = vec![("image", frame_bytes)];
+    if let Some(json) = metadata_json {
+        parts.push(("metadata", json.as_bytes()));
+    }
+    if let Some(previous) = previous_frame {
+        parts.push(("prev_image", previous));
+    }
+    parts
+}
+
+/// A failed detection attempt and whether the next backend should be tried
+struct DetectionFailure {
+    error: AgentError,
+    failover: bool,
+    retry_after: Option<Duration>,
+}
+
+impl DetectionFailure {
+    fn fatal(error: AgentError) -> Self {
+        Self { error, failover: false, retry_after: None }
+    }
+}
+
+/// Why `send_detection_with_failover` got no result
+#[derive(Debug)]
+pub struct DetectionError {
+    pub error: AgentError,
+    /// Wait the backend asked for along with a 429, when it said
+    pub retry_after: Option<Duration>,
+}
+
+impl From<AgentError> for DetectionError {
+    fn from(error: AgentError) -> Self {
+        Self { error, retry_after: None }
+    }
+}
+
+impl From<DetectionError> for AgentError {
+    fn from(error: DetectionError) -> Self {
+        error.error
+    }
+}
+
+/// POST a frame to one backend's detect endpoint; v2 when metadata is given
+#[allow(clippy::too_many_arguments)]
+async fn post_detection(
     backend_url: &str,
     camera_id: &str,
     frame_bytes: &[u8],
+    previous_frame: Option<&[u8]>,
+    format: ImageFormat,
+    metadata: Option<&FrameMetadata>,
     api_key: &str,
-) -> Result<DetectionResponse, String> {
-    let client = reqwest::Client::new();
-
-    let url = format!("{}/api/v1/detect", backend_url);
+    auth: &AuthScheme,
+) -> Result<String, DetectionFailure> {
+    let client = crate::proxy::http_client();
 
-    // Create multipart form
-    let part = multipart::Part::bytes(frame_bytes.to_vec())
-        .file_name("frame.jpg")
-        .mime_str("image/jpeg")
-        .map_err(|e| format!("Failed to create multipart: {}", e))?;
+    let metadata_json = metadata
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| DetectionFailure::fatal(AgentError::Internal(format!("Failed to serialize metadata: {}", e))))?;
 
-    let form = multipart::Form::new()
-        .part("image", part)
-        .text("camera_id", camera_id.to_string());
+    let (url, form) = match &metadata_json {
+        Some(json) => (
+            format!("{}/api/v2/detect", backend_url),
+            detection_form_v2(json, frame_bytes, format),
+        ),
+        None => (
+            format!("{}/api/v1/detect", backend_url),
+            detection_form(camera_id, frame_bytes, format),
+        ),
+    };
+    let form = form
+        .and_then(|form| with_previous_frame(form, previous_frame, format))
+        .map_err(DetectionFailure::fatal)?;
 
-    let response = client
-        .post(&url)
-        .header("Authorization", format!("Bearer {}", api_key))
+    // Connection problems are worth retrying elsewhere
+    let parts = signed_parts(frame_bytes, metadata_json.as_deref(), previous_frame);
+    let request = crate::signing::sign(auth.apply(client.post(&url), api_key), camera_id, &parts);
+    let response = request
         .multipart(form)
         .send()
         .await
-        .map_err(|e| format!("Request failed: {}", e))?;
+        .map_err(|e| DetectionFailure { error: request_error(e), failover: true, retry_after: None })?;
 
+    // A 5xx means this backend is unhealthy and a 429 that it's busy, so
+    // another may answer; other 4xx mean the request itself is wrong and
+    // another backend would reject it too
     if !response.status().is_success() {
         let status = response.status();
-        let text = response.text().await.unwrap_or_default();
-        return Err(format!("API error {}: {}", status, text));
+        let failover = status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+        let retry_after = response.headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|_| status == reqwest::StatusCode::TOO_MANY_REQUESTS)
+            .and_then(|value| parse_retry_after(value, chrono::Utc::now()));
+        return Err(DetectionFailure { error: status_error(response).await, failover, retry_after });
     }
 
-    let detection: DetectionResponse = response
-        .json()
+    // Return the raw text so a schema mismatch can show what came back
+    response
+        .text()
         .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+        .map_err(|e| DetectionFailure::fatal(AgentError::Decode(format!("Failed to read response: {}", e))))
+}
+
+/// Send frame to each backend in order until one answers
+///
+/// Falls through to the next backend on connection errors, 5xx and 429
+/// responses only. The response's `served_by` names the backend that answered. Frames
+/// go to the v2 endpoint when `metadata` is given, v1 otherwise, and the
+/// response is read through `adapter`. `previous_frame`, in the same
+/// format, is sent alongside as `prev_image`.
+#[allow(clippy::too_many_arguments)]
+pub async fn send_detection_with_failover(
+    backends: &[BackendConfig],
+    auth: &AuthScheme,
+    camera_id: &str,
+    frame_bytes: &[u8],
+    previous_frame: Option<&[u8]>,
+    format: ImageFormat,
+    metadata: Option<&FrameMetadata>,
+    adapter: &ResponseAdapter,
+) -> Result<DetectionResponse, DetectionError> {
+    let mut last_error = DetectionError::from(AgentError::InvalidInput("No detection backends configured".to_string()));
+
+    for (index, backend) in backends.iter().enumerate() {
+        match post_detection(&backend.url, camera_id, frame_bytes, previous_frame, format, metadata, &backend.api_key, auth).await {
+            Ok(body) => {
+                let mut response = adapter.parse(&body, camera_id, frame_bytes)?;
+                if index > 0 {
+                    println!("[Rust] Detection served by fallback backend {}", backend.url);
+                }
+                response.served_by = Some(backend.url.clone());
+                return Ok(response);
+            }
+            Err(failure) if failure.failover => {
+                println!("[Rust] Backend {} failed, trying next: {}", backend.url, failure.error);
+                last_error = DetectionError { error: failure.error, retry_after: failure.retry_after };
+            }
+            Err(failure) => return Err(failure.error.into()),
+        }
+    }
 
-    Ok(detection)
+    Err(last_error)
 }
 
 /// Create a new zone for a camera
@@ -104,8 +479,9 @@ pub async fn create_zone(
     coordinates: &[[f64; 2]],
     alert_type: &str,
     api_key: &str,
-) -> Result<ZoneResponse, String> {
-    let client = reqwest::Client::new();
+    auth: &AuthScheme,
+) -> Result<ZoneResponse, AgentError> {
+    let client = crate::proxy::http_client();
 
     let url = format!("{}/api/v1/cameras/{}/zones", backend_url, camera_id);
 
@@ -116,25 +492,21 @@ pub async fn create_zone(
         active: true,
     };
 
-    let response = client
-        .post(&url)
-        .header("Authorization", format!("Bearer {}", api_key))
+    let response = auth.apply(client.post(&url), api_key)
         .header("Content-Type", "application/json")
         .json(&request_body)
         .send()
         .await
-        .map_err(|e| format!("Request failed: {}", e))?;
+        .map_err(request_error)?;
 
     if !response.status().is_success() {
-        let status = response.status();
-        let text = response.text().await.unwrap_or_default();
-        return Err(format!("API error {}: {}", status, text));
+        return Err(status_error(response).await);
     }
 
     let zone: ZoneResponse = response
         .json()
         .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+        .map_err(|e| AgentError::Decode(format!("Failed to parse response: {}", e)))?;
 
     Ok(zone)
 }
@@ -144,28 +516,25 @@ pub async fn get_zones(
     backend_url: &str,
     camera_id: &str,
     api_key: &str,
-) -> Result<Vec<ZoneResponse>, String> {
-    let client = reqwest::Client::new();
+    auth: &AuthScheme,
+) -> Result<Vec<ZoneResponse>, AgentError> {
+    let client = crate::proxy::http_client();
 
     let url = format!("{}/api/v1/cameras/{}/zones", backend_url, camera_id);
 
-    let response = client
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", api_key))
+    let response = auth.apply(client.get(&url), api_key)
         .send()
         .await
-        .map_err(|e| format!("Request failed: {}", e))?;
+        .map_err(request_error)?;
 
     if !response.status().is_success() {
-        let status = response.status();
-        let text = response.text().await.unwrap_or_default();
-        return Err(format!("API error {}: {}", status, text));
+        return Err(status_error(response).await);
     }
 
     let zones: Vec<ZoneResponse> = response
         .json()
         .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+        .map_err(|e| AgentError::Decode(format!("Failed to parse response: {}", e)))?;
 
     Ok(zones)
 }
@@ -176,22 +545,19 @@ pub async fn delete_zone(
     camera_id: &str,
     zone_id: i64,
     api_key: &str,
-) -> Result<(), String> {
-    let client = reqwest::Client::new();
+    auth: &AuthScheme,
+) -> Result<(), AgentError> {
+    let client = crate::proxy::http_client();
 
     let url = format!("{}/api/v1/cameras/{}/zones/{}", backend_url, camera_id, zone_id);
 
-    let response = client
-        .delete(&url)
-        .header("Authorization", format!("Bearer {}", api_key))
+    let response = auth.apply(client.delete(&url), api_key)
         .send()
         .await
-        .map_err(|e| format!("Request failed: {}", e))?;
+        .map_err(request_error)?;
 
     if !response.status().is_success() {
-        let status = response.status();
-        let text = response.text().await.unwrap_or_default();
-        return Err(format!("API error {}: {}", status, text));
+        return Err(status_error(response).await);
     }
 
     Ok(())
@@ -221,11 +587,12 @@ pub struct AlertListResponse {
 pub async fn get_alerts(
     backend_url: &str,
     api_key: &str,
+    auth: &AuthScheme,
     camera_id: Option<&str>,
     page: i64,
     page_size: i64,
-) -> Result<AlertListResponse, String> {
-    let client = reqwest::Client::new();
+) -> Result<AlertListResponse, AgentError> {
+    let client = crate::proxy::http_client();
 
     let mut url = format!("{}/api/v1/alerts?page={}&page_size={}", backend_url, page, page_size);
 
@@ -233,23 +600,1047 @@ pub async fn get_alerts(
         url = format!("{}&camera_id={}", url, cam_id);
     }
 
-    let response = client
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", api_key))
+    let response = auth.apply(client.get(&url), api_key)
         .send()
         .await
-        .map_err(|e| format!("Request failed: {}", e))?;
+        .map_err(request_error)?;
 
     if !response.status().is_success() {
-        let status = response.status();
-        let text = response.text().await.unwrap_or_default();
-        return Err(format!("API error {}: {}", status, text));
+        return Err(status_error(response).await);
     }
 
     let alerts: AlertListResponse = response
         .json()
         .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+        .map_err(|e| AgentError::Decode(format!("Failed to parse response: {}", e)))?;
 
     Ok(alerts)
 }
+
+/// Most alerts the backend returns per page
+const MAX_ALERT_PAGE: i64 = 100;
+
+/// Alerts newer than a cursor and the cursor to poll with next
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewAlerts {
+    pub alerts: Vec<AlertResponse>,
+    /// Largest alert id seen; unchanged when nothing new arrived
+    pub cursor: i64,
+}
+
+/// Get alerts with an id greater than `since_id`, oldest first
+pub async fn poll_new_alerts(
+    backend_url: &str,
+    api_key: &str,
+    auth: &AuthScheme,
+    since_id: i64,
+) -> Result<NewAlerts, AgentError> {
+    let client = crate::proxy::http_client();
+
+    let url = format!("{}/api/v1/alerts?since_id={}&page=1&page_size={}", backend_url, since_id, MAX_ALERT_PAGE);
+
+    let response = auth.apply(client.get(&url), api_key)
+        .send()
+        .await
+        .map_err(request_error)?;
+
+    if !response.status().is_success() {
+        return Err(status_error(response).await);
+    }
+
+    let page: AlertListResponse = response
+        .json()
+        .await
+        .map_err(|e| AgentError::Decode(format!("Failed to parse response: {}", e)))?;
+
+    // Anything beyond the page is picked up by the next poll from this cursor
+    let cursor = page.alerts.iter().map(|alert| alert.id).max().unwrap_or(since_id).max(since_id);
+
+    Ok(NewAlerts { alerts: page.alerts, cursor })
+}
+
+/// Download an alert's image
+///
+/// Relative `image_url`s are resolved against the backend. The API key is only
+/// sent to the backend's own origin so it never leaks to third-party storage.
+pub async fn download_alert_image(
+    backend_url: &str,
+    api_key: &str,
+    auth: &AuthScheme,
+    image_url: &str,
+) -> Result<Vec<u8>, AgentError> {
+    let backend = reqwest::Url::parse(backend_url)
+        .map_err(|e| AgentError::InvalidInput(format!("Invalid backend URL: {}", e)))?;
+    let url = backend.join(image_url)
+        .map_err(|e| AgentError::InvalidInput(format!("Invalid image URL {}: {}", image_url, e)))?;
+
+    let client = crate::proxy::http_client();
+    let mut request = client.get(url.clone());
+    if url.origin() == backend.origin() {
+        request = auth.apply(request, api_key);
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(request_error)?;
+
+    if !response.status().is_success() {
+        return Err(status_error(response).await);
+    }
+
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| AgentError::Network(format!("Failed to read image: {}", e)))?;
+
+    Ok(bytes.to_vec())
+}
+
+/// Fresh location of an alert's image, from `/api/v1/alerts/{id}/image`
+#[derive(Debug, Clone, Deserialize)]
+struct AlertImageLocation {
+    #[serde(alias = "url")]
+    image_url: String,
+}
+
+/// Download an alert's image through a freshly issued URL
+///
+/// Presigned `image_url`s in alert lists can expire before they are opened,
+/// so this asks the backend for the image again. The endpoint may answer
+/// with the image itself (directly or via redirect) or with JSON holding a
+/// new `image_url`.
+pub async fn fetch_fresh_alert_image(
+    backend_url: &str,
+    api_key: &str,
+    auth: &AuthScheme,
+    alert_id: i64,
+) -> Result<Vec<u8>, AgentError> {
+    let client = crate::proxy::http_client();
+
+    let url = format!("{}/api/v1/alerts/{}/image", backend_url, alert_id);
+
+    let response = auth.apply(client.get(&url), api_key)
+        .send()
+        .await
+        .map_err(request_error)?;
+
+    if !response.status().is_success() {
+        return Err(status_error(response).await);
+    }
+
+    let is_image = response.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("image/"));
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| AgentError::Network(format!("Failed to read image: {}", e)))?;
+    if is_image {
+        return Ok(bytes.to_vec());
+    }
+
+    let location: AlertImageLocation = serde_json::from_slice(&bytes)
+        .map_err(|e| AgentError::Decode(format!("Failed to parse image location: {}", e)))?;
+    download_alert_image(backend_url, api_key, auth, &location.image_url).await
+}
+
+/// Liveness report posted by the heartbeat task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Heartbeat {
+    pub agent_id: String,
+    pub version: String,
+    pub cameras_connected: usize,
+    /// Cameras with a periodic capture task running
+    pub cameras_monitored: usize,
+    pub cameras_paused: usize,
+}
+
+/// Tell the backend this agent is alive
+pub async fn send_heartbeat(
+    backend_url: &str,
+    api_key: &str,
+    auth: &AuthScheme,
+    heartbeat: &Heartbeat,
+) -> Result<(), AgentError> {
+    let client = crate::proxy::http_client();
+
+    let url = format!("{}/api/v1/agents/heartbeat", backend_url);
+
+    let response = auth.apply(client.post(&url), api_key)
+        .json(heartbeat)
+        .send()
+        .await
+        .map_err(request_error)?;
+
+    if !response.status().is_success() {
+        return Err(status_error(response).await);
+    }
+
+    Ok(())
+}
+
+/// Backend health as reported by `/api/v1/health`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackendHealth {
+    pub status: String,
+    /// Missing on backends that predate version reporting
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+/// Fetch the backend's health; the endpoint needs no API key
+pub async fn get_health(backend_url: &str) -> Result<BackendHealth, AgentError> {
+    let client = crate::proxy::http_client();
+
+    let url = format!("{}/api/v1/health", backend_url);
+
+    let response = client.get(&url)
+        .timeout(std::time::Duration::from_secs(5))
+        .send()
+        .await
+        .map_err(request_error)?;
+
+    if !response.status().is_success() {
+        return Err(status_error(response).await);
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| AgentError::Decode(format!("Failed to parse response: {}", e)))
+}
+
+/// Outcome of a test request against the detect endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct DetectionTestResult {
+    pub ok: bool,
+    pub status: Option<u16>,
+    pub latency_ms: u64,
+    pub detections: usize,
+    /// Upload format the test image was sent in
+    pub format: ImageFormat,
+    pub error: Option<AgentError>,
+}
+
+/// Small solid-grey JPEG used to exercise the detect endpoint
+pub(crate) fn test_jpeg() -> Result<Vec<u8>, AgentError> {
+    let image = image::RgbImage::from_pixel(64, 64, image::Rgb([128, 128, 128]));
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image.write_to(&mut bytes, image::ImageFormat::Jpeg)
+        .map_err(|e| AgentError::Internal(format!("Failed to encode test image: {}", e)))?;
+    Ok(bytes.into_inner())
+}
+
+/// Send a test image to the detect endpoint and check the response shape
+///
+/// The image is sent in `format`, so a backend that rejects the configured
+/// upload format shows up here rather than as silently failing detections.
+pub async fn test_detection_endpoint(
+    backend_url: &str,
+    api_key: &str,
+    auth: &AuthScheme,
+    format: ImageFormat,
+    adapter: &ResponseAdapter,
+) -> Result<DetectionTestResult, AgentError> {
+    let client = crate::proxy::http_client();
+
+    let url = format!("{}/api/v1/detect", backend_url);
+
+    let image = crate::frame::encode_frame(test_jpeg()?, format)?;
+    let form = detection_form("connection-test", &image, format)?;
+
+    let started = std::time::Instant::now();
+    let mut result = DetectionTestResult {
+        ok: false,
+        status: None,
+        latency_ms: 0,
+        detections: 0,
+        format,
+        error: None,
+    };
+
+    let request = crate::signing::sign(auth.apply(client.post(&url), api_key), "connection-test", &[("image", &image)]);
+    let response = match request
+        .multipart(form)
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            result.error = Some(request_error(e));
+            return Ok(result);
+        }
+    };
+
+    result.latency_ms = started.elapsed().as_millis() as u64;
+    result.status = Some(response.status().as_u16());
+
+    if !response.status().is_success() {
+        result.error = Some(status_error(response).await);
+        return Ok(result);
+    }
+
+    // Parse from text so a schema mismatch can quote what the backend sent
+    let body = response.text().await.unwrap_or_default();
+    match adapter.parse(&body, "connection-test", &image) {
+        Ok(detection) => {
+            result.ok = true;
+            result.detections = detection.detections.len();
+        }
+        Err(e) => result.error = Some(e),
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_string_contains, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const API_KEY: &str = "test-key";
+
+    fn detection_body() -> serde_json::Value {
+        json!({
+            "camera_id": "cam-1",
+            "timestamp": "2024-01-01T00:00:00Z",
+            "detections": [{
+                "class": "person",
+                "confidence": 0.9,
+                "bbox": { "x1": 0.1, "y1": 0.2, "x2": 0.3, "y2": 0.4 }
+            }],
+            "alerts": [],
+            "inference_ms": 12.5
+        })
+    }
+
+    /// Detect against a single backend, as when no failover list is set
+    async fn detect(url: &str) -> Result<DetectionResponse, AgentError> {
+        let backends = [BackendConfig { url: url.to_string(), api_key: API_KEY.to_string() }];
+        send_detection_with_failover(&backends, &AuthScheme::Bearer, "cam-1", b"jpeg-bytes", None, ImageFormat::Jpeg, None, &ResponseAdapter::default())
+            .await
+            .map_err(AgentError::from)
+    }
+
+    fn zone_body(id: i64) -> serde_json::Value {
+        json!({
+            "id": id,
+            "camera_id": "cam-1",
+            "name": "Gate",
+            "coordinates": [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]],
+            "alert_type": "intrusion",
+            "active": true,
+            "active_hours": null,
+            "created_at": "2024-01-01T00:00:00Z"
+        })
+    }
+
+    #[tokio::test]
+    async fn detect_posts_multipart_frame() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/detect"))
+            .and(header("Authorization", "Bearer test-key"))
+            .and(body_string_contains("name=\"camera_id\""))
+            .and(body_string_contains("cam-1"))
+            .and(body_string_contains("name=\"image\"; filename=\"frame.jpg\""))
+            .and(body_string_contains("Content-Type: image/jpeg"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(detection_body()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let response = detect(&server.uri())
+            .await
+            .unwrap();
+
+        assert_eq!(response.camera_id, "cam-1");
+        assert_eq!(response.detections.len(), 1);
+        assert_eq!(response.detections[0].class_name, "person");
+        assert!(response.alerts.is_empty());
+        assert_eq!(response.inference_ms, Some(12.5));
+    }
+
+    #[tokio::test]
+    async fn detect_v2_sends_metadata_part() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/detect"))
+            .and(body_string_contains("name=\"image\"; filename=\"frame.jpg\""))
+            .and(body_string_contains("name=\"metadata\""))
+            .and(body_string_contains("Content-Type: application/json"))
+            .and(body_string_contains("\"sequence\":7"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(detection_body()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let metadata = FrameMetadata {
+            camera_id: "cam-1".to_string(),
+            capture_timestamp: "2024-01-01T00:00:00+00:00".to_string(),
+            sequence: 7,
+        };
+        let backends = [BackendConfig { url: server.uri(), api_key: API_KEY.to_string() }];
+        send_detection_with_failover(&backends, &AuthScheme::Bearer, "cam-1", b"jpeg-bytes", None, ImageFormat::Jpeg, Some(&metadata), &ResponseAdapter::default())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn detect_attaches_previous_frame() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/detect"))
+            .and(body_string_contains("name=\"image\"; filename=\"frame.jpg\""))
+            .and(body_string_contains("name=\"prev_image\"; filename=\"prev_frame.jpg\""))
+            .and(body_string_contains("previous-bytes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(detection_body()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let backends = [BackendConfig { url: server.uri(), api_key: API_KEY.to_string() }];
+        send_detection_with_failover(
+            &backends, &AuthScheme::Bearer, "cam-1", b"jpeg-bytes", Some(b"previous-bytes"), ImageFormat::Jpeg, None,
+            &ResponseAdapter::default(),
+        ).await.unwrap();
+    }
+
+    /// Named parts of a multipart body, as the backend would read them
+    fn multipart_parts(request: &wiremock::Request) -> Vec<(String, Vec<u8>)> {
+        let content_type = request.headers.get("content-type").unwrap().to_str().unwrap();
+        let delimiter = format!("--{}", content_type.split("boundary=").nth(1).unwrap());
+        let body = &request.body;
+
+        let mut starts: Vec<usize> = body.windows(delimiter.len())
+            .enumerate()
+            .filter(|(_, window)| *window == delimiter.as_bytes())
+            .map(|(at, _)| at)
+            .collect();
+        starts.push(body.len());
+
+        starts.windows(2)
+            .filter_map(|bounds| {
+                let segment = &body[bounds[0] + delimiter.len()..bounds[1]];
+                let split = segment.windows(4).position(|window| window == b"\r\n\r\n")?;
+                let headers = String::from_utf8_lossy(&segment[..split]);
+                let name = headers.split("name=\"").nth(1)?.split('"').next()?.to_string();
+                let content = segment[split + 4..].strip_suffix(b"\r\n").unwrap_or(&segment[split + 4..]);
+                Some((name, content.to_vec()))
+            })
+            .collect()
+    }
+
+    /// Accepts requests whose signature matches the parts actually received
+    struct ValidSignature;
+
+    impl wiremock::Match for ValidSignature {
+        fn matches(&self, request: &wiremock::Request) -> bool {
+            let header = |name: &str| request.headers.get(name).and_then(|value| value.to_str().ok());
+            let (Some(signature), Some(timestamp)) = (header(crate::signing::SIGNATURE_HEADER), header(crate::signing::TIMESTAMP_HEADER)) else {
+                return false;
+            };
+            let received = multipart_parts(request);
+            let parts: Vec<(&str, &[u8])> = received.iter()
+                .filter(|(name, _)| name != "camera_id")
+                .map(|(name, content)| (name.as_str(), content.as_slice()))
+                .collect();
+            crate::signing::signature(b"secret", &parts, "cam-1", timestamp.parse().unwrap()) == signature
+        }
+    }
+
+    #[tokio::test]
+    async fn tampered_previous_frame_fails_signature() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(ValidSignature)
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401))
+            .with_priority(10)
+            .mount(&server)
+            .await;
+
+        let send = |sent_previous: &'static [u8]| {
+            let url = server.uri();
+            async move {
+                let parts = signed_parts(b"jpeg-bytes", None, Some(b"previous-bytes"));
+                let form = detection_form("cam-1", b"jpeg-bytes", ImageFormat::Jpeg)
+                    .and_then(|form| with_previous_frame(form, Some(sent_previous), ImageFormat::Jpeg))
+                    .unwrap();
+                crate::signing::apply(reqwest::Client::new().post(url), Some(b"secret"), "cam-1", &parts, 1_700_000_000)
+                    .multipart(form)
+                    .send()
+                    .await
+                    .unwrap()
+                    .status()
+            }
+        };
+
+        assert_eq!(send(b"previous-bytes").await, reqwest::StatusCode::OK);
+        assert_eq!(send(b"tampered-bytes").await, reqwest::StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn detect_labels_png_upload() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/detect"))
+            .and(body_string_contains("name=\"image\"; filename=\"frame.png\""))
+            .and(body_string_contains("Content-Type: image/png"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(detection_body()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let backends = [BackendConfig { url: server.uri(), api_key: API_KEY.to_string() }];
+        send_detection_with_failover(&backends, &AuthScheme::Bearer, "cam-1", b"png-bytes", None, ImageFormat::Png, None, &ResponseAdapter::default())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs ffmpeg to encode WebP"]
+    async fn test_detection_endpoint_reports_rejected_format() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/detect"))
+            .respond_with(ResponseTemplate::new(415).set_body_string("unsupported image type"))
+            .mount(&server)
+            .await;
+
+        let result = test_detection_endpoint(&server.uri(), API_KEY, &AuthScheme::Bearer, ImageFormat::Webp, &ResponseAdapter::default())
+            .await
+            .unwrap();
+
+        assert!(!result.ok);
+        assert_eq!(result.status, Some(415));
+        assert_eq!(result.format, ImageFormat::Webp);
+
+        // The encoded image isn't UTF-8, so look for the part header in raw bytes
+        let body = &server.received_requests().await.unwrap()[0].body;
+        let part_header = b"Content-Type: image/webp";
+        assert!(body.windows(part_header.len()).any(|w| w == part_header));
+    }
+
+    #[tokio::test]
+    async fn detect_quotes_unparseable_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/detect"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html>oops</html>"))
+            .mount(&server)
+            .await;
+
+        let err = detect(&server.uri())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, AgentError::Decode(_)));
+        assert!(err.message().contains("(body: <html>oops</html>)"));
+    }
+
+    #[tokio::test]
+    async fn error_statuses_map_to_error_kinds() {
+        let cases = [
+            (401, "Auth"),
+            (403, "Auth"),
+            (404, "NotFound"),
+            (408, "Timeout"),
+            (504, "Timeout"),
+            (429, "RateLimited"),
+            (500, "Backend"),
+            (422, "Backend"),
+        ];
+
+        for (status, kind) in cases {
+            let server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/api/v1/detect"))
+                .respond_with(ResponseTemplate::new(status).set_body_string("nope"))
+                .mount(&server)
+                .await;
+
+            let err = detect(&server.uri())
+                .await
+                .unwrap_err();
+
+            let serialized = serde_json::to_value(&err).unwrap();
+            assert_eq!(serialized["kind"], kind, "status {}", status);
+            assert!(err.message().starts_with(&format!("API error {}", status)));
+            assert!(err.message().ends_with(": nope"));
+        }
+    }
+
+    #[tokio::test]
+    async fn unreachable_backend_is_a_network_error() {
+        // Bind then release a port so nothing is listening on it
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let uri = format!("http://127.0.0.1:{}", port);
+
+        let err = detect(&uri)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, AgentError::Network(_)));
+        assert!(err.message().starts_with("Request failed:"));
+    }
+
+    fn backend(server: &MockServer, api_key: &str) -> BackendConfig {
+        BackendConfig { url: server.uri(), api_key: api_key.to_string() }
+    }
+
+    #[tokio::test]
+    async fn failover_skips_backend_returning_5xx() {
+        let primary = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/detect"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(1)
+            .mount(&primary)
+            .await;
+
+        let fallback = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/detect"))
+            .and(header("Authorization", "Bearer fallback-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(detection_body()))
+            .expect(1)
+            .mount(&fallback)
+            .await;
+
+        let backends = [backend(&primary, API_KEY), backend(&fallback, "fallback-key")];
+        let response = send_detection_with_failover(&backends, &AuthScheme::Bearer, "cam-1", b"jpeg-bytes", None, ImageFormat::Jpeg, None, &ResponseAdapter::default())
+            .await
+            .unwrap();
+
+        assert_eq!(response.served_by, Some(fallback.uri()));
+    }
+
+    #[tokio::test]
+    async fn failover_stops_on_4xx() {
+        let primary = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/detect"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&primary)
+            .await;
+
+        let fallback = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(detection_body()))
+            .expect(0)
+            .mount(&fallback)
+            .await;
+
+        let backends = [backend(&primary, API_KEY), backend(&fallback, API_KEY)];
+        let err = send_detection_with_failover(&backends, &AuthScheme::Bearer, "cam-1", b"jpeg-bytes", None, ImageFormat::Jpeg, None, &ResponseAdapter::default())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err.error, AgentError::Auth(_)));
+    }
+
+    #[tokio::test]
+    async fn failover_returns_last_error_when_all_fail() {
+        let primary = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("primary down"))
+            .mount(&primary)
+            .await;
+
+        let fallback = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(502).set_body_string("fallback down"))
+            .mount(&fallback)
+            .await;
+
+        let backends = [backend(&primary, API_KEY), backend(&fallback, API_KEY)];
+        let err = send_detection_with_failover(&backends, &AuthScheme::Bearer, "cam-1", b"jpeg-bytes", None, ImageFormat::Jpeg, None, &ResponseAdapter::default())
+            .await
+            .unwrap_err();
+
+        assert!(err.error.message().ends_with("fallback down"));
+    }
+
+    #[tokio::test]
+    async fn rate_limited_detection_reports_retry_after() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "42").set_body_string("slow down"))
+            .mount(&server)
+            .await;
+
+        let backends = [backend(&server, API_KEY)];
+        let err = send_detection_with_failover(&backends, &AuthScheme::Bearer, "cam-1", b"jpeg-bytes", None, ImageFormat::Jpeg, None, &ResponseAdapter::default())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err.error, AgentError::RateLimited(_)));
+        assert_eq!(err.retry_after, Some(Duration::from_secs(42)));
+
+        let now = chrono::DateTime::parse_from_rfc3339("2015-10-21T07:27:30Z").unwrap().with_timezone(&chrono::Utc);
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[tokio::test]
+    async fn create_zone_posts_json_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/cameras/cam-1/zones"))
+            .and(header("Authorization", "Bearer test-key"))
+            .and(header("Content-Type", "application/json"))
+            .and(wiremock::matchers::body_json(json!({
+                "name": "Gate",
+                "coordinates": [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]],
+                "alert_type": "intrusion",
+                "active": true
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(zone_body(7)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let coordinates = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]];
+        let zone = create_zone(&server.uri(), "cam-1", "Gate", &coordinates, "intrusion", API_KEY, &AuthScheme::Bearer)
+            .await
+            .unwrap();
+
+        assert_eq!(zone.id, 7);
+        assert_eq!(zone.name, "Gate");
+    }
+
+    #[tokio::test]
+    async fn get_zones_lists_camera_zones() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/cameras/cam-1/zones"))
+            .and(header("Authorization", "Bearer test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([zone_body(1), zone_body(2)])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let zones = get_zones(&server.uri(), "cam-1", API_KEY, &AuthScheme::Bearer).await.unwrap();
+
+        assert_eq!(zones.iter().map(|z| z.id).collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn delete_zone_sends_delete() {
+        let server = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/v1/cameras/cam-1/zones/7"))
+            .and(header("Authorization", "Bearer test-key"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        delete_zone(&server.uri(), "cam-1", 7, API_KEY, &AuthScheme::Bearer).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn get_alerts_sends_paging_and_camera_filter() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/alerts"))
+            .and(header("Authorization", "Bearer test-key"))
+            .and(query_param("page", "2"))
+            .and(query_param("page_size", "25"))
+            .and(query_param("camera_id", "cam-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "alerts": [{
+                    "id": 3,
+                    "camera_id": "cam-1",
+                    "zone_id": 7,
+                    "detection_type": "person",
+                    "confidence": 0.8,
+                    "bbox": null,
+                    "image_url": null,
+                    "timestamp": "2024-01-01T00:00:00Z"
+                }],
+                "total": 26,
+                "page": 2,
+                "page_size": 25
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let alerts = get_alerts(&server.uri(), API_KEY, &AuthScheme::Bearer, Some("cam-1"), 2, 25).await.unwrap();
+
+        assert_eq!(alerts.total, 26);
+        assert_eq!(alerts.alerts[0].zone_id, 7);
+    }
+
+    #[tokio::test]
+    async fn get_alerts_omits_camera_filter_when_unset() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/alerts"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "alerts": [], "total": 0, "page": 1, "page_size": 50
+            })))
+            .mount(&server)
+            .await;
+
+        get_alerts(&server.uri(), API_KEY, &AuthScheme::Bearer, None, 1, 50).await.unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        assert!(!requests[0].url.query_pairs().any(|(key, _)| key == "camera_id"));
+    }
+
+    #[tokio::test]
+    async fn download_alert_image_resolves_relative_urls_with_auth() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/media/alerts/42.jpg"))
+            .and(header("Authorization", "Bearer test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"jpeg-bytes".to_vec()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let bytes = download_alert_image(&server.uri(), API_KEY, &AuthScheme::Bearer, "/media/alerts/42.jpg").await.unwrap();
+        assert_eq!(bytes, b"jpeg-bytes");
+    }
+
+    #[tokio::test]
+    async fn download_alert_image_keeps_api_key_off_other_hosts() {
+        let backend = MockServer::start().await;
+        let storage = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/bucket/42.jpg"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"jpeg-bytes".to_vec()))
+            .mount(&storage)
+            .await;
+
+        let image_url = format!("{}/bucket/42.jpg", storage.uri());
+        download_alert_image(&backend.uri(), API_KEY, &AuthScheme::Bearer, &image_url).await.unwrap();
+
+        let requests = storage.received_requests().await.unwrap();
+        assert!(!requests[0].headers.contains_key("authorization"));
+    }
+
+    #[tokio::test]
+    async fn fresh_alert_image_follows_reissued_url() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/alerts/42/image"))
+            .and(header("Authorization", "Bearer test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "image_url": "/media/alerts/42.jpg?sig=new" })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/media/alerts/42.jpg"))
+            .and(query_param("sig", "new"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"jpeg-bytes".to_vec()))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/alerts/43/image"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(b"png-bytes".to_vec(), "image/png"))
+            .mount(&server)
+            .await;
+
+        let bytes = fetch_fresh_alert_image(&server.uri(), API_KEY, &AuthScheme::Bearer, 42).await.unwrap();
+        assert_eq!(bytes, b"jpeg-bytes");
+        let bytes = fetch_fresh_alert_image(&server.uri(), API_KEY, &AuthScheme::Bearer, 43).await.unwrap();
+        assert_eq!(bytes, b"png-bytes");
+    }
+
+    #[tokio::test]
+    async fn api_key_header_scheme_replaces_bearer() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/cameras/cam-1/zones"))
+            .and(header("X-API-Key", "test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let auth = AuthScheme::ApiKeyHeader("X-API-Key".to_string());
+        get_zones(&server.uri(), "cam-1", API_KEY, &auth).await.unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        assert!(!requests[0].headers.contains_key("authorization"));
+    }
+
+    #[tokio::test]
+    async fn empty_api_key_sends_no_credential() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/cameras/cam-1/zones"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        get_zones(&server.uri(), "cam-1", "", &AuthScheme::Bearer).await.unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        assert!(!requests[0].headers.contains_key("authorization"));
+    }
+
+    #[tokio::test]
+    async fn query_param_scheme_keeps_existing_query() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/alerts"))
+            .and(query_param("token", "test-key"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "alerts": [], "total": 0, "page": 1, "page_size": 50
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let auth = AuthScheme::QueryParam("token".to_string());
+        get_alerts(&server.uri(), API_KEY, &auth, None, 1, 50).await.unwrap();
+    }
+
+    #[test]
+    fn auth_scheme_serializes_with_header_name() {
+        let scheme: AuthScheme = serde_json::from_value(json!({ "type": "api_key_header", "name": "X-API-Key" })).unwrap();
+        assert_eq!(scheme, AuthScheme::ApiKeyHeader("X-API-Key".to_string()));
+        assert_eq!(serde_json::to_value(AuthScheme::Bearer).unwrap(), json!({ "type": "bearer" }));
+    }
+
+    #[tokio::test]
+    async fn poll_new_alerts_advances_cursor() {
+        let server = MockServer::start().await;
+        let alert = |id: i64| json!({
+            "id": id, "camera_id": "cam-1", "zone_id": 1, "detection_type": "person",
+            "confidence": 0.9, "bbox": null, "image_url": null, "timestamp": "2024-01-01T00:00:00Z"
+        });
+        Mock::given(method("GET"))
+            .and(path("/api/v1/alerts"))
+            .and(query_param("since_id", "10"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "alerts": [alert(11), alert(14)], "total": 2, "page": 1, "page_size": 100
+            })))
+            .mount(&server)
+            .await;
+
+        let new = poll_new_alerts(&server.uri(), API_KEY, &AuthScheme::Bearer, 10).await.unwrap();
+
+        assert_eq!(new.alerts.len(), 2);
+        assert_eq!(new.cursor, 14);
+    }
+
+    #[tokio::test]
+    async fn poll_new_alerts_keeps_cursor_when_empty() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/alerts"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "alerts": [], "total": 0, "page": 1, "page_size": 100
+            })))
+            .mount(&server)
+            .await;
+
+        assert_eq!(poll_new_alerts(&server.uri(), API_KEY, &AuthScheme::Bearer, 42).await.unwrap().cursor, 42);
+    }
+
+    #[tokio::test]
+    async fn get_health_reads_version_when_present() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/health"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "status": "healthy",
+                "version": "0.1.3",
+                "model_loaded": true,
+                "database_connected": true,
+                "timestamp": "2024-01-01T00:00:00"
+            })))
+            .mount(&server)
+            .await;
+
+        let health = get_health(&server.uri()).await.unwrap();
+        assert_eq!(health.status, "healthy");
+        assert_eq!(health.version.as_deref(), Some("0.1.3"));
+    }
+
+    #[tokio::test]
+    async fn send_heartbeat_posts_agent_state() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/agents/heartbeat"))
+            .and(header("Authorization", "Bearer test-key"))
+            .and(wiremock::matchers::body_json(json!({
+                "agent_id": "agent-1",
+                "version": "0.1.0",
+                "cameras_connected": 3,
+                "cameras_monitored": 2,
+                "cameras_paused": 1
+            })))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let heartbeat = Heartbeat {
+            agent_id: "agent-1".to_string(),
+            version: "0.1.0".to_string(),
+            cameras_connected: 3,
+            cameras_monitored: 2,
+            cameras_paused: 1,
+        };
+        send_heartbeat(&server.uri(), API_KEY, &AuthScheme::Bearer, &heartbeat).await.unwrap();
+    }
+
+    #[test]
+    fn yolo_raw_rows_scale_to_frame_pixels() {
+        let adapter = ResponseAdapter {
+            format: ResponseFormat::YoloRaw,
+            labels: vec!["person".to_string(), "car".to_string()],
+        };
+        let frame = test_jpeg().unwrap();
+        let body = "[[1, 0.5, 0.5, 0.5, 0.25, 0.8], [7, 0.25, 0.25, 0.1, 0.1, 0.4]]";
+
+        let response = adapter.parse(body, "cam-1", &frame).unwrap();
+        assert_eq!(response.camera_id, "cam-1");
+        assert_eq!(response.detections.len(), 2);
+
+        let car = &response.detections[0];
+        assert_eq!(car.class_name, "car");
+        assert_eq!(car.confidence, 0.8);
+        assert_eq!((car.bbox.x1, car.bbox.y1, car.bbox.x2, car.bbox.y2), (16.0, 24.0, 48.0, 40.0));
+        // No label for index 7
+        assert_eq!(response.detections[1].class_name, "7");
+    }
+
+    #[test]
+    fn coco_results_convert_xywh_to_corners() {
+        let adapter = ResponseAdapter {
+            format: ResponseFormat::CocoJson,
+            labels: vec!["background".to_string(), "person".to_string()],
+        };
+        let body = r#"[{"image_id": 3, "category_id": 1, "bbox": [10.0, 20.0, 30.0, 40.0], "score": 0.9}]"#;
+
+        let response = adapter.parse(body, "cam-1", b"").unwrap();
+        let person = &response.detections[0];
+        assert_eq!(person.class_name, "person");
+        assert_eq!((person.bbox.x1, person.bbox.y1, person.bbox.x2, person.bbox.y2), (10.0, 20.0, 40.0, 60.0));
+    }
+
+    #[test]
+    fn raw_format_mismatch_is_a_decode_error() {
+        let adapter = ResponseAdapter { format: ResponseFormat::YoloRaw, labels: Vec::new() };
+        let err = adapter.parse(&detection_body().to_string(), "cam-1", &test_jpeg().unwrap()).unwrap_err();
+        assert!(matches!(err, AgentError::Decode(_)));
+    }
+}
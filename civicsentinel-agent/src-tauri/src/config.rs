@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize};
+use crate::api::{AuthScheme, BackendConfig};
+use crate::camera::{CaptureMode, FrameTransform, RetryPolicy, RtspTransport, StreamSelection};
+use crate::error::AgentError;
+use crate::settings::UploadSettings;
+use crate::sink::DetectionSink;
+use crate::storage::S3Config;
+use crate::webhook::WebhookConfig;
+
+/// Bumped when the bundle layout changes incompatibly
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Everything needed to set up another agent the same way, minus secrets
+///
+/// API keys, camera passwords and S3 access keys are never included; they
+/// have to be set on each install through the keychain and settings commands.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AgentConfig {
+    pub version: u32,
+    /// Detection backends in failover order
+    pub backend_urls: Vec<String>,
+    pub auth_scheme: AuthScheme,
+    pub detection_sink: DetectionSink,
+    pub upload: UploadSettings,
+    pub alert_webhook: Option<WebhookConfig>,
+    pub alert_consecutive_frames: u32,
+    pub dedup_iou_threshold: f64,
+    pub evidence_storage: Option<EvidenceLocation>,
+    pub cameras: Vec<CameraConfig>,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            backend_urls: Vec::new(),
+            auth_scheme: AuthScheme::default(),
+            detection_sink: DetectionSink::default(),
+            upload: UploadSettings::default(),
+            alert_webhook: None,
+            alert_consecutive_frames: crate::webhook::DEFAULT_ALERT_CONSECUTIVE_FRAMES,
+            dedup_iou_threshold: crate::dedup::DEFAULT_IOU_THRESHOLD,
+            evidence_storage: None,
+            cameras: Vec::new(),
+        }
+    }
+}
+
+/// Evidence bucket without its access keys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceLocation {
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+}
+
+/// A connected camera's capture settings; logins stay in the keychain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraConfig {
+    pub camera_id: String,
+    /// Main stream URL
+    pub source_url: String,
+    #[serde(default)]
+    pub sub_stream_url: Option<String>,
+    /// Stream live capture reads
+    #[serde(default)]
+    pub active_stream: StreamSelection,
+    #[serde(default)]
+    pub rtsp_transport: RtspTransport,
+    #[serde(default)]
+    pub roi: Option<[f64; 4]>,
+    #[serde(default)]
+    pub transform: FrameTransform,
+    #[serde(default)]
+    pub capture_mode: CaptureMode,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    #[serde(default)]
+    pub adaptive_capture: Option<AdaptiveCaptureConfig>,
+    /// Operator-chosen group, e.g. a building or floor
+    #[serde(default)]
+    pub group: Option<String>,
+}
+
+/// Arguments of `set_adaptive_capture`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdaptiveCaptureConfig {
+    pub base: u64,
+    pub fast: u64,
+    pub slow: u64,
+    pub idle_cycles: u32,
+}
+
+/// What `import_config` applied and what still has to be set by hand
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub cameras_connected: Vec<String>,
+    /// Camera id and why it could not be connected
+    pub cameras_failed: Vec<(String, String)>,
+    /// Secrets this install is missing, e.g. the API key for a backend
+    pub missing_secrets: Vec<String>,
+}
+
+/// Parse an exported bundle, rejecting ones from a newer agent
+pub fn parse(json: &str) -> Result<AgentConfig, AgentError> {
+    let config: AgentConfig = serde_json::from_str(json)
+        .map_err(|e| AgentError::InvalidInput(format!("Invalid configuration: {}", e)))?;
+
+    if config.version > CONFIG_VERSION {
+        return Err(AgentError::InvalidInput(format!(
+            "Configuration version {} is newer than this agent supports ({})", config.version, CONFIG_VERSION
+        )));
+    }
+
+    Ok(config)
+}
+
+/// Pair imported backend URLs with keys this install already has
+///
+/// Backends without a known key keep an empty one and are reported.
+pub fn backends_with_keys(
+    urls: &[String],
+    existing: &[BackendConfig],
+    report: &mut ImportReport,
+) -> Vec<BackendConfig> {
+    urls.iter()
+        .map(|url| {
+            let api_key = existing.iter()
+                .find(|backend| &backend.url == url && !backend.api_key.is_empty())
+                .map(|backend| backend.api_key.clone());
+            if api_key.is_none() {
+                report.missing_secrets.push(format!("API key for backend {}", url));
+            }
+            BackendConfig { url: url.clone(), api_key: api_key.unwrap_or_default() }
+        })
+        .collect()
+}
+
+/// Point evidence upload at the imported bucket, keeping this install's access keys
+pub fn evidence_with_keys(
+    location: Option<EvidenceLocation>,
+    existing: Option<&S3Config>,
+    report: &mut ImportReport,
+) -> Option<S3Config> {
+    let location = location?;
+    match existing {
+        Some(existing) => Some(S3Config {
+            endpoint: location.endpoint,
+            bucket: location.bucket,
+            region: location.region,
+            access_key: existing.access_key.clone(),
+            secret_key: existing.secret_key.clone(),
+        }),
+        None => {
+            report.missing_secrets.push(format!(
+                "Access keys for evidence bucket {}/{}", location.endpoint, location.bucket
+            ));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_json() {
+        let config = AgentConfig {
+            backend_urls: vec!["https://a.example".to_string()],
+            cameras: vec![CameraConfig {
+                camera_id: "cam-1".to_string(),
+                source_url: "rtsp://10.0.0.5/stream".to_string(),
+                sub_stream_url: Some("rtsp://10.0.0.5/stream2".to_string()),
+                active_stream: StreamSelection::Sub,
+                rtsp_transport: RtspTransport::default(),
+                roi: Some([0.0, 0.0, 0.5, 0.5]),
+                transform: FrameTransform { rotate: 90, ..FrameTransform::default() },
+                capture_mode: CaptureMode::OneShot,
+                retry: RetryPolicy::default(),
+                adaptive_capture: None,
+                group: Some("Lobby".to_string()),
+            }],
+            ..AgentConfig::default()
+        };
+
+        let parsed = parse(&serde_json::to_string(&config).unwrap()).unwrap();
+        assert_eq!(parsed.backend_urls, config.backend_urls);
+        assert_eq!(parsed.cameras[0].capture_mode, CaptureMode::OneShot);
+        assert_eq!(parsed.cameras[0].transform, config.cameras[0].transform);
+        assert_eq!(parsed.cameras[0].active_stream, StreamSelection::Sub);
+        assert_eq!(parsed.cameras[0].group.as_deref(), Some("Lobby"));
+    }
+
+    #[test]
+    fn missing_fields_take_defaults() {
+        let parsed = parse("{}").unwrap();
+        assert_eq!(parsed.version, CONFIG_VERSION);
+        assert!(parsed.cameras.is_empty());
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        let err = parse(&format!("{{\"version\": {}}}", CONFIG_VERSION + 1)).unwrap_err();
+        assert!(matches!(err, AgentError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn backend_keys_are_reused_or_reported() {
+        let existing = [BackendConfig { url: "https://a.example".to_string(), api_key: "key-a".to_string() }];
+        let mut report = ImportReport::default();
+
+        let backends = backends_with_keys(
+            &["https://a.example".to_string(), "https://b.example".to_string()],
+            &existing,
+            &mut report,
+        );
+
+        assert_eq!(backends[0].api_key, "key-a");
+        assert_eq!(backends[1].api_key, "");
+        assert_eq!(report.missing_secrets, vec!["API key for backend https://b.example".to_string()]);
+    }
+
+    #[test]
+    fn evidence_without_local_keys_is_not_enabled() {
+        let location = EvidenceLocation {
+            endpoint: "https://s3.example".to_string(),
+            bucket: "evidence".to_string(),
+            region: "us-east-1".to_string(),
+        };
+        let mut report = ImportReport::default();
+
+        assert!(evidence_with_keys(Some(location), None, &mut report).is_none());
+        assert_eq!(report.missing_secrets.len(), 1);
+    }
+}
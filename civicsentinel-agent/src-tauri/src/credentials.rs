@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use crate::error::AgentError;
+
+/// Keychain service every camera login is filed under
+const KEYCHAIN_SERVICE: &str = "civicsentinel-agent";
+
+/// Keychain account holding the backend request signing secret
+const SIGNING_SECRET_ACCOUNT: &str = "backend-signing-secret";
+
+/// Camera login kept in the OS keychain, keyed by camera id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+fn entry(camera_id: &str) -> Result<keyring::Entry, AgentError> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, camera_id)
+        .map_err(|e| AgentError::Internal(format!("Keychain unavailable: {}", e)))
+}
+
+/// Save a camera's login, replacing any stored one
+pub fn store(camera_id: &str, credentials: &CameraCredentials) -> Result<(), AgentError> {
+    let secret = serde_json::to_string(credentials)
+        .map_err(|e| AgentError::Internal(format!("Failed to encode credentials: {}", e)))?;
+
+    entry(camera_id)?
+        .set_password(&secret)
+        .map_err(|e| AgentError::Internal(format!("Failed to save credentials for {}: {}", camera_id, e)))
+}
+
+/// A camera's stored login, if it has one
+pub fn load(camera_id: &str) -> Result<Option<CameraCredentials>, AgentError> {
+    let secret = match entry(camera_id)?.get_password() {
+        Ok(secret) => secret,
+        Err(keyring::Error::NoEntry) => return Ok(None),
+        Err(e) => {
+            return Err(AgentError::Internal(format!("Failed to read credentials for {}: {}", camera_id, e)));
+        }
+    };
+
+    serde_json::from_str(&secret)
+        .map(Some)
+        .map_err(|e| AgentError::Decode(format!("Stored credentials for {} are corrupt: {}", camera_id, e)))
+}
+
+/// Forget a camera's login; succeeds if there was none
+pub fn delete(camera_id: &str) -> Result<(), AgentError> {
+    match entry(camera_id)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(AgentError::Internal(format!("Failed to delete credentials for {}: {}", camera_id, e))),
+    }
+}
+
+/// Save the secret frame uploads are signed with, replacing any stored one
+pub fn store_signing_secret(secret: &str) -> Result<(), AgentError> {
+    entry(SIGNING_SECRET_ACCOUNT)?
+        .set_password(secret)
+        .map_err(|e| AgentError::Internal(format!("Failed to save signing secret: {}", e)))
+}
+
+/// Keychain account holding the password for an SMTP login
+fn smtp_account(host: &str, username: &str) -> String {
+    format!("smtp:{}@{}", username, host)
+}
+
+/// Save the password for an SMTP login, replacing any stored one
+pub fn store_smtp_password(host: &str, username: &str, password: &str) -> Result<(), AgentError> {
+    entry(&smtp_account(host, username))?
+        .set_password(password)
+        .map_err(|e| AgentError::Internal(format!("Failed to save SMTP password for {}: {}", host, e)))
+}
+
+/// The stored password for an SMTP login, if one has been set
+pub fn load_smtp_password(host: &str, username: &str) -> Result<Option<String>, AgentError> {
+    match entry(&smtp_account(host, username))?.get_password() {
+        Ok(password) => Ok(Some(password)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(AgentError::Internal(format!("Failed to read SMTP password for {}: {}", host, e))),
+    }
+}
+
+/// The stored signing secret, if one has been set
+pub fn load_signing_secret() -> Result<Option<String>, AgentError> {
+    match entry(SIGNING_SECRET_ACCOUNT)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(AgentError::Internal(format!("Failed to read signing secret: {}", e))),
+    }
+}
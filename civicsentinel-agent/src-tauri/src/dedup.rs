@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use crate::api::{BoundingBox, Detection, DetectionResponse, ZoneAlert};
+
+/// Boxes overlapping at least this much are treated as the same object
+pub const DEFAULT_IOU_THRESHOLD: f64 = 0.8;
+
+/// Boxes in different frames of a burst overlapping at least this much are
+/// the same object; looser than the repeat threshold as objects move between frames
+pub const BURST_MERGE_IOU_THRESHOLD: f64 = 0.5;
+
+/// Intersection over union of two boxes (0 = disjoint, 1 = identical)
+pub fn iou(a: &BoundingBox, b: &BoundingBox) -> f64 {
+    let width = (a.x2.min(b.x2) - a.x1.max(b.x1)).max(0.0);
+    let height = (a.y2.min(b.y2) - a.y1.max(b.y1)).max(0.0);
+    let intersection = width * height;
+
+    let area = |r: &BoundingBox| (r.x2 - r.x1).max(0.0) * (r.y2 - r.y1).max(0.0);
+    let union = area(a) + area(b) - intersection;
+
+    if union <= 0.0 { 0.0 } else { intersection / union }
+}
+
+/// Drops detection results that only repeat the previous one for a camera
+#[derive(Debug)]
+pub struct DetectionDedup {
+    iou_threshold: f64,
+    last: HashMap<String, DetectionResponse>,
+}
+
+impl Default for DetectionDedup {
+    fn default() -> Self {
+        Self {
+            iou_threshold: DEFAULT_IOU_THRESHOLD,
+            last: HashMap::new(),
+        }
+    }
+}
+
+impl DetectionDedup {
+    pub fn set_iou_threshold(&mut self, iou_threshold: f64) {
+        self.iou_threshold = iou_threshold.clamp(0.0, 1.0);
+    }
+
+    /// Drop a camera's last result so its next one always counts as new
+    pub fn forget(&mut self, camera_id: &str) {
+        self.last.remove(camera_id);
+    }
+
+    /// Remember `response` as the camera's latest and report whether it
+    /// differs meaningfully from the one before
+    pub fn is_new(&mut self, response: &DetectionResponse) -> bool {
+        let changed = match self.last.get(&response.camera_id) {
+            Some(previous) => !self.same_result(previous, response),
+            None => true,
+        };
+        self.last.insert(response.camera_id.clone(), response.clone());
+        changed
+    }
+
+    /// Same alerts, and every detection pairs with one of the same class in
+    /// roughly the same place
+    fn same_result(&self, a: &DetectionResponse, b: &DetectionResponse) -> bool {
+        let alert_keys = |r: &DetectionResponse| {
+            let mut keys: Vec<_> = r.alerts.iter().map(|alert| (alert.zone_id, alert.alert_type.clone())).collect();
+            keys.sort();
+            keys
+        };
+        if alert_keys(a) != alert_keys(b) || a.detections.len() != b.detections.len() {
+            return false;
+        }
+
+        let mut unmatched: Vec<&Detection> = a.detections.iter().collect();
+        b.detections.iter().all(|detection| {
+            let best = unmatched.iter()
+                .enumerate()
+                .filter(|(_, other)| other.class_name == detection.class_name)
+                .map(|(i, other)| (i, iou(&detection.bbox, &other.bbox)))
+                .filter(|(_, overlap)| *overlap >= self.iou_threshold)
+                .max_by(|x, y| x.1.total_cmp(&y.1));
+
+            match best {
+                Some((i, _)) => {
+                    unmatched.swap_remove(i);
+                    true
+                }
+                None => false,
+            }
+        })
+    }
+}
+
+/// Combine the results for the frames of one burst
+///
+/// Detections are unioned, keeping overlapping boxes of the same class once
+/// at their highest confidence; alerts are unioned per zone and type. Also
+/// returns the index of the frame with the most detections, which stands in
+/// for the burst wherever a single frame is needed.
+pub fn merge_burst(responses: Vec<DetectionResponse>, iou_threshold: f64) -> Option<(DetectionResponse, usize)> {
+    let best = responses.iter()
+        .enumerate()
+        .max_by_key(|(i, response)| (response.detections.len(), std::cmp::Reverse(*i)))
+        .map(|(i, _)| i)?;
+
+    let mut detections: Vec<Detection> = Vec::new();
+    let mut alerts: Vec<ZoneAlert> = Vec::new();
+    for response in &responses {
+        for detection in &response.detections {
+            let same = detections.iter_mut()
+                .filter(|other| other.class_name == detection.class_name)
+                .map(|other| (iou(&detection.bbox, &other.bbox), other))
+                .filter(|(overlap, _)| *overlap >= iou_threshold)
+                .max_by(|x, y| x.0.total_cmp(&y.0));
+            match same {
+                Some((_, other)) if other.confidence < detection.confidence => *other = detection.clone(),
+                Some(_) => {}
+                None => detections.push(detection.clone()),
+            }
+        }
+        for alert in &response.alerts {
+            match alerts.iter_mut().find(|other| other.zone_id == alert.zone_id && other.alert_type == alert.alert_type) {
+                Some(other) if other.confidence < alert.confidence => *other = alert.clone(),
+                Some(_) => {}
+                None => alerts.push(alert.clone()),
+            }
+        }
+    }
+
+    let mut merged = responses.into_iter().nth(best)?;
+    merged.detections = detections;
+    merged.alerts = alerts;
+    Some((merged, best))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detection(class_name: &str, x1: f64) -> Detection {
+        Detection {
+            class_name: class_name.to_string(),
+            confidence: 0.9,
+            bbox: BoundingBox { x1, y1: 0.0, x2: x1 + 0.2, y2: 0.2 },
+        }
+    }
+
+    fn response(detections: Vec<Detection>) -> DetectionResponse {
+        DetectionResponse {
+            detections,
+            ..crate::sink::empty_response("cam-1")
+        }
+    }
+
+    #[test]
+    fn iou_of_identical_and_disjoint_boxes() {
+        let a = BoundingBox { x1: 0.0, y1: 0.0, x2: 1.0, y2: 1.0 };
+        let b = BoundingBox { x1: 2.0, y1: 2.0, x2: 3.0, y2: 3.0 };
+        assert_eq!(iou(&a, &a), 1.0);
+        assert_eq!(iou(&a, &b), 0.0);
+    }
+
+    #[test]
+    fn suppresses_jittered_repeat() {
+        let mut dedup = DetectionDedup::default();
+        assert!(dedup.is_new(&response(vec![detection("person", 0.10), detection("car", 0.5)])));
+        assert!(!dedup.is_new(&response(vec![detection("car", 0.505), detection("person", 0.101)])));
+    }
+
+    #[test]
+    fn reports_moved_new_or_reclassified_objects() {
+        let mut dedup = DetectionDedup::default();
+        dedup.is_new(&response(vec![detection("person", 0.1)]));
+
+        assert!(dedup.is_new(&response(vec![detection("person", 0.3)])));
+        assert!(dedup.is_new(&response(vec![detection("car", 0.3)])));
+        assert!(dedup.is_new(&response(vec![detection("car", 0.3), detection("car", 0.6)])));
+    }
+
+    #[test]
+    fn reports_alert_changes() {
+        let mut dedup = DetectionDedup::default();
+        let quiet = response(vec![detection("person", 0.1)]);
+        let mut alerting = quiet.clone();
+        alerting.alerts.push(ZoneAlert {
+            zone_id: 1,
+            zone_name: "Gate".to_string(),
+            alert_type: "intrusion".to_string(),
+            confidence: 0.9,
+        });
+
+        dedup.is_new(&quiet);
+        assert!(dedup.is_new(&alerting));
+        assert!(!dedup.is_new(&alerting));
+    }
+
+    #[test]
+    fn burst_merge_keeps_each_object_once_at_best_confidence() {
+        let mut passing = detection("car", 0.52);
+        passing.confidence = 0.95;
+        let frames = vec![
+            response(vec![detection("person", 0.1)]),
+            response(vec![detection("person", 0.11), passing]),
+            response(vec![detection("car", 0.5)]),
+        ];
+
+        let (merged, best) = merge_burst(frames, BURST_MERGE_IOU_THRESHOLD).unwrap();
+        assert_eq!(best, 1);
+        assert_eq!(merged.detections.len(), 2);
+        let car = merged.detections.iter().find(|d| d.class_name == "car").unwrap();
+        assert_eq!(car.confidence, 0.95);
+
+        assert!(merge_burst(Vec::new(), BURST_MERGE_IOU_THRESHOLD).is_none());
+    }
+
+    #[test]
+    fn cameras_are_tracked_separately() {
+        let mut dedup = DetectionDedup::default();
+        let mut other = response(Vec::new());
+        other.camera_id = "cam-2".to_string();
+
+        assert!(dedup.is_new(&response(Vec::new())));
+        assert!(dedup.is_new(&other));
+    }
+}
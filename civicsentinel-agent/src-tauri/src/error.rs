@@ -0,0 +1,77 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Error returned by agent commands
+///
+/// Serializes as `{ "kind": "...", "message": "..." }` so the frontend can
+/// branch on `kind` while still showing `message` to the user.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "message")]
+pub enum AgentError {
+    /// Camera, zone, file or cached entry does not exist
+    NotFound(String),
+    /// Host unreachable, connection refused or request could not be sent
+    Network(String),
+    /// Credentials or API key rejected
+    Auth(String),
+    /// FFmpeg could not be run or failed to produce a frame
+    Ffmpeg(String),
+    /// Operation did not complete in time
+    Timeout(String),
+    /// Response or payload could not be decoded
+    Decode(String),
+    /// Malformed URL or argument supplied by the caller
+    InvalidInput(String),
+    /// Backend returned an unexpected error status
+    Backend(String),
+    /// Backend answered 429, or uploads are held back until it allows more
+    RateLimited(String),
+    /// Camera monitoring is paused by the operator
+    Paused(String),
+    /// Lock poisoning, task join failures and other internal faults
+    Internal(String),
+}
+
+impl AgentError {
+    /// Human readable message, identical to the text shown in the UI
+    pub fn message(&self) -> &str {
+        match self {
+            AgentError::NotFound(msg)
+            | AgentError::Network(msg)
+            | AgentError::Auth(msg)
+            | AgentError::Ffmpeg(msg)
+            | AgentError::Timeout(msg)
+            | AgentError::Decode(msg)
+            | AgentError::InvalidInput(msg)
+            | AgentError::Backend(msg)
+            | AgentError::RateLimited(msg)
+            | AgentError::Paused(msg)
+            | AgentError::Internal(msg) => msg,
+        }
+    }
+
+    /// Rewrite the message while keeping the error kind
+    pub fn map_message(self, f: impl FnOnce(String) -> String) -> Self {
+        match self {
+            AgentError::NotFound(msg) => AgentError::NotFound(f(msg)),
+            AgentError::Network(msg) => AgentError::Network(f(msg)),
+            AgentError::Auth(msg) => AgentError::Auth(f(msg)),
+            AgentError::Ffmpeg(msg) => AgentError::Ffmpeg(f(msg)),
+            AgentError::Timeout(msg) => AgentError::Timeout(f(msg)),
+            AgentError::Decode(msg) => AgentError::Decode(f(msg)),
+            AgentError::InvalidInput(msg) => AgentError::InvalidInput(f(msg)),
+            AgentError::Backend(msg) => AgentError::Backend(f(msg)),
+            AgentError::RateLimited(msg) => AgentError::RateLimited(f(msg)),
+            AgentError::Paused(msg) => AgentError::Paused(f(msg)),
+            AgentError::Internal(msg) => AgentError::Internal(f(msg)),
+        }
+    }
+}
+
+impl fmt::Display for AgentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for AgentError {}
@@ -0,0 +1,436 @@
+use image::imageops::FilterType;
+use image::{GrayImage, RgbImage};
+use img_parts::jpeg::Jpeg;
+use img_parts::{Bytes, ImageEXIF};
+use crate::api::Detection;
+use crate::error::AgentError;
+use crate::labels::LabelMap;
+use crate::settings::ImageFormat;
+
+/// Width frames are downscaled to before comparing; plenty for change detection
+const DIFF_WIDTH: u32 = 320;
+
+/// Encoding of captured and cached frames; ffmpeg always outputs MJPEG
+pub const CAPTURE_FORMAT: ImageFormat = ImageFormat::Jpeg;
+
+/// JPEG qualities tried, in order, when a frame is over the size limit
+const SHRINK_QUALITIES: [u8; 4] = [85, 70, 55, 40];
+
+/// JPEG quality range `encode_within` searches; beyond 95 files grow with
+/// no visible gain, below 10 frames are too blocky to detect on
+pub const MIN_SEARCH_QUALITY: u8 = 10;
+pub const MAX_SEARCH_QUALITY: u8 = 95;
+
+/// Detection outlines and tags on evidence frames, matching the live view
+const BOX_THICKNESS: u32 = 3;
+const LABEL_PADDING: u32 = 4;
+const LABEL_TEXT: image::Rgb<u8> = image::Rgb([0, 0, 0]);
+
+/// Decode a JPEG/PNG frame into an 8-bit grayscale image
+fn decode_gray(frame_bytes: &[u8]) -> Result<GrayImage, AgentError> {
+    image::load_from_memory(frame_bytes)
+        .map(|img| img.to_luma8())
+        .map_err(|e| AgentError::Decode(format!("Failed to decode frame: {}", e)))
+}
+
+/// `data:` URL for base64 image data, usable directly as an `<img src>`
+pub fn data_url(format: ImageFormat, base64: &str) -> String {
+    format!("data:{};base64,{}", format.mime_type(), base64)
+}
+
+/// Width and height of an encoded frame, read from its header
+pub fn frame_dimensions(frame_bytes: &[u8]) -> Result<(u32, u32), AgentError> {
+    image::ImageReader::new(std::io::Cursor::new(frame_bytes))
+        .with_guessed_format()
+        .map_err(|e| AgentError::Decode(format!("Failed to read frame: {}", e)))?
+        .into_dimensions()
+        .map_err(|e| AgentError::Decode(format!("Failed to read frame size: {}", e)))
+}
+
+/// Normalized 0-1 difference between two frames (0 = identical)
+///
+/// Both frames are converted to grayscale and scaled to the same size, so
+/// frames of differing dimensions can still be compared.
+pub fn frame_diff(frame_a: &[u8], frame_b: &[u8]) -> Result<f64, AgentError> {
+    let a = decode_gray(frame_a)?;
+    let b = decode_gray(frame_b)?;
+
+    let width = a.width().clamp(1, DIFF_WIDTH);
+    let height = ((a.height() as u64 * width as u64) / a.width().max(1) as u64).max(1) as u32;
+
+    let a = image::imageops::resize(&a, width, height, FilterType::Triangle);
+    let b = image::imageops::resize(&b, width, height, FilterType::Triangle);
+
+    let total: u64 = a.as_raw()
+        .iter()
+        .zip(b.as_raw())
+        .map(|(pa, pb)| pa.abs_diff(*pb) as u64)
+        .sum();
+
+    Ok(total as f64 / (a.as_raw().len() as f64 * 255.0))
+}
+
+/// 64-bit difference hash of a frame, for spotting the same picture twice
+///
+/// Each bit says whether a pixel of a 9x8 grayscale thumbnail is brighter
+/// than its right-hand neighbour, so re-encoding and small changes flip few bits.
+pub fn perceptual_hash(frame_bytes: &[u8]) -> Result<u64, AgentError> {
+    let thumbnail = image::imageops::resize(&decode_gray(frame_bytes)?, 9, 8, FilterType::Triangle);
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let brighter = thumbnail.get_pixel(x, y)[0] > thumbnail.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | brighter as u64;
+        }
+    }
+    Ok(hash)
+}
+
+/// Embed capture time and camera id into a JPEG's EXIF block
+///
+/// Sets DateTimeOriginal/OffsetTimeOriginal and a UserComment of
+/// `camera_id=<id>`, so exported frames can be traced back to their source.
+pub fn embed_metadata(
+    jpeg_bytes: &[u8],
+    camera_id: &str,
+    captured_at: chrono::DateTime<chrono::Local>,
+) -> Result<Vec<u8>, AgentError> {
+    use exif::{Field, In, Tag, Value};
+
+    let ascii = |tag: Tag, text: String| Field {
+        tag,
+        ifd_num: In::PRIMARY,
+        value: Value::Ascii(vec![text.into_bytes()]),
+    };
+
+    let date_time = ascii(Tag::DateTimeOriginal, captured_at.format("%Y:%m:%d %H:%M:%S").to_string());
+    let offset = ascii(Tag::OffsetTimeOriginal, captured_at.format("%:z").to_string());
+
+    // UserComment starts with an 8-byte character code
+    let mut comment = b"ASCII\0\0\0".to_vec();
+    comment.extend_from_slice(format!("camera_id={}", camera_id).as_bytes());
+    let user_comment = Field {
+        tag: Tag::UserComment,
+        ifd_num: In::PRIMARY,
+        value: Value::Undefined(comment, 0),
+    };
+
+    let mut writer = exif::experimental::Writer::new();
+    writer.push_field(&date_time);
+    writer.push_field(&offset);
+    writer.push_field(&user_comment);
+
+    let mut exif_bytes = std::io::Cursor::new(Vec::new());
+    writer.write(&mut exif_bytes, false)
+        .map_err(|e| AgentError::Internal(format!("Failed to encode EXIF: {}", e)))?;
+
+    let mut jpeg = Jpeg::from_bytes(Bytes::copy_from_slice(jpeg_bytes))
+        .map_err(|e| AgentError::Decode(format!("Failed to parse JPEG: {}", e)))?;
+    jpeg.set_exif(Some(Bytes::from(exif_bytes.into_inner())));
+
+    Ok(jpeg.encoder().bytes().to_vec())
+}
+
+/// Re-encode a frame at decreasing JPEG quality until it fits in `max_bytes`
+///
+/// Frames already under the limit are returned unchanged. Fails if the frame
+/// is still too large at the lowest quality step.
+pub fn fit_frame(frame_bytes: Vec<u8>, max_bytes: usize) -> Result<Vec<u8>, AgentError> {
+    if frame_bytes.len() <= max_bytes {
+        return Ok(frame_bytes);
+    }
+
+    let image = image::load_from_memory(&frame_bytes)
+        .map_err(|e| AgentError::Decode(format!("Failed to decode frame: {}", e)))?
+        .to_rgb8();
+
+    let mut smallest = frame_bytes.len();
+    for quality in SHRINK_QUALITIES {
+        let mut encoded = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut encoded, quality)
+            .encode_image(&image)
+            .map_err(|e| AgentError::Internal(format!("Failed to re-encode frame: {}", e)))?;
+
+        if encoded.len() <= max_bytes {
+            println!("[Frame] Re-encoded frame at quality {}: {} -> {} bytes (limit {})",
+                     quality, frame_bytes.len(), encoded.len(), max_bytes);
+            return Ok(encoded);
+        }
+        smallest = smallest.min(encoded.len());
+    }
+
+    Err(AgentError::InvalidInput(format!(
+        "Frame is too large to upload: {} bytes ({} at lowest quality), limit is {} bytes",
+        frame_bytes.len(), smallest, max_bytes
+    )))
+}
+
+/// Re-encode a frame at the highest JPEG quality that fits in `target_bytes`
+///
+/// Binary-searches the quality, trying `hint` first (e.g. the quality last
+/// chosen for the same camera) so a repeat search usually takes a couple
+/// of encodes. Returns the frame and the quality it was encoded at.
+pub fn encode_within(frame_bytes: &[u8], target_bytes: usize, hint: Option<u8>) -> Result<(Vec<u8>, u8), AgentError> {
+    let image = image::load_from_memory(frame_bytes)
+        .map_err(|e| AgentError::Decode(format!("Failed to decode frame: {}", e)))?
+        .to_rgb8();
+    let encode = |quality: u8| {
+        let mut encoded = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut encoded, quality)
+            .encode_image(&image)
+            .map(|_| encoded)
+            .map_err(|e| AgentError::Internal(format!("Failed to re-encode frame: {}", e)))
+    };
+
+    let (mut low, mut high) = (MIN_SEARCH_QUALITY, MAX_SEARCH_QUALITY);
+    let mut best: Option<(Vec<u8>, u8)> = None;
+    let mut quality = hint.map_or((low + high) / 2, |hint| hint.clamp(low, high));
+    loop {
+        let encoded = encode(quality)?;
+        if encoded.len() <= target_bytes {
+            best = Some((encoded, quality));
+            low = quality + 1;
+        } else {
+            high = quality - 1;
+        }
+        if low > high {
+            break;
+        }
+        quality = low + (high - low) / 2;
+    }
+
+    best.ok_or_else(|| AgentError::InvalidInput(format!(
+        "Frame doesn't fit in {} bytes even at JPEG quality {}", target_bytes, MIN_SEARCH_QUALITY
+    )))
+}
+
+/// Encode a captured JPEG frame in the configured upload format
+///
+/// JPEG frames are passed through untouched; ffmpeg writes PNG and lossless
+/// WebP straight from the captured frame, adding no loss of its own.
+pub fn encode_frame(jpeg_bytes: Vec<u8>, format: ImageFormat) -> Result<Vec<u8>, AgentError> {
+    match format {
+        ImageFormat::Jpeg => Ok(jpeg_bytes),
+        ImageFormat::Png => ffmpeg_encode(&jpeg_bytes, &["-c:v", "png"]),
+        ImageFormat::Webp => ffmpeg_encode(&jpeg_bytes, &["-c:v", "libwebp", "-lossless", "1"]),
+    }
+}
+
+/// Encode a captured JPEG frame for upload, then bring the encoded bytes
+/// under `max_bytes`
+///
+/// JPEG and WebP step down through lossy qualities; PNG can't shrink without
+/// losing its point, so an oversized PNG is an error.
+pub fn encode_for_upload(jpeg_bytes: Vec<u8>, format: ImageFormat, max_bytes: usize) -> Result<Vec<u8>, AgentError> {
+    if format == ImageFormat::Jpeg {
+        return fit_frame(jpeg_bytes, max_bytes);
+    }
+
+    let encoded = encode_frame(jpeg_bytes.clone(), format)?;
+    if encoded.len() <= max_bytes {
+        return Ok(encoded);
+    }
+    if format == ImageFormat::Png {
+        return Err(AgentError::InvalidInput(format!(
+            "Frame is {} bytes as PNG, limit is {} bytes; raise max_frame_bytes or upload WebP or JPEG",
+            encoded.len(), max_bytes
+        )));
+    }
+
+    let mut smallest = encoded.len();
+    for quality in SHRINK_QUALITIES {
+        let quality_arg = quality.to_string();
+        let lossy = ffmpeg_encode(&jpeg_bytes, &["-c:v", "libwebp", "-quality", &quality_arg])?;
+        if lossy.len() <= max_bytes {
+            println!("[Frame] Re-encoded WebP frame at quality {}: {} -> {} bytes (limit {})",
+                     quality, encoded.len(), lossy.len(), max_bytes);
+            return Ok(lossy);
+        }
+        smallest = smallest.min(lossy.len());
+    }
+
+    Err(AgentError::InvalidInput(format!(
+        "Frame is too large to upload: {} bytes as WebP ({} at lowest quality), limit is {} bytes",
+        encoded.len(), smallest, max_bytes
+    )))
+}
+
+/// Run one JPEG frame through ffmpeg with the given output codec arguments
+fn ffmpeg_encode(jpeg_bytes: &[u8], codec_args: &[&str]) -> Result<Vec<u8>, AgentError> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new(crate::camera::get_ffmpeg_path())
+        .args(["-hide_banner", "-loglevel", "error", "-f", "image2pipe", "-c:v", "mjpeg", "-i", "-", "-frames:v", "1"])
+        .args(codec_args)
+        .args(["-f", "image2pipe", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| AgentError::Ffmpeg(format!("Failed to start ffmpeg: {}", e)))?;
+
+    // Feed stdin from a thread so a full stdout pipe can't deadlock us
+    let mut stdin = child.stdin.take()
+        .ok_or_else(|| AgentError::Ffmpeg("Failed to open ffmpeg stdin".to_string()))?;
+    let input = jpeg_bytes.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+    let output = child.wait_with_output()
+        .map_err(|e| AgentError::Ffmpeg(format!("ffmpeg failed: {}", e)))?;
+    // A write error just means ffmpeg quit early; its stderr says why
+    let _ = writer.join();
+
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(AgentError::Ffmpeg(format!(
+            "ffmpeg failed to encode frame: {}", String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Draw detection boxes onto a frame for use as alert evidence
+///
+/// Boxes are in frame pixel coordinates, as returned by the backend. The
+/// result carries the same EXIF stamp as `embed_metadata` gives saved frames.
+pub fn annotate(
+    frame_bytes: &[u8],
+    detections: &[Detection],
+    labels: &LabelMap,
+    camera_id: &str,
+    captured_at: chrono::DateTime<chrono::Local>,
+) -> Result<Vec<u8>, AgentError> {
+    let mut image = image::load_from_memory(frame_bytes)
+        .map_err(|e| AgentError::Decode(format!("Failed to decode frame: {}", e)))?
+        .to_rgb8();
+
+    draw_boxes(&mut image, detections, labels);
+
+    let mut encoded = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut encoded, 90)
+        .encode_image(&image)
+        .map_err(|e| AgentError::Internal(format!("Failed to encode annotated frame: {}", e)))?;
+
+    embed_metadata(&encoded, camera_id, captured_at)
+}
+
+/// Outline each detection's bounding box, clamped to the image, with a
+/// "label confidence%" tag in the class color above it
+pub fn draw_boxes(image: &mut RgbImage, detections: &[Detection], labels: &LabelMap) {
+    let (width, height) = image.dimensions();
+    let clamp_x = |x: f64| (x.max(0.0) as u32).min(width.saturating_sub(1));
+    let clamp_y = |y: f64| (y.max(0.0) as u32).min(height.saturating_sub(1));
+
+    for detection in detections {
+        let (x1, x2) = (clamp_x(detection.bbox.x1), clamp_x(detection.bbox.x2));
+        let (y1, y2) = (clamp_y(detection.bbox.y1), clamp_y(detection.bbox.y2));
+        let color = labels.color(&detection.class_name);
+
+        for x in x1..=x2 {
+            for y in y1..=y2 {
+                let on_edge = x < x1 + BOX_THICKNESS || x + BOX_THICKNESS > x2
+                    || y < y1 + BOX_THICKNESS || y + BOX_THICKNESS > y2;
+                if on_edge {
+                    image.put_pixel(x, y, color);
+                }
+            }
+        }
+
+        let text = format!(
+            "{} {:.0}%",
+            labels.display_name(&detection.class_name),
+            detection.confidence * 100.0
+        );
+        let tag_width = text.chars().count() as u32 * crate::report::CHAR_ADVANCE + LABEL_PADDING * 2;
+        let tag_height = crate::report::TEXT_HEIGHT + LABEL_PADDING * 2;
+        // Above the box, or just inside it when the box touches the top edge
+        let tag_y = y1.checked_sub(tag_height).unwrap_or(y1);
+        for x in x1..(x1 + tag_width).min(width) {
+            for y in tag_y..(tag_y + tag_height).min(height) {
+                image.put_pixel(x, y, color);
+            }
+        }
+        crate::report::draw_text(image, x1 + LABEL_PADDING, tag_y + LABEL_PADDING, &text, LABEL_TEXT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noisy_frame() -> Vec<u8> {
+        // Pseudo-random pixels, so file size tracks quality closely
+        let mut seed = 12345u32;
+        let image = RgbImage::from_fn(160, 120, |_, _| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let [r, g, b, _] = seed.to_le_bytes();
+            image::Rgb([r, g, b])
+        });
+        let mut encoded = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut encoded, 100).encode_image(&image).unwrap();
+        encoded
+    }
+
+    #[test]
+    fn encode_within_settles_on_the_best_quality_that_fits() {
+        let frame = noisy_frame();
+        let size_at = |quality: u8| {
+            let image = image::load_from_memory(&frame).unwrap().to_rgb8();
+            let mut encoded = Vec::new();
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut encoded, quality).encode_image(&image).unwrap();
+            encoded.len()
+        };
+        let target = (size_at(40) + size_at(80)) / 2;
+
+        let (encoded, quality) = encode_within(&frame, target, None).unwrap();
+        assert!(encoded.len() <= target);
+        assert!(size_at(quality + 1) > target, "quality {} isn't the highest that fits", quality);
+        assert_eq!(encode_within(&frame, target, Some(quality)).unwrap().1, quality);
+        assert_eq!(encode_within(&frame, target, Some(95)).unwrap().1, quality);
+
+        assert_eq!(encode_within(&frame, usize::MAX, None).unwrap().1, MAX_SEARCH_QUALITY);
+        assert!(matches!(encode_within(&frame, 100, None), Err(AgentError::InvalidInput(_))));
+    }
+
+    #[test]
+    #[ignore = "needs ffmpeg"]
+    fn uploads_are_encoded_by_ffmpeg_and_capped_after_encoding() {
+        let frame = noisy_frame();
+
+        let png = encode_frame(frame.clone(), ImageFormat::Png).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+        assert_eq!(frame_dimensions(&png).unwrap(), (160, 120));
+        let webp = encode_frame(frame.clone(), ImageFormat::Webp).unwrap();
+        assert_eq!(&webp[8..12], b"WEBP");
+
+        // The cap applies to the encoded bytes, not the JPEG they came from
+        let limit = png.len() - 1;
+        assert!(matches!(encode_for_upload(frame.clone(), ImageFormat::Png, limit), Err(AgentError::InvalidInput(_))));
+        let shrunk = encode_for_upload(frame.clone(), ImageFormat::Webp, limit).unwrap();
+        assert!(shrunk.len() <= limit);
+        assert_eq!(frame_dimensions(&shrunk).unwrap(), (160, 120));
+    }
+
+    #[test]
+    fn embedded_metadata_reads_back() {
+        use chrono::TimeZone;
+        use exif::{In, Tag, Value};
+
+        let captured_at = chrono::Local.with_ymd_and_hms(2024, 5, 6, 7, 8, 9).unwrap();
+        let stamped = embed_metadata(&noisy_frame(), "front-door", captured_at).unwrap();
+        assert!(image::load_from_memory(&stamped).is_ok());
+
+        let exif = exif::Reader::new()
+            .read_from_container(&mut std::io::Cursor::new(stamped))
+            .unwrap();
+        let field = |tag: Tag| exif.get_field(tag, In::PRIMARY).map(|field| field.value.clone());
+
+        let Some(Value::Ascii(date_time)) = field(Tag::DateTimeOriginal) else { panic!("no DateTimeOriginal") };
+        assert_eq!(date_time[0], b"2024:05:06 07:08:09");
+        let Some(Value::Ascii(offset)) = field(Tag::OffsetTimeOriginal) else { panic!("no OffsetTimeOriginal") };
+        assert_eq!(offset[0], captured_at.format("%:z").to_string().into_bytes());
+        let Some(Value::Undefined(comment, _)) = field(Tag::UserComment) else { panic!("no UserComment") };
+        assert_eq!(comment, b"ASCII\0\0\0camera_id=front-door");
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::path::Path;
+use image::Rgb;
+use serde::{Deserialize, Serialize};
+use crate::error::AgentError;
+
+/// File in the app data directory mapping classes to labels and colors
+pub const LABELS_FILE: &str = "labels.json";
+
+/// Colors for classes without one configured, picked by a hash of the name
+/// so a class keeps its color across restarts
+const DEFAULT_PALETTE: [[u8; 3]; 10] = [
+    [0, 255, 0],
+    [255, 64, 64],
+    [0, 160, 255],
+    [255, 200, 0],
+    [255, 0, 255],
+    [0, 255, 255],
+    [255, 128, 0],
+    [160, 96, 255],
+    [128, 255, 128],
+    [255, 160, 200],
+];
+
+/// How one class is shown; unset fields fall back to the defaults
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LabelStyle {
+    /// Display name, e.g. "Pedestrian" for `person`
+    pub label: Option<String>,
+    /// RGB outline and label background
+    pub color: Option<[u8; 3]>,
+}
+
+/// Label and color actually used for a class, for overlays
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResolvedLabel {
+    pub label: String,
+    pub color: [u8; 3],
+}
+
+/// Contents of `labels.json`: class name to style
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct LabelMap {
+    pub classes: HashMap<String, LabelStyle>,
+}
+
+impl LabelMap {
+    /// Read `labels.json`; a missing file is an empty map, a malformed one
+    /// is an error so `reload_labels` can report it
+    pub fn load(path: &Path) -> Result<Self, AgentError> {
+        let json = match std::fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(AgentError::Internal(format!("Failed to read {}: {}", path.display(), e))),
+        };
+        serde_json::from_str(&json)
+            .map_err(|e| AgentError::InvalidInput(format!("Invalid {}: {}", path.display(), e)))
+    }
+
+    pub fn display_name(&self, class_name: &str) -> String {
+        self.classes.get(class_name)
+            .and_then(|style| style.label.clone())
+            .unwrap_or_else(|| class_name.to_string())
+    }
+
+    pub fn color(&self, class_name: &str) -> Rgb<u8> {
+        let configured = self.classes.get(class_name).and_then(|style| style.color);
+        Rgb(configured.unwrap_or_else(|| default_color(class_name)))
+    }
+
+    pub fn resolve(&self, class_name: &str) -> ResolvedLabel {
+        ResolvedLabel { label: self.display_name(class_name), color: self.color(class_name).0 }
+    }
+}
+
+/// Palette entry for a class, from an FNV-1a hash of its name
+fn default_color(class_name: &str) -> [u8; 3] {
+    let hash = class_name.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    DEFAULT_PALETTE[(hash % DEFAULT_PALETTE.len() as u64) as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configured_styles_override_defaults() {
+        let labels: LabelMap = serde_json::from_str(r#"{
+            "person": { "label": "Pedestrian", "color": [255, 0, 0] },
+            "car": { "label": "Vehicle" }
+        }"#).unwrap();
+
+        assert_eq!(labels.resolve("person"), ResolvedLabel { label: "Pedestrian".to_string(), color: [255, 0, 0] });
+        assert_eq!(labels.display_name("car"), "Vehicle");
+        assert_eq!(labels.color("car"), Rgb(default_color("car")));
+        assert_eq!(labels.display_name("dog"), "dog");
+        assert!(DEFAULT_PALETTE.contains(&labels.color("dog").0));
+    }
+
+    #[test]
+    fn missing_file_is_empty_and_malformed_is_an_error() {
+        let dir = std::env::temp_dir().join(format!("civicsentinel-labels-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(LABELS_FILE);
+
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(LabelMap::load(&path).unwrap(), LabelMap::default());
+
+        std::fs::write(&path, r#"{ "person": { "color": "red" } }"#).unwrap();
+        assert!(matches!(LabelMap::load(&path), Err(AgentError::InvalidInput(_))));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -3,291 +3,4634 @@
 
 mod camera;
 mod api;
+mod config;
+mod credentials;
+mod dedup;
+mod error;
+mod metrics;
+mod monitoring;
+mod notify;
+mod onvif;
+mod privacy;
+mod proxy;
+mod recording;
+mod report;
+mod retention;
+mod rtsp;
+mod settings;
+mod signing;
+mod sink;
+mod storage;
+mod frame;
+mod labels;
+mod version;
+mod webhook;
+mod zones;
+#[cfg(test)]
+mod test_support;
 
-use tauri::{Manager, State, Window};
+use tauri::{Emitter, Manager, State, Window};
 use tauri::menu::{Menu, MenuItem};
 use tauri::tray::{TrayIconBuilder, TrayIconEvent};
 use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
+use tokio::sync::RwLock;
+use error::AgentError;
 
 // Shared state for camera connections
-type CameraMap = Arc<Mutex<HashMap<String, camera::CameraHandle>>>;
+type CameraMap = Arc<RwLock<HashMap<String, camera::CameraHandle>>>;
+
+// Recent detection counts per camera, for trend charts
+type DetectionHistories = Arc<Mutex<HashMap<String, monitoring::DetectionHistory>>>;
+
+// Serializes connect/reconnect/disconnect per camera id, so a slow ffmpeg
+// start on one camera never holds up another. Outlives the handles it guards.
+type CameraLocks = Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>;
+
+/// Wait for exclusive use of `camera_id`'s connection
+async fn lock_camera(
+    locks: &CameraLocks,
+    camera_id: &str,
+) -> Result<tokio::sync::OwnedMutexGuard<()>, AgentError> {
+    let lock = locks.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .entry(camera_id.to_string())
+        .or_default()
+        .clone();
+    Ok(lock.lock_owned().await)
+}
+
+// ONVIF logins by camera id, used when probing without explicit credentials
+type OnvifCredentialStore = Arc<Mutex<HashMap<String, onvif::OnvifCredentials>>>;
 
 // Cache for latest frames and detection results
 #[derive(Clone)]
 struct CachedData {
     frame: String, // base64 encoded
     detections: api::DetectionResponse,
+    /// Local time the frame was captured, before it was sent for detection
     timestamp: std::time::SystemTime,
 }
 
-type FrameCache = Arc<Mutex<HashMap<String, CachedData>>>;
+/// Capture time as RFC 3339 in the agent's own timezone
+fn local_timestamp(time: std::time::SystemTime) -> String {
+    chrono::DateTime::<chrono::Local>::from(time).to_rfc3339()
+}
+
+type FrameCache = Arc<RwLock<HashMap<String, CachedData>>>;
+
+/// Metadata for a v2 detect request, or `None` when the v1 endpoint is used
+fn frame_metadata(
+    sequences: &Mutex<HashMap<String, u64>>,
+    detection_api: settings::DetectionApi,
+    camera_id: &str,
+    captured_at: std::time::SystemTime,
+) -> Result<Option<api::FrameMetadata>, AgentError> {
+    if detection_api == settings::DetectionApi::V1 {
+        return Ok(None);
+    }
+
+    let mut sequences = sequences.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+    let sequence = sequences.entry(camera_id.to_string()).or_insert(0);
+    *sequence += 1;
+
+    Ok(Some(api::FrameMetadata {
+        camera_id: camera_id.to_string(),
+        capture_timestamp: chrono::DateTime::<chrono::Utc>::from(captured_at).to_rfc3339(),
+        sequence: *sequence,
+    }))
+}
+
+/// A running periodic capture and the schedule it was started with
+struct PeriodicTask {
+    handle: tauri::async_runtime::JoinHandle<()>,
+    interval_secs: u64,
+    jitter_secs: u64,
+    /// Kept so the watchdog can restart the task as it was started
+    api_key: String,
+    backend_url: String,
+    stats: Arc<Mutex<monitoring::TaskStats>>,
+    restarts: u32,
+}
+
+// Background periodic capture tasks, keyed by camera id
+type PeriodicTasks = Arc<Mutex<HashMap<String, PeriodicTask>>>;
+
+// Operator-assigned group per camera id; kept across reconnects and restarts
+type CameraGroups = Arc<Mutex<monitoring::CameraGroupMap>>;
+
+// Activity-driven intervals overriding a periodic task's fixed schedule
+type AdaptiveSchedules = Arc<Mutex<HashMap<String, monitoring::AdaptiveSchedule>>>;
+
+// Health counters exported by the `metrics` command
+type SharedMetrics = Arc<metrics::Metrics>;
+
+// Agent process CPU/memory readings for `resource_stats`
+type ResourceSampler = Arc<Mutex<metrics::ProcessSampler>>;
+
+// Cameras whose uploads wait out a backend 429
+type UploadRateLimits = Arc<Mutex<monitoring::RateLimits>>;
+
+// Limits applied to frames before upload
+type UploadConfig = Arc<Mutex<settings::UploadSettings>>;
+
+// Caps simultaneous detection uploads across all cameras
+type UploadSlots = Arc<settings::UploadLimiter>;
+
+// Upload byte counters and the monthly cap, saved in the app data dir
+type BandwidthMeter = Arc<Mutex<settings::BandwidthUsage>>;
+
+// Where each zone's alerts are delivered, saved in the app data dir
+type ZoneNotify = Arc<Mutex<zones::ZoneNotifyPolicies>>;
+
+// Regions blurred out of each camera's frames before upload, saved in the app data dir
+type PrivacyMaskConfig = Arc<Mutex<privacy::PrivacyMasks>>;
+
+// Agent-wide hours when monitoring runs, saved in the app data dir
+type Schedule = Arc<Mutex<settings::MonitoringSchedule>>;
+
+// Set to stop the running network scan
+type ScanCancel = Arc<std::sync::atomic::AtomicBool>;
+
+// Fails detection requests fast while the backend is down
+type BackendBreaker = Arc<Mutex<monitoring::CircuitBreaker>>;
+
+// Last v2 metadata sequence number sent, keyed by camera id
+type FrameSequences = Arc<Mutex<HashMap<String, u64>>>;
+
+// Hardware decoder applied to newly opened cameras
+type HwAccelConfig = Arc<Mutex<settings::HwAccel>>;
+
+// Cooldown, debounce and dedup settings; updates are serialized by this lock
+type DetectionConfig = Arc<Mutex<settings::DetectionSettings>>;
+
+// Where frames go for detection (cloud by default)
+type SinkConfig = Arc<Mutex<sink::DetectionSink>>;
+
+// Webhook that new zone alerts are forwarded to, if configured
+type AlertWebhook = Arc<Mutex<Option<webhook::WebhookConfig>>>;
+// Channels zone alerts are delivered to (OS notifications by default)
+type Notifiers = Arc<Mutex<notify::NotifierSettings>>;
+type AlertCooldowns = Arc<Mutex<webhook::AlertCooldown>>;
+type AlertDebouncer = Arc<Mutex<webhook::AlertDebounce>>;
+
+// Last emitted detection per camera, so repeats don't flood the UI
+type DetectionDeduper = Arc<Mutex<dedup::DetectionDedup>>;
+
+// Bucket annotated alert frames are uploaded to; None keeps the backend's image_url
+type EvidenceStorage = Arc<Mutex<Option<storage::S3Config>>>;
+
+/// Ordered detection backends; empty means use the URL passed by the caller
+type BackendList = Arc<Mutex<Vec<api::BackendConfig>>>;
+
+// How the API key is sent on every backend request
+type AuthConfig = Arc<Mutex<api::AuthScheme>>;
+
+/// Snapshot the configured auth scheme so the lock isn't held across requests
+fn auth_scheme(auth: &AuthConfig) -> Result<api::AuthScheme, AgentError> {
+    Ok(auth.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .clone())
+}
+
+/// Where an alert's image can be downloaded from, recorded by `get_alerts`
+#[derive(Clone)]
+struct AlertImageSource {
+    backend_url: String,
+    image_url: String,
+}
+
+type AlertImageSources = Arc<Mutex<HashMap<i64, AlertImageSource>>>;
+
+// Background task emitting `new-alert` events, if running
+type AlertPoller = Arc<Mutex<Option<tauri::async_runtime::JoinHandle<()>>>>;
+
+// Background tasks pushing `live-frame:{camera_id}` events, keyed by camera id
+type LiveViews = Arc<Mutex<HashMap<String, tauri::async_runtime::JoinHandle<()>>>>;
+
+// JPEG quality `capture_best_frame` last settled on, keyed by camera id
+type BestQualities = Arc<Mutex<HashMap<String, u8>>>;
+
+// Continuous segmented recordings (DVR mode), keyed by camera id
+type Recordings = Arc<Mutex<HashMap<String, recording::Recording>>>;
+
+// Limits applied to agent-written files on startup and every hour
+type RetentionConfig = Arc<Mutex<retention::RetentionPolicy>>;
+
+// Display names and colors per detection class, from labels.json
+type Labels = Arc<Mutex<labels::LabelMap>>;
+
+// Outbound proxy, saved in the app data dir; the shared HTTP client is
+// rebuilt whenever it changes
+type ProxyConfig = Arc<Mutex<proxy::ProxySettings>>;
+
+// Whether frame uploads carry an HMAC signature
+type SigningConfig = Arc<Mutex<signing::SigningSettings>>;
+
+// Background task posting liveness to the backend, if running
+type HeartbeatTask = Arc<Mutex<Option<tauri::async_runtime::JoinHandle<()>>>>;
+
+// Set once shutdown starts; periodic tasks stop scheduling new cycles
+type ShutdownFlag = Arc<std::sync::atomic::AtomicBool>;
+
+/// How often `fps-update` and `stream-reopened` are emitted for persistent streams
+const FPS_REPORT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Longest shutdown waits for in-flight detection requests
+const SHUTDOWN_DEADLINE: std::time::Duration = std::time::Duration::from_secs(5);
 
 #[derive(Clone, serde::Serialize)]
 struct Payload {
     message: String,
 }
 
-// Tauri Commands
+// Tauri Commands
+
+#[tauri::command]
+async fn scan_network(
+    options: Option<camera::ScanOptions>,
+    scan_cancel: State<'_, ScanCancel>,
+) -> Result<Vec<camera::DiscoveredCamera>, AgentError> {
+    println!("[Rust] Starting network scan...");
+    scan_cancel.store(false, std::sync::atomic::Ordering::SeqCst);
+    camera::scan_for_cameras(options.unwrap_or_default(), scan_cancel.inner().clone()).await
+}
+
+/// Probe specific IPs or hostnames, e.g. cameras on remote sites
+#[tauri::command]
+async fn scan_hosts(
+    hosts: Vec<String>,
+    ports: Vec<u16>,
+    scan_cancel: State<'_, ScanCancel>,
+) -> Result<Vec<camera::DiscoveredCamera>, AgentError> {
+    println!("[Rust] Probing {} host(s)...", hosts.len());
+    scan_cancel.store(false, std::sync::atomic::Ordering::SeqCst);
+    let options = camera::ScanOptions { ports, ..camera::ScanOptions::default() };
+    camera::scan_hosts(hosts, options, scan_cancel.inner().clone()).await
+}
+
+/// Stop a running `scan_network` or `scan_hosts`, which then returns what it found so far
+#[tauri::command]
+async fn cancel_scan(scan_cancel: State<'_, ScanCancel>) -> Result<(), AgentError> {
+    println!("[Rust] Cancelling network scan");
+    scan_cancel.store(true, std::sync::atomic::Ordering::SeqCst);
+    Ok(())
+}
+
+/// Grab a frame from each camera and flag those showing the same picture
+/// as an earlier one, e.g. one device reachable on two addresses
+#[tauri::command]
+async fn find_duplicate_cameras(
+    cameras: Vec<camera::DiscoveredCamera>,
+    username: Option<String>,
+    password: Option<String>,
+) -> Result<Vec<camera::DiscoveredCamera>, AgentError> {
+    camera::find_duplicates(cameras, username, password).await
+}
+
+#[tauri::command]
+async fn list_network_interfaces() -> Result<Vec<camera::NetworkInterface>, AgentError> {
+    camera::list_network_interfaces()
+}
+
+#[tauri::command]
+async fn test_camera(
+    rtsp_url: String,
+    rtsp_transport: Option<camera::RtspTransport>,
+) -> Result<bool, AgentError> {
+    println!("[Rust] Testing camera: {}", camera::redact_url(&rtsp_url));
+    camera::test_camera_connection(&rtsp_url, rtsp_transport.unwrap_or_default()).await
+}
+
+/// Tell "wrong password" apart from "can't reach the camera"
+#[tauri::command]
+async fn test_camera_auth(rtsp_url: String) -> Result<rtsp::AuthResult, AgentError> {
+    rtsp::test_camera_auth(&rtsp_url).await
+}
+
+/// Stable id for a new camera, the same on every agent; `use_frame` also
+/// fingerprints a frame so different cameras that reuse a URL differ
+#[tauri::command]
+async fn derive_camera_id(
+    rtsp_url: String,
+    use_frame: Option<bool>,
+    username: Option<String>,
+    password: Option<String>,
+) -> Result<String, AgentError> {
+    let frame_hash = if use_frame.unwrap_or(false) {
+        Some(camera::fingerprint_stream(&rtsp_url, username, password).await?)
+    } else {
+        None
+    };
+    Ok(camera::derive_camera_id(&rtsp_url, frame_hash))
+}
+
+#[tauri::command]
+async fn diagnose_rtsp(rtsp_url: String) -> Result<camera::RtspDiagnostics, AgentError> {
+    println!("[Rust] Running RTSP diagnostics: {}", camera::redact_url(&rtsp_url));
+    camera::diagnose_rtsp_connection(&rtsp_url).await
+}
+
+#[tauri::command]
+async fn probe_streams(
+    url: String,
+    username: Option<String>,
+    password: Option<String>,
+    camera_id: Option<String>,
+    rtsp_transport: Option<camera::RtspTransport>,
+    cameras: State<'_, CameraMap>,
+    onvif_credentials: State<'_, OnvifCredentialStore>,
+) -> Result<Vec<camera::StreamProfile>, AgentError> {
+    println!("[Rust] Probing streams at {}", camera::redact_url(&url));
+
+    // A connected camera is probed over the transport it is set up with
+    let transport = match (rtsp_transport, &camera_id) {
+        (Some(transport), _) => transport,
+        (None, Some(camera_id)) => cameras.read().await
+            .get(camera_id)
+            .map(|handle| handle.rtsp_transport)
+            .unwrap_or_default(),
+        (None, None) => camera::RtspTransport::default(),
+    };
+
+    // Fall back to the login saved for this camera
+    let (username, password) = match (username, password, camera_id) {
+        (None, None, Some(camera_id)) => {
+            let stored = onvif_credentials.lock()
+                .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+                .get(&camera_id)
+                .cloned();
+            match stored {
+                Some(credentials) => (Some(credentials.username), Some(credentials.password)),
+                None => (None, None),
+            }
+        }
+        (username, password, _) => (username, password),
+    };
+
+    camera::probe_streams(&url, username, password, transport).await
+}
+
+#[tauri::command]
+async fn set_onvif_credentials(
+    camera_id: String,
+    credentials: Option<onvif::OnvifCredentials>,
+    onvif_credentials: State<'_, OnvifCredentialStore>,
+) -> Result<(), AgentError> {
+    let mut store = onvif_credentials.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+
+    match credentials {
+        Some(credentials) => {
+            println!("[Rust] Saved ONVIF login for {} (user {})", camera_id, credentials.username);
+            store.insert(camera_id, credentials);
+        }
+        None => {
+            println!("[Rust] Cleared ONVIF login for {}", camera_id);
+            store.remove(&camera_id);
+        }
+    }
+
+    Ok(())
+}
+
+/// Connect to a source and start its persistent capture
+#[allow(clippy::too_many_arguments)]
+async fn open_camera(
+    rtsp_url: &str,
+    username: Option<String>,
+    password: Option<String>,
+    rtsp_transport: camera::RtspTransport,
+    roi: Option<[f64; 4]>,
+    transform: camera::FrameTransform,
+    capture_mode: camera::CaptureMode,
+    hwaccel: settings::HwAccel,
+) -> Result<camera::CameraHandle, AgentError> {
+    let mut handle = camera::connect(rtsp_url, username.clone(), password.clone(), rtsp_transport, roi, transform).await?;
+    handle.hwaccel = hwaccel;
+
+    if capture_mode == camera::CaptureMode::OneShot {
+        println!("[Rust] One-shot capture; not starting a persistent ffmpeg process");
+        return Ok(handle);
+    }
+
+    let source_type = handle.source.lock().await.kind().to_string();
+
+    println!("[Rust] Source type: {}", source_type);
+
+    // Start persistent capture, falling back to one-shot if ffmpeg won't stay up
+    match camera::PersistentCapture::new(
+        rtsp_url.to_string(),
+        source_type,
+        username,
+        password,
+        rtsp_transport,
+        roi,
+        transform,
+        handle.sample_aspect,
+        hwaccel,
+    ) {
+        Ok(persistent_capture) => {
+            handle.persistent_capture = Some(Arc::new(std::sync::Mutex::new(persistent_capture)));
+            handle.capture_mode = camera::CaptureMode::Persistent;
+        }
+        Err(e) => println!("[Rust] Persistent capture failed to start ({}), using one-shot capture", e),
+    }
+
+    Ok(handle)
+}
+
+/// Stop a handle's persistent capture, if it has one
+///
+/// Killing and reaping ffmpeg blocks, so it runs off the async runtime.
+async fn close_camera(handle: &mut camera::CameraHandle) -> Result<(), AgentError> {
+    handle.is_connected = false;
+    handle.closed.store(true, std::sync::atomic::Ordering::SeqCst);
+    let Some(capture) = handle.persistent_capture.take() else {
+        return Ok(());
+    };
+
+    tokio::task::spawn_blocking(move || {
+        capture.lock()
+            .map_err(|e| AgentError::Internal(format!("Capture lock error: {}", e)))?
+            .stop()
+    })
+    .await
+    .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))?
+}
+
+#[tauri::command]
+async fn connect_camera(
+    camera_id: String,
+    rtsp_url: String,
+    username: Option<String>,
+    password: Option<String>,
+    options: Option<camera::ConnectOptions>,
+    app: tauri::AppHandle,
+) -> Result<(), AgentError> {
+    println!("[Rust] Connecting camera: {}", camera_id);
+    let options = options.unwrap_or_default();
+    options.retry.validate()?;
+    let _camera_lock = lock_camera(&app.state::<CameraLocks>(), &camera_id).await?;
+
+    let (username, password) = match (username, password) {
+        (Some(username), Some(password)) => (Some(username), Some(password)),
+        _ => match stored_credentials(&camera_id).await {
+            Some(stored) => (Some(stored.username), Some(stored.password)),
+            None => (None, None),
+        },
+    };
+
+    let mut handle = open_camera(
+        &rtsp_url,
+        username,
+        password,
+        options.rtsp_transport,
+        options.roi,
+        options.transform,
+        options.capture_mode,
+        snapshot(&app.state::<HwAccelConfig>())?,
+    ).await?;
+    handle.retry = options.retry;
+
+    println!("[Rust] ✅ Capture started for {} ({:?})", camera_id, handle.capture_mode);
+
+    // Slow or absent ONVIF services shouldn't hold up the connection
+    tauri::async_runtime::spawn(check_clock_drift(app.clone(), camera_id.clone(), handle.source_url.clone()));
+
+    let previous = app.state::<CameraMap>().write().await.insert(camera_id.clone(), handle);
+    if let Some(mut previous) = previous {
+        if let Err(e) = close_camera(&mut previous).await {
+            println!("[Rust] Failed to stop replaced capture for {}: {}", camera_id, e);
+        }
+    }
+
+    println!("[Rust] ✅ Camera {} connected and ready", camera_id);
+
+    Ok(())
+}
+
+/// Read a camera's clock over ONVIF and keep the offset on its handle,
+/// emitting `camera-clock-drift` when it's far enough off to skew timelines
+async fn check_clock_drift(app: tauri::AppHandle, camera_id: String, source_url: String) {
+    let Some(device_url) = onvif::default_device_url(&source_url) else { return };
+    let offset_secs = match onvif::clock_offset(&device_url).await {
+        Ok(Some(offset)) => offset.num_seconds(),
+        Ok(None) => return,
+        Err(e) => {
+            println!("[Rust] Could not read clock of camera {}: {}", camera_id, e);
+            return;
+        }
+    };
+
+    // The camera may have been reconnected elsewhere while we waited
+    let cameras = app.state::<CameraMap>();
+    match cameras.write().await.get_mut(&camera_id) {
+        Some(handle) if handle.source_url == source_url => handle.clock_offset_secs = Some(offset_secs),
+        _ => return,
+    }
+
+    if offset_secs.abs() > monitoring::CLOCK_DRIFT_WARN_SECS {
+        println!("[Rust] ⚠️ Camera {} clock is {}s off", camera_id, offset_secs);
+        let _ = app.emit("camera-clock-drift", monitoring::ClockDrift { camera_id, offset_secs });
+    }
+}
+
+/// Camera login from the OS keychain; keychain errors are logged, not fatal
+async fn stored_credentials(camera_id: &str) -> Option<credentials::CameraCredentials> {
+    let key = camera_id.to_string();
+    match tokio::task::spawn_blocking(move || credentials::load(&key)).await {
+        Ok(Ok(stored)) => stored,
+        Ok(Err(e)) => {
+            println!("[Rust] No keychain credentials for {}: {}", camera_id, e);
+            None
+        }
+        Err(e) => {
+            println!("[Rust] Keychain task failed: {}", e);
+            None
+        }
+    }
+}
+
+/// Store a camera's login in the OS keychain instead of the camera URL
+///
+/// `connect_camera` uses it whenever no credentials are passed. A connected
+/// camera picks it up on its next capture (or reconnect, for persistent mode).
+#[tauri::command]
+async fn set_camera_credentials(
+    camera_id: String,
+    username: String,
+    password: String,
+    cameras: State<'_, CameraMap>,
+) -> Result<(), AgentError> {
+    if username.is_empty() {
+        return Err(AgentError::InvalidInput("Username must not be empty".to_string()));
+    }
+
+    println!("[Rust] Storing credentials for camera {} in the keychain", camera_id);
+
+    let stored = credentials::CameraCredentials { username, password };
+    let key = camera_id.clone();
+    let to_store = stored.clone();
+    tokio::task::spawn_blocking(move || credentials::store(&key, &to_store))
+        .await
+        .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))??;
+
+    if let Some(handle) = cameras.write().await.get_mut(&camera_id) {
+        handle.username = Some(stored.username);
+        handle.password = Some(stored.password);
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn delete_camera_credentials(camera_id: String) -> Result<(), AgentError> {
+    println!("[Rust] Removing keychain credentials for camera {}", camera_id);
+
+    tokio::task::spawn_blocking(move || credentials::delete(&camera_id))
+        .await
+        .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))?
+}
+
+/// Capture the most recent frame from a camera and count the outcome
+async fn latest_frame(
+    cameras: &CameraMap,
+    metrics: &metrics::Metrics,
+    camera_id: &str,
+) -> Result<Vec<u8>, AgentError> {
+    // Clone the handle so the map isn't locked while capturing
+    let handle = cameras.read().await
+        .get(camera_id)
+        .cloned()
+        .ok_or_else(|| AgentError::NotFound(format!("Camera {} not found", camera_id)))?;
+
+    let result = camera::capture_frame(&handle).await;
+
+    match &result {
+        Ok(_) => metrics.record_frame(camera_id),
+        Err(_) => metrics.record_capture_failure(camera_id),
+    }
+
+    result
+}
+
+/// Compare a monitoring capture with the previous one, emitting
+/// `camera-frozen` once the picture has stopped changing
+async fn check_frozen(app: &tauri::AppHandle, cameras: &CameraMap, camera_id: &str, frame_bytes: &[u8]) {
+    let Some(freeze) = cameras.read().await.get(camera_id).map(|handle| handle.freeze.clone()) else {
+        return;
+    };
+    let previous = freeze.lock().ok().and_then(|detector| detector.last_frame().map(<[u8]>::to_vec));
+
+    let diff = match previous {
+        Some(previous) if previous == frame_bytes => Some(0.0),
+        Some(previous) => {
+            let current = frame_bytes.to_vec();
+            tokio::task::spawn_blocking(move || frame::frame_diff(&previous, &current))
+                .await
+                .ok()
+                .and_then(Result::ok)
+        }
+        None => None,
+    };
+
+    let now = std::time::Instant::now();
+    let Ok(mut detector) = freeze.lock() else { return };
+    match detector.record(frame_bytes.to_vec(), diff, now) {
+        Some(monitoring::FreezeTransition::Froze) => {
+            let frozen = monitoring::FrozenCamera {
+                camera_id: camera_id.to_string(),
+                unchanged_captures: detector.unchanged_captures(),
+                unchanged_secs: detector.unchanged_for(now).as_secs(),
+            };
+            println!("[Rust] ⚠️ Camera {} looks frozen: {} identical captures over {}s",
+                     camera_id, frozen.unchanged_captures, frozen.unchanged_secs);
+            let _ = app.emit("camera-frozen", frozen);
+        }
+        Some(monitoring::FreezeTransition::Thawed) => println!("[Rust] Camera {} picture is changing again", camera_id),
+        None => {}
+    }
+}
+
+/// Frame returned by `get_frame` and pushed by live views
+#[derive(Clone, serde::Serialize)]
+struct FrameSnapshot {
+    /// Base64 encoded, or a full `data:` URL when requested
+    frame: String,
+    /// True when live capture failed and this is the last cached frame
+    stale: bool,
+    /// Seconds since a stale frame was captured; 0 for live frames
+    age_secs: u64,
+}
+
+/// Capture a frame; `data_url` returns it ready for `<img src>`
+#[tauri::command]
+async fn get_frame(
+    camera_id: String,
+    allow_stale: Option<bool>,
+    data_url: Option<bool>,
+    cameras: State<'_, CameraMap>,
+    cache: State<'_, FrameCache>,
+    metrics: State<'_, SharedMetrics>,
+) -> Result<FrameSnapshot, AgentError> {
+    let mut snapshot = frame_snapshot(&cameras, &cache, &metrics, &camera_id, allow_stale.unwrap_or(false)).await?;
+    if data_url == Some(true) {
+        snapshot.frame = frame::data_url(frame::CAPTURE_FORMAT, &snapshot.frame);
+    }
+    Ok(snapshot)
+}
+
+/// Capture a frame as base64, serving the cached one if capture fails and
+/// `allow_stale` is set
+async fn frame_snapshot(
+    cameras: &CameraMap,
+    cache: &FrameCache,
+    metrics: &metrics::Metrics,
+    camera_id: &str,
+    allow_stale: bool,
+) -> Result<FrameSnapshot, AgentError> {
+    // Get frame from persistent capture
+    let error = match latest_frame(cameras, metrics, camera_id).await {
+        Ok(frame_bytes) => {
+            // Convert to base64 for frontend
+            use base64::{Engine as _, engine::general_purpose};
+            return Ok(FrameSnapshot {
+                frame: general_purpose::STANDARD.encode(&frame_bytes),
+                stale: false,
+                age_secs: 0,
+            });
+        }
+        Err(e) => e,
+    };
+
+    if !allow_stale {
+        return Err(error);
+    }
+
+    // Keep the last known image on screen through a brief outage
+    let cached = cache.read().await
+        .get(camera_id)
+        .cloned()
+        .ok_or(error)?;
+
+    let age_secs = cached.timestamp.elapsed().map(|age| age.as_secs()).unwrap_or(0);
+    println!("[Rust] Live capture failed for {}, serving cached frame ({}s old)", camera_id, age_secs);
+
+    Ok(FrameSnapshot {
+        frame: cached.frame,
+        stale: true,
+        age_secs,
+    })
+}
+
+/// Push frames to `live-frame:{camera_id}` events at up to `fps`
+///
+/// The rate is held to what the camera's capture mode delivers (see
+/// `monitoring::live_view_fps`), unchanged frames aren't re-sent, and the
+/// cached frame stands in while capture fails. Replaces any live view
+/// already running for the camera; it ends when the camera disconnects.
+#[tauri::command]
+async fn start_live_view(
+    camera_id: String,
+    fps: f64,
+    app: tauri::AppHandle,
+    cameras: State<'_, CameraMap>,
+    live_views: State<'_, LiveViews>,
+) -> Result<(), AgentError> {
+    if !(fps.is_finite() && fps >= monitoring::MIN_LIVE_VIEW_FPS) {
+        return Err(AgentError::InvalidInput(format!(
+            "Live view rate must be at least {} fps, got {}", monitoring::MIN_LIVE_VIEW_FPS, fps
+        )));
+    }
+    if !cameras.read().await.contains_key(&camera_id) {
+        return Err(AgentError::NotFound(format!("Camera {} not found", camera_id)));
+    }
+
+    println!("[Rust] Starting live view of {} at up to {} fps", camera_id, fps);
+
+    let task_camera_id = camera_id.clone();
+    let handle = tauri::async_runtime::spawn(async move {
+        let camera_id = task_camera_id;
+        let event = format!("live-frame:{}", camera_id);
+        let cameras = app.state::<CameraMap>().inner().clone();
+        let cache = app.state::<FrameCache>().inner().clone();
+        let metrics = app.state::<SharedMetrics>().inner().clone();
+        let mut last_sent: Option<String> = None;
+
+        loop {
+            let started = std::time::Instant::now();
+            let persistent = match cameras.read().await.get(&camera_id) {
+                Some(handle) => handle.capture_mode == camera::CaptureMode::Persistent,
+                None => {
+                    println!("[LiveView] Camera {} is gone, stopping live view", camera_id);
+                    break;
+                }
+            };
+            let current_fps = if persistent { stream_fps(&cameras, &camera_id).await.ok().flatten() } else { None };
+            let rate = monitoring::live_view_fps(fps, persistent, current_fps);
+
+            match frame_snapshot(&cameras, &cache, &metrics, &camera_id, true).await {
+                Ok(snapshot) if last_sent.as_deref() == Some(snapshot.frame.as_str()) => {}
+                Ok(snapshot) => {
+                    let _ = app.emit(&event, snapshot.clone());
+                    last_sent = Some(snapshot.frame);
+                }
+                Err(e) => println!("[LiveView] No frame for {}: {}", camera_id, e),
+            }
+
+            let interval = std::time::Duration::from_secs_f64(1.0 / rate);
+            tokio::time::sleep(interval.saturating_sub(started.elapsed())).await;
+        }
+    });
+
+    if let Some(previous) = live_views.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .insert(camera_id, handle)
+    {
+        previous.abort();
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_live_view(
+    camera_id: String,
+    live_views: State<'_, LiveViews>,
+) -> Result<(), AgentError> {
+    println!("[Rust] Stopping live view of {}", camera_id);
+
+    if let Some(handle) = live_views.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .remove(&camera_id)
+    {
+        handle.abort();
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn disconnect_camera(
+    camera_id: String,
+    cameras: State<'_, CameraMap>,
+    camera_locks: State<'_, CameraLocks>,
+    periodic: State<'_, PeriodicTasks>,
+    cache: State<'_, FrameCache>,
+    dedup: State<'_, DetectionDeduper>,
+    histories: State<'_, DetectionHistories>,
+) -> Result<(), AgentError> {
+    println!("[Rust] Disconnecting camera: {}", camera_id);
+    let _camera_lock = lock_camera(&camera_locks, &camera_id).await?;
+
+    clear_cached(&cache, &dedup, &camera_id).await?;
+
+    // Periodic capture has nothing to sample once the camera is gone
+    if let Some(task) = periodic.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .remove(&camera_id)
+    {
+        task.handle.abort();
+    }
+
+    histories.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .remove(&camera_id);
+    let removed = cameras.write().await.remove(&camera_id);
+
+    if let Some(mut handle) = removed {
+        // Stop persistent capture if exists
+        close_camera(&mut handle).await?;
+        println!("[Rust] ✅ Camera {} disconnected", camera_id);
+    }
+
+    Ok(())
+}
+
+/// Refuse monitoring work outside the agent's schedule
+fn ensure_in_schedule(app: &tauri::AppHandle) -> Result<(), AgentError> {
+    let active = app.state::<Schedule>().lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .is_active(chrono::Local::now().naive_local());
+
+    if !active {
+        return Err(AgentError::Paused("Outside the monitoring schedule".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Refuse monitoring work for cameras the operator has paused
+async fn ensure_not_paused(cameras: &CameraMap, camera_id: &str) -> Result<(), AgentError> {
+    let paused = cameras.read().await
+        .get(camera_id)
+        .map(|handle| handle.paused)
+        .unwrap_or(false);
+
+    if paused {
+        return Err(AgentError::Paused(format!("Camera {} is paused", camera_id)));
+    }
+
+    Ok(())
+}
+
+/// Set or clear a camera's paused flag
+async fn set_paused(cameras: &CameraMap, camera_id: &str, paused: bool) -> Result<(), AgentError> {
+    cameras.write().await
+        .get_mut(camera_id)
+        .ok_or_else(|| AgentError::NotFound(format!("Camera {} not found", camera_id)))?
+        .paused = paused;
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn pause_camera(
+    camera_id: String,
+    cameras: State<'_, CameraMap>,
+) -> Result<(), AgentError> {
+    println!("[Rust] Pausing monitoring for camera: {}", camera_id);
+    set_paused(&cameras, &camera_id, true).await
+}
+
+#[tauri::command]
+async fn resume_camera(
+    camera_id: String,
+    cameras: State<'_, CameraMap>,
+) -> Result<(), AgentError> {
+    println!("[Rust] Resuming monitoring for camera: {}", camera_id);
+    set_paused(&cameras, &camera_id, false).await
+}
+
+/// Pause monitoring for every camera in a group; returns the cameras paused
+#[tauri::command]
+async fn pause_group(
+    group: String,
+    cameras: State<'_, CameraMap>,
+    groups: State<'_, CameraGroups>,
+) -> Result<Vec<String>, AgentError> {
+    println!("[Rust] Pausing monitoring for group: {}", group);
+    let members = group_cameras(&groups, &group)?;
+    for camera_id in &members {
+        set_paused(&cameras, camera_id, true).await?;
+    }
+    Ok(members)
+}
+
+/// Resume monitoring for every camera in a group; returns the cameras resumed
+#[tauri::command]
+async fn resume_group(
+    group: String,
+    cameras: State<'_, CameraMap>,
+    groups: State<'_, CameraGroups>,
+) -> Result<Vec<String>, AgentError> {
+    println!("[Rust] Resuming monitoring for group: {}", group);
+    let members = group_cameras(&groups, &group)?;
+    for camera_id in &members {
+        set_paused(&cameras, camera_id, false).await?;
+    }
+    Ok(members)
+}
+
+/// Estimate how many frames, how much money and how long a batch
+/// detection run over a video would take, before starting it
+///
+/// `camera_id_or_path` is a connected video-file camera or a file path.
+#[tauri::command]
+async fn estimate_batch(
+    camera_id_or_path: String,
+    sample_interval_secs: f64,
+    cameras: State<'_, CameraMap>,
+    detection: State<'_, DetectionConfig>,
+    metrics: State<'_, SharedMetrics>,
+) -> Result<monitoring::BatchEstimate, AgentError> {
+    let path = match cameras.read().await.get(&camera_id_or_path) {
+        Some(handle) => match &*handle.source.lock().await {
+            camera::CameraSource::VideoFile { path, .. } => path.clone(),
+            _ => return Err(AgentError::InvalidInput(format!(
+                "Camera {} is not a video file", camera_id_or_path
+            ))),
+        },
+        None if std::path::Path::new(&camera_id_or_path).is_file() => camera_id_or_path.clone(),
+        None => return Err(AgentError::NotFound(format!(
+            "No camera or video file {}", camera_id_or_path
+        ))),
+    };
+
+    let duration = camera::probe_duration(&path).await?;
+    let cost_per_request = snapshot(&detection)?.cost_per_request;
+    let request_time = metrics.mean_backend_latency().unwrap_or(monitoring::DEFAULT_BATCH_REQUEST_TIME);
+
+    let estimate = monitoring::estimate_batch(duration, sample_interval_secs, cost_per_request, request_time)?;
+    println!("[Rust] Batch estimate for {} ({:.0}s): {:?}", path, duration, estimate);
+    Ok(estimate)
+}
+
+/// Trade frame accuracy for speed when stepping through a video file
+#[tauri::command]
+async fn set_seek_mode(
+    camera_id: String,
+    seek_mode: camera::SeekMode,
+    cameras: State<'_, CameraMap>,
+) -> Result<(), AgentError> {
+    println!("[Rust] Seek mode for {}: {:?}", camera_id, seek_mode);
+
+    let cameras = cameras.read().await;
+    let handle = cameras
+        .get(&camera_id)
+        .ok_or_else(|| AgentError::NotFound(format!("Camera {} not found", camera_id)))?;
+
+    camera::set_seek_mode(handle, seek_mode).await
+}
+
+#[tauri::command]
+async fn get_camera_status(
+    camera_id: String,
+    cameras: State<'_, CameraMap>,
+    periodic: State<'_, PeriodicTasks>,
+    groups: State<'_, CameraGroups>,
+) -> Result<camera::CameraStatus, AgentError> {
+    let periodic_capture = periodic.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .contains_key(&camera_id);
+    let group = snapshot(&groups)?.get(&camera_id);
+
+    let cameras_lock = cameras.read().await;
+
+    let handle = cameras_lock.get(&camera_id)
+        .ok_or_else(|| AgentError::NotFound(format!("Camera {} not found", camera_id)))?;
+
+    camera_status(camera_id, handle, periodic_capture, group)
+}
+
+/// Connected cameras sorted by id, only those in `group` when given
+#[tauri::command]
+async fn list_cameras(
+    group: Option<String>,
+    cameras: State<'_, CameraMap>,
+    periodic: State<'_, PeriodicTasks>,
+    groups: State<'_, CameraGroups>,
+) -> Result<Vec<camera::CameraStatus>, AgentError> {
+    let periodic_ids: Vec<String> = periodic.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .keys()
+        .cloned()
+        .collect();
+    let groups = snapshot(&groups)?;
+    let group = monitoring::normalize_group(group);
+
+    let cameras = cameras.read().await;
+    let mut statuses = Vec::new();
+    for (camera_id, handle) in cameras.iter() {
+        let camera_group = groups.get(camera_id);
+        if group.is_some() && camera_group != group {
+            continue;
+        }
+        statuses.push(camera_status(camera_id.clone(), handle, periodic_ids.contains(camera_id), camera_group)?);
+    }
+    statuses.sort_by(|a, b| a.camera_id.cmp(&b.camera_id));
+    Ok(statuses)
+}
+
+fn camera_status(
+    camera_id: String,
+    handle: &camera::CameraHandle,
+    periodic_capture: bool,
+    group: Option<String>,
+) -> Result<camera::CameraStatus, AgentError> {
+    let frames_buffered = match &handle.persistent_capture {
+        Some(capture) => capture.lock()
+            .map_err(|e| AgentError::Internal(format!("Capture lock error: {}", e)))?
+            .get_frame_count(),
+        None => 0,
+    };
+
+    let frozen = handle.freeze.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .is_frozen();
+
+    Ok(camera::CameraStatus {
+        camera_id,
+        source_url: handle.source_url.clone(),
+        connected: handle.is_connected,
+        paused: handle.paused,
+        capture_mode: handle.capture_mode,
+        active_stream: handle.streams.active,
+        frames_buffered,
+        periodic_capture,
+        frozen,
+        clock_offset_secs: handle.clock_offset_secs,
+        sample_aspect: handle.sample_aspect,
+        group,
+    })
+}
+
+/// Put a camera in a group, or take it out of its group with None
+#[tauri::command]
+async fn set_camera_group(
+    camera_id: String,
+    group: Option<String>,
+    app: tauri::AppHandle,
+    cameras: State<'_, CameraMap>,
+    groups: State<'_, CameraGroups>,
+) -> Result<(), AgentError> {
+    if !cameras.read().await.contains_key(&camera_id) {
+        return Err(AgentError::NotFound(format!("Camera {} not found", camera_id)));
+    }
+
+    let group = monitoring::normalize_group(group);
+    println!("[Rust] Camera {} group set to {:?}", camera_id, group);
+
+    let mut groups = groups.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+    groups.set(&camera_id, group);
+    groups.save(&camera_groups_path(&app)?)
+}
+
+fn camera_groups_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, AgentError> {
+    app.path().app_data_dir()
+        .map(|dir| dir.join(monitoring::CAMERA_GROUPS_FILE))
+        .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))
+}
+
+/// Cameras in a group, or NotFound if it has none
+fn group_cameras(groups: &CameraGroups, group: &str) -> Result<Vec<String>, AgentError> {
+    let members = snapshot(groups)?.members(group.trim());
+    if members.is_empty() {
+        return Err(AgentError::NotFound(format!("No cameras in group {}", group)));
+    }
+    Ok(members)
+}
+
+/// Effective frame rate of a camera's persistent stream
+async fn stream_fps(cameras: &CameraMap, camera_id: &str) -> Result<Option<f64>, AgentError> {
+    let cameras = cameras.read().await;
+    let handle = cameras
+        .get(camera_id)
+        .ok_or_else(|| AgentError::NotFound(format!("Camera {} not found", camera_id)))?;
+    let capture = handle.persistent_capture.as_ref()
+        .ok_or_else(|| AgentError::NotFound(format!("Camera {} has no persistent stream", camera_id)))?;
+
+    let fps = capture.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .fps();
+    Ok(fps)
+}
+
+/// Screens and capture cards that can be connected as cameras
+#[tauri::command]
+async fn list_capture_devices() -> Result<Vec<camera::CaptureDevice>, AgentError> {
+    camera::list_capture_devices().await
+}
+
+/// Frames per second actually arriving from a camera's persistent stream;
+/// None until at least two frames have been received
+#[tauri::command]
+async fn get_stream_fps(
+    camera_id: String,
+    cameras: State<'_, CameraMap>,
+) -> Result<Option<f64>, AgentError> {
+    stream_fps(&cameras, &camera_id).await
+}
+
+/// How often the tray is brought in line with the monitoring schedule
+const SCHEDULE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+fn schedule_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, AgentError> {
+    app.path().app_data_dir()
+        .map(|dir| dir.join(settings::SCHEDULE_FILE))
+        .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))
+}
+
+/// Show whether the schedule currently allows monitoring, until shutdown
+async fn watch_schedule(app: tauri::AppHandle, set_tray_text: impl Fn(&str) + Send + 'static) {
+    let mut was_active = None;
+    loop {
+        if app.state::<ShutdownFlag>().load(std::sync::atomic::Ordering::SeqCst) {
+            break;
+        }
+
+        let active = ensure_in_schedule(&app).is_ok();
+        if was_active != Some(active) {
+            println!("[Rust] Monitoring schedule: {}", if active { "active" } else { "paused until the next window" });
+            set_tray_text(if active { "Monitoring: ON" } else { "Monitoring: OFF (scheduled)" });
+            was_active = Some(active);
+        }
+
+        tokio::time::sleep(SCHEDULE_CHECK_INTERVAL).await;
+    }
+}
+
+/// The monitoring schedule and whether it allows monitoring right now
+#[derive(serde::Serialize)]
+struct ScheduleStatus {
+    schedule: settings::MonitoringSchedule,
+    active_now: bool,
+    /// Offset of the local time the windows are evaluated in, e.g. `+02:00`
+    utc_offset: String,
+}
+
+#[tauri::command]
+async fn get_schedule(schedule: State<'_, Schedule>) -> Result<ScheduleStatus, AgentError> {
+    let schedule = snapshot(&schedule)?;
+    let now = chrono::Local::now();
+    Ok(ScheduleStatus {
+        active_now: schedule.is_active(now.naive_local()),
+        utc_offset: now.format("%:z").to_string(),
+        schedule,
+    })
+}
+
+/// Replace the agent-wide monitoring schedule; windows are in local time
+#[tauri::command]
+async fn set_schedule(
+    schedule: settings::MonitoringSchedule,
+    app: tauri::AppHandle,
+) -> Result<(), AgentError> {
+    schedule.validate()?;
+    println!("[Rust] Monitoring schedule {} with {} window(s)",
+             if schedule.enabled { "enabled" } else { "disabled" }, schedule.windows.len());
+
+    schedule.save(&schedule_path(&app)?)?;
+    replace_state(&app.state::<Schedule>(), schedule)
+}
+
+/// Emit `fps-update` with every persistent stream's frame rate, and
+/// `stream-reopened` for streams that were reopened, until shutdown
+async fn report_stream_fps(app: tauri::AppHandle) {
+    loop {
+        tokio::time::sleep(FPS_REPORT_INTERVAL).await;
+        if app.state::<ShutdownFlag>().load(std::sync::atomic::Ordering::SeqCst) {
+            break;
+        }
+
+        let camera_ids: Vec<String> = app.state::<CameraMap>().read().await
+            .iter()
+            .filter(|(_, handle)| handle.persistent_capture.is_some())
+            .map(|(camera_id, _)| camera_id.clone())
+            .collect();
+
+        report_stream_reopens(&app).await;
+
+        let mut streams = Vec::with_capacity(camera_ids.len());
+        for camera_id in camera_ids {
+            if let Ok(fps) = stream_fps(&app.state::<CameraMap>(), &camera_id).await {
+                streams.push(monitoring::StreamFps { camera_id, fps });
+            }
+        }
+
+        if !streams.is_empty() {
+            let _ = app.emit("fps-update", streams);
+        }
+    }
+}
+
+/// Move reopen counts from persistent streams into capture stats, emitting
+/// `stream-reopened` for each camera whose stream was reopened
+async fn report_stream_reopens(app: &tauri::AppHandle) {
+    let reopened: Vec<(String, u64)> = {
+        let cameras = app.state::<CameraMap>();
+        let cameras = cameras.read().await;
+        cameras.iter()
+            .filter_map(|(camera_id, handle)| {
+                let reopens = handle.persistent_capture.as_ref()?.lock().ok()?.take_reopens();
+                if reopens == 0 {
+                    return None;
+                }
+                let total = handle.capture_stats.lock().ok()?.record_reopens(reopens);
+                Some((camera_id.clone(), total))
+            })
+            .collect()
+    };
+
+    for (camera_id, stream_reopens) in reopened {
+        println!("[Rust] Stream for {} was reopened ({} reopens so far)", camera_id, stream_reopens);
+        let _ = app.emit("stream-reopened", monitoring::StreamReopened { camera_id, stream_reopens });
+    }
+}
+
+#[tauri::command]
+async fn get_capture_stats(
+    camera_id: String,
+    cameras: State<'_, CameraMap>,
+) -> Result<monitoring::CaptureStatsReport, AgentError> {
+    let stats = cameras.read().await
+        .get(&camera_id)
+        .map(|handle| handle.capture_stats.clone())
+        .ok_or_else(|| AgentError::NotFound(format!("Camera {} not found", camera_id)))?;
+
+    let report = stats.lock()
+        .map_err(|e| AgentError::Internal(format!("Stats lock error: {}", e)))?
+        .report(&camera_id);
+
+    Ok(report)
+}
+
+/// Rotate or mirror a connected camera's frames; a persistent capture is
+/// restarted to pick the change up
+#[tauri::command]
+async fn set_camera_transform(
+    camera_id: String,
+    transform: camera::FrameTransform,
+    cameras: State<'_, CameraMap>,
+    camera_locks: State<'_, CameraLocks>,
+) -> Result<(), AgentError> {
+    transform.validate()?;
+    println!("[Rust] Transform for {} set to {:?}", camera_id, transform);
+    let _camera_lock = lock_camera(&camera_locks, &camera_id).await?;
+
+    let restart = {
+        let mut cameras_lock = cameras.write().await;
+        let handle = cameras_lock.get_mut(&camera_id)
+            .ok_or_else(|| AgentError::NotFound(format!("Camera {} not found", camera_id)))?;
+        handle.transform = transform;
+        handle.persistent_capture.is_some()
+    };
+
+    if restart {
+        reopen_camera(&cameras, &camera_id).await?;
+    }
+    Ok(())
+}
+
+/// Capture one frame with candidate crop, transform and upload settings and
+/// return it as it would be uploaded; nothing is saved or sent
+#[tauri::command]
+async fn preview_capture(
+    camera_id: String,
+    settings: camera::PreviewSettings,
+    data_url: Option<bool>,
+    cameras: State<'_, CameraMap>,
+    upload: State<'_, UploadConfig>,
+) -> Result<camera::CapturePreview, AgentError> {
+    if let Some(roi) = &settings.roi {
+        camera::validate_roi(roi)?;
+    }
+    if let Some(transform) = &settings.transform {
+        transform.validate()?;
+    }
+
+    let handle = cameras.read().await
+        .get(&camera_id)
+        .cloned()
+        .ok_or_else(|| AgentError::NotFound(format!("Camera {} not found", camera_id)))?;
+    let upload = snapshot(&upload)?;
+    let format = settings.upload_format.unwrap_or(upload.upload_format);
+    let max_frame_bytes = settings.max_frame_bytes.unwrap_or(upload.max_frame_bytes);
+
+    println!("[Rust] Previewing capture settings for {}: {:?}", camera_id, settings);
+    let roi = settings.roi.or(handle.roi);
+    let frame = camera::capture_preview(&handle, roi, settings.transform.unwrap_or(handle.transform)).await?;
+
+    // Same steps as the upload path: encode, then shrink to the size limit
+    let (encoded, (width, height)) = tokio::task::spawn_blocking(move || {
+        let encoded = frame::encode_for_upload(frame, format, max_frame_bytes)?;
+        let dimensions = frame::frame_dimensions(&encoded)?;
+        Ok::<_, AgentError>((encoded, dimensions))
+    })
+    .await
+    .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))??;
+
+    use base64::{Engine as _, engine::general_purpose};
+    let base64 = general_purpose::STANDARD.encode(&encoded);
+    Ok(camera::CapturePreview {
+        frame: if data_url.unwrap_or(false) { frame::data_url(format, &base64) } else { base64 },
+        format,
+        bytes: encoded.len(),
+        width,
+        height,
+    })
+}
+
+/// Frame returned by `capture_best_frame`
+#[derive(serde::Serialize)]
+struct BestFrame {
+    /// Base64 encoded JPEG
+    frame: String,
+    quality: u8,
+    bytes: usize,
+}
+
+/// Capture a frame at the highest JPEG quality that fits in `target_bytes`
+///
+/// The quality found is remembered for the camera and tried first next
+/// time, so repeat captures at a steady budget settle in a couple of encodes.
+#[tauri::command]
+async fn capture_best_frame(
+    camera_id: String,
+    target_bytes: usize,
+    cameras: State<'_, CameraMap>,
+    metrics: State<'_, SharedMetrics>,
+    qualities: State<'_, BestQualities>,
+) -> Result<BestFrame, AgentError> {
+    let hint = qualities.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .get(&camera_id)
+        .copied();
+
+    let frame_bytes = latest_frame(&cameras, &metrics, &camera_id).await?;
+    let (encoded, quality) = tokio::task::spawn_blocking(move || frame::encode_within(&frame_bytes, target_bytes, hint))
+        .await
+        .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))??;
+
+    println!("[Rust] Best frame for {}: quality {}, {} bytes (budget {})", camera_id, quality, encoded.len(), target_bytes);
+    qualities.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .insert(camera_id, quality);
+
+    use base64::{Engine as _, engine::general_purpose};
+    Ok(BestFrame {
+        frame: general_purpose::STANDARD.encode(&encoded),
+        quality,
+        bytes: encoded.len(),
+    })
+}
+
+/// Point live capture at another of the camera's streams; a persistent
+/// capture is restarted on the new URL, one-shot captures just switch
+async fn switch_stream(
+    cameras: &CameraMap,
+    camera_id: &str,
+    stream: camera::StreamSelection,
+) -> Result<(), AgentError> {
+    let restart = {
+        let mut cameras_lock = cameras.write().await;
+        let handle = cameras_lock.get_mut(camera_id)
+            .ok_or_else(|| AgentError::NotFound(format!("Camera {} not found", camera_id)))?;
+        if handle.streams.active == stream {
+            return Ok(());
+        }
+
+        let url = handle.streams.url(stream)
+            .ok_or_else(|| AgentError::InvalidInput(format!("Camera {} has no sub stream configured", camera_id)))?
+            .to_string();
+        let mut source = handle.source.lock().await;
+        *source = source.with_url(&url)
+            .ok_or_else(|| AgentError::InvalidInput("Video file sources have no other streams".to_string()))?;
+        drop(source);
+
+        println!("[Rust] Camera {} now reads its {:?} stream: {}", camera_id, stream, camera::redact_url(&url));
+        handle.source_url = url;
+        handle.streams.active = stream;
+        handle.persistent_capture.is_some()
+    };
+
+    if restart {
+        reopen_camera(cameras, camera_id).await?;
+    }
+    Ok(())
+}
+
+/// A camera's main and sub stream URLs and which one live capture reads
+#[tauri::command]
+async fn list_camera_streams(
+    camera_id: String,
+    cameras: State<'_, CameraMap>,
+) -> Result<camera::CameraStreams, AgentError> {
+    cameras.read().await
+        .get(&camera_id)
+        .map(|handle| handle.streams.clone())
+        .ok_or_else(|| AgentError::NotFound(format!("Camera {} not found", camera_id)))
+}
+
+/// Set (or with None, remove) the low-resolution stream of a connected
+/// camera; the camera must not be reading it when it is removed
+#[tauri::command]
+async fn set_sub_stream(
+    camera_id: String,
+    url: Option<String>,
+    cameras: State<'_, CameraMap>,
+    camera_locks: State<'_, CameraLocks>,
+) -> Result<(), AgentError> {
+    let _camera_lock = lock_camera(&camera_locks, &camera_id).await?;
+    let mut cameras_lock = cameras.write().await;
+    let handle = cameras_lock.get_mut(&camera_id)
+        .ok_or_else(|| AgentError::NotFound(format!("Camera {} not found", camera_id)))?;
+
+    let url = url.filter(|url| !url.trim().is_empty());
+    if url.is_none() && handle.streams.active == camera::StreamSelection::Sub {
+        return Err(AgentError::InvalidInput(format!(
+            "Camera {} is reading its sub stream; switch to the main stream first", camera_id
+        )));
+    }
+
+    println!("[Rust] Sub stream for {} set to {:?}", camera_id, url.as_deref().map(camera::redact_url));
+    handle.streams.sub = url;
+    Ok(())
+}
+
+/// Run live capture (and so detection) on the main or sub stream;
+/// recordings pick their stream separately
+#[tauri::command]
+async fn set_active_stream(
+    camera_id: String,
+    stream: camera::StreamSelection,
+    cameras: State<'_, CameraMap>,
+    camera_locks: State<'_, CameraLocks>,
+) -> Result<(), AgentError> {
+    let _camera_lock = lock_camera(&camera_locks, &camera_id).await?;
+    switch_stream(&cameras, &camera_id, stream).await
+}
+
+#[tauri::command]
+async fn reconnect_camera(
+    camera_id: String,
+    cameras: State<'_, CameraMap>,
+    camera_locks: State<'_, CameraLocks>,
+) -> Result<bool, AgentError> {
+    println!("[Rust] Reconnecting camera: {}", camera_id);
+    let _camera_lock = lock_camera(&camera_locks, &camera_id).await?;
+    reopen_camera(&cameras, &camera_id).await
+}
+
+/// Restart a camera's capture with its current settings; the caller holds
+/// the camera's lock. Returns whether the new capture delivered a frame.
+async fn reopen_camera(cameras: &CameraMap, camera_id: &str) -> Result<bool, AgentError> {
+    // Tear down the old capture but keep the entry, so a failed reconnect
+    // can be retried with the same source and credentials
+    let mut old_handle = {
+        let mut cameras_lock = cameras.write().await;
+        let handle = cameras_lock.get_mut(camera_id)
+            .ok_or_else(|| AgentError::NotFound(format!("Camera {} not found", camera_id)))?;
+        let snapshot = handle.clone();
+        handle.persistent_capture = None;
+        handle.is_connected = false;
+        snapshot
+    };
+
+    // A dead ffmpeg may fail to stop cleanly; that shouldn't block recovery
+    if let Err(e) = close_camera(&mut old_handle).await {
+        println!("[Rust] Ignoring error stopping old capture for {}: {}", camera_id, e);
+    }
+
+    let mut handle = open_camera(
+        &old_handle.source_url,
+        old_handle.username.clone(),
+        old_handle.password.clone(),
+        old_handle.rtsp_transport,
+        old_handle.roi,
+        old_handle.transform,
+        old_handle.capture_mode,
+        old_handle.hwaccel,
+    ).await?;
+    handle.paused = old_handle.paused;
+    handle.streams = old_handle.streams.clone();
+    handle.clock_offset_secs = old_handle.clock_offset_secs;
+    handle.retry = old_handle.retry;
+    // Link health spans reconnects; that's when it's most interesting
+    handle.capture_stats = old_handle.capture_stats.clone();
+
+    // Opening only starts the capture; a frame shows the stream is really back
+    let connected = match camera::capture_frame(&handle).await {
+        Ok(_) => {
+            println!("[Rust] ✅ Camera {} reconnected", camera_id);
+            true
+        }
+        Err(e) => {
+            println!("[Rust] Camera {} reopened but gave no frame: {}", camera_id, e);
+            false
+        }
+    };
+
+    cameras.write().await.insert(camera_id.to_string(), handle);
+
+    Ok(connected)
+}
+
+/// Deliver alerts outside their cooldown to every configured notifier and
+/// the alert webhook
+fn forward_alerts(app: &tauri::AppHandle, response: &api::DetectionResponse) {
+    if response.alerts.is_empty() {
+        return;
+    }
+
+    let Ok(config) = app.state::<AlertWebhook>().lock().map(|config| config.clone()) else { return };
+    let Ok(notifiers) = app.state::<Notifiers>().lock().map(|settings| settings.notifiers.clone()) else { return };
+    let Ok(policies) = app.state::<ZoneNotify>().lock().map(|policies| policies.clone()) else { return };
+
+    let cooldowns = app.state::<AlertCooldowns>();
+    let Ok(mut cooldowns) = cooldowns.lock() else { return };
+
+    for alert in &response.alerts {
+        let policy = policies.get(&response.camera_id, alert.zone_id);
+        if policy == zones::NotifyPolicy::None {
+            println!("[Rust] Alert in zone {} on {} (logged only)", alert.zone_name, response.camera_id);
+            continue;
+        }
+        if !cooldowns.should_fire(&response.camera_id, alert.zone_id) {
+            continue;
+        }
+
+        let payload = webhook::WebhookPayload::from_alert(
+            &response.camera_id,
+            &response.timestamp,
+            alert,
+            response.evidence_url.clone(),
+        );
+
+        for notifier in &notifiers {
+            let enabled = if notifier.is_webhook() { policy.webhook() } else { policy.notification() };
+            if !enabled {
+                continue;
+            }
+            let notifier = notifier.build(app);
+            let payload = payload.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = notifier.notify(&payload).await {
+                    println!("[Notify] {} failed for zone {}: {}", notifier.name(), payload.zone_name, e);
+                }
+            });
+        }
+
+        let Some(config) = config.as_ref().filter(|_| policy.webhook()) else { continue };
+        let config = config.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = webhook::send_webhook(&config, &payload).await {
+                println!("[Webhook] Failed to forward alert for zone {}: {}", payload.zone_name, e);
+            }
+        });
+    }
+}
+
+#[tauri::command]
+async fn benchmark_capture(
+    camera_id: String,
+    samples: u32,
+    cameras: State<'_, CameraMap>,
+) -> Result<camera::CaptureBenchmark, AgentError> {
+    if samples == 0 {
+        return Err(AgentError::InvalidInput("Benchmark needs at least one sample".to_string()));
+    }
+
+    println!("[Rust] Benchmarking capture for {} ({} samples)", camera_id, samples);
+
+    // Clone the handle so the map isn't locked while ffmpeg runs
+    let handle = cameras.read().await
+        .get(&camera_id)
+        .cloned()
+        .ok_or_else(|| AgentError::NotFound(format!("Camera {} not found", camera_id)))?;
+
+    let report = camera::benchmark_capture(&handle, samples).await?;
+
+    println!("[Rust] Benchmark for {}: avg {:.0}ms, p95 {:.0}ms, {} failures",
+             camera_id, report.avg_ms, report.p95_ms, report.failures);
+
+    Ok(report)
+}
+
+/// Run detection on the frames of a burst through the configured sink
+///
+/// Each frame is sent on its own and the results merged, so a single frame
+/// is just a burst of one. Returns the merged result and the (privacy-masked)
+/// frame that stands in for the burst in caches, evidence and saved files.
+async fn run_detection(
+    app: &tauri::AppHandle,
+    camera_id: &str,
+    frames: &[Vec<u8>],
+    captured_at: std::time::SystemTime,
+    backend_url: &str,
+    api_key: &str,
+) -> Result<(api::DetectionResponse, Vec<u8>), AgentError> {
+    let sink = app.state::<SinkConfig>()
+        .lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .clone();
+
+    let metrics = app.state::<SharedMetrics>().inner().clone();
+
+    let upload = app.state::<UploadConfig>()
+        .lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .clone();
+
+    let single_backend = |url: &str, api_key: &str| vec![api::BackendConfig {
+        url: url.to_string(),
+        api_key: api_key.to_string(),
+    }];
+
+    let backends = match &sink {
+        sink::DetectionSink::Cloud => {
+            let configured = app.state::<BackendList>()
+                .lock()
+                .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+                .clone();
+            if configured.is_empty() { single_backend(backend_url, api_key) } else { configured }
+        }
+        // The cloud key stays off the LAN; local servers get no credential
+        sink::DetectionSink::LocalHttp(local_url) => single_backend(local_url, ""),
+        sink::DetectionSink::FileOnly => Vec::new(),
+    };
+
+    let privacy = snapshot(&app.state::<PrivacyMaskConfig>())?.get(camera_id).cloned();
+
+    // Context for temporal models: the last cached frame, then within a
+    // burst the frame before
+    let mut previous = if upload.previous_frame { cached_frame(app, camera_id).await } else { None };
+
+    // A failed frame only costs the burst that frame's detections
+    let mut masked = Vec::new();
+    let mut responses = Vec::new();
+    let mut first_error = None;
+    for frame_bytes in frames {
+        // Masks go on before anything else so nothing private survives in
+        // any copy; `send_frame` shrinks the upload to the size limit
+        let frame = frame_bytes.clone();
+        let masks = privacy.clone();
+        let prepare = move || match &masks {
+            Some(masks) => masks.apply(&frame),
+            None => Ok(frame),
+        };
+        let frame_bytes = tokio::task::spawn_blocking(prepare)
+            .await
+            .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))??;
+
+        let result = if backends.is_empty() {
+            Ok(sink::empty_response(camera_id))
+        } else {
+            send_frame(app, &backends, &upload, camera_id, &frame_bytes, previous.as_deref(), captured_at).await
+        };
+        match result {
+            Ok(mut response) => {
+                if let Some(privacy) = &privacy {
+                    privacy.suppress(&mut response.detections, frame::frame_dimensions(&frame_bytes)?);
+                }
+                if upload.previous_frame {
+                    previous = Some(frame_bytes.clone());
+                }
+                masked.push(frame_bytes);
+                responses.push(response);
+            }
+            Err(e) => {
+                println!("[Rust] Burst frame for {} failed: {}", camera_id, e);
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    let Some((mut response, best)) = dedup::merge_burst(responses, dedup::BURST_MERGE_IOU_THRESHOLD) else {
+        return Err(first_error.unwrap_or_else(|| AgentError::InvalidInput("No frames to detect on".to_string())));
+    };
+    let frame_bytes = masked.swap_remove(best);
+
+    // Drop single-frame flickers; only sustained zone alerts are surfaced
+    let alerts = std::mem::take(&mut response.alerts);
+    response.alerts = app.state::<AlertDebouncer>()
+        .lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .filter(camera_id, alerts);
+
+    metrics.record_detections(response.detections.len(), response.alerts.len());
+
+    if !response.alerts.is_empty() {
+        response.evidence_url = upload_evidence(app, camera_id, &frame_bytes, &response, captured_at.into()).await;
+    }
+
+    // Let the frontend map cropped coordinates back onto the full frame
+    response.roi = app.state::<CameraMap>()
+        .read()
+        .await
+        .get(camera_id)
+        .and_then(|handle| handle.roi);
+
+    if sink.writes_files() {
+        let root = app.path().app_data_dir()
+            .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))?
+            .join("detections");
+        let camera_id = camera_id.to_string();
+        let frame = frame_bytes.clone();
+        let saved = response.clone();
+        tokio::task::spawn_blocking(move || sink::write_frame(&root, &camera_id, &frame, &saved, captured_at.into()))
+            .await
+            .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))??;
+    }
+
+    Ok((response, frame_bytes))
+}
+
+/// The camera's last cached frame, decoded
+async fn cached_frame(app: &tauri::AppHandle, camera_id: &str) -> Option<Vec<u8>> {
+    use base64::{Engine as _, engine::general_purpose};
+
+    let cached = app.state::<FrameCache>().read().await.get(camera_id)?.frame.clone();
+    general_purpose::STANDARD.decode(cached).ok()
+}
+
+/// Send one frame to the detection backends, subject to the bandwidth cap,
+/// circuit breaker and upload slots
+async fn send_frame(
+    app: &tauri::AppHandle,
+    backends: &[api::BackendConfig],
+    upload: &settings::UploadSettings,
+    camera_id: &str,
+    frame_bytes: &[u8],
+    previous_frame: Option<&[u8]>,
+    captured_at: std::time::SystemTime,
+) -> Result<api::DetectionResponse, AgentError> {
+    let metrics = app.state::<SharedMetrics>().inner().clone();
+
+    // Keep capturing but stop sending once the month's allowance is used
+    if app.state::<BandwidthMeter>().lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .cap_reached(chrono::Local::now().date_naive())
+    {
+        return Err(AgentError::Paused(format!(
+            "Monthly upload cap reached, not sending frame for {}", camera_id
+        )));
+    }
+
+    // The backend asked this camera to back off; sending now would only prolong it
+    let rate_limits = app.state::<UploadRateLimits>().inner().clone();
+    if let Some(remaining) = rate_limits.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .remaining(camera_id, std::time::Instant::now())
+    {
+        return Err(AgentError::RateLimited(format!(
+            "Backend rate limit: not sending frame for {} for another {}s", camera_id, remaining.as_secs().max(1)
+        )));
+    }
+
+    let breaker = app.state::<BackendBreaker>().inner().clone();
+    {
+        let mut breaker = breaker.lock()
+            .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+        if !breaker.allow(std::time::Instant::now()) {
+            return Err(AgentError::Backend(format!(
+                "Backend unavailable, skipping detection for {}", camera_id
+            )));
+        }
+    }
+    // Queue behind other uploads rather than piling requests on a slow backend
+    let slots = app.state::<UploadSlots>().inner().clone();
+    let Some(_slot) = slots.acquire().await else {
+        metrics.record_upload_skipped();
+        return Err(AgentError::Timeout(format!(
+            "Dropped frame for {}: all {} upload slots busy", camera_id, slots.max()
+        )));
+    };
+    let _in_flight = metrics.track_in_flight();
+    // Saved frames stay JPEG, full size, for EXIF; only the upload is
+    // re-encoded, then shrunk so the encoded bytes fit the size limit
+    let frame = frame_bytes.to_vec();
+    let previous = previous_frame.map(<[u8]>::to_vec);
+    let upload_format = upload.upload_format;
+    let max_frame_bytes = upload.max_frame_bytes;
+    let encode = move || {
+        let previous = previous
+            .map(|previous| frame::encode_for_upload(previous, upload_format, max_frame_bytes))
+            .transpose()?;
+        Ok::<_, AgentError>((frame::encode_for_upload(frame, upload_format, max_frame_bytes)?, previous))
+    };
+    let (upload_bytes, previous_bytes) = tokio::task::spawn_blocking(encode)
+        .await
+        .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))??;
+    let auth = auth_scheme(&app.state::<AuthConfig>())?;
+    let metadata = frame_metadata(&app.state::<FrameSequences>(), upload.detection_api, camera_id, captured_at)?;
+    let sent_bytes = upload_bytes.len() + previous_bytes.as_ref().map_or(0, Vec::len);
+    record_upload_bytes(app, sent_bytes as u64)?;
+    let started = std::time::Instant::now();
+    let result = api::send_detection_with_failover(
+        backends, &auth, camera_id, &upload_bytes, previous_bytes.as_deref(), upload.upload_format, metadata.as_ref(),
+        &upload.response,
+    ).await;
+    let round_trip = started.elapsed();
+    if let Err(api::DetectionError { error: AgentError::RateLimited(_), retry_after }) = &result {
+        let pause = rate_limits.lock()
+            .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+            .pause(camera_id, *retry_after, std::time::Instant::now());
+        println!("[Rust] ⚠️ Backend rate limited {}, pausing its uploads for {}s", camera_id, pause.as_secs());
+        let _ = app.emit("rate-limited", monitoring::RateLimited {
+            camera_id: camera_id.to_string(),
+            retry_after_secs: pause.as_secs(),
+        });
+    }
+    let result = result.map_err(AgentError::from);
+    metrics.record_backend_request(round_trip, result.is_ok());
+    record_backend_result(app, &breaker, &result)?;
+    let mut response = result?;
+    response.latency_ms = Some(round_trip.as_millis() as u64);
+    Ok(response)
+}
+
+/// Upload an annotated copy of an alerting frame, if evidence storage is set
+///
+/// Failures are logged rather than returned so an unreachable bucket never
+/// drops the detection itself.
+async fn upload_evidence(
+    app: &tauri::AppHandle,
+    camera_id: &str,
+    frame_bytes: &[u8],
+    response: &api::DetectionResponse,
+    captured_at: chrono::DateTime<chrono::Local>,
+) -> Option<String> {
+    let config = app.state::<EvidenceStorage>().lock().ok()?.clone()?;
+
+    let labels = snapshot(&app.state::<Labels>()).ok()?;
+
+    match store_evidence(&config, camera_id, frame_bytes, response, captured_at, labels).await {
+        Ok(url) => {
+            println!("[Evidence] Uploaded {}", url);
+            Some(url)
+        }
+        Err(e) => {
+            println!("[Evidence] Upload failed for {}: {}", camera_id, e);
+            None
+        }
+    }
+}
+
+/// Annotate a frame with its detections and upload it, returning the object URL
+async fn store_evidence(
+    config: &storage::S3Config,
+    camera_id: &str,
+    frame_bytes: &[u8],
+    response: &api::DetectionResponse,
+    captured_at: chrono::DateTime<chrono::Local>,
+    labels: labels::LabelMap,
+) -> Result<String, AgentError> {
+    let frame = frame_bytes.to_vec();
+    let detections = response.detections.clone();
+    let id = camera_id.to_string();
+    let annotated = tokio::task::spawn_blocking(move || frame::annotate(&frame, &detections, &labels, &id, captured_at))
+        .await
+        .map_err(|e| AgentError::Internal(format!("Annotation task failed: {}", e)))??;
+
+    let key = format!("{}/{}.jpg", camera_id, captured_at.with_timezone(&chrono::Utc).format("%Y%m%dT%H%M%S%.3fZ"));
+    storage::upload_frame(config, &key, annotated).await
+}
+
+#[tauri::command]
+async fn send_frame_to_cloud(
+    camera_id: String,
+    frame_base64: String,
+    api_key: String,
+    backend_url: String,
+    app: tauri::AppHandle,
+    cameras: State<'_, CameraMap>,
+    cache: State<'_, FrameCache>,
+) -> Result<api::DetectionResponse, AgentError> {
+    ensure_in_schedule(&app)?;
+    ensure_not_paused(&cameras, &camera_id).await?;
+
+    println!("[Rust] Sending frame to cloud for camera: {}", camera_id);
+
+    use base64::{Engine as _, engine::general_purpose};
+    let frame_bytes = general_purpose::STANDARD.decode(&frame_base64)
+        .map_err(|e| AgentError::Decode(format!("Base64 decode error: {}", e)))?;
+
+    // The frontend grabbed this frame just before sending it
+    let captured_at = std::time::SystemTime::now();
+
+    let (mut response, sent_frame) = run_detection(
+        &app,
+        &camera_id,
+        &[frame_bytes],
+        captured_at,
+        &backend_url,
+        &api_key,
+    ).await?;
+    response.local_timestamp = Some(local_timestamp(captured_at));
+
+    // Cache the frame as sent (masked and resized) with its detection results
+    cache.write().await
+        .insert(camera_id.clone(), CachedData {
+            frame: general_purpose::STANDARD.encode(&sent_frame),
+            detections: response.clone(),
+            timestamp: captured_at,
+        });
+    record_detection_sample(&app, &camera_id, captured_at, &response)?;
+
+    forward_alerts(&app, &response);
+
+    Ok(response)
+}
+
+fn bandwidth_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, AgentError> {
+    app.path().app_data_dir()
+        .map(|dir| dir.join(settings::BANDWIDTH_FILE))
+        .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))
+}
+
+/// Count an upload against the bandwidth counters, emitting
+/// `bandwidth-cap-reached` when it uses up the monthly cap
+fn record_upload_bytes(app: &tauri::AppHandle, bytes: u64) -> Result<(), AgentError> {
+    let today = chrono::Local::now().date_naive();
+    let bandwidth = app.state::<BandwidthMeter>();
+    let mut usage = bandwidth.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+
+    let cap_reached = usage.record(bytes, today);
+    // Losing a few counts is better than failing the upload
+    if let Err(e) = usage.save(&bandwidth_path(app)?) {
+        println!("[Rust] {}", e);
+    }
+
+    if cap_reached {
+        println!("[Rust] ⚠️ Monthly upload cap of {} bytes reached, pausing uploads",
+                 usage.monthly_cap_bytes.unwrap_or_default());
+        let _ = app.emit("bandwidth-cap-reached", usage.current(today));
+    }
+
+    Ok(())
+}
+
+/// Bytes uploaded today, this month and in total, with the monthly cap
+#[tauri::command]
+async fn get_bandwidth_usage(
+    bandwidth: State<'_, BandwidthMeter>,
+) -> Result<settings::BandwidthUsage, AgentError> {
+    Ok(bandwidth.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .current(chrono::Local::now().date_naive()))
+}
+
+/// Set or clear (None) the monthly upload cap
+#[tauri::command]
+async fn set_bandwidth_cap(
+    monthly_cap_bytes: Option<u64>,
+    app: tauri::AppHandle,
+    bandwidth: State<'_, BandwidthMeter>,
+) -> Result<(), AgentError> {
+    println!("[Rust] Monthly upload cap set to {:?} bytes", monthly_cap_bytes);
+
+    let mut usage = bandwidth.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+    usage.monthly_cap_bytes = monthly_cap_bytes;
+    usage.save(&bandwidth_path(&app)?)
+}
+
+/// Feed a detection outcome to the circuit breaker, emitting `backend-down`
+/// when it opens
+fn record_backend_result<T>(
+    app: &tauri::AppHandle,
+    breaker: &Mutex<monitoring::CircuitBreaker>,
+    result: &Result<T, AgentError>,
+) -> Result<(), AgentError> {
+    let now = std::time::Instant::now();
+    let mut breaker = breaker.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+
+    match result {
+        Err(AgentError::Network(_) | AgentError::Timeout(_) | AgentError::Backend(_)) => {
+            if breaker.record_failure(now) {
+                let status = breaker.status(now);
+                println!(
+                    "[Rust] Backend down after {} consecutive failures, pausing detection for {}s",
+                    status.consecutive_failures,
+                    status.retry_in_secs.unwrap_or_default()
+                );
+                let _ = app.emit("backend-down", status);
+            }
+        }
+        // The backend answered, even if it rejected the request
+        _ => {
+            if breaker.record_success() {
+                println!("[Rust] Backend reachable again, resuming detection");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Circuit breaker state for detection requests
+#[tauri::command]
+async fn get_backend_breaker(
+    breaker: State<'_, BackendBreaker>,
+) -> Result<monitoring::BreakerStatus, AgentError> {
+    Ok(breaker.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .status(std::time::Instant::now()))
+}
+
+/// Pick a sleep of `interval ± jitter`, never shorter than one second
+fn jittered_interval(interval_secs: u64, jitter_secs: u64) -> std::time::Duration {
+    use rand::Rng;
+
+    let interval_ms = interval_secs as i64 * 1000;
+    let jitter_ms = jitter_secs.min(interval_secs) as i64 * 1000;
+    let offset_ms = if jitter_ms > 0 {
+        rand::thread_rng().gen_range(-jitter_ms..=jitter_ms)
+    } else {
+        0
+    };
+
+    std::time::Duration::from_millis((interval_ms + offset_ms).max(1000) as u64)
+}
+
+/// Delay before a camera's next periodic cycle
+///
+/// `active` is whether the last cycle saw detections, or None if it didn't
+/// complete. Without an adaptive schedule this is the fixed jittered interval.
+fn next_delay(
+    app: &tauri::AppHandle,
+    camera_id: &str,
+    active: Option<bool>,
+    interval_secs: u64,
+    jitter_secs: u64,
+) -> std::time::Duration {
+    let schedules = app.state::<AdaptiveSchedules>();
+    let mut schedules = match schedules.lock() {
+        Ok(schedules) => schedules,
+        Err(_) => return jittered_interval(interval_secs, jitter_secs),
+    };
+
+    match (schedules.get_mut(camera_id), active) {
+        (Some(schedule), Some(active)) => schedule.next_interval(active),
+        (Some(schedule), None) => schedule.current(),
+        (None, _) => jittered_interval(interval_secs, jitter_secs),
+    }
+}
+
+/// Capture `count` frames `spacing` apart; a capture failure ends the burst
+/// early unless it is the first frame
+///
+/// A frame identical to the one before it is dropped: a persistent stream
+/// slower than the spacing hands back the same buffered JPEG, and detecting
+/// on it again only costs a request.
+async fn burst_frames(
+    cameras: &CameraMap,
+    metrics: &metrics::Metrics,
+    camera_id: &str,
+    count: u32,
+    spacing: std::time::Duration,
+) -> Result<Vec<Vec<u8>>, AgentError> {
+    let mut frames = vec![latest_frame(cameras, metrics, camera_id).await?];
+    for _ in 1..count {
+        tokio::time::sleep(spacing).await;
+        match latest_frame(cameras, metrics, camera_id).await {
+            Ok(frame_bytes) if frames.last() == Some(&frame_bytes) => {
+                println!("[Rust] Burst for {} dropped a repeated frame", camera_id);
+            }
+            Ok(frame_bytes) => frames.push(frame_bytes),
+            Err(e) => {
+                println!("[Rust] Burst for {} cut short at {} frames: {}", camera_id, frames.len(), e);
+                break;
+            }
+        }
+    }
+    Ok(frames)
+}
+
+/// Burst size and spacing: the given values, else the detection settings
+fn burst_settings(
+    app: &tauri::AppHandle,
+    count: Option<u32>,
+    spacing_ms: Option<u64>,
+) -> Result<(u32, std::time::Duration), AgentError> {
+    let detection = snapshot(&app.state::<DetectionConfig>())?;
+    let count = count.unwrap_or(detection.burst_count);
+    if !(1..=settings::MAX_BURST_FRAMES).contains(&count) {
+        return Err(AgentError::InvalidInput(format!(
+            "Burst count must be between 1 and {}, got {}", settings::MAX_BURST_FRAMES, count
+        )));
+    }
+    let spacing_ms = spacing_ms.unwrap_or(detection.burst_spacing_ms);
+    Ok((count, std::time::Duration::from_millis(spacing_ms)))
+}
+
+/// One periodic cycle: grab the latest frame, run detection, cache the result
+async fn periodic_cycle(
+    app: &tauri::AppHandle,
+    cameras: &CameraMap,
+    cache: &FrameCache,
+    camera_id: &str,
+    backend_url: &str,
+    api_key: &str,
+) -> Result<api::DetectionResponse, AgentError> {
+    let (count, spacing) = burst_settings(app, None, None)?;
+    detect_and_cache(app, cameras, cache, camera_id, count, spacing, backend_url, api_key).await
+}
+
+/// Capture a burst from the camera, run detection on it and cache the result
+#[allow(clippy::too_many_arguments)]
+async fn detect_and_cache(
+    app: &tauri::AppHandle,
+    cameras: &CameraMap,
+    cache: &FrameCache,
+    camera_id: &str,
+    count: u32,
+    spacing: std::time::Duration,
+    backend_url: &str,
+    api_key: &str,
+) -> Result<api::DetectionResponse, AgentError> {
+    ensure_in_schedule(app)?;
+    ensure_not_paused(cameras, camera_id).await?;
+
+    let metrics = app.state::<SharedMetrics>();
+    let frames = burst_frames(cameras, &metrics, camera_id, count, spacing).await?;
+    let captured_at = std::time::SystemTime::now();
+    check_frozen(app, cameras, camera_id, &frames[0]).await;
+
+    let (mut response, frame_bytes) = run_detection(
+        app,
+        camera_id,
+        &frames,
+        captured_at,
+        backend_url,
+        api_key,
+    ).await?;
+    response.local_timestamp = Some(local_timestamp(captured_at));
+
+    use base64::{Engine as _, engine::general_purpose};
+    cache.write().await
+        .insert(camera_id.to_string(), CachedData {
+            frame: general_purpose::STANDARD.encode(&frame_bytes),
+            detections: response.clone(),
+            timestamp: captured_at,
+        });
+    record_detection_sample(app, camera_id, captured_at, &response)?;
+
+    Ok(response)
+}
+
+/// Add a cached detection result to the camera's chart history
+fn record_detection_sample(
+    app: &tauri::AppHandle,
+    camera_id: &str,
+    captured_at: std::time::SystemTime,
+    response: &api::DetectionResponse,
+) -> Result<(), AgentError> {
+    app.state::<DetectionHistories>().lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .entry(camera_id.to_string())
+        .or_default()
+        .record(captured_at, response.detections.len(), response.alerts.len());
+    Ok(())
+}
+
+/// Detection and alert counts over the last `window_secs`, in
+/// `bucket_secs`-long buckets, oldest first
+#[tauri::command]
+async fn get_detection_timeseries(
+    camera_id: String,
+    window_secs: u64,
+    bucket_secs: u64,
+    histories: State<'_, DetectionHistories>,
+) -> Result<Vec<monitoring::DetectionBucket>, AgentError> {
+    if window_secs == 0 || bucket_secs == 0 {
+        return Err(AgentError::InvalidInput("Window and bucket must be at least 1 second".to_string()));
+    }
+    if window_secs.div_ceil(bucket_secs) > monitoring::MAX_TIMESERIES_BUCKETS {
+        return Err(AgentError::InvalidInput(format!(
+            "At most {} buckets per request; use larger buckets", monitoring::MAX_TIMESERIES_BUCKETS
+        )));
+    }
+
+    let now = std::time::SystemTime::now();
+    let window = std::time::Duration::from_secs(window_secs);
+    let bucket = std::time::Duration::from_secs(bucket_secs);
+    let histories = histories.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+
+    Ok(match histories.get(&camera_id) {
+        Some(history) => history.buckets(now, window, bucket),
+        None => monitoring::DetectionHistory::default().buckets(now, window, bucket),
+    })
+}
+
+/// Capture `count` frames `spacing_ms` apart, base64 encoded; both default
+/// to the detection settings
+#[tauri::command]
+async fn capture_burst(
+    camera_id: String,
+    count: Option<u32>,
+    spacing_ms: Option<u64>,
+    app: tauri::AppHandle,
+    cameras: State<'_, CameraMap>,
+    metrics: State<'_, SharedMetrics>,
+) -> Result<Vec<String>, AgentError> {
+    let (count, spacing) = burst_settings(&app, count, spacing_ms)?;
+    let frames = burst_frames(&cameras, &metrics, &camera_id, count, spacing).await?;
+
+    use base64::{Engine as _, engine::general_purpose};
+    Ok(frames.iter().map(|frame_bytes| general_purpose::STANDARD.encode(frame_bytes)).collect())
+}
+
+/// Capture a burst and detect on all of it, merging the results so objects
+/// seen in any frame are reported once at their best confidence
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn detect_burst(
+    camera_id: String,
+    count: Option<u32>,
+    spacing_ms: Option<u64>,
+    api_key: String,
+    backend_url: String,
+    app: tauri::AppHandle,
+    cameras: State<'_, CameraMap>,
+    cache: State<'_, FrameCache>,
+) -> Result<api::DetectionResponse, AgentError> {
+    let (count, spacing) = burst_settings(&app, count, spacing_ms)?;
+    println!("[Rust] Burst detection for {}: {} frames {}ms apart", camera_id, count, spacing.as_millis());
+
+    let response = detect_and_cache(&app, &cameras, &cache, &camera_id, count, spacing, &backend_url, &api_key).await?;
+    forward_alerts(&app, &response);
+    Ok(response)
+}
+
+/// Set how many frames each detection cycle captures, and how far apart
+#[tauri::command]
+async fn set_burst_capture(
+    count: u32,
+    spacing_ms: u64,
+    app: tauri::AppHandle,
+) -> Result<(), AgentError> {
+    patch_detection_settings(&app, &settings::DetectionSettingsPatch {
+        burst_count: Some(count),
+        burst_spacing_ms: Some(spacing_ms),
+        ..Default::default()
+    })?;
+    Ok(())
+}
+
+#[tauri::command]
+async fn start_periodic_capture(
+    camera_id: String,
+    interval_secs: u64,
+    jitter_secs: u64,
+    api_key: String,
+    backend_url: String,
+    app: tauri::AppHandle,
+    periodic: State<'_, PeriodicTasks>,
+) -> Result<(), AgentError> {
+    if interval_secs == 0 {
+        return Err(AgentError::InvalidInput("Interval must be at least 1 second".to_string()));
+    }
+
+    println!("[Rust] Starting periodic capture for {}: every {}s ± {}s",
+             camera_id, interval_secs, jitter_secs);
+
+    let task = spawn_periodic_task(&app, camera_id.clone(), interval_secs, jitter_secs, api_key, backend_url);
+
+    // Replace any schedule already running for this camera
+    if let Some(previous) = periodic.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .insert(camera_id, task)
+    {
+        previous.handle.abort();
+    }
+
+    Ok(())
+}
+
+/// Spawn the capture loop behind `start_periodic_capture`, with fresh stats
+fn spawn_periodic_task(
+    app: &tauri::AppHandle,
+    camera_id: String,
+    interval_secs: u64,
+    jitter_secs: u64,
+    api_key: String,
+    backend_url: String,
+) -> PeriodicTask {
+    let app = app.clone();
+    let cameras = app.state::<CameraMap>().inner().clone();
+    let cache = app.state::<FrameCache>().inner().clone();
+    let stats = Arc::new(Mutex::new(monitoring::TaskStats::default()));
+    let task_stats = stats.clone();
+    let task_camera_id = camera_id;
+    let (task_api_key, task_backend_url) = (api_key.clone(), backend_url.clone());
+
+    let handle = tauri::async_runtime::spawn(async move {
+        let (api_key, backend_url) = (task_api_key, task_backend_url);
+        let mut delay = next_delay(&app, &task_camera_id, None, interval_secs, jitter_secs);
+        loop {
+            if let Ok(mut stats) = task_stats.lock() {
+                stats.expect_progress_within(delay);
+            }
+            tokio::time::sleep(delay).await;
+
+            if app.state::<ShutdownFlag>().load(std::sync::atomic::Ordering::SeqCst) {
+                break;
+            }
+
+            let outcome = periodic_cycle(&app, &cameras, &cache, &task_camera_id, &backend_url, &api_key).await;
+            let active = outcome.as_ref().ok().map(|response| !response.detections.is_empty());
+
+            match outcome {
+                Ok(response) => {
+                    println!("[Periodic] {}: {} detections, {} alerts",
+                             task_camera_id, response.detections.len(), response.alerts.len());
+                    if let Ok(mut stats) = task_stats.lock() {
+                        stats.record_success(response.detections.len());
+                    }
+                    forward_alerts(&app, &response);
+
+                    // The cache always has the latest; only meaningful changes are emitted
+                    let changed = app.state::<DetectionDeduper>()
+                        .lock()
+                        .map(|mut dedup| dedup.is_new(&response))
+                        .unwrap_or(true);
+                    if changed {
+                        let _ = app.emit("periodic-detection", response);
+                    }
+                }
+                Err(AgentError::Paused(_)) => {}
+                // Waiting out the backend's Retry-After, which was logged when it started
+                Err(AgentError::RateLimited(_)) => {}
+                Err(e) => {
+                    println!("[Periodic] {}: cycle failed: {}", task_camera_id, e);
+                    if let Ok(mut stats) = task_stats.lock() {
+                        stats.record_failure();
+                    }
+                }
+            }
+
+            delay = next_delay(&app, &task_camera_id, active, interval_secs, jitter_secs);
+        }
+    });
+
+    PeriodicTask { handle, interval_secs, jitter_secs, api_key, backend_url, stats, restarts: 0 }
+}
+
+/// Restart periodic captures that died or hung, until shutdown
+///
+/// A task that panicked or is stuck in a cycle stops moving its progress
+/// deadline, so both show up the same way. A poisoned stats lock means the
+/// task panicked while holding it.
+async fn supervise_periodic_tasks(app: tauri::AppHandle) {
+    loop {
+        tokio::time::sleep(monitoring::WATCHDOG_INTERVAL).await;
+
+        if app.state::<ShutdownFlag>().load(std::sync::atomic::Ordering::SeqCst) {
+            break;
+        }
+
+        let periodic = app.state::<PeriodicTasks>();
+        let Ok(mut tasks) = periodic.lock() else { continue };
+        let now = std::time::Instant::now();
+        let stuck: Vec<String> = tasks.iter()
+            .filter(|(_, task)| task.stats.lock().map(|stats| stats.is_stuck(now)).unwrap_or(true))
+            .map(|(camera_id, _)| camera_id.clone())
+            .collect();
+
+        for camera_id in stuck {
+            let Some(old) = tasks.remove(&camera_id) else { continue };
+            println!("[Watchdog] Periodic capture for {} made no progress; restarting (restart #{})",
+                     camera_id, old.restarts + 1);
+            old.handle.abort();
+
+            let mut task = spawn_periodic_task(
+                &app,
+                camera_id.clone(),
+                old.interval_secs,
+                old.jitter_secs,
+                old.api_key,
+                old.backend_url,
+            );
+            task.restarts = old.restarts + 1;
+            tasks.insert(camera_id, task);
+        }
+    }
+}
+
+#[tauri::command]
+async fn stop_periodic_capture(
+    camera_id: String,
+    periodic: State<'_, PeriodicTasks>,
+) -> Result<(), AgentError> {
+    println!("[Rust] Stopping periodic capture for {}", camera_id);
+
+    let task = periodic.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .remove(&camera_id)
+        .ok_or_else(|| AgentError::NotFound(format!("No periodic capture running for camera: {}", camera_id)))?;
+
+    task.handle.abort();
+    Ok(())
+}
+
+/// Start periodic capture on every camera in a group with the same
+/// schedule; returns the cameras started
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn start_group_capture(
+    group: String,
+    interval_secs: u64,
+    jitter_secs: u64,
+    api_key: String,
+    backend_url: String,
+    app: tauri::AppHandle,
+    periodic: State<'_, PeriodicTasks>,
+    groups: State<'_, CameraGroups>,
+) -> Result<Vec<String>, AgentError> {
+    if interval_secs == 0 {
+        return Err(AgentError::InvalidInput("Interval must be at least 1 second".to_string()));
+    }
+    let members = group_cameras(&groups, &group)?;
+
+    println!("[Rust] Starting periodic capture for group {} ({} cameras): every {}s ± {}s",
+             group, members.len(), interval_secs, jitter_secs);
+
+    let mut tasks = periodic.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+    for camera_id in &members {
+        let task = spawn_periodic_task(&app, camera_id.clone(), interval_secs, jitter_secs, api_key.clone(), backend_url.clone());
+        if let Some(previous) = tasks.insert(camera_id.clone(), task) {
+            previous.handle.abort();
+        }
+    }
+
+    Ok(members)
+}
+
+/// Stop periodic capture on every camera in a group; returns the cameras
+/// that had one running
+#[tauri::command]
+async fn stop_group_capture(
+    group: String,
+    periodic: State<'_, PeriodicTasks>,
+    groups: State<'_, CameraGroups>,
+) -> Result<Vec<String>, AgentError> {
+    println!("[Rust] Stopping periodic capture for group {}", group);
+    let members = group_cameras(&groups, &group)?;
+
+    let mut tasks = periodic.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+    let stopped = members.into_iter()
+        .filter(|camera_id| match tasks.remove(camera_id) {
+            Some(task) => {
+                task.handle.abort();
+                true
+            }
+            None => false,
+        })
+        .collect();
+
+    Ok(stopped)
+}
+
+fn adaptive_schedule(
+    base: u64,
+    fast: u64,
+    slow: u64,
+    idle_cycles: u32,
+) -> Result<monitoring::AdaptiveSchedule, AgentError> {
+    if fast == 0 || !(fast <= base && base <= slow) {
+        return Err(AgentError::InvalidInput(format!(
+            "Intervals must satisfy 1 <= fast <= base <= slow, got fast={} base={} slow={}", fast, base, slow
+        )));
+    }
+    if idle_cycles == 0 {
+        return Err(AgentError::InvalidInput("idle_cycles must be at least 1".to_string()));
+    }
+    Ok(monitoring::AdaptiveSchedule::new(base, fast, slow, idle_cycles))
+}
+
+/// Sample a camera every `fast` seconds while it sees detections, backing off
+/// toward `slow` after `idle_cycles` quiet cycles
+#[tauri::command]
+async fn set_adaptive_capture(
+    camera_id: String,
+    base: u64,
+    fast: u64,
+    slow: u64,
+    idle_cycles: u32,
+    adaptive: State<'_, AdaptiveSchedules>,
+) -> Result<(), AgentError> {
+    let schedule = adaptive_schedule(base, fast, slow, idle_cycles)?;
+
+    println!("[Rust] Adaptive capture for {}: base {}s, fast {}s, slow {}s after {} idle cycles",
+             camera_id, base, fast, slow, idle_cycles);
+
+    adaptive.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .insert(camera_id, schedule);
+
+    Ok(())
+}
+
+/// Return a camera to the fixed interval its periodic capture was started with
+#[tauri::command]
+async fn clear_adaptive_capture(
+    camera_id: String,
+    adaptive: State<'_, AdaptiveSchedules>,
+) -> Result<(), AgentError> {
+    adaptive.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .remove(&camera_id);
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn list_monitoring_tasks(
+    cameras: State<'_, CameraMap>,
+    periodic: State<'_, PeriodicTasks>,
+    adaptive: State<'_, AdaptiveSchedules>,
+) -> Result<Vec<monitoring::MonitoringTaskStatus>, AgentError> {
+    let cameras_lock = cameras.read().await;
+    let periodic_lock = periodic.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+    let adaptive_lock = adaptive.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+
+    let now = std::time::Instant::now();
+    let mut tasks = Vec::with_capacity(periodic_lock.len());
+    for (camera_id, task) in periodic_lock.iter() {
+        let stats = task.stats.lock()
+            .map_err(|e| AgentError::Internal(format!("Stats lock error: {}", e)))?;
+
+        tasks.push(monitoring::MonitoringTaskStatus {
+            camera_id: camera_id.clone(),
+            paused: cameras_lock.get(camera_id).map(|handle| handle.paused).unwrap_or(false),
+            interval_secs: task.interval_secs,
+            jitter_secs: task.jitter_secs,
+            last_capture: stats.last_capture.map(|t| t.to_rfc3339()),
+            last_detection_count: stats.last_detection_count,
+            consecutive_failures: stats.consecutive_failures,
+            adaptive_interval_secs: adaptive_lock.get(camera_id).map(|schedule| schedule.current().as_secs()),
+            stuck: stats.is_stuck(now),
+            restarts: task.restarts,
+        });
+    }
+
+    tasks.sort_by(|a, b| a.camera_id.cmp(&b.camera_id));
+    Ok(tasks)
+}
+
+/// Drop a camera's cached frame and detections, and forget its last result
+/// so the next detection is emitted even if it matches the old one
+async fn clear_cached(cache: &FrameCache, dedup: &DetectionDeduper, camera_id: &str) -> Result<bool, AgentError> {
+    let removed = cache.write().await.remove(camera_id).is_some();
+    dedup.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .forget(camera_id);
+    Ok(removed)
+}
+
+/// Reset what the UI shows for a camera, e.g. after changing its ROI or source
+#[tauri::command]
+async fn clear_cache(
+    camera_id: String,
+    cache: State<'_, FrameCache>,
+    dedup: State<'_, DetectionDeduper>,
+) -> Result<(), AgentError> {
+    if clear_cached(&cache, &dedup, &camera_id).await? {
+        println!("[Rust] Cleared cached frame for camera: {}", camera_id);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn clear_all_cache(
+    cache: State<'_, FrameCache>,
+    dedup: State<'_, DetectionDeduper>,
+) -> Result<(), AgentError> {
+    let camera_ids: Vec<String> = cache.read().await.keys().cloned().collect();
+    for camera_id in &camera_ids {
+        clear_cached(&cache, &dedup, camera_id).await?;
+    }
+    println!("[Rust] Cleared cached frames for {} camera(s)", camera_ids.len());
+    Ok(())
+}
+
+/// Last monitored frame; `data_url` returns it ready for `<img src>`
+#[tauri::command]
+async fn get_latest_frame(
+    camera_id: String,
+    data_url: Option<bool>,
+    cache: State<'_, FrameCache>,
+) -> Result<String, AgentError> {
+    println!("[Rust] get_latest_frame called for camera: {}", camera_id);
+
+    let result = cache.read().await
+        .get(&camera_id)
+        .map(|cached| match data_url {
+            Some(true) => frame::data_url(frame::CAPTURE_FORMAT, &cached.frame),
+            _ => cached.frame.clone(),
+        })
+        .ok_or_else(|| AgentError::NotFound(format!("No cached frame for camera: {}", camera_id)));
+
+    match &result {
+        Ok(frame) => println!("[Rust] Returning cached frame, length: {}", frame.len()),
+        Err(e) => println!("[Rust] Error getting frame: {}", e),
+    }
+
+    result
+}
+
+#[tauri::command]
+async fn get_latest_detections(
+    camera_id: String,
+    cache: State<'_, FrameCache>,
+) -> Result<api::DetectionResponse, AgentError> {
+    println!("[Rust] get_latest_detections called for camera: {}", camera_id);
+
+    let result = cache.read().await
+        .get(&camera_id)
+        .map(|cached| cached.detections.clone())
+        .ok_or_else(|| AgentError::NotFound(format!("No cached detections for camera: {}", camera_id)));
+
+    match &result {
+        Ok(detections) => println!("[Rust] Returning cached detections: {} detections, {} alerts",
+                                    detections.detections.len(), detections.alerts.len()),
+        Err(e) => println!("[Rust] Error getting detections: {}", e),
+    }
+
+    result
+}
+
+#[tauri::command]
+async fn generate_incident_report(
+    camera_id: String,
+    app: tauri::AppHandle,
+    cache: State<'_, FrameCache>,
+    labels: State<'_, Labels>,
+) -> Result<String, AgentError> {
+    println!("[Rust] Generating incident report for camera: {}", camera_id);
+
+    let cached = cache.read().await
+        .get(&camera_id)
+        .cloned()
+        .ok_or_else(|| AgentError::NotFound(format!("No cached frame for camera: {}", camera_id)))?;
+
+    use base64::{Engine as _, engine::general_purpose};
+    let frame_bytes = general_purpose::STANDARD.decode(&cached.frame)
+        .map_err(|e| AgentError::Decode(format!("Base64 decode error: {}", e)))?;
+    let captured_at = chrono::DateTime::<chrono::Local>::from(cached.timestamp);
+    let labels = snapshot(&labels)?;
+    // Kept as reports/<camera>/<capture time>.png, where retention sweeps them
+    let path = app.path().app_data_dir()
+        .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))
+        .map(|dir| recording::camera_dir(&dir.join(report::REPORTS_DIR), &camera_id)
+            .join(format!("{}.png", captured_at.format("%Y%m%d-%H%M%S"))))?;
+
+    let png = tokio::task::spawn_blocking(move || {
+        let png = report::render_incident_report(&camera_id, &frame_bytes, &cached.detections, captured_at, &labels)?;
+        let saved = path.parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, &png));
+        if let Err(e) = saved {
+            println!("[Rust] Failed to save incident report {:?}: {}", path, e);
+        }
+        Ok::<_, AgentError>(png)
+    })
+    .await
+    .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))??;
+
+    Ok(general_purpose::STANDARD.encode(&png))
+}
+
+/// Save every camera's latest cached frame and detections as one ZIP,
+/// asking where to save it when no path is given. Returns the saved path,
+/// or None if the save dialog was cancelled.
+#[tauri::command]
+async fn export_snapshot_bundle(
+    path: Option<String>,
+    app: tauri::AppHandle,
+    cache: State<'_, FrameCache>,
+) -> Result<Option<String>, AgentError> {
+    use base64::{Engine as _, engine::general_purpose};
+    let mut cameras = cache.read().await
+        .iter()
+        .map(|(camera_id, cached)| {
+            let frame = general_purpose::STANDARD.decode(&cached.frame)
+                .map_err(|e| AgentError::Decode(format!("Base64 decode error for {}: {}", camera_id, e)))?;
+            Ok(report::CameraSnapshot {
+                camera_id: camera_id.clone(),
+                frame,
+                response: cached.detections.clone(),
+                captured_at: chrono::DateTime::<chrono::Local>::from(cached.timestamp),
+            })
+        })
+        .collect::<Result<Vec<_>, AgentError>>()?;
+    if cameras.is_empty() {
+        return Err(AgentError::NotFound("No cached frames to export".to_string()));
+    }
+    cameras.sort_by(|a, b| a.camera_id.cmp(&b.camera_id));
+
+    let labels = snapshot(&app.state::<Labels>())?;
+    let exported_at = chrono::Local::now();
+    let path = match path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => {
+            use tauri_plugin_dialog::DialogExt;
+            let file_name = format!("snapshot-{}.zip", exported_at.format("%Y%m%d-%H%M%S"));
+            let chosen = tokio::task::spawn_blocking(move || {
+                app.dialog().file()
+                    .add_filter("ZIP archive", &["zip"])
+                    .set_file_name(file_name)
+                    .blocking_save_file()
+            })
+            .await
+            .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))?;
+
+            let Some(chosen) = chosen else {
+                println!("[Rust] Snapshot bundle export cancelled");
+                return Ok(None);
+            };
+            chosen.into_path()
+                .map_err(|e| AgentError::InvalidInput(format!("Invalid save location: {}", e)))?
+        }
+    };
+
+    let camera_count = cameras.len();
+    let bundle = tokio::task::spawn_blocking(move || report::snapshot_bundle(&cameras, exported_at, &labels))
+        .await
+        .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))??;
+    std::fs::write(&path, &bundle)
+        .map_err(|e| AgentError::Internal(format!("Failed to write {}: {}", path.display(), e)))?;
+
+    println!("[Rust] Exported snapshot bundle of {} camera(s) to {}", camera_count, path.display());
+    Ok(Some(path.display().to_string()))
+}
+
+#[tauri::command]
+async fn test_detection(
+    backend_url: String,
+    api_key: String,
+    upload_config: State<'_, UploadConfig>,
+    auth: State<'_, AuthConfig>,
+) -> Result<api::DetectionTestResult, AgentError> {
+    let upload = snapshot(&upload_config)?;
+    let format = upload.upload_format;
+
+    println!("[Rust] Testing detection endpoint at {} with {:?} upload", backend_url, format);
+
+    let result = api::test_detection_endpoint(&backend_url, &api_key, &auth_scheme(&auth)?, format, &upload.response).await?;
+
+    match &result.error {
+        None => println!("[Rust] ✅ Detection endpoint OK ({}ms)", result.latency_ms),
+        Some(e) => println!("[Rust] ✗ Detection endpoint test failed: {}", e),
+    }
+
+    Ok(result)
+}
+
+#[tauri::command]
+async fn frame_diff(
+    frame_a_base64: String,
+    frame_b_base64: String,
+) -> Result<f64, AgentError> {
+    use base64::{Engine as _, engine::general_purpose};
+    let frame_a = general_purpose::STANDARD.decode(&frame_a_base64)
+        .map_err(|e| AgentError::Decode(format!("Base64 decode error: {}", e)))?;
+    let frame_b = general_purpose::STANDARD.decode(&frame_b_base64)
+        .map_err(|e| AgentError::Decode(format!("Base64 decode error: {}", e)))?;
+
+    // Decoding and scaling is CPU-bound; keep it off the async runtime
+    tokio::task::spawn_blocking(move || frame::frame_diff(&frame_a, &frame_b))
+        .await
+        .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))?
+}
+
+fn privacy_masks_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, AgentError> {
+    app.path().app_data_dir()
+        .map(|dir| dir.join(privacy::PRIVACY_MASKS_FILE))
+        .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))
+}
+
+/// Replace a camera's privacy masks; frames are blurred (or filled) in these
+/// normalized polygons before any upload, and detections inside them dropped.
+/// An empty list removes the masks.
+#[tauri::command]
+async fn set_privacy_masks(
+    camera_id: String,
+    polygons: Vec<Vec<[f64; 2]>>,
+    style: Option<privacy::MaskStyle>,
+    app: tauri::AppHandle,
+    masks: State<'_, PrivacyMaskConfig>,
+) -> Result<(), AgentError> {
+    let privacy = privacy::CameraPrivacy { polygons, style: style.unwrap_or_default() };
+    privacy.validate()?;
+
+    println!("[Rust] Privacy masks for {}: {} polygon(s), {:?}", camera_id, privacy.polygons.len(), privacy.style);
+
+    let mut masks = masks.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+    let mut updated = masks.clone();
+    updated.set(&camera_id, privacy);
+    updated.save(&privacy_masks_path(&app)?)?;
+    *masks = updated;
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_privacy_masks(
+    camera_id: String,
+    masks: State<'_, PrivacyMaskConfig>,
+) -> Result<privacy::CameraPrivacy, AgentError> {
+    Ok(snapshot(&masks)?.get(&camera_id).cloned().unwrap_or_default())
+}
+
+fn zone_notify_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, AgentError> {
+    app.path().app_data_dir()
+        .map(|dir| dir.join(zones::ZONE_NOTIFY_FILE))
+        .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))
+}
+
+/// Change zone notification policies and save them
+fn update_zone_notify(
+    app: &tauri::AppHandle,
+    update: impl FnOnce(&mut zones::ZoneNotifyPolicies),
+) -> Result<(), AgentError> {
+    let state = app.state::<ZoneNotify>();
+    let mut policies = state.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+    update(&mut policies);
+    policies.save(&zone_notify_path(app)?)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn create_zone(
+    camera_id: String,
+    zone_name: String,
+    coordinates: Vec<[f64; 2]>,
+    alert_type: String,
+    notify: Option<zones::NotifyPolicy>,
+    api_key: String,
+    backend_url: String,
+    app: tauri::AppHandle,
+) -> Result<api::ZoneResponse, AgentError> {
+    println!("[Rust] Creating zone for camera: {}", camera_id);
+
+    let validation = zones::validate_polygon(&coordinates)?;
+    for warning in &validation.warnings {
+        println!("[Rust] Zone '{}': {}", zone_name, warning);
+    }
+
+    let mut zone = api::create_zone(
+        &backend_url,
+        &camera_id,
+        &zone_name,
+        &coordinates,
+        &alert_type,
+        &api_key,
+        &auth_scheme(&app.state::<AuthConfig>())?,
+    ).await?;
+
+    zone.notify = notify.unwrap_or_default();
+    update_zone_notify(&app, |policies| policies.set(&camera_id, zone.id, zone.notify))?;
+    Ok(zone)
+}
+
+/// Route a zone's alerts to notifications, the webhook, both or neither
+#[tauri::command]
+async fn set_zone_notify(
+    camera_id: String,
+    zone_id: i64,
+    notify: zones::NotifyPolicy,
+    app: tauri::AppHandle,
+) -> Result<(), AgentError> {
+    println!("[Rust] Zone {} on {} notifies via {:?}", zone_id, camera_id, notify);
+    update_zone_notify(&app, |policies| policies.set(&camera_id, zone_id, notify))
+}
+
+/// Create the same zones on several cameras, reporting each camera's outcome
+#[tauri::command]
+async fn apply_zone_template(
+    camera_ids: Vec<String>,
+    zones: Vec<zones::ZoneTemplate>,
+    api_key: String,
+    backend_url: String,
+    app: tauri::AppHandle,
+) -> Result<HashMap<String, zones::TemplateResult>, AgentError> {
+    println!("[Rust] Applying {} zone(s) to {} camera(s)", zones.len(), camera_ids.len());
+    let auth = auth_scheme(&app.state::<AuthConfig>())?;
+    let results = zones::apply_template(&backend_url, &api_key, &auth, camera_ids, zones).await?;
+
+    update_zone_notify(&app, |policies| {
+        for zone in results.values().flat_map(|result| &result.created) {
+            policies.set(&zone.camera_id, zone.id, zone.notify);
+        }
+    })?;
+    Ok(results)
+}
+
+/// Send a frame straight to one backend, bypassing the sink, cache, alerts
+/// and evidence that monitoring captures go through
+async fn detect_one_off(
+    app: &tauri::AppHandle,
+    camera_id: &str,
+    frame_bytes: Vec<u8>,
+    backend_url: &str,
+    api_key: &str,
+) -> Result<api::DetectionResponse, AgentError> {
+    let upload = snapshot(&app.state::<UploadConfig>())?;
+    let format = upload.upload_format;
+    let max_frame_bytes = upload.max_frame_bytes;
+    let privacy = snapshot(&app.state::<PrivacyMaskConfig>())?.get(camera_id).cloned();
+    let masks = privacy.clone();
+    let prepare = move || {
+        let frame_bytes = match &masks {
+            Some(masks) => masks.apply(&frame_bytes)?,
+            None => frame_bytes,
+        };
+        let dimensions = frame::frame_dimensions(&frame_bytes)?;
+        Ok::<_, AgentError>((frame::encode_for_upload(frame_bytes, format, max_frame_bytes)?, dimensions))
+    };
+    let (upload_bytes, dimensions) = tokio::task::spawn_blocking(prepare)
+        .await
+        .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))??;
+
+    let metadata = frame_metadata(&app.state::<FrameSequences>(), upload.detection_api, camera_id, std::time::SystemTime::now())?;
+    let backend = [api::BackendConfig { url: backend_url.to_string(), api_key: api_key.to_string() }];
+    let mut response = api::send_detection_with_failover(
+        &backend, &auth_scheme(&app.state::<AuthConfig>())?, camera_id, &upload_bytes, None, format, metadata.as_ref(),
+        &upload.response,
+    ).await?;
+    if let Some(privacy) = &privacy {
+        privacy.suppress(&mut response.detections, dimensions);
+    }
+    Ok(response)
+}
+
+/// Time a fresh capture plus a detection round trip through `backend_url`
+///
+/// Uses the backend's reported inference time when it has one, otherwise
+/// estimates it from a health check. The result also feeds `metrics`.
+#[tauri::command]
+async fn measure_e2e_latency(
+    camera_id: String,
+    api_key: String,
+    backend_url: String,
+    app: tauri::AppHandle,
+    cameras: State<'_, CameraMap>,
+    metrics: State<'_, SharedMetrics>,
+) -> Result<metrics::E2eLatency, AgentError> {
+    let started = std::time::Instant::now();
+    let frame_bytes = latest_frame(&cameras, &metrics, &camera_id).await?;
+    let capture = started.elapsed();
+
+    let started = std::time::Instant::now();
+    let response = detect_one_off(&app, &camera_id, frame_bytes, &backend_url, &api_key).await?;
+    let round_trip = started.elapsed();
+
+    let baseline_rtt = match response.inference_ms {
+        Some(_) => None,
+        None => {
+            let started = std::time::Instant::now();
+            api::get_health(&backend_url).await.ok().map(|_| started.elapsed())
+        }
+    };
+
+    let latency = metrics::E2eLatency::new(capture, round_trip, response.inference_ms, baseline_rtt);
+    println!("[Rust] End-to-end latency for {}: {:.0}ms (capture {:.0}ms, upload {:.0}ms, inference {:.0}ms{})",
+             camera_id, latency.total_ms, latency.capture_ms, latency.upload_ms, latency.inference_ms,
+             if latency.inference_estimated { " estimated" } else { "" });
+
+    metrics.record_e2e_latency(&latency);
+    Ok(latency)
+}
+
+/// Result of `detect_image`
+#[derive(serde::Serialize)]
+struct ImageDetection {
+    response: api::DetectionResponse,
+    /// Base64 JPEG with detection boxes drawn, when requested
+    annotated: Option<String>,
+}
+
+/// Run detection on an image from outside the camera pipeline, given either
+/// as base64 or as a file path. Nothing is cached, alerted on or saved.
+#[tauri::command]
+async fn detect_image(
+    camera_id: String,
+    image_base64: Option<String>,
+    path: Option<String>,
+    api_key: String,
+    backend_url: String,
+    annotate: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<ImageDetection, AgentError> {
+    use base64::{Engine as _, engine::general_purpose};
+    let image_bytes = match (image_base64, path) {
+        (Some(image_base64), None) => general_purpose::STANDARD.decode(&image_base64)
+            .map_err(|e| AgentError::Decode(format!("Base64 decode error: {}", e)))?,
+        (None, Some(path)) => tokio::fs::read(&path).await
+            .map_err(|e| AgentError::NotFound(format!("Failed to read {}: {}", path, e)))?,
+        _ => return Err(AgentError::InvalidInput("Give exactly one of image_base64 or path".to_string())),
+    };
+
+    println!("[Rust] Detecting on uploaded image ({} bytes) as camera {}", image_bytes.len(), camera_id);
+
+    let response = detect_one_off(&app, &camera_id, image_bytes.clone(), &backend_url, &api_key).await?;
+
+    let annotated = if annotate.unwrap_or(false) {
+        let detections = response.detections.clone();
+        let labels = snapshot(&app.state::<Labels>())?;
+        let id = camera_id.clone();
+        let annotated = tokio::task::spawn_blocking(move || {
+            frame::annotate(&image_bytes, &detections, &labels, &id, chrono::Local::now())
+        })
+            .await
+            .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))??;
+        Some(general_purpose::STANDARD.encode(&annotated))
+    } else {
+        None
+    };
+
+    Ok(ImageDetection { response, annotated })
+}
+
+/// Check which detections a zone would alert on without waiting for a live event
+///
+/// Uses the camera's latest cached detections, or runs `frame_base64` through
+/// the detection backend when given (without raising alerts or saving evidence).
+#[tauri::command]
+async fn test_zone(
+    camera_id: String,
+    coordinates: Vec<[f64; 2]>,
+    frame_base64: Option<String>,
+    api_key: Option<String>,
+    backend_url: Option<String>,
+    app: tauri::AppHandle,
+    cache: State<'_, FrameCache>,
+) -> Result<zones::ZoneTestResult, AgentError> {
+    zones::validate_polygon(&coordinates)?;
+
+    let detections = match frame_base64 {
+        Some(frame_base64) => {
+            let (Some(api_key), Some(backend_url)) = (api_key, backend_url) else {
+                return Err(AgentError::InvalidInput("api_key and backend_url are required to detect on a frame".to_string()));
+            };
+            use base64::{Engine as _, engine::general_purpose};
+            let frame_bytes = general_purpose::STANDARD.decode(&frame_base64)
+                .map_err(|e| AgentError::Decode(format!("Base64 decode error: {}", e)))?;
+
+            detect_one_off(&app, &camera_id, frame_bytes, &backend_url, &api_key)
+                .await?
+                .detections
+        }
+        None => cache.read().await
+            .get(&camera_id)
+            .map(|cached| cached.detections.detections.clone())
+            .ok_or_else(|| AgentError::NotFound(format!("No cached detections for camera: {}", camera_id)))?,
+    };
+
+    let result = zones::test_zone(&coordinates, &detections);
+    println!("[Rust] Zone test for {}: {} of {} detection(s) inside", camera_id, result.hits.len(), result.total_detections);
+    Ok(result)
+}
+
+#[tauri::command]
+async fn validate_zone(coordinates: Vec<[f64; 2]>) -> Result<zones::ZoneValidation, AgentError> {
+    zones::validate_polygon(&coordinates)
+}
+
+#[tauri::command]
+async fn get_zones(
+    camera_id: String,
+    api_key: String,
+    backend_url: String,
+    auth: State<'_, AuthConfig>,
+    zone_notify: State<'_, ZoneNotify>,
+) -> Result<Vec<api::ZoneResponse>, AgentError> {
+    let mut zones = api::get_zones(&backend_url, &camera_id, &api_key, &auth_scheme(&auth)?).await?;
+    zone_notify.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .annotate(&mut zones);
+    Ok(zones)
+}
+
+#[tauri::command]
+async fn delete_zone(
+    camera_id: String,
+    zone_id: i64,
+    api_key: String,
+    backend_url: String,
+    app: tauri::AppHandle,
+) -> Result<(), AgentError> {
+    println!("[Rust] Deleting zone {} for camera: {}", zone_id, camera_id);
+    api::delete_zone(&backend_url, &camera_id, zone_id, &api_key, &auth_scheme(&app.state::<AuthConfig>())?).await?;
+    update_zone_notify(&app, |policies| policies.remove(&camera_id, zone_id))
+}
+
+/// Agent, ffmpeg and backend versions with a compatibility verdict, for support
+///
+/// Checks `backend_url`, or the first configured backend if none is given.
+#[tauri::command]
+async fn version_info(
+    backend_url: Option<String>,
+    backends: State<'_, BackendList>,
+) -> Result<version::VersionInfo, AgentError> {
+    let mut notes = Vec::new();
+
+    let ffmpeg_version = camera::ffmpeg_version().await;
+    if ffmpeg_version.is_none() {
+        notes.push("ffmpeg was not found or could not be run".to_string());
+    }
+
+    let backend_url = backend_url.or_else(|| snapshot(&backends).ok()?.first().map(|backend| backend.url.clone()));
+    let health = match &backend_url {
+        Some(url) => match api::get_health(url).await {
+            Ok(health) => Some(health),
+            Err(e) => {
+                notes.push(format!("Backend {} unreachable: {}", url, e));
+                None
+            }
+        },
+        None => {
+            notes.push("No backend configured".to_string());
+            None
+        }
+    };
+
+    let backend_version = health.as_ref().and_then(|health| health.version.clone());
+    let compatibility = match &backend_version {
+        Some(backend_version) => {
+            let (compatibility, note) = version::check_compatibility(version::AGENT_VERSION, backend_version);
+            notes.extend(note);
+            compatibility
+        }
+        None => {
+            if health.is_some() {
+                notes.push("Backend does not report its version; it predates this agent".to_string());
+            }
+            version::Compatibility::Warn
+        }
+    };
+
+    println!("[Rust] Agent {}, ffmpeg {:?}, backend {:?}: {:?}",
+        version::AGENT_VERSION, ffmpeg_version, backend_version, compatibility);
+
+    Ok(version::VersionInfo {
+        agent_version: version::AGENT_VERSION.to_string(),
+        ffmpeg_version,
+        backend_version,
+        backend_status: health.map(|health| health.status),
+        compatibility,
+        notes,
+    })
+}
+
+#[tauri::command]
+async fn metrics(
+    metrics: State<'_, SharedMetrics>,
+) -> Result<String, AgentError> {
+    Ok(metrics.render())
+}
+
+/// CPU, memory, ffmpeg processes and runtime tasks of the running agent
+#[tauri::command]
+async fn resource_stats(
+    sampler: State<'_, ResourceSampler>,
+    cameras: State<'_, CameraMap>,
+    recordings: State<'_, Recordings>,
+) -> Result<metrics::ResourceStats, AgentError> {
+    let sampler = sampler.inner().clone();
+    let process = tokio::task::spawn_blocking(move || {
+        sampler.lock()
+            .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+            .sample()
+    })
+    .await
+    .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))??;
+
+    let persistent_captures = cameras.read().await
+        .values()
+        .filter(|handle| handle.persistent_capture.is_some())
+        .count();
+    let recordings = recordings.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .len();
+    let runtime = tokio::runtime::Handle::current().metrics();
+
+    Ok(metrics::ResourceStats {
+        cpu_percent: process.cpu_percent,
+        memory_bytes: process.memory_bytes,
+        ffmpeg_processes: process.ffmpeg_processes,
+        persistent_captures,
+        recordings,
+        runtime_tasks: runtime.num_alive_tasks(),
+        runtime_workers: runtime.num_workers(),
+    })
+}
+
+/// Show a desktop notification, reporting why when it can't appear
+#[tauri::command]
+async fn show_notification(title: String, body: String, window: Window) -> Result<(), AgentError> {
+    notify::show_os_notification(window.app_handle(), &title, &body)
+}
+
+/// Plugins running in this build and whether notifications will show, so
+/// a missing grant is visible instead of features silently doing nothing
+#[derive(serde::Serialize)]
+struct Capabilities {
+    notification: bool,
+    shell: bool,
+    fs: bool,
+    dialog: bool,
+    /// `None` when the notification plugin isn't running
+    notification_permission: Option<tauri::plugin::PermissionState>,
+    notifications_permitted: bool,
+}
+
+#[tauri::command]
+async fn capabilities(app: tauri::AppHandle) -> Result<Capabilities, AgentError> {
+    let notification_permission = notify::notification_permission(&app)?;
+
+    Ok(Capabilities {
+        notification: notification_permission.is_some(),
+        shell: app.try_state::<tauri_plugin_shell::Shell<tauri::Wry>>().is_some(),
+        fs: app.try_state::<tauri_plugin_fs::Fs<tauri::Wry>>().is_some(),
+        dialog: app.try_state::<tauri_plugin_dialog::Dialog<tauri::Wry>>().is_some(),
+        notifications_permitted: matches!(notification_permission, Some(tauri::plugin::PermissionState::Granted)),
+        notification_permission,
+    })
+}
+
+#[tauri::command]
+async fn set_detection_sink(
+    sink: sink::DetectionSink,
+    sink_config: State<'_, SinkConfig>,
+) -> Result<(), AgentError> {
+    println!("[Rust] Detection sink set to {:?}", sink);
+
+    *sink_config.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))? = sink;
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_detection_sink(
+    sink_config: State<'_, SinkConfig>,
+) -> Result<sink::DetectionSink, AgentError> {
+    Ok(sink_config.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .clone())
+}
+
+#[tauri::command]
+async fn set_backends(
+    backends: Vec<api::BackendConfig>,
+    backend_list: State<'_, BackendList>,
+) -> Result<(), AgentError> {
+    if let Some(backend) = backends.iter()
+        .find(|b| !b.url.starts_with("http://") && !b.url.starts_with("https://"))
+    {
+        return Err(AgentError::InvalidInput(format!("Invalid backend URL: {}", backend.url)));
+    }
+
+    println!("[Rust] Detection backends set to {:?}",
+             backends.iter().map(|b| b.url.as_str()).collect::<Vec<_>>());
+
+    *backend_list.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))? = backends;
+
+    Ok(())
+}
+
+/// Catch a bad header name when the scheme is set rather than on every request
+fn validate_auth_scheme(scheme: &api::AuthScheme) -> Result<(), AgentError> {
+    if let api::AuthScheme::ApiKeyHeader(name) = scheme {
+        reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| AgentError::InvalidInput(format!("Invalid header name {:?}: {}", name, e)))?;
+    }
+    if let api::AuthScheme::QueryParam(name) = scheme {
+        if name.is_empty() {
+            return Err(AgentError::InvalidInput("Query parameter name must not be empty".to_string()));
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_auth_scheme(
+    scheme: api::AuthScheme,
+    auth: State<'_, AuthConfig>,
+) -> Result<(), AgentError> {
+    validate_auth_scheme(&scheme)?;
+
+    println!("[Rust] Backend auth scheme set to {:?}", scheme);
+
+    *auth.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))? = scheme;
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_max_frame_bytes(
+    max_frame_bytes: usize,
+    upload_config: State<'_, UploadConfig>,
+) -> Result<(), AgentError> {
+    if max_frame_bytes == 0 {
+        return Err(AgentError::InvalidInput("max_frame_bytes must be greater than zero".to_string()));
+    }
+
+    println!("[Rust] Max frame size set to {} bytes", max_frame_bytes);
+
+    upload_config.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .max_frame_bytes = max_frame_bytes;
+
+    Ok(())
+}
+
+/// Hardware decoders the installed ffmpeg supports, for the settings UI
+#[tauri::command]
+async fn probe_hwaccel() -> Result<Vec<settings::HwAccel>, AgentError> {
+    camera::probe_hwaccel().await
+}
+
+/// Decode camera streams on the GPU; captures fall back to software if it fails
+///
+/// One-shot captures switch immediately; persistent streams on their next reconnect.
+#[tauri::command]
+async fn set_hwaccel(
+    hwaccel: settings::HwAccel,
+    config: State<'_, HwAccelConfig>,
+    cameras: State<'_, CameraMap>,
+) -> Result<(), AgentError> {
+    println!("[Rust] Hardware acceleration: {:?}", hwaccel);
+
+    replace_state(&config, hwaccel)?;
+    for handle in cameras.write().await.values_mut() {
+        handle.hwaccel = hwaccel;
+    }
+    Ok(())
+}
+
+/// Limit simultaneous detection uploads; frames waiting longer than
+/// `queue_timeout_secs` for a slot are dropped
+#[tauri::command]
+async fn set_upload_concurrency(
+    max_uploads: usize,
+    queue_timeout_secs: Option<u64>,
+    slots: State<'_, UploadSlots>,
+) -> Result<(), AgentError> {
+    if max_uploads == 0 {
+        return Err(AgentError::InvalidInput("max_uploads must be at least 1".to_string()));
+    }
+
+    let queue_timeout = queue_timeout_secs
+        .map(std::time::Duration::from_secs)
+        .unwrap_or(settings::DEFAULT_UPLOAD_QUEUE_TIMEOUT);
+
+    println!("[Rust] Up to {} concurrent uploads, queue timeout {:?}", max_uploads, queue_timeout);
+
+    slots.configure(max_uploads, queue_timeout);
+    Ok(())
+}
+
+/// Send each camera's previous frame along with the current one, for
+/// backends running temporal models
+#[tauri::command]
+async fn set_previous_frame_context(
+    enabled: bool,
+    upload_config: State<'_, UploadConfig>,
+) -> Result<(), AgentError> {
+    println!("[Rust] Previous frame context {}", if enabled { "enabled" } else { "disabled" });
+
+    upload_config.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .previous_frame = enabled;
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_upload_format(
+    format: settings::ImageFormat,
+    upload_config: State<'_, UploadConfig>,
+) -> Result<(), AgentError> {
+    println!("[Rust] Upload format set to {:?}", format);
+
+    upload_config.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .upload_format = format;
+
+    Ok(())
+}
+
+/// Switch between the v1 and v2 detect endpoints
+#[tauri::command]
+async fn set_detection_api(
+    detection_api: settings::DetectionApi,
+    upload_config: State<'_, UploadConfig>,
+) -> Result<(), AgentError> {
+    println!("[Rust] Detection API set to {:?}", detection_api);
+
+    upload_config.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .detection_api = detection_api;
+
+    Ok(())
+}
+
+/// Choose how detect responses are read, for backends that don't return
+/// the CivicSentinel schema
+#[tauri::command]
+async fn set_response_format(
+    format: api::ResponseFormat,
+    labels: Option<Vec<String>>,
+    upload_config: State<'_, UploadConfig>,
+) -> Result<(), AgentError> {
+    println!("[Rust] Response format set to {:?}", format);
+
+    upload_config.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .response = api::ResponseAdapter { format, labels: labels.unwrap_or_default() };
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn set_alert_webhook(
+    url: String,
+    template: Option<String>,
+    alert_webhook: State<'_, AlertWebhook>,
+) -> Result<(), AgentError> {
+    let mut config = alert_webhook.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+
+    // An empty URL turns forwarding off
+    if url.trim().is_empty() {
+        println!("[Rust] Alert webhook disabled");
+        *config = None;
+    } else {
+        println!("[Rust] Alert webhook set to {}", url);
+        *config = Some(webhook::WebhookConfig { url, template });
+    }
+
+    Ok(())
+}
+
+fn notifiers_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, AgentError> {
+    app.path().app_data_dir()
+        .map(|dir| dir.join(notify::NOTIFIERS_FILE))
+        .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))
+}
+
+/// Keep email notifiers' SMTP passwords in the keychain: a given password
+/// replaces the stored one, a missing one is filled in from it
+fn sync_smtp_passwords(notifiers: &mut [notify::NotifierConfig]) -> Result<(), AgentError> {
+    for notifier in notifiers {
+        let notify::NotifierConfig::Email(config) = notifier else { continue };
+        let Some(username) = &config.username else { continue };
+        match &config.password {
+            Some(password) => credentials::store_smtp_password(&config.host, username, password)?,
+            None => config.password = credentials::load_smtp_password(&config.host, username)?,
+        }
+    }
+    Ok(())
+}
+
+/// Replace the channels zone alerts are delivered to; an empty list turns
+/// delivery off (the alert webhook is configured separately)
+///
+/// An email notifier given without its password keeps the one stored for
+/// its SMTP login.
+#[tauri::command]
+async fn set_notifiers(
+    notifiers: Vec<notify::NotifierConfig>,
+    app: tauri::AppHandle,
+    settings: State<'_, Notifiers>,
+) -> Result<(), AgentError> {
+    for notifier in &notifiers {
+        notifier.validate()?;
+    }
+
+    let notifiers = tokio::task::spawn_blocking(move || {
+        let mut notifiers = notifiers;
+        sync_smtp_passwords(&mut notifiers).map(|()| notifiers)
+    })
+    .await
+    .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))??;
+
+    println!("[Rust] Alert notifiers set to {} channel(s)", notifiers.len());
+    let updated = notify::NotifierSettings { notifiers };
+    updated.save(&notifiers_path(&app)?)?;
+    replace_state(&settings, updated)
+}
+
+/// The alert channels, without SMTP passwords
+#[tauri::command]
+async fn get_notifiers(settings: State<'_, Notifiers>) -> Result<notify::NotifierSettings, AgentError> {
+    snapshot(&settings)
+}
+
+#[tauri::command]
+async fn set_evidence_storage(
+    config: Option<storage::S3Config>,
+    evidence: State<'_, EvidenceStorage>,
+) -> Result<(), AgentError> {
+    match &config {
+        Some(config) => {
+            reqwest::Url::parse(&config.endpoint)
+                .map_err(|e| AgentError::InvalidInput(format!("Invalid S3 endpoint: {}", e)))?;
+            if config.bucket.trim().is_empty() {
+                return Err(AgentError::InvalidInput("S3 bucket is required".to_string()));
+            }
+            println!("[Rust] Alert evidence will be uploaded to {}/{}", config.endpoint, config.bucket);
+        }
+        None => println!("[Rust] Alert evidence upload disabled"),
+    }
+
+    *evidence.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))? = config;
+
+    Ok(())
+}
+
+fn detection_settings_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, AgentError> {
+    app.path().app_data_dir()
+        .map(|dir| dir.join(settings::DETECTION_SETTINGS_FILE))
+        .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))
+}
+
+/// Push detection settings into the cooldown, debounce and dedup state
+fn apply_detection_settings(app: &tauri::AppHandle, detection: &settings::DetectionSettings) -> Result<(), AgentError> {
+    app.state::<AlertCooldowns>().lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .set_cooldown(std::time::Duration::from_secs(detection.alert_cooldown_secs));
+    app.state::<AlertDebouncer>().lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .set_required(detection.alert_consecutive_frames);
+    app.state::<DetectionDeduper>().lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .set_iou_threshold(detection.dedup_iou_threshold);
+    Ok(())
+}
+
+/// Validate, save and apply a patch while holding the settings lock, so
+/// concurrent updates can't interleave and readers never see half of one
+fn patch_detection_settings(
+    app: &tauri::AppHandle,
+    patch: &settings::DetectionSettingsPatch,
+) -> Result<settings::DetectionSettings, AgentError> {
+    let config = app.state::<DetectionConfig>();
+    let mut current = config.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+
+    let updated = current.patched(patch)?;
+    updated.save(&detection_settings_path(app)?)?;
+    apply_detection_settings(app, &updated)?;
+
+    println!("[Rust] Detection settings: {:?}", updated);
+    *current = updated.clone();
+    Ok(updated)
+}
+
+#[tauri::command]
+async fn get_detection_settings(
+    config: State<'_, DetectionConfig>,
+) -> Result<settings::DetectionSettings, AgentError> {
+    snapshot(&config)
+}
+
+/// Change any subset of the detection settings in one step and persist them
+#[tauri::command]
+async fn update_detection_settings(
+    patch: settings::DetectionSettingsPatch,
+    app: tauri::AppHandle,
+) -> Result<settings::DetectionSettings, AgentError> {
+    patch_detection_settings(&app, &patch)
+}
+
+#[tauri::command]
+async fn set_alert_debounce(
+    consecutive_frames: u32,
+    app: tauri::AppHandle,
+) -> Result<(), AgentError> {
+    patch_detection_settings(&app, &settings::DetectionSettingsPatch {
+        alert_consecutive_frames: Some(consecutive_frames),
+        ..Default::default()
+    })?;
+    Ok(())
+}
+
+/// Set how much boxes must overlap (IoU) for a repeat detection to be suppressed
+#[tauri::command]
+async fn set_detection_dedup(
+    iou_threshold: f64,
+    app: tauri::AppHandle,
+) -> Result<(), AgentError> {
+    patch_detection_settings(&app, &settings::DetectionSettingsPatch {
+        dedup_iou_threshold: Some(iou_threshold),
+        ..Default::default()
+    })?;
+    Ok(())
+}
+
+#[tauri::command]
+async fn test_webhook(
+    alert_webhook: State<'_, AlertWebhook>,
+) -> Result<(), AgentError> {
+    let config = alert_webhook.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .clone()
+        .ok_or_else(|| AgentError::NotFound("No alert webhook configured".to_string()))?;
+
+    println!("[Rust] Sending test payload to webhook {}", config.url);
+    webhook::send_webhook(&config, &webhook::WebhookPayload::sample()).await
+}
+
+/// Camera id synthetic alerts are raised on, so their cooldown is separate
+const TEST_ALERT_CAMERA: &str = "test-camera";
+
+/// Send a synthetic, clearly marked alert through evidence upload, the
+/// webhook (subject to cooldown) and a desktop notification
+#[tauri::command]
+async fn test_alert_pipeline(app: tauri::AppHandle) -> Result<webhook::AlertPipelineReport, AgentError> {
+    use webhook::StageOutcome;
+
+    println!("[Rust] Testing alert pipeline");
+
+    let alert = api::ZoneAlert {
+        zone_id: 0,
+        zone_name: "[TEST] Alert pipeline check".to_string(),
+        alert_type: "test".to_string(),
+        confidence: 0.99,
+    };
+    let mut response = sink::empty_response(TEST_ALERT_CAMERA);
+    response.detections.push(api::Detection {
+        class_name: "person".to_string(),
+        confidence: 0.99,
+        bbox: api::BoundingBox { x1: 0.25, y1: 0.25, x2: 0.75, y2: 0.75 },
+    });
+    response.alerts.push(alert.clone());
+
+    let evidence_config = app.state::<EvidenceStorage>().lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .clone();
+    let evidence = match evidence_config {
+        None => StageOutcome::Skipped("Evidence storage is not configured".to_string()),
+        Some(config) => {
+            let frame = api::test_jpeg()?;
+            let labels = snapshot(&app.state::<Labels>())?;
+            match store_evidence(&config, TEST_ALERT_CAMERA, &frame, &response, chrono::Local::now(), labels).await {
+                Ok(url) => {
+                    response.evidence_url = Some(url.clone());
+                    StageOutcome::Delivered(Some(url))
+                }
+                Err(e) => StageOutcome::Failed(e.message().to_string()),
+            }
+        }
+    };
+
+    let webhook_config = app.state::<AlertWebhook>().lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .clone();
+    let webhook = match webhook_config {
+        None => StageOutcome::Skipped("No alert webhook configured".to_string()),
+        Some(config) => {
+            let fire = app.state::<AlertCooldowns>().lock()
+                .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+                .should_fire(TEST_ALERT_CAMERA, alert.zone_id);
+            if fire {
+                let mut payload = webhook::WebhookPayload::from_alert(
+                    TEST_ALERT_CAMERA,
+                    &response.timestamp,
+                    &alert,
+                    response.evidence_url.clone(),
+                );
+                payload.test = true;
+                match webhook::send_webhook(&config, &payload).await {
+                    Ok(()) => StageOutcome::Delivered(None),
+                    Err(e) => StageOutcome::Failed(e.message().to_string()),
+                }
+            } else {
+                StageOutcome::Skipped("Suppressed by the alert cooldown; try again later".to_string())
+            }
+        }
+    };
+
+    let mut payload = webhook::WebhookPayload::from_alert(
+        TEST_ALERT_CAMERA,
+        &response.timestamp,
+        &alert,
+        response.evidence_url.clone(),
+    );
+    payload.test = true;
+
+    let mut notification = StageOutcome::Skipped("OS notifications are not enabled".to_string());
+    let mut notifiers = Vec::new();
+    for config in snapshot(&app.state::<Notifiers>())?.notifiers {
+        let notifier = config.build(&app);
+        let outcome = match notifier.notify(&payload).await {
+            Ok(()) => StageOutcome::Delivered(None),
+            Err(e) => StageOutcome::Failed(e.message().to_string()),
+        };
+        if matches!(config, notify::NotifierConfig::Os) {
+            notification = outcome.clone();
+        }
+        notifiers.push(webhook::NotifierOutcome { notifier: notifier.name(), outcome });
+    }
+
+    Ok(webhook::AlertPipelineReport { evidence, webhook, notification, notifiers })
+}
+
+#[tauri::command]
+async fn get_alerts(
+    api_key: String,
+    backend_url: String,
+    camera_id: Option<String>,
+    page: i64,
+    page_size: i64,
+    image_sources: State<'_, AlertImageSources>,
+    auth: State<'_, AuthConfig>,
+) -> Result<api::AlertListResponse, AgentError> {
+    println!("[Rust] Fetching alerts from backend");
+
+    let response = api::get_alerts(
+        &backend_url,
+        &api_key,
+        &auth_scheme(&auth)?,
+        camera_id.as_deref(),
+        page,
+        page_size,
+    ).await?;
+
+    remember_alert_images(&image_sources, &backend_url, &response.alerts)?;
+
+    Ok(response)
+}
+
+/// Remember image locations so fetch_alert_image can work from an id alone
+fn remember_alert_images(
+    image_sources: &AlertImageSources,
+    backend_url: &str,
+    alerts: &[api::AlertResponse],
+) -> Result<(), AgentError> {
+    let mut sources = image_sources.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+    for alert in alerts {
+        if let Some(image_url) = &alert.image_url {
+            sources.insert(alert.id, AlertImageSource {
+                backend_url: backend_url.to_string(),
+                image_url: image_url.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn poll_new_alerts(
+    api_key: String,
+    backend_url: String,
+    since_id: i64,
+    image_sources: State<'_, AlertImageSources>,
+    auth: State<'_, AuthConfig>,
+) -> Result<api::NewAlerts, AgentError> {
+    let new_alerts = api::poll_new_alerts(&backend_url, &api_key, &auth_scheme(&auth)?, since_id).await?;
+    remember_alert_images(&image_sources, &backend_url, &new_alerts.alerts)?;
+    Ok(new_alerts)
+}
+
+#[tauri::command]
+async fn start_alert_polling(
+    api_key: String,
+    backend_url: String,
+    since_id: i64,
+    interval_secs: u64,
+    app: tauri::AppHandle,
+    poller: State<'_, AlertPoller>,
+) -> Result<(), AgentError> {
+    if interval_secs == 0 {
+        return Err(AgentError::InvalidInput("Interval must be at least 1 second".to_string()));
+    }
+
+    println!("[Rust] Polling for alerts after #{} every {}s", since_id, interval_secs);
+
+    let image_sources = app.state::<AlertImageSources>().inner().clone();
+    let auth = app.state::<AuthConfig>().inner().clone();
+    let handle = tauri::async_runtime::spawn(async move {
+        let mut cursor = since_id;
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(interval_secs)).await;
+
+            let result = match auth_scheme(&auth) {
+                Ok(scheme) => api::poll_new_alerts(&backend_url, &api_key, &scheme, cursor).await,
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(new_alerts) => {
+                    let _ = remember_alert_images(&image_sources, &backend_url, &new_alerts.alerts);
+                    for alert in new_alerts.alerts {
+                        let _ = app.emit("new-alert", alert);
+                    }
+                    cursor = new_alerts.cursor;
+                }
+                Err(e) => println!("[Alerts] Poll after #{} failed: {}", cursor, e),
+            }
+        }
+    });
+
+    // Only one poller; restarting replaces the cursor and credentials
+    if let Some(previous) = poller.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .replace(handle)
+    {
+        previous.abort();
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_alert_polling(
+    poller: State<'_, AlertPoller>,
+) -> Result<(), AgentError> {
+    println!("[Rust] Stopping alert polling");
+
+    if let Some(handle) = poller.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .take()
+    {
+        handle.abort();
+    }
+
+    Ok(())
+}
+
+fn alert_image_cache_path(app: &tauri::AppHandle, alert_id: i64) -> Result<std::path::PathBuf, AgentError> {
+    app.path().app_data_dir()
+        .map(|dir| dir.join("alert_images").join(alert_id.to_string()))
+        .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))
+}
+
+fn cache_alert_image(cache_path: &std::path::Path, bytes: &[u8]) -> Result<(), AgentError> {
+    if let Some(cache_dir) = cache_path.parent() {
+        std::fs::create_dir_all(cache_dir)
+            .map_err(|e| AgentError::Internal(format!("Failed to create {}: {}", cache_dir.display(), e)))?;
+    }
+    std::fs::write(cache_path, bytes)
+        .map_err(|e| AgentError::Internal(format!("Failed to write {}: {}", cache_path.display(), e)))
+}
+
+#[tauri::command]
+async fn fetch_alert_image(
+    alert_id: i64,
+    api_key: String,
+    app: tauri::AppHandle,
+    image_sources: State<'_, AlertImageSources>,
+    auth: State<'_, AuthConfig>,
+) -> Result<String, AgentError> {
+    use base64::{Engine as _, engine::general_purpose};
+
+    let cache_path = alert_image_cache_path(&app, alert_id)?;
+
+    if let Ok(bytes) = std::fs::read(&cache_path) {
+        return Ok(general_purpose::STANDARD.encode(&bytes));
+    }
+
+    let source = image_sources.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .get(&alert_id)
+        .cloned()
+        .ok_or_else(|| AgentError::NotFound(format!("No image known for alert {}; load alerts first", alert_id)))?;
+
+    println!("[Rust] Downloading image for alert {}", alert_id);
+
+    let bytes = api::download_alert_image(&source.backend_url, &api_key, &auth_scheme(&auth)?, &source.image_url).await?;
+    cache_alert_image(&cache_path, &bytes)?;
+
+    Ok(general_purpose::STANDARD.encode(&bytes))
+}
+
+/// Alert image fetched through a URL the backend issues on demand, so
+/// expired presigned links in the alert list don't matter; base64 encoded
+#[tauri::command]
+async fn stream_alert_image(
+    alert_id: i64,
+    api_key: String,
+    backend_url: String,
+    app: tauri::AppHandle,
+    auth: State<'_, AuthConfig>,
+) -> Result<String, AgentError> {
+    use base64::{Engine as _, engine::general_purpose};
+
+    let cache_path = alert_image_cache_path(&app, alert_id)?;
+
+    if let Ok(bytes) = std::fs::read(&cache_path) {
+        return Ok(general_purpose::STANDARD.encode(&bytes));
+    }
+
+    println!("[Rust] Fetching fresh image for alert {}", alert_id);
+
+    let bytes = api::fetch_fresh_alert_image(&backend_url, &api_key, &auth_scheme(&auth)?, alert_id).await?;
+    cache_alert_image(&cache_path, &bytes)?;
+
+    Ok(general_purpose::STANDARD.encode(&bytes))
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn start_recording(
+    camera_id: String,
+    segment_secs: u64,
+    retention_hours: u64,
+    output_dir: String,
+    stream: Option<camera::StreamSelection>,
+    app: tauri::AppHandle,
+    cameras: State<'_, CameraMap>,
+    recordings: State<'_, Recordings>,
+) -> Result<String, AgentError> {
+    let handle = cameras.read().await
+        .get(&camera_id)
+        .cloned()
+        .ok_or_else(|| AgentError::NotFound(format!("Camera {} not found", camera_id)))?;
+
+    let mut recordings = recordings.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+
+    if let Some(existing) = recordings.get(&camera_id) {
+        if existing.is_running() {
+            return Err(AgentError::InvalidInput(format!("Camera {} is already recording", camera_id)));
+        }
+    }
+
+    // Evidence is worth the bandwidth: record the main stream unless told otherwise
+    let stream = stream.unwrap_or_default();
+    let url = handle.streams.url(stream)
+        .ok_or_else(|| AgentError::InvalidInput(format!("Camera {} has no sub stream configured", camera_id)))?;
+
+    let recording = recording::Recording::start(
+        &camera_id,
+        url,
+        handle.username.as_deref(),
+        handle.password.as_deref(),
+        handle.rtsp_transport,
+        segment_secs,
+        retention_hours,
+        std::path::Path::new(&output_dir),
+    )?;
+    let dir = recording.output_dir.to_string_lossy().into_owned();
+
+    // Retention keeps sweeping this directory after the recording stops
+    let roots_path = recording_roots_path(&app)?;
+    let mut roots = recording::RecordingRoots::load(&roots_path);
+    if roots.insert(std::path::Path::new(&output_dir)) {
+        roots.save(&roots_path)?;
+    }
+
+    // A previous recording that was left stopped is replaced
+    if let Some(mut previous) = recordings.insert(camera_id.clone(), recording) {
+        let _ = previous.stop();
+    }
+
+    println!("[Rust] Recording camera {} into {}", camera_id, dir);
+    Ok(dir)
+}
+
+#[tauri::command]
+async fn stop_recording(
+    camera_id: String,
+    recordings: State<'_, Recordings>,
+) -> Result<(), AgentError> {
+    let mut recording = recordings.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .remove(&camera_id)
+        .ok_or_else(|| AgentError::NotFound(format!("Camera {} is not recording", camera_id)))?;
+
+    // Waits for ffmpeg to finish the current segment
+    tokio::task::spawn_blocking(move || recording.stop())
+        .await
+        .map_err(|e| AgentError::Internal(format!("Stop task failed: {}", e)))?
+}
+
+/// Whether a camera is recording, restarting a crashed ffmpeg, or stopped
+#[tauri::command]
+async fn get_recording_status(
+    camera_id: String,
+    recordings: State<'_, Recordings>,
+) -> Result<recording::RecordingStatus, AgentError> {
+    Ok(recordings.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .get(&camera_id)
+        .map(|recording| recording.status())
+        .unwrap_or_else(recording::RecordingStatus::stopped))
+}
+
+fn retention_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, AgentError> {
+    app.path().app_data_dir()
+        .map(|dir| dir.join(retention::RETENTION_FILE))
+        .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))
+}
 
-#[tauri::command]
-async fn scan_network() -> Result<Vec<camera::DiscoveredCamera>, String> {
-    println!("[Rust] Starting network scan...");
-    camera::scan_for_cameras().await
+fn recording_roots_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, AgentError> {
+    app.path().app_data_dir()
+        .map(|dir| dir.join(recording::RECORDING_ROOTS_FILE))
+        .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))
 }
 
-#[tauri::command]
-async fn test_camera(rtsp_url: String) -> Result<bool, String> {
-    println!("[Rust] Testing camera: {}", rtsp_url);
-    camera::test_camera_connection(&rtsp_url).await
+fn labels_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, AgentError> {
+    app.path().app_data_dir()
+        .map(|dir| dir.join(labels::LABELS_FILE))
+        .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))
 }
 
+/// Re-read labels.json after editing it; the old labels stay in use if the
+/// file is invalid
 #[tauri::command]
-async fn diagnose_rtsp(rtsp_url: String) -> Result<camera::RtspDiagnostics, String> {
-    println!("[Rust] Running RTSP diagnostics: {}", rtsp_url);
-    camera::diagnose_rtsp_connection(&rtsp_url).await
+async fn reload_labels(app: tauri::AppHandle) -> Result<labels::LabelMap, AgentError> {
+    let labels = labels::LabelMap::load(&labels_path(&app)?)?;
+    println!("[Rust] Loaded {} class label(s)", labels.classes.len());
+    replace_state(&app.state::<Labels>(), labels.clone())?;
+    Ok(labels)
 }
 
+/// Display name and color for each class, for drawing overlays
 #[tauri::command]
-async fn connect_camera(
-    camera_id: String,
-    rtsp_url: String,
-    username: Option<String>,
-    password: Option<String>,
-    cameras: State<'_, CameraMap>,
-) -> Result<(), String> {
-    println!("[Rust] Connecting camera: {}", camera_id);
-
-    let mut handle = camera::connect(&rtsp_url, username.clone(), password.clone()).await?;
+async fn get_labels(
+    class_names: Vec<String>,
+    labels: State<'_, Labels>,
+) -> Result<HashMap<String, labels::ResolvedLabel>, AgentError> {
+    let labels = labels.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+    Ok(class_names.into_iter()
+        .map(|class_name| {
+            let resolved = labels.resolve(&class_name);
+            (class_name, resolved)
+        })
+        .collect())
+}
 
-    // Determine source type
-    let source_type = if rtsp_url.starts_with("rtsp://") || rtsp_url.starts_with("rtsps://") {
-        "rtsp".to_string()
-    } else if rtsp_url.starts_with("http://") || rtsp_url.starts_with("https://") {
-        "http".to_string()
-    } else {
-        "file".to_string()
-    };
+/// Directories the agent writes files into: the detection sink, the alert
+/// image cache, saved incident reports and the camera directories of every
+/// recording, running or stopped
+fn data_dirs(app: &tauri::AppHandle) -> Result<Vec<retention::DataDir>, AgentError> {
+    let root = app.path().app_data_dir()
+        .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))?;
+    let mut dirs = vec![
+        retention::DataDir { category: "detections".to_string(), path: root.join("detections") },
+        retention::DataDir { category: "alert_images".to_string(), path: root.join("alert_images") },
+        retention::DataDir { category: "reports".to_string(), path: root.join(report::REPORTS_DIR) },
+    ];
 
-    println!("[Rust] Source type: {}", source_type);
+    let mut recording_dirs = recording::RecordingRoots::load(&recording_roots_path(app)?).camera_dirs();
+    let recordings = app.state::<Recordings>();
+    let recordings = recordings.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+    for recording in recordings.values() {
+        if !recording_dirs.contains(&recording.output_dir) {
+            recording_dirs.push(recording.output_dir.clone());
+        }
+    }
+    dirs.extend(recording_dirs.into_iter().map(|path| retention::DataDir {
+        category: "recordings".to_string(),
+        path,
+    }));
+    Ok(dirs)
+}
 
-    // Start persistent capture
-    let persistent_capture = camera::PersistentCapture::new(
-        rtsp_url.clone(),
-        source_type.clone(),
-        username.clone(),
-        password.clone(),
-    )?;
+/// Apply the configured retention policy once
+async fn run_retention(app: &tauri::AppHandle) -> Result<retention::RetentionReport, AgentError> {
+    let policy = snapshot(&app.state::<RetentionConfig>())?;
+    if policy.is_unlimited() {
+        return Ok(retention::RetentionReport::default());
+    }
 
-    println!("[Rust] ✅ Persistent capture started for {}", camera_id);
+    let dirs = data_dirs(app)?;
+    let report = tokio::task::spawn_blocking(move || {
+        retention::apply_retention(&dirs, &policy, std::time::SystemTime::now())
+    })
+    .await
+    .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))?;
 
-    // Store persistent capture in handle
-    handle.persistent_capture = Some(Arc::new(std::sync::Mutex::new(persistent_capture)));
+    if report.files_deleted > 0 {
+        println!("[Rust] Retention deleted {} file(s), freed {} bytes", report.files_deleted, report.bytes_freed);
+    }
+    Ok(report)
+}
 
-    cameras.lock()
-        .map_err(|e| format!("Lock error: {}", e))?
-        .insert(camera_id.clone(), handle);
+/// Apply the retention policy at startup and then hourly, until shutdown
+async fn enforce_retention(app: tauri::AppHandle) {
+    loop {
+        if app.state::<ShutdownFlag>().load(std::sync::atomic::Ordering::SeqCst) {
+            break;
+        }
 
-    println!("[Rust] ✅ Camera {} connected and ready", camera_id);
+        if let Err(e) = run_retention(&app).await {
+            println!("[Rust] Retention pass failed: {}", e);
+        }
 
-    Ok(())
+        tokio::time::sleep(retention::RETENTION_INTERVAL).await;
+    }
 }
 
+/// Set the retention limits (None = no limit), save them for the hourly
+/// pass, and delete what's over them now
 #[tauri::command]
-async fn get_frame(
-    camera_id: String,
-    cameras: State<'_, CameraMap>,
-) -> Result<String, String> {
-    // Get frame from persistent capture
-    let cameras_lock = cameras.lock()
-        .map_err(|e| format!("Lock error: {}", e))?;
+async fn apply_retention(
+    max_age_days: Option<u64>,
+    max_total_bytes: Option<u64>,
+    app: tauri::AppHandle,
+) -> Result<retention::RetentionReport, AgentError> {
+    if max_age_days == Some(0) {
+        return Err(AgentError::InvalidInput("Maximum age must be at least 1 day".to_string()));
+    }
 
-    let handle = cameras_lock.get(&camera_id)
-        .ok_or_else(|| format!("Camera {} not found", camera_id))?;
+    let policy = retention::RetentionPolicy { max_age_days, max_total_bytes };
+    println!("[Rust] Retention policy set to {:?}", policy);
+    policy.save(&retention_path(&app)?)?;
+    replace_state(&app.state::<RetentionConfig>(), policy)?;
 
-    if let Some(capture) = &handle.persistent_capture {
-        let capture_lock = capture.lock()
-            .map_err(|e| format!("Capture lock error: {}", e))?;
+    run_retention(&app).await
+}
 
-        let frame_bytes = capture_lock.get_frame()?;
+fn proxy_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, AgentError> {
+    app.path().app_data_dir()
+        .map(|dir| dir.join(proxy::PROXY_FILE))
+        .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))
+}
 
-        // Convert to base64 for frontend
-        use base64::{Engine as _, engine::general_purpose};
-        Ok(general_purpose::STANDARD.encode(&frame_bytes))
-    } else {
-        Err("Persistent capture not initialized".to_string())
-    }
+/// Route backend, webhook and storage requests through a proxy; leaving
+/// both URLs unset falls back to the HTTP(S)_PROXY environment variables
+#[tauri::command]
+async fn set_proxy(settings: proxy::ProxySettings, app: tauri::AppHandle) -> Result<(), AgentError> {
+    proxy::configure(&settings)?;
+    println!("[Rust] Proxy set to http: {:?}, https: {:?}", settings.http_proxy, settings.https_proxy);
+    settings.save(&proxy_path(&app)?)?;
+    replace_state(&app.state::<ProxyConfig>(), settings)
 }
 
 #[tauri::command]
-async fn disconnect_camera(
-    camera_id: String,
-    cameras: State<'_, CameraMap>,
-) -> Result<(), String> {
-    println!("[Rust] Disconnecting camera: {}", camera_id);
+async fn get_proxy(proxy: State<'_, ProxyConfig>) -> Result<proxy::ProxySettings, AgentError> {
+    snapshot(&proxy)
+}
+
+fn signing_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, AgentError> {
+    app.path().app_data_dir()
+        .map(|dir| dir.join(signing::SIGNING_FILE))
+        .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))
+}
 
-    let mut cameras_lock = cameras.lock()
-        .map_err(|e| format!("Lock error: {}", e))?;
+/// Turn HMAC signing of frame uploads on or off
+///
+/// A given `secret` replaces the one in the keychain; enabling without one
+/// uses the stored secret.
+#[tauri::command]
+async fn set_request_signing(
+    enabled: bool,
+    secret: Option<String>,
+    app: tauri::AppHandle,
+) -> Result<(), AgentError> {
+    if secret.as_deref().is_some_and(|secret| secret.is_empty()) {
+        return Err(AgentError::InvalidInput("Signing secret must not be empty".to_string()));
+    }
 
-    if let Some(mut handle) = cameras_lock.remove(&camera_id) {
-        // Stop persistent capture if exists
-        if let Some(capture) = handle.persistent_capture.take() {
-            let mut capture_lock = capture.lock()
-                .map_err(|e| format!("Capture lock error: {}", e))?;
-            capture_lock.stop()?;
+    let secret = tokio::task::spawn_blocking(move || {
+        match secret {
+            Some(secret) => credentials::store_signing_secret(&secret).map(|()| Some(secret)),
+            None => credentials::load_signing_secret(),
         }
-        println!("[Rust] ✅ Camera {} disconnected", camera_id);
+    })
+    .await
+    .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))??;
+
+    if enabled && secret.is_none() {
+        return Err(AgentError::InvalidInput("Set a signing secret before enabling request signing".to_string()));
     }
 
-    Ok(())
+    let settings = signing::SigningSettings { enabled };
+    settings.save(&signing_path(&app)?)?;
+    signing::configure(secret.as_deref().filter(|_| enabled))?;
+    println!("[Rust] Request signing {}", if enabled { "enabled" } else { "disabled" });
+    replace_state(&app.state::<SigningConfig>(), settings)
 }
 
 #[tauri::command]
-async fn send_frame_to_cloud(
+async fn get_request_signing(signing: State<'_, SigningConfig>) -> Result<signing::SigningSettings, AgentError> {
+    snapshot(&signing)
+}
+
+/// Check the app data directory is writable before features depend on it
+#[tauri::command]
+async fn check_storage(app: tauri::AppHandle) -> Result<retention::StorageStatus, AgentError> {
+    let dir = app.path().app_data_dir()
+        .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))?;
+    tokio::task::spawn_blocking(move || retention::check_storage(&dir))
+        .await
+        .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))?
+}
+
+/// Files and bytes kept by the agent, per category
+#[tauri::command]
+async fn get_disk_usage(app: tauri::AppHandle) -> Result<Vec<retention::CategoryUsage>, AgentError> {
+    let dirs = data_dirs(&app)?;
+    tokio::task::spawn_blocking(move || retention::disk_usage(&dirs))
+        .await
+        .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))
+}
+
+/// Segments recorded for a camera, from its active recording or `output_dir`
+#[tauri::command]
+async fn list_recordings(
     camera_id: String,
-    frame_base64: String,
-    api_key: String,
-    backend_url: String,
-    cache: State<'_, FrameCache>,
-) -> Result<api::DetectionResponse, String> {
-    println!("[Rust] Sending frame to cloud for camera: {}", camera_id);
+    output_dir: Option<String>,
+    recordings: State<'_, Recordings>,
+) -> Result<Vec<recording::RecordingSegment>, AgentError> {
+    let active_dir = recordings.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .get(&camera_id)
+        .map(|recording| recording.output_dir.clone());
 
-    use base64::{Engine as _, engine::general_purpose};
-    let frame_bytes = general_purpose::STANDARD.decode(&frame_base64)
-        .map_err(|e| format!("Base64 decode error: {}", e))?;
+    let dir = match (active_dir, output_dir) {
+        (Some(dir), _) => dir,
+        (None, Some(output_dir)) => recording::camera_dir(std::path::Path::new(&output_dir), &camera_id),
+        (None, None) => {
+            return Err(AgentError::NotFound(format!(
+                "Camera {} is not recording; pass the output directory to list old segments", camera_id
+            )));
+        }
+    };
 
-    let response = api::send_detection_request(
-        &backend_url,
-        &camera_id,
-        &frame_bytes,
-        &api_key,
-    ).await?;
+    tokio::task::spawn_blocking(move || recording::list_segments(&dir))
+        .await
+        .map_err(|e| AgentError::Internal(format!("List task failed: {}", e)))
+}
 
-    // Cache the frame and detection results
-    cache.lock()
-        .map_err(|e| format!("Cache lock error: {}", e))?
-        .insert(camera_id.clone(), CachedData {
-            frame: frame_base64,
-            detections: response.clone(),
-            timestamp: std::time::SystemTime::now(),
-        });
+/// Lock a std-mutex managed value and clone it out
+fn snapshot<T: Clone>(state: &Mutex<T>) -> Result<T, AgentError> {
+    Ok(state.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .clone())
+}
 
-    Ok(response)
+/// Overwrite a std-mutex managed value
+fn replace_state<T>(state: &Mutex<T>, value: T) -> Result<(), AgentError> {
+    *state.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))? = value;
+    Ok(())
 }
 
+/// Bundle this agent's settings and connected cameras as JSON, without secrets
 #[tauri::command]
-async fn get_latest_frame(
-    camera_id: String,
-    cache: State<'_, FrameCache>,
-) -> Result<String, String> {
-    println!("[Rust] get_latest_frame called for camera: {}", camera_id);
+async fn export_config(app: tauri::AppHandle) -> Result<String, AgentError> {
+    let adaptive = snapshot(&app.state::<AdaptiveSchedules>())?;
+    let groups = snapshot(&app.state::<CameraGroups>())?;
 
-    let result = cache.lock()
-        .map_err(|e| format!("Cache lock error: {}", e))?
-        .get(&camera_id)
-        .map(|cached| cached.frame.clone())
-        .ok_or_else(|| format!("No cached frame for camera: {}", camera_id));
+    let mut cameras: Vec<config::CameraConfig> = app.state::<CameraMap>().read().await
+        .iter()
+        .map(|(camera_id, handle)| config::CameraConfig {
+            camera_id: camera_id.clone(),
+            source_url: camera::strip_userinfo(&handle.streams.main),
+            sub_stream_url: handle.streams.sub.as_deref().map(camera::strip_userinfo),
+            active_stream: handle.streams.active,
+            rtsp_transport: handle.rtsp_transport,
+            roi: handle.roi,
+            transform: handle.transform,
+            capture_mode: handle.capture_mode,
+            retry: handle.retry,
+            adaptive_capture: adaptive.get(camera_id).map(|schedule| config::AdaptiveCaptureConfig {
+                base: schedule.base_secs,
+                fast: schedule.fast_secs,
+                slow: schedule.slow_secs,
+                idle_cycles: schedule.idle_cycles,
+            }),
+            group: groups.get(camera_id),
+        })
+        .collect();
+    cameras.sort_by(|a, b| a.camera_id.cmp(&b.camera_id));
 
-    match &result {
-        Ok(frame) => println!("[Rust] Returning cached frame, length: {}", frame.len()),
-        Err(e) => println!("[Rust] Error getting frame: {}", e),
-    }
+    let detection = snapshot(&app.state::<DetectionConfig>())?;
+    let bundle = config::AgentConfig {
+        version: config::CONFIG_VERSION,
+        backend_urls: snapshot(&app.state::<BackendList>())?.into_iter().map(|backend| backend.url).collect(),
+        auth_scheme: snapshot(&app.state::<AuthConfig>())?,
+        detection_sink: snapshot(&app.state::<SinkConfig>())?,
+        upload: snapshot(&app.state::<UploadConfig>())?,
+        alert_webhook: snapshot(&app.state::<AlertWebhook>())?,
+        alert_consecutive_frames: detection.alert_consecutive_frames,
+        dedup_iou_threshold: detection.dedup_iou_threshold,
+        evidence_storage: snapshot(&app.state::<EvidenceStorage>())?.map(|s3| config::EvidenceLocation {
+            endpoint: s3.endpoint,
+            bucket: s3.bucket,
+            region: s3.region,
+        }),
+        cameras,
+    };
 
-    result
+    println!("[Rust] Exported configuration with {} camera(s)", bundle.cameras.len());
+
+    serde_json::to_string_pretty(&bundle)
+        .map_err(|e| AgentError::Internal(format!("Failed to encode configuration: {}", e)))
 }
 
+/// Apply a bundle from `export_config` and connect its cameras
+///
+/// Secrets are kept from this install where it has them; anything still
+/// missing is listed in the report so it can be set afterwards.
 #[tauri::command]
-async fn get_latest_detections(
-    camera_id: String,
-    cache: State<'_, FrameCache>,
-) -> Result<api::DetectionResponse, String> {
-    println!("[Rust] get_latest_detections called for camera: {}", camera_id);
+async fn import_config(json: String, app: tauri::AppHandle) -> Result<config::ImportReport, AgentError> {
+    let bundle = config::parse(&json)?;
 
-    let result = cache.lock()
-        .map_err(|e| format!("Cache lock error: {}", e))?
-        .get(&camera_id)
-        .map(|cached| cached.detections.clone())
-        .ok_or_else(|| format!("No cached detections for camera: {}", camera_id));
+    // Validate everything before changing anything
+    validate_auth_scheme(&bundle.auth_scheme)?;
+    if bundle.upload.max_frame_bytes == 0 {
+        return Err(AgentError::InvalidInput("max_frame_bytes must be greater than zero".to_string()));
+    }
+    let detection_patch = settings::DetectionSettingsPatch {
+        alert_consecutive_frames: Some(bundle.alert_consecutive_frames),
+        dedup_iou_threshold: Some(bundle.dedup_iou_threshold),
+        ..Default::default()
+    };
+    snapshot(&app.state::<DetectionConfig>())?.patched(&detection_patch)?;
+    if let Some(url) = bundle.backend_urls.iter()
+        .find(|url| !url.starts_with("http://") && !url.starts_with("https://"))
+    {
+        return Err(AgentError::InvalidInput(format!("Invalid backend URL: {}", url)));
+    }
+    let mut schedules = HashMap::new();
+    for camera in &bundle.cameras {
+        if let Some(roi) = camera.roi {
+            camera::validate_roi(&roi)?;
+        }
+        camera.retry.validate()?;
+        if let Some(adaptive) = &camera.adaptive_capture {
+            schedules.insert(
+                camera.camera_id.clone(),
+                adaptive_schedule(adaptive.base, adaptive.fast, adaptive.slow, adaptive.idle_cycles)?,
+            );
+        }
+    }
 
-    match &result {
-        Ok(detections) => println!("[Rust] Returning cached detections: {} detections, {} alerts",
-                                    detections.detections.len(), detections.alerts.len()),
-        Err(e) => println!("[Rust] Error getting detections: {}", e),
+    let mut report = config::ImportReport::default();
+
+    let existing_backends = snapshot(&app.state::<BackendList>())?;
+    let backends = config::backends_with_keys(&bundle.backend_urls, &existing_backends, &mut report);
+    let existing_evidence = snapshot(&app.state::<EvidenceStorage>())?;
+    let evidence = config::evidence_with_keys(bundle.evidence_storage, existing_evidence.as_ref(), &mut report);
+
+    replace_state(&app.state::<BackendList>(), backends)?;
+    replace_state(&app.state::<AuthConfig>(), bundle.auth_scheme)?;
+    replace_state(&app.state::<SinkConfig>(), bundle.detection_sink)?;
+    replace_state(&app.state::<UploadConfig>(), bundle.upload)?;
+    replace_state(&app.state::<AlertWebhook>(), bundle.alert_webhook)?;
+    replace_state(&app.state::<EvidenceStorage>(), evidence)?;
+    patch_detection_settings(&app, &detection_patch)?;
+    app.state::<AdaptiveSchedules>().lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .extend(schedules);
+
+    {
+        let groups = app.state::<CameraGroups>();
+        let mut groups = groups.lock()
+            .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+        for camera in &bundle.cameras {
+            if let Some(group) = monitoring::normalize_group(camera.group.clone()) {
+                groups.set(&camera.camera_id, Some(group));
+            }
+        }
+        groups.save(&camera_groups_path(&app)?)?;
     }
 
-    result
+    for camera in bundle.cameras {
+        let _camera_lock = lock_camera(&app.state::<CameraLocks>(), &camera.camera_id).await?;
+        let stored = stored_credentials(&camera.camera_id).await;
+        let (username, password) = match stored {
+            Some(stored) => (Some(stored.username), Some(stored.password)),
+            None => (None, None),
+        };
+
+        let hwaccel = snapshot(&app.state::<HwAccelConfig>())?;
+        let opened = open_camera(
+            &camera.source_url, username, password, camera.rtsp_transport, camera.roi, camera.transform,
+            camera.capture_mode, hwaccel,
+        ).await;
+        match opened {
+            Ok(mut handle) => {
+                handle.streams.sub = camera.sub_stream_url.clone();
+                handle.retry = camera.retry;
+                let previous = app.state::<CameraMap>().write().await.insert(camera.camera_id.clone(), handle);
+                if let Some(mut previous) = previous {
+                    let _ = close_camera(&mut previous).await;
+                }
+                if let Err(e) = switch_stream(&app.state::<CameraMap>(), &camera.camera_id, camera.active_stream).await {
+                    println!("[Rust] Imported camera {} stays on its main stream: {}", camera.camera_id, e);
+                }
+                report.cameras_connected.push(camera.camera_id);
+            }
+            Err(e) => {
+                println!("[Rust] Imported camera {} failed to connect: {}", camera.camera_id, e);
+                report.cameras_failed.push((camera.camera_id, e.message().to_string()));
+            }
+        }
+    }
+
+    println!("[Rust] Imported configuration: {} camera(s) connected, {} failed, {} secret(s) missing",
+             report.cameras_connected.len(), report.cameras_failed.len(), report.missing_secrets.len());
+
+    Ok(report)
 }
 
-#[tauri::command]
-async fn create_zone(
-    camera_id: String,
-    zone_name: String,
-    coordinates: Vec<[f64; 2]>,
-    alert_type: String,
-    api_key: String,
-    backend_url: String,
-) -> Result<api::ZoneResponse, String> {
-    println!("[Rust] Creating zone for camera: {}", camera_id);
+/// Stable id for this install, created on first use under the app data dir
+fn agent_id(app: &tauri::AppHandle) -> Result<String, AgentError> {
+    let dir = app.path().app_data_dir()
+        .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))?;
+    let path = dir.join("agent_id");
 
-    api::create_zone(
-        &backend_url,
-        &camera_id,
-        &zone_name,
-        &coordinates,
-        &alert_type,
-        &api_key,
-    ).await
+    if let Ok(id) = std::fs::read_to_string(&path) {
+        if !id.trim().is_empty() {
+            return Ok(id.trim().to_string());
+        }
+    }
+
+    use rand::Rng;
+    let id = format!("agent-{}", hex::encode(rand::thread_rng().gen::<[u8; 8]>()));
+    std::fs::create_dir_all(&dir)
+        .and_then(|_| std::fs::write(&path, &id))
+        .map_err(|e| AgentError::Internal(format!("Failed to save agent id: {}", e)))?;
+
+    Ok(id)
 }
 
-#[tauri::command]
-async fn get_zones(
-    camera_id: String,
-    api_key: String,
-    backend_url: String,
-) -> Result<Vec<api::ZoneResponse>, String> {
-    api::get_zones(&backend_url, &camera_id, &api_key).await
+/// What this agent is doing right now, for the heartbeat
+async fn heartbeat_snapshot(app: &tauri::AppHandle, agent_id: &str) -> api::Heartbeat {
+    let (cameras_connected, cameras_paused) = {
+        let cameras = app.state::<CameraMap>();
+        let cameras = cameras.read().await;
+        (
+            cameras.values().filter(|handle| handle.is_connected).count(),
+            cameras.values().filter(|handle| handle.paused).count(),
+        )
+    };
+    let cameras_monitored = app.state::<PeriodicTasks>().lock().map(|tasks| tasks.len()).unwrap_or(0);
+
+    api::Heartbeat {
+        agent_id: agent_id.to_string(),
+        version: version::AGENT_VERSION.to_string(),
+        cameras_connected,
+        cameras_monitored,
+        cameras_paused,
+    }
 }
 
+/// Post agent liveness to `backend_url` every `interval_secs`, backing off while it fails
 #[tauri::command]
-async fn delete_zone(
-    camera_id: String,
-    zone_id: i64,
+async fn start_heartbeat(
+    interval_secs: u64,
     api_key: String,
     backend_url: String,
-) -> Result<(), String> {
-    println!("[Rust] Deleting zone {} for camera: {}", zone_id, camera_id);
-    api::delete_zone(&backend_url, &camera_id, zone_id, &api_key).await
+    app: tauri::AppHandle,
+    heartbeat: State<'_, HeartbeatTask>,
+) -> Result<String, AgentError> {
+    if interval_secs == 0 {
+        return Err(AgentError::InvalidInput("Interval must be at least 1 second".to_string()));
+    }
+
+    let id = agent_id(&app)?;
+    println!("[Rust] Sending heartbeat as {} every {}s", id, interval_secs);
+
+    let task_id = id.clone();
+    let handle = tauri::async_runtime::spawn(async move {
+        let interval = std::time::Duration::from_secs(interval_secs);
+        let mut failures = 0u32;
+        loop {
+            let beat = heartbeat_snapshot(&app, &task_id).await;
+            let result = match auth_scheme(&app.state::<AuthConfig>()) {
+                Ok(scheme) => api::send_heartbeat(&backend_url, &api_key, &scheme, &beat).await,
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(()) => {
+                    if failures > 0 {
+                        println!("[Heartbeat] Backend reachable again after {} failed attempt(s)", failures);
+                    }
+                    failures = 0;
+                }
+                Err(e) => {
+                    failures = failures.saturating_add(1);
+                    println!("[Heartbeat] Attempt failed ({} in a row): {}", failures, e);
+                }
+            }
+
+            tokio::time::sleep(monitoring::heartbeat_delay(interval, failures)).await;
+        }
+    });
+
+    // Only one heartbeat; restarting replaces the interval and credentials
+    if let Some(previous) = heartbeat.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .replace(handle)
+    {
+        previous.abort();
+    }
+
+    Ok(id)
 }
 
 #[tauri::command]
-async fn show_notification(title: String, body: String, window: Window) {
-    use tauri_plugin_notification::NotificationExt;
+async fn stop_heartbeat(
+    heartbeat: State<'_, HeartbeatTask>,
+) -> Result<(), AgentError> {
+    println!("[Rust] Stopping heartbeat");
+
+    if let Some(handle) = heartbeat.lock()
+        .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?
+        .take()
+    {
+        handle.abort();
+    }
 
-    let _ = window.app_handle()
-        .notification()
-        .builder()
-        .title(title)
-        .body(body)
-        .show();
+    Ok(())
 }
 
-#[tauri::command]
-async fn get_alerts(
-    api_key: String,
-    backend_url: String,
-    camera_id: Option<String>,
-    page: i64,
-    page_size: i64,
-) -> Result<api::AlertListResponse, String> {
-    println!("[Rust] Fetching alerts from backend");
+/// Stop background work, let in-flight uploads finish, then exit
+///
+/// Runs at most once; tray quit and window close can both trigger it.
+async fn shutdown(app: tauri::AppHandle) {
+    if app.state::<ShutdownFlag>().swap(true, std::sync::atomic::Ordering::SeqCst) {
+        return;
+    }
 
-    api::get_alerts(
-        &backend_url,
-        &api_key,
-        camera_id.as_deref(),
-        page,
-        page_size,
-    ).await
+    println!("[Shutdown] Stopping monitoring");
+
+    if let Ok(mut poller) = app.state::<AlertPoller>().lock() {
+        if let Some(handle) = poller.take() {
+            handle.abort();
+        }
+    }
+    if let Ok(mut heartbeat) = app.state::<HeartbeatTask>().lock() {
+        if let Some(handle) = heartbeat.take() {
+            handle.abort();
+        }
+    }
+    if let Ok(mut live_views) = app.state::<LiveViews>().lock() {
+        for (_, handle) in live_views.drain() {
+            handle.abort();
+        }
+    }
+
+    // Periodic tasks see the flag and stop starting cycles; give requests
+    // already sent a chance to land before anything is aborted
+    let metrics = app.state::<SharedMetrics>().inner().clone();
+    let started = std::time::Instant::now();
+    while metrics.requests_in_flight() > 0 && started.elapsed() < SHUTDOWN_DEADLINE {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    if metrics.requests_in_flight() > 0 {
+        println!("[Shutdown] Abandoning {} in-flight requests after {}s",
+                 metrics.requests_in_flight(), SHUTDOWN_DEADLINE.as_secs());
+    }
+
+    if let Ok(mut periodic) = app.state::<PeriodicTasks>().lock() {
+        for (_, task) in periodic.drain() {
+            task.handle.abort();
+        }
+    }
+
+    // Don't leave ffmpeg processes running after the app is gone
+    let handles: Vec<_> = app.state::<CameraMap>().write().await.drain().collect();
+    for (camera_id, mut handle) in handles {
+        if let Err(e) = close_camera(&mut handle).await {
+            println!("[Shutdown] Failed to stop capture for {}: {}", camera_id, e);
+        }
+    }
+
+    // Close the open segment of every recording so it stays playable
+    let active: Vec<_> = app.state::<Recordings>().lock()
+        .map(|mut recordings| recordings.drain().collect())
+        .unwrap_or_default();
+    for (camera_id, mut recording) in active {
+        let stopped = tokio::task::spawn_blocking(move || recording.stop()).await;
+        if let Ok(Err(e)) = stopped {
+            println!("[Shutdown] Failed to stop recording for {}: {}", camera_id, e);
+        }
+    }
+
+    println!("[Shutdown] Done");
+    app.exit(0);
 }
 
 fn main() {
@@ -297,14 +4640,124 @@ fn main() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
         .manage(CameraMap::default())
+        .manage(CameraLocks::default())
+        .manage(DetectionHistories::default())
+        .manage(OnvifCredentialStore::default())
         .manage(FrameCache::default())
+        .manage(PeriodicTasks::default())
+        .manage(AdaptiveSchedules::default())
+        .manage(CameraGroups::default())
+        .manage(SharedMetrics::default())
+        .manage(ResourceSampler::default())
+        .manage(SinkConfig::default())
+        .manage(UploadConfig::default())
+        .manage(UploadSlots::default())
+        .manage(FrameSequences::default())
+        .manage(BackendBreaker::default())
+        .manage(UploadRateLimits::default())
+        .manage(BandwidthMeter::default())
+        .manage(ZoneNotify::default())
+        .manage(PrivacyMaskConfig::default())
+        .manage(Schedule::default())
+        .manage(ScanCancel::default())
+        .manage(HwAccelConfig::default())
+        .manage(DetectionConfig::default())
+        .manage(AlertWebhook::default())
+        .manage(Notifiers::default())
+        .manage(AlertCooldowns::default())
+        .manage(AlertDebouncer::default())
+        .manage(DetectionDeduper::default())
+        .manage(EvidenceStorage::default())
+        .manage(BackendList::default())
+        .manage(AuthConfig::default())
+        .manage(AlertImageSources::default())
+        .manage(AlertPoller::default())
+        .manage(HeartbeatTask::default())
+        .manage(Recordings::default())
+        .manage(BestQualities::default())
+        .manage(LiveViews::default())
+        .manage(RetentionConfig::default())
+        .manage(Labels::default())
+        .manage(ProxyConfig::default())
+        .manage(SigningConfig::default())
+        .manage(ShutdownFlag::default())
         .setup(|app| {
+            // Warn early; settings, recordings and logs all need this directory
+            let data_dir = app.path().app_data_dir()
+                .map_err(|e| AgentError::Internal(format!("Failed to resolve data dir: {}", e)))?;
+            match retention::check_storage(&data_dir) {
+                Ok(status) => println!("[Rust] Data directory {} ({} bytes free)",
+                                       status.path, status.free_bytes.map_or("unknown".to_string(), |b| b.to_string())),
+                Err(e) => println!("[Rust] ⚠️ {}", e),
+            }
+
+            // Restore detection settings saved by a previous run
+            let detection = settings::DetectionSettings::load(&detection_settings_path(app.handle())?);
+            apply_detection_settings(app.handle(), &detection)?;
+            *app.state::<DetectionConfig>().lock()
+                .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))? = detection;
+
+            let bandwidth = settings::BandwidthUsage::load(&bandwidth_path(app.handle())?);
+            replace_state(&app.state::<BandwidthMeter>(), bandwidth)?;
+
+            let zone_notify = zones::ZoneNotifyPolicies::load(&zone_notify_path(app.handle())?);
+            replace_state(&app.state::<ZoneNotify>(), zone_notify)?;
+
+            let camera_groups = monitoring::CameraGroupMap::load(&camera_groups_path(app.handle())?);
+            replace_state(&app.state::<CameraGroups>(), camera_groups)?;
+
+            let mut notifiers = notify::NotifierSettings::load(&notifiers_path(app.handle())?);
+            if let Err(e) = sync_smtp_passwords(&mut notifiers.notifiers) {
+                println!("[Rust] ⚠️ SMTP passwords can't be read; email alerts may fail to log in: {}", e);
+            }
+            replace_state(&app.state::<Notifiers>(), notifiers)?;
+
+            // Refuse to start rather than upload frames the masks should cover
+            let privacy_masks = privacy::PrivacyMasks::load(&privacy_masks_path(app.handle())?)?;
+            replace_state(&app.state::<PrivacyMaskConfig>(), privacy_masks)?;
+
+            let schedule = settings::MonitoringSchedule::load(&schedule_path(app.handle())?);
+            replace_state(&app.state::<Schedule>(), schedule)?;
+
+            let retention = retention::RetentionPolicy::load(&retention_path(app.handle())?);
+            replace_state(&app.state::<RetentionConfig>(), retention)?;
+            tauri::async_runtime::spawn(enforce_retention(app.handle().clone()));
+
+            let proxy_settings = proxy::ProxySettings::load(&proxy_path(app.handle())?);
+            if let Err(e) = proxy::configure(&proxy_settings) {
+                println!("[Rust] Ignoring saved proxy settings: {}", e);
+            }
+            replace_state(&app.state::<ProxyConfig>(), proxy_settings)?;
+
+            let signing_settings = signing::SigningSettings::load(&signing_path(app.handle())?);
+            if signing_settings.enabled {
+                match credentials::load_signing_secret() {
+                    Ok(Some(secret)) => signing::configure(Some(&secret))?,
+                    Ok(None) => println!("[Rust] ⚠️ Request signing is on but no secret is stored; uploads are unsigned"),
+                    Err(e) => println!("[Rust] ⚠️ Request signing is on but the secret can't be read: {}", e),
+                }
+            }
+            replace_state(&app.state::<SigningConfig>(), signing_settings)?;
+
+            match labels::LabelMap::load(&labels_path(app.handle())?) {
+                Ok(labels) => replace_state(&app.state::<Labels>(), labels)?,
+                Err(e) => println!("[Rust] Ignoring labels file: {}", e),
+            }
+
+            tauri::async_runtime::spawn(report_stream_fps(app.handle().clone()));
+            tauri::async_runtime::spawn(supervise_periodic_tasks(app.handle().clone()));
+
             // Create system tray
             let toggle = MenuItem::with_id(app, "toggle", "Monitoring: ON", true, None::<&str>)?;
             let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
 
             let menu = Menu::with_items(app, &[&toggle, &quit])?;
 
+            let tray_toggle = toggle.clone();
+            tauri::async_runtime::spawn(watch_schedule(app.handle().clone(), move |text| {
+                let _ = tray_toggle.set_text(text);
+            }));
+
             let _tray = TrayIconBuilder::new()
                 .menu(&menu)
                 .on_menu_event(|app, event| match event.id.as_ref() {
@@ -314,7 +4767,7 @@ fn main() {
                     }
                     "quit" => {
                         println!("Quit from tray");
-                        app.exit(0);
+                        tauri::async_runtime::spawn(shutdown(app.clone()));
                     }
                     _ => {}
                 })
@@ -331,22 +4784,318 @@ fn main() {
 
             Ok(())
         })
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                // Exit happens once shutdown has cleaned up
+                api.prevent_close();
+                tauri::async_runtime::spawn(shutdown(window.app_handle().clone()));
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             scan_network,
+            scan_hosts,
+            cancel_scan,
+            find_duplicate_cameras,
+            list_network_interfaces,
             test_camera,
+            test_camera_auth,
+            derive_camera_id,
             diagnose_rtsp,
+            probe_streams,
+            set_onvif_credentials,
+            set_camera_credentials,
+            delete_camera_credentials,
             connect_camera,
             get_frame,
+            start_live_view,
+            stop_live_view,
             disconnect_camera,
+            reconnect_camera,
+            set_camera_transform,
+            list_camera_streams,
+            set_sub_stream,
+            set_active_stream,
+            preview_capture,
+            capture_best_frame,
+            pause_camera,
+            resume_camera,
+            pause_group,
+            resume_group,
+            set_seek_mode,
+            get_camera_status,
+            list_cameras,
+            set_camera_group,
+            get_capture_stats,
+            get_stream_fps,
+            list_capture_devices,
+            estimate_batch,
+            set_request_signing,
+            get_request_signing,
+            get_backend_breaker,
+            get_bandwidth_usage,
+            get_schedule,
+            set_schedule,
+            set_bandwidth_cap,
+            benchmark_capture,
             send_frame_to_cloud,
+            test_detection,
+            frame_diff,
             get_latest_frame,
+            clear_cache,
+            clear_all_cache,
             get_latest_detections,
+            get_detection_timeseries,
+            capture_burst,
+            detect_burst,
+            set_burst_capture,
+            generate_incident_report,
+            export_snapshot_bundle,
             create_zone,
+            set_zone_notify,
+            set_privacy_masks,
+            get_privacy_masks,
+            apply_zone_template,
+            validate_zone,
+            test_zone,
+            detect_image,
+            measure_e2e_latency,
             get_zones,
             delete_zone,
             show_notification,
+            capabilities,
+            resource_stats,
+            metrics,
+            version_info,
             get_alerts,
+            fetch_alert_image,
+            stream_alert_image,
+            poll_new_alerts,
+            start_alert_polling,
+            stop_alert_polling,
+            start_heartbeat,
+            stop_heartbeat,
+            start_periodic_capture,
+            stop_periodic_capture,
+            start_group_capture,
+            stop_group_capture,
+            set_adaptive_capture,
+            clear_adaptive_capture,
+            list_monitoring_tasks,
+            start_recording,
+            stop_recording,
+            get_recording_status,
+            list_recordings,
+            apply_retention,
+            get_disk_usage,
+            check_storage,
+            reload_labels,
+            get_labels,
+            set_proxy,
+            get_proxy,
+            set_detection_sink,
+            get_detection_sink,
+            set_backends,
+            set_auth_scheme,
+            set_max_frame_bytes,
+            set_upload_format,
+            set_previous_frame_context,
+            set_detection_api,
+            set_response_format,
+            set_upload_concurrency,
+            probe_hwaccel,
+            set_hwaccel,
+            set_alert_webhook,
+            set_notifiers,
+            get_notifiers,
+            set_alert_debounce,
+            set_detection_dedup,
+            get_detection_settings,
+            update_detection_settings,
+            set_evidence_storage,
+            test_webhook,
+            test_alert_pipeline,
+            export_config,
+            import_config,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_handle() -> camera::CameraHandle {
+        camera::CameraHandle {
+            source: Arc::new(tokio::sync::Mutex::new(camera::CameraSource::Http("http://127.0.0.1/".to_string()))),
+            source_url: "http://127.0.0.1/".to_string(),
+            streams: camera::CameraStreams {
+                main: "http://127.0.0.1/".to_string(),
+                sub: None,
+                active: camera::StreamSelection::Main,
+            },
+            username: None,
+            password: None,
+            rtsp_transport: camera::RtspTransport::default(),
+            roi: None,
+            transform: camera::FrameTransform::default(),
+            is_connected: true,
+            paused: false,
+            capture_mode: camera::CaptureMode::OneShot,
+            hwaccel: settings::HwAccel::None,
+            persistent_capture: None,
+            capture_stats: Arc::default(),
+            freeze: Arc::default(),
+            clock_offset_secs: None,
+            retry: camera::RetryPolicy::default(),
+            closed: Arc::default(),
+            sample_aspect: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn benchmark_leaves_video_position_alone() {
+        let mut handle = test_handle();
+        handle.source = Arc::new(tokio::sync::Mutex::new(camera::CameraSource::VideoFile {
+            path: "missing.mp4".to_string(),
+            current_frame: 5,
+            seek_mode: camera::SeekMode::default(),
+            fps: None,
+        }));
+
+        camera::benchmark_capture(&handle, 2).await.unwrap();
+
+        let camera::CameraSource::VideoFile { current_frame, .. } = &*handle.source.lock().await else { unreachable!() };
+        assert_eq!(*current_frame, 5);
+    }
+
+    #[tokio::test]
+    async fn switching_streams_swaps_the_live_source() {
+        let cameras = CameraMap::default();
+        cameras.write().await.insert("cam".to_string(), test_handle());
+
+        let missing = switch_stream(&cameras, "cam", camera::StreamSelection::Sub).await;
+        assert!(matches!(missing, Err(AgentError::InvalidInput(_))));
+
+        cameras.write().await.get_mut("cam").unwrap().streams.sub = Some("http://127.0.0.1/sub".to_string());
+        switch_stream(&cameras, "cam", camera::StreamSelection::Sub).await.unwrap();
+
+        let handle = cameras.read().await.get("cam").cloned().unwrap();
+        assert_eq!(handle.source_url, "http://127.0.0.1/sub");
+        assert_eq!(handle.streams.main, "http://127.0.0.1/");
+        assert!(matches!(&*handle.source.lock().await, camera::CameraSource::Http(url) if url == "http://127.0.0.1/sub"));
+    }
+
+    #[tokio::test]
+    async fn clearing_cache_resets_dedup_for_that_camera_only() {
+        let cache = FrameCache::default();
+        let dedup = DetectionDeduper::default();
+        for camera_id in ["cam-1", "cam-2"] {
+            let detections = sink::empty_response(camera_id);
+            dedup.lock().unwrap().is_new(&detections);
+            cache.write().await.insert(camera_id.to_string(), CachedData {
+                frame: "frame".to_string(),
+                detections,
+                timestamp: std::time::SystemTime::now(),
+            });
+        }
+
+        assert!(clear_cached(&cache, &dedup, "cam-1").await.unwrap());
+        assert!(!clear_cached(&cache, &dedup, "cam-1").await.unwrap());
+        assert!(cache.read().await.contains_key("cam-2"));
+
+        let mut dedup = dedup.lock().unwrap();
+        assert!(dedup.is_new(&sink::empty_response("cam-1")));
+        assert!(!dedup.is_new(&sink::empty_response("cam-2")));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn camera_map_and_cache_survive_concurrent_access() {
+        let cameras = CameraMap::default();
+        let cache = FrameCache::default();
+        for i in 0..8 {
+            cameras.write().await.insert(format!("cam-{}", i), test_handle());
+        }
+
+        let mut tasks = Vec::new();
+        for worker in 0..64 {
+            let cameras = cameras.clone();
+            let cache = cache.clone();
+            tasks.push(tokio::spawn(async move {
+                for round in 0..200 {
+                    let camera_id = format!("cam-{}", round % 8);
+                    match (worker + round) % 4 {
+                        0 => {
+                            set_paused(&cameras, &camera_id, round % 2 == 0).await.unwrap();
+                        }
+                        1 => {
+                            cache.write().await.insert(camera_id.clone(), CachedData {
+                                frame: format!("frame-{}", round),
+                                detections: sink::empty_response(&camera_id),
+                                timestamp: std::time::SystemTime::now(),
+                            });
+                        }
+                        2 => {
+                            // Holding a read guard across an await must not stall writers
+                            let guard = cache.read().await;
+                            tokio::task::yield_now().await;
+                            let _ = guard.get(&camera_id).map(|cached| cached.frame.clone());
+                        }
+                        _ => {
+                            let _ = ensure_not_paused(&cameras, &camera_id).await;
+                        }
+                    }
+                }
+            }));
+        }
+
+        let all = async {
+            for task in tasks {
+                task.await.unwrap();
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(10), all)
+            .await
+            .expect("concurrent camera map/cache access deadlocked");
+
+        assert_eq!(cameras.read().await.len(), 8);
+        assert_eq!(cache.read().await.len(), 8);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn slow_camera_does_not_hold_up_the_others() {
+        let cameras = CameraMap::default();
+        let locks = CameraLocks::default();
+        for i in 0..20 {
+            cameras.write().await.insert(format!("cam-{}", i), test_handle());
+        }
+
+        // cam-0 is stuck in a slow connect
+        let stuck = lock_camera(&locks, "cam-0").await.unwrap();
+
+        let mut tasks = Vec::new();
+        for i in 1..20 {
+            let (cameras, locks) = (cameras.clone(), locks.clone());
+            tasks.push(tokio::spawn(async move {
+                let camera_id = format!("cam-{}", i);
+                let _lock = lock_camera(&locks, &camera_id).await.unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                set_paused(&cameras, &camera_id, true).await.unwrap();
+            }));
+        }
+        let all = async {
+            for task in tasks {
+                task.await.unwrap();
+            }
+        };
+        // Run side by side, the 19 operations take about one sleep, not 19
+        tokio::time::timeout(std::time::Duration::from_millis(500), all)
+            .await
+            .expect("operations on other cameras waited on cam-0");
+
+        let second = tokio::time::timeout(std::time::Duration::from_millis(50), lock_camera(&locks, "cam-0")).await;
+        assert!(second.is_err(), "cam-0 operations must still be serialized");
+        drop(stuck);
+        assert!(lock_camera(&locks, "cam-0").await.is_ok());
+    }
+}
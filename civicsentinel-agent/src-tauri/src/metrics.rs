@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use serde::Serialize;
+use crate::error::AgentError;
+
+/// Upper bounds (seconds) of the backend latency histogram buckets
+const LATENCY_BUCKETS: [f64; 8] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Non-cumulative count per bucket; the extra slot is +Inf
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        let index = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[index] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+/// Agent health counters, rendered in Prometheus text format
+#[derive(Debug, Default)]
+pub struct Metrics {
+    frames_captured: Mutex<HashMap<String, u64>>,
+    capture_failures: Mutex<HashMap<String, u64>>,
+    detections_total: AtomicU64,
+    alerts_total: AtomicU64,
+    backend_errors_total: AtomicU64,
+    requests_in_flight: AtomicI64,
+    uploads_skipped_total: AtomicU64,
+    backend_latency: Mutex<Histogram>,
+    last_e2e_latency: Mutex<Option<E2eLatency>>,
+}
+
+/// Breakdown of one capture → upload → detection pass, from `measure_e2e_latency`
+#[derive(Debug, Clone, Serialize)]
+pub struct E2eLatency {
+    pub capture_ms: f64,
+    /// Encoding, upload and response transfer: the round trip minus inference
+    pub upload_ms: f64,
+    pub inference_ms: f64,
+    /// True when the backend didn't report its processing time and
+    /// `inference_ms` is the round trip minus a health check's round trip
+    pub inference_estimated: bool,
+    pub total_ms: f64,
+}
+
+impl E2eLatency {
+    /// Split a detection round trip into upload and inference time
+    ///
+    /// `baseline_rtt` is a request the backend answers without inference,
+    /// used to estimate inference when the backend doesn't report it.
+    pub fn new(capture: Duration, round_trip: Duration, reported_inference_ms: Option<f64>, baseline_rtt: Option<Duration>) -> Self {
+        let round_trip_ms = round_trip.as_secs_f64() * 1000.0;
+        let (inference_ms, inference_estimated) = match reported_inference_ms {
+            Some(inference_ms) => (inference_ms.min(round_trip_ms), false),
+            None => {
+                let baseline_ms = baseline_rtt.map_or(0.0, |rtt| rtt.as_secs_f64() * 1000.0);
+                ((round_trip_ms - baseline_ms).max(0.0), true)
+            }
+        };
+        let capture_ms = capture.as_secs_f64() * 1000.0;
+
+        Self {
+            capture_ms,
+            upload_ms: round_trip_ms - inference_ms,
+            inference_ms,
+            inference_estimated,
+            total_ms: capture_ms + round_trip_ms,
+        }
+    }
+}
+
+/// Decrements the in-flight gauge when dropped
+pub struct InFlightGuard<'a>(&'a Metrics);
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.0.requests_in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Metrics {
+    pub fn record_frame(&self, camera_id: &str) {
+        if let Ok(mut frames) = self.frames_captured.lock() {
+            *frames.entry(camera_id.to_string()).or_default() += 1;
+        }
+    }
+
+    pub fn record_capture_failure(&self, camera_id: &str) {
+        if let Ok(mut failures) = self.capture_failures.lock() {
+            *failures.entry(camera_id.to_string()).or_default() += 1;
+        }
+    }
+
+    pub fn record_detections(&self, detections: usize, alerts: usize) {
+        self.detections_total.fetch_add(detections as u64, Ordering::Relaxed);
+        self.alerts_total.fetch_add(alerts as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_backend_request(&self, latency: Duration, success: bool) {
+        if !success {
+            self.backend_errors_total.fetch_add(1, Ordering::Relaxed);
+        }
+        if let Ok(mut histogram) = self.backend_latency.lock() {
+            histogram.observe(latency.as_secs_f64());
+        }
+    }
+
+    pub fn record_e2e_latency(&self, latency: &E2eLatency) {
+        if let Ok(mut last) = self.last_e2e_latency.lock() {
+            *last = Some(latency.clone());
+        }
+    }
+
+    /// Count a frame dropped because no upload slot freed up in time
+    pub fn record_upload_skipped(&self) {
+        self.uploads_skipped_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a backend request as queued until the guard is dropped
+    pub fn track_in_flight(&self) -> InFlightGuard<'_> {
+        self.requests_in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlightGuard(self)
+    }
+
+    /// Backend requests currently waiting on a response
+    /// Mean detection request latency so far; None before the first request
+    pub fn mean_backend_latency(&self) -> Option<Duration> {
+        let histogram = self.backend_latency.lock().ok()?;
+        (histogram.count > 0).then(|| Duration::from_secs_f64(histogram.sum / histogram.count as f64))
+    }
+
+    pub fn requests_in_flight(&self) -> i64 {
+        self.requests_in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Render all metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        write_per_camera(
+            &mut out,
+            "civicsentinel_frames_captured_total",
+            "Frames read from camera captures",
+            &self.frames_captured,
+        );
+        write_per_camera(
+            &mut out,
+            "civicsentinel_capture_failures_total",
+            "Failed frame reads",
+            &self.capture_failures,
+        );
+
+        write_scalar(&mut out, "civicsentinel_detections_total", "counter",
+                     "Objects detected across all cameras",
+                     self.detections_total.load(Ordering::Relaxed) as f64);
+        write_scalar(&mut out, "civicsentinel_alerts_total", "counter",
+                     "Zone alerts returned by the backend",
+                     self.alerts_total.load(Ordering::Relaxed) as f64);
+        write_scalar(&mut out, "civicsentinel_backend_errors_total", "counter",
+                     "Detection requests that failed",
+                     self.backend_errors_total.load(Ordering::Relaxed) as f64);
+        write_scalar(&mut out, "civicsentinel_uploads_skipped_total", "counter",
+                     "Frames dropped while waiting for an upload slot",
+                     self.uploads_skipped_total.load(Ordering::Relaxed) as f64);
+        write_scalar(&mut out, "civicsentinel_requests_in_flight", "gauge",
+                     "Detection requests waiting on the backend",
+                     self.requests_in_flight.load(Ordering::Relaxed) as f64);
+
+        if let Ok(histogram) = self.backend_latency.lock() {
+            let name = "civicsentinel_backend_latency_seconds";
+            let _ = writeln!(out, "# HELP {} Detection request latency", name);
+            let _ = writeln!(out, "# TYPE {} histogram", name);
+
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets.iter()) {
+                cumulative += count;
+                let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulative);
+            }
+            let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, histogram.count);
+            let _ = writeln!(out, "{}_sum {}", name, histogram.sum);
+            let _ = writeln!(out, "{}_count {}", name, histogram.count);
+        }
+
+        if let Ok(Some(latency)) = self.last_e2e_latency.lock().as_deref() {
+            let name = "civicsentinel_e2e_latency_seconds";
+            let _ = writeln!(out, "# HELP {} Stages of the last measured capture-to-detection pass", name);
+            let _ = writeln!(out, "# TYPE {} gauge", name);
+            for (stage, ms) in [
+                ("capture", latency.capture_ms),
+                ("upload", latency.upload_ms),
+                ("inference", latency.inference_ms),
+                ("total", latency.total_ms),
+            ] {
+                let _ = writeln!(out, "{}{{stage=\"{}\"}} {}", name, stage, ms / 1000.0);
+            }
+        }
+
+        out
+    }
+}
+
+fn write_scalar(out: &mut String, name: &str, kind: &str, help: &str, value: f64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn write_per_camera(out: &mut String, name: &str, help: &str, values: &Mutex<HashMap<String, u64>>) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+
+    if let Ok(values) = values.lock() {
+        let mut entries: Vec<_> = values.iter().collect();
+        entries.sort();
+        for (camera_id, value) in entries {
+            let _ = writeln!(out, "{}{{camera_id=\"{}\"}} {}", name, escape_label(camera_id), value);
+        }
+    }
+}
+
+/// Escape a label value per the exposition format
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Resource usage snapshot from `resource_stats`
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceStats {
+    /// Agent CPU since the previous snapshot, in percent of one core;
+    /// `None` on the first snapshot, which has nothing to compare against
+    pub cpu_percent: Option<f32>,
+    pub memory_bytes: u64,
+    /// ffmpeg processes started by the agent (captures, recordings, probes)
+    pub ffmpeg_processes: usize,
+    pub persistent_captures: usize,
+    pub recordings: usize,
+    /// Tasks alive on the async runtime, including background loops
+    pub runtime_tasks: usize,
+    pub runtime_workers: usize,
+}
+
+/// CPU, memory and child processes of the agent itself
+///
+/// Keeps its `System` between samples, since CPU usage is measured over the
+/// interval since the last refresh.
+pub struct ProcessSampler {
+    system: sysinfo::System,
+    sampled: bool,
+}
+
+/// One `ProcessSampler` reading
+#[derive(Debug, Clone, Copy)]
+pub struct ProcessSample {
+    pub cpu_percent: Option<f32>,
+    pub memory_bytes: u64,
+    pub ffmpeg_processes: usize,
+}
+
+impl Default for ProcessSampler {
+    fn default() -> Self {
+        Self { system: sysinfo::System::new(), sampled: false }
+    }
+}
+
+impl std::fmt::Debug for ProcessSampler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProcessSampler").field("sampled", &self.sampled).finish()
+    }
+}
+
+impl ProcessSampler {
+    pub fn sample(&mut self) -> Result<ProcessSample, AgentError> {
+        let pid = sysinfo::get_current_pid()
+            .map_err(|e| AgentError::Internal(format!("Failed to get agent pid: {}", e)))?;
+
+        // All processes, not just ours, so ffmpeg children can be found
+        self.system.refresh_processes_specifics(
+            sysinfo::ProcessRefreshKind::new().with_cpu().with_memory(),
+        );
+        let process = self.system.process(pid)
+            .ok_or_else(|| AgentError::Internal("Agent process not found".to_string()))?;
+
+        let ffmpeg_processes = self.system.processes()
+            .values()
+            .filter(|child| child.parent() == Some(pid) && child.name().to_lowercase().starts_with("ffmpeg"))
+            .count();
+
+        let sample = ProcessSample {
+            cpu_percent: self.sampled.then(|| process.cpu_usage()),
+            memory_bytes: process.memory(),
+            ffmpeg_processes,
+        };
+        self.sampled = true;
+        Ok(sample)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn e2e_latency_uses_reported_inference_or_estimates_it() {
+        let reported = E2eLatency::new(Duration::from_millis(40), Duration::from_millis(200), Some(150.0), None);
+        assert_eq!((reported.upload_ms, reported.inference_ms, reported.total_ms), (50.0, 150.0, 240.0));
+        assert!(!reported.inference_estimated);
+
+        let estimated = E2eLatency::new(Duration::ZERO, Duration::from_millis(200), None, Some(Duration::from_millis(30)));
+        assert_eq!((estimated.upload_ms, estimated.inference_ms), (30.0, 170.0));
+        assert!(estimated.inference_estimated);
+    }
+
+    #[test]
+    fn renders_last_e2e_latency() {
+        let metrics = Metrics::default();
+        assert!(!metrics.render().contains("e2e"));
+
+        metrics.record_e2e_latency(&E2eLatency::new(Duration::from_millis(100), Duration::from_millis(400), Some(250.0), None));
+        let rendered = metrics.render();
+        assert!(rendered.contains("civicsentinel_e2e_latency_seconds{stage=\"inference\"} 0.25"));
+        assert!(rendered.contains("civicsentinel_e2e_latency_seconds{stage=\"total\"} 0.5"));
+    }
+}
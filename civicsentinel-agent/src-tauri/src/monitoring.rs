@@ -0,0 +1,966 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+use serde::{Deserialize, Serialize};
+
+/// File in the app data directory holding camera group assignments
+pub const CAMERA_GROUPS_FILE: &str = "camera_groups.json";
+
+/// How long a periodic cycle may run past its due time before the watchdog
+/// treats the task as stuck
+pub const STALL_GRACE: Duration = Duration::from_secs(5 * 60);
+
+/// How often the watchdog checks on periodic capture tasks
+pub const WATCHDOG_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Running totals a periodic capture task keeps about itself
+#[derive(Debug, Default)]
+pub struct TaskStats {
+    pub last_capture: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_detection_count: Option<usize>,
+    pub consecutive_failures: u32,
+    /// When the next cycle must have finished by, set before each sleep
+    pub progress_deadline: Option<Instant>,
+}
+
+impl TaskStats {
+    /// Note that the next cycle is due after `delay`
+    pub fn expect_progress_within(&mut self, delay: Duration) {
+        self.progress_deadline = Some(Instant::now() + delay + STALL_GRACE);
+    }
+
+    /// Whether the task missed its deadline, e.g. because it died or a
+    /// cycle hung
+    pub fn is_stuck(&self, now: Instant) -> bool {
+        self.progress_deadline.is_some_and(|deadline| now > deadline)
+    }
+
+    pub fn record_success(&mut self, detections: usize) {
+        self.last_capture = Some(chrono::Utc::now());
+        self.last_detection_count = Some(detections);
+        self.consecutive_failures = 0;
+    }
+
+    pub fn record_failure(&mut self) {
+        self.consecutive_failures += 1;
+    }
+}
+
+/// One row of the monitoring status panel
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitoringTaskStatus {
+    pub camera_id: String,
+    pub paused: bool,
+    pub interval_secs: u64,
+    pub jitter_secs: u64,
+    /// RFC 3339 time of the last successful cycle
+    pub last_capture: Option<String>,
+    pub last_detection_count: Option<usize>,
+    pub consecutive_failures: u32,
+    /// Interval before the next cycle when an adaptive schedule is set
+    pub adaptive_interval_secs: Option<u64>,
+    /// Missed its progress deadline; the watchdog will restart it
+    pub stuck: bool,
+    /// Times the watchdog has restarted this task
+    pub restarts: u32,
+}
+
+/// Monitoring cadence that speeds up while a camera sees activity
+///
+/// Activity switches straight to `fast`. Once `idle_cycles` cycles in a row
+/// see nothing, the interval doubles each idle cycle until it reaches `slow`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AdaptiveSchedule {
+    pub base_secs: u64,
+    pub fast_secs: u64,
+    pub slow_secs: u64,
+    pub idle_cycles: u32,
+    current_secs: u64,
+    idle_streak: u32,
+}
+
+impl AdaptiveSchedule {
+    pub fn new(base_secs: u64, fast_secs: u64, slow_secs: u64, idle_cycles: u32) -> Self {
+        Self {
+            base_secs,
+            fast_secs,
+            slow_secs,
+            idle_cycles,
+            current_secs: base_secs,
+            idle_streak: 0,
+        }
+    }
+
+    /// Interval to wait before the next cycle
+    pub fn current(&self) -> Duration {
+        Duration::from_secs(self.current_secs)
+    }
+
+    /// Account for one completed cycle and return the interval before the next
+    pub fn next_interval(&mut self, active: bool) -> Duration {
+        if active {
+            self.idle_streak = 0;
+            self.current_secs = self.fast_secs;
+        } else {
+            self.idle_streak = self.idle_streak.saturating_add(1);
+            if self.idle_streak >= self.idle_cycles {
+                self.current_secs = (self.current_secs.max(self.base_secs) * 2).min(self.slow_secs);
+            }
+        }
+        self.current()
+    }
+}
+
+/// Longest a failing heartbeat waits between attempts
+pub const MAX_HEARTBEAT_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Heartbeat delay after `failures` consecutive failed attempts
+///
+/// Doubles per failure from the normal interval, capped at `MAX_HEARTBEAT_BACKOFF`
+/// (or the interval itself if that is longer).
+pub fn heartbeat_delay(interval: Duration, failures: u32) -> Duration {
+    let backoff = interval.saturating_mul(2u32.saturating_pow(failures.min(16)));
+    backoff.min(MAX_HEARTBEAT_BACKOFF.max(interval))
+}
+
+/// Captures the rolling success rate is computed over
+const CAPTURE_WINDOW: usize = 50;
+
+/// Camera link health, updated on every `capture_frame`
+#[derive(Debug, Default)]
+pub struct CaptureStats {
+    total: u64,
+    successes: u64,
+    total_latency: Duration,
+    last_success: Option<chrono::DateTime<chrono::Utc>>,
+    last_failure: Option<chrono::DateTime<chrono::Utc>>,
+    /// Outcomes of the most recent captures, oldest first
+    recent: VecDeque<bool>,
+    /// Times the persistent stream ended and was started again
+    stream_reopens: u64,
+}
+
+impl CaptureStats {
+    pub fn record(&mut self, success: bool, latency: Duration) {
+        self.total += 1;
+        self.total_latency += latency;
+
+        if success {
+            self.successes += 1;
+            self.last_success = Some(chrono::Utc::now());
+        } else {
+            self.last_failure = Some(chrono::Utc::now());
+        }
+
+        if self.recent.len() == CAPTURE_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(success);
+    }
+
+    /// Count persistent stream reopens; returns the new total
+    pub fn record_reopens(&mut self, reopens: u64) -> u64 {
+        self.stream_reopens += reopens;
+        self.stream_reopens
+    }
+
+    pub fn report(&self, camera_id: &str) -> CaptureStatsReport {
+        let recent_successes = self.recent.iter().filter(|success| **success).count();
+
+        CaptureStatsReport {
+            camera_id: camera_id.to_string(),
+            total_captures: self.total,
+            successful_captures: self.successes,
+            avg_latency_ms: (self.total > 0)
+                .then(|| self.total_latency.as_secs_f64() * 1000.0 / self.total as f64),
+            last_success: self.last_success.map(|t| t.to_rfc3339()),
+            last_failure: self.last_failure.map(|t| t.to_rfc3339()),
+            recent_success_rate: (!self.recent.is_empty())
+                .then(|| recent_successes as f64 / self.recent.len() as f64),
+            recent_window: self.recent.len(),
+            stream_reopens: self.stream_reopens,
+        }
+    }
+}
+
+/// Weight of the newest inter-frame interval in the frame rate average
+const FPS_SMOOTHING: f64 = 0.2;
+
+/// Effective frame rate of a stream, as an EMA of the time between frames
+#[derive(Debug, Default)]
+pub struct FpsMeter {
+    last_frame: Option<Instant>,
+    avg_interval: Option<f64>,
+}
+
+impl FpsMeter {
+    pub fn tick(&mut self, now: Instant) {
+        if let Some(last) = self.last_frame {
+            let interval = now.duration_since(last).as_secs_f64();
+            self.avg_interval = Some(match self.avg_interval {
+                Some(avg) => avg + FPS_SMOOTHING * (interval - avg),
+                None => interval,
+            });
+        }
+        self.last_frame = Some(now);
+    }
+
+    /// Frames per second, or None before two frames have arrived
+    ///
+    /// A stream that has stopped delivering decays toward zero instead of
+    /// reporting its last healthy rate.
+    pub fn fps(&self, now: Instant) -> Option<f64> {
+        let avg = self.avg_interval?;
+        let since_last = now.duration_since(self.last_frame?).as_secs_f64();
+        let interval = avg.max(since_last);
+        (interval > 0.0).then(|| 1.0 / interval)
+    }
+}
+
+/// One stream's effective frame rate, sent in `fps-update` events
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamFps {
+    pub camera_id: String,
+    pub fps: Option<f64>,
+}
+
+/// Fastest a live view pushes frames; base64 JPEGs beyond this swamp the IPC bridge
+pub const MAX_LIVE_VIEW_FPS: f64 = 10.0;
+
+/// Live view rate on one-shot cameras, where every frame starts ffmpeg
+pub const ONE_SHOT_LIVE_VIEW_FPS: f64 = 1.0;
+
+/// Slowest a live view runs, one frame every 10 seconds
+pub const MIN_LIVE_VIEW_FPS: f64 = 0.1;
+
+/// Rate a live view actually runs at: the requested one, but never faster
+/// than a persistent stream delivers frames or one-shot capture can keep up,
+/// nor slower than `MIN_LIVE_VIEW_FPS` however slow the stream reports
+pub fn live_view_fps(requested: f64, persistent: bool, stream_fps: Option<f64>) -> f64 {
+    let ceiling = match (persistent, stream_fps) {
+        (true, Some(stream_fps)) => stream_fps.min(MAX_LIVE_VIEW_FPS),
+        (true, None) => MAX_LIVE_VIEW_FPS,
+        (false, _) => ONE_SHOT_LIVE_VIEW_FPS,
+    };
+    requested.min(ceiling).max(MIN_LIVE_VIEW_FPS)
+}
+
+/// `frame_diff` below which two captures count as the same picture; sensor
+/// noise keeps a live but static scene above it
+pub const FROZEN_DIFF_THRESHOLD: f64 = 0.0005;
+
+/// Unchanged captures in a row before a camera counts as frozen
+pub const FROZEN_MIN_CAPTURES: u32 = 5;
+
+/// The unchanged run must also span at least this long, so fast polling of
+/// a buffered stream can't trip it
+pub const FROZEN_MIN_DURATION: Duration = Duration::from_secs(30);
+
+/// A change in whether a camera's picture is frozen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FreezeTransition {
+    Froze,
+    Thawed,
+}
+
+/// Camera clocks further than this from ours raise `camera-clock-drift`
+pub const CLOCK_DRIFT_WARN_SECS: i64 = 30;
+
+/// Payload of the `camera-clock-drift` event
+#[derive(Debug, Clone, Serialize)]
+pub struct ClockDrift {
+    pub camera_id: String,
+    /// Positive when the camera is ahead of the agent
+    pub offset_secs: i64,
+}
+
+/// Upload pause after a 429 that didn't say how long to wait
+pub const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// Longest upload pause a `Retry-After` can impose, so a bad header can't
+/// silence a camera for days
+pub const MAX_RETRY_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// Cameras whose uploads are held back after the backend answered 429
+#[derive(Debug, Default)]
+pub struct RateLimits {
+    until: HashMap<String, Instant>,
+}
+
+impl RateLimits {
+    /// Hold back a camera's uploads for `retry_after` (or the default),
+    /// returning the pause actually applied
+    pub fn pause(&mut self, camera_id: &str, retry_after: Option<Duration>, now: Instant) -> Duration {
+        let pause = retry_after.unwrap_or(DEFAULT_RETRY_AFTER).min(MAX_RETRY_AFTER);
+        self.until.insert(camera_id.to_string(), now + pause);
+        pause
+    }
+
+    /// Time left before a camera may upload again; None once it may
+    pub fn remaining(&mut self, camera_id: &str, now: Instant) -> Option<Duration> {
+        let until = *self.until.get(camera_id)?;
+        if until <= now {
+            self.until.remove(camera_id);
+            return None;
+        }
+        Some(until - now)
+    }
+}
+
+/// A group name with surrounding whitespace removed; None clears the group
+pub fn normalize_group(group: Option<String>) -> Option<String> {
+    group.map(|group| group.trim().to_string()).filter(|group| !group.is_empty())
+}
+
+/// Cameras assigned to `group`, sorted by id
+pub fn group_members(groups: &HashMap<String, String>, group: &str) -> Vec<String> {
+    let mut members: Vec<String> = groups.iter()
+        .filter(|(_, camera_group)| camera_group.as_str() == group)
+        .map(|(camera_id, _)| camera_id.clone())
+        .collect();
+    members.sort();
+    members
+}
+
+/// Operator-assigned group per camera id
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CameraGroupMap(HashMap<String, String>);
+
+impl CameraGroupMap {
+    pub fn get(&self, camera_id: &str) -> Option<String> {
+        self.0.get(camera_id).cloned()
+    }
+
+    /// Put a camera in a group, or take it out of its group with None
+    pub fn set(&mut self, camera_id: &str, group: Option<String>) {
+        match normalize_group(group) {
+            Some(group) => self.0.insert(camera_id.to_string(), group),
+            None => self.0.remove(camera_id),
+        };
+    }
+
+    pub fn members(&self, group: &str) -> Vec<String> {
+        group_members(&self.0, group)
+    }
+
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), crate::error::AgentError> {
+        use crate::error::AgentError;
+
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| AgentError::Internal(format!("Failed to encode camera groups: {}", e)))?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| AgentError::Internal(format!("Failed to create settings directory: {}", e)))?;
+        }
+        std::fs::write(path, json)
+            .map_err(|e| AgentError::Internal(format!("Failed to save camera groups: {}", e)))
+    }
+}
+
+/// Time per detection request assumed before any have been measured
+pub const DEFAULT_BATCH_REQUEST_TIME: Duration = Duration::from_secs(1);
+
+/// What sampling a video for detection would send, cost and take
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatchEstimate {
+    pub frame_count: u64,
+    /// `frame_count` times the configured cost per request
+    pub estimated_cost: f64,
+    /// Seconds the run would take, one request after another
+    pub estimated_duration: f64,
+}
+
+/// Estimate a batch run sampling a frame every `sample_interval_secs` of
+/// a `video_secs` long video, from the first frame on
+pub fn estimate_batch(
+    video_secs: f64,
+    sample_interval_secs: f64,
+    cost_per_request: f64,
+    request_time: Duration,
+) -> Result<BatchEstimate, crate::error::AgentError> {
+    if !sample_interval_secs.is_finite() || sample_interval_secs <= 0.0 {
+        return Err(crate::error::AgentError::InvalidInput(format!(
+            "sample_interval_secs must be positive, got {}", sample_interval_secs
+        )));
+    }
+
+    let frame_count = (video_secs / sample_interval_secs).ceil().max(0.0) as u64;
+    Ok(BatchEstimate {
+        frame_count,
+        estimated_cost: frame_count as f64 * cost_per_request,
+        estimated_duration: frame_count as f64 * request_time.as_secs_f64(),
+    })
+}
+
+/// Payload of the `rate-limited` event
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimited {
+    pub camera_id: String,
+    /// Uploads for the camera resume after this long
+    pub retry_after_secs: u64,
+}
+
+/// Payload of the `camera-frozen` event
+#[derive(Debug, Clone, Serialize)]
+pub struct FrozenCamera {
+    pub camera_id: String,
+    pub unchanged_captures: u32,
+    pub unchanged_secs: u64,
+}
+
+/// Spots streams that stay connected but keep repeating the same frame
+#[derive(Debug, Default)]
+pub struct FreezeDetector {
+    last_frame: Option<Vec<u8>>,
+    unchanged: u32,
+    unchanged_since: Option<Instant>,
+    frozen: bool,
+}
+
+impl FreezeDetector {
+    /// Capture the next frame should be compared with
+    pub fn last_frame(&self) -> Option<&[u8]> {
+        self.last_frame.as_deref()
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    pub fn unchanged_captures(&self) -> u32 {
+        self.unchanged
+    }
+
+    pub fn unchanged_for(&self, now: Instant) -> Duration {
+        self.unchanged_since.map_or(Duration::ZERO, |since| now.duration_since(since))
+    }
+
+    /// Record a capture and its diff against `last_frame`; `None` when there
+    /// was nothing to compare or the frames couldn't be decoded
+    pub fn record(&mut self, frame: Vec<u8>, diff: Option<f64>, now: Instant) -> Option<FreezeTransition> {
+        self.last_frame = Some(frame);
+
+        if diff.is_some_and(|diff| diff < FROZEN_DIFF_THRESHOLD) {
+            self.unchanged += 1;
+            // The run started when the repeated frame was first seen
+            let since = *self.unchanged_since.get_or_insert(now);
+            if !self.frozen && self.unchanged >= FROZEN_MIN_CAPTURES && now.duration_since(since) >= FROZEN_MIN_DURATION {
+                self.frozen = true;
+                return Some(FreezeTransition::Froze);
+            }
+            return None;
+        }
+
+        self.unchanged = 0;
+        self.unchanged_since = None;
+        if std::mem::take(&mut self.frozen) {
+            return Some(FreezeTransition::Thawed);
+        }
+        None
+    }
+}
+
+/// Consecutive failed backend requests that open the circuit
+pub const BREAKER_FAILURE_THRESHOLD: u32 = 5;
+
+/// How long an open circuit rejects requests before allowing a trial
+pub const BREAKER_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Whether detection requests are reaching the backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    /// Requests fail fast until the cooldown ends
+    Open,
+    /// Cooldown over; one trial request decides whether to close again
+    HalfOpen,
+}
+
+/// Breaker state returned by `get_backend_breaker` and sent in `backend-down`
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerStatus {
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    /// Seconds until a trial request is allowed, while open
+    pub retry_in_secs: Option<u64>,
+}
+
+/// Stops sending detection requests to a backend that keeps failing
+///
+/// Opens after `threshold` consecutive failures. Once `cooldown` has passed a
+/// single trial request is let through; its outcome closes the circuit or
+/// opens it for another cooldown.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    failures: u32,
+    opened_at: Option<Instant>,
+    /// When the half-open trial was let through; a trial that never reports
+    /// back stops blocking others after a cooldown
+    trial_started: Option<Instant>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(BREAKER_FAILURE_THRESHOLD, BREAKER_COOLDOWN)
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self { threshold: threshold.max(1), cooldown, failures: 0, opened_at: None, trial_started: None }
+    }
+
+    pub fn state(&self, now: Instant) -> BreakerState {
+        match self.opened_at {
+            None => BreakerState::Closed,
+            Some(opened) if now.duration_since(opened) < self.cooldown => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Whether a request may be sent now
+    pub fn allow(&mut self, now: Instant) -> bool {
+        match self.state(now) {
+            BreakerState::Closed => true,
+            BreakerState::Open => false,
+            BreakerState::HalfOpen => {
+                let trial_pending = self.trial_started
+                    .is_some_and(|started| now.duration_since(started) < self.cooldown);
+                if !trial_pending {
+                    self.trial_started = Some(now);
+                }
+                !trial_pending
+            }
+        }
+    }
+
+    /// Returns true if the circuit was open and is now closed
+    pub fn record_success(&mut self) -> bool {
+        let was_open = self.opened_at.is_some();
+        self.failures = 0;
+        self.opened_at = None;
+        self.trial_started = None;
+        was_open
+    }
+
+    /// Returns true if this failure opened the circuit
+    pub fn record_failure(&mut self, now: Instant) -> bool {
+        self.failures = self.failures.saturating_add(1);
+        self.trial_started = None;
+        if self.opened_at.is_some() {
+            // Failed trial: wait out another cooldown
+            self.opened_at = Some(now);
+            return false;
+        }
+        if self.failures >= self.threshold {
+            self.opened_at = Some(now);
+            return true;
+        }
+        false
+    }
+
+    pub fn status(&self, now: Instant) -> BreakerStatus {
+        BreakerStatus {
+            state: self.state(now),
+            consecutive_failures: self.failures,
+            retry_in_secs: self.opened_at
+                .map(|opened| self.cooldown.saturating_sub(now.duration_since(opened)).as_secs())
+                .filter(|_| self.state(now) == BreakerState::Open),
+        }
+    }
+}
+
+/// Capture statistics returned by `get_capture_stats`
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureStatsReport {
+    pub camera_id: String,
+    pub total_captures: u64,
+    pub successful_captures: u64,
+    /// Mean time per capture attempt, successful or not
+    pub avg_latency_ms: Option<f64>,
+    /// RFC 3339 times of the last successful and failed captures
+    pub last_success: Option<String>,
+    pub last_failure: Option<String>,
+    /// Success rate over the last `recent_window` captures
+    pub recent_success_rate: Option<f64>,
+    pub recent_window: usize,
+    /// Times the persistent stream ended (e.g. an RTSP session timeout)
+    /// and was reopened
+    pub stream_reopens: u64,
+}
+
+/// Payload of the `stream-reopened` event
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamReopened {
+    pub camera_id: String,
+    /// Reopens of this camera's stream so far
+    pub stream_reopens: u64,
+}
+
+/// Detection results kept per camera for charts; an hour at one per second
+pub const DETECTION_HISTORY_CAPACITY: usize = 3600;
+
+/// Most buckets one timeseries request may ask for
+pub const MAX_TIMESERIES_BUCKETS: u64 = 1440;
+
+#[derive(Debug, Clone, Copy)]
+struct DetectionSample {
+    at: SystemTime,
+    detections: usize,
+    alerts: usize,
+}
+
+/// Recent per-result detection and alert counts for one camera, oldest
+/// dropped once `DETECTION_HISTORY_CAPACITY` is reached
+#[derive(Debug, Default)]
+pub struct DetectionHistory {
+    samples: VecDeque<DetectionSample>,
+}
+
+/// Totals for one slice of a detection timeseries
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DetectionBucket {
+    /// Bucket start, in milliseconds since the Unix epoch
+    pub start_ms: u64,
+    /// Detection results that landed in the bucket
+    pub results: usize,
+    pub detections: usize,
+    pub alerts: usize,
+}
+
+impl DetectionHistory {
+    pub fn record(&mut self, at: SystemTime, detections: usize, alerts: usize) {
+        if self.samples.len() == DETECTION_HISTORY_CAPACITY {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(DetectionSample { at, detections, alerts });
+    }
+
+    /// Counts over the `window` ending at `now`, split into `bucket`-long
+    /// slices, oldest first; empty slices are included so charts stay evenly
+    /// spaced
+    pub fn buckets(&self, now: SystemTime, window: Duration, bucket: Duration) -> Vec<DetectionBucket> {
+        if bucket.is_zero() {
+            return Vec::new();
+        }
+        let count = window.as_nanos().div_ceil(bucket.as_nanos()) as u32;
+        let start = now.checked_sub(bucket * count).unwrap_or(SystemTime::UNIX_EPOCH);
+
+        let mut buckets: Vec<DetectionBucket> = (0..count)
+            .map(|i| DetectionBucket {
+                start_ms: (start + bucket * i)
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map(|since| since.as_millis() as u64)
+                    .unwrap_or(0),
+                results: 0,
+                detections: 0,
+                alerts: 0,
+            })
+            .collect();
+
+        for sample in &self.samples {
+            if sample.at > now {
+                continue;
+            }
+            let Ok(offset) = sample.at.duration_since(start) else { continue };
+            // A sample at exactly `now` belongs to the last bucket
+            let index = ((offset.as_nanos() / bucket.as_nanos()) as usize).min(buckets.len().saturating_sub(1));
+            if let Some(slot) = buckets.get_mut(index) {
+                slot.results += 1;
+                slot.detections += sample.detections;
+                slot.alerts += sample.alerts;
+            }
+        }
+        buckets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_is_stuck_only_after_deadline_and_grace() {
+        let mut stats = TaskStats::default();
+        assert!(!stats.is_stuck(Instant::now()), "tasks that never slept aren't judged");
+
+        stats.expect_progress_within(Duration::from_secs(60));
+        let now = Instant::now();
+        assert!(!stats.is_stuck(now + Duration::from_secs(60)));
+        assert!(stats.is_stuck(now + Duration::from_secs(61) + STALL_GRACE));
+    }
+
+    #[test]
+    fn fps_tracks_and_smooths_frame_interval() {
+        let start = Instant::now();
+        let mut meter = FpsMeter::default();
+        assert_eq!(meter.fps(start), None);
+
+        for i in 0..=10 {
+            meter.tick(start + Duration::from_millis(200 * i));
+        }
+        let now = start + Duration::from_millis(2000);
+        assert!((meter.fps(now).unwrap() - 5.0).abs() < 1e-6);
+
+        // One slow frame moves the average only part of the way
+        meter.tick(start + Duration::from_millis(3000));
+        let fps = meter.fps(start + Duration::from_millis(3000)).unwrap();
+        assert!(fps < 5.0 && fps > 1.0);
+    }
+
+    #[test]
+    fn stalled_stream_fps_decays() {
+        let start = Instant::now();
+        let mut meter = FpsMeter::default();
+        meter.tick(start);
+        meter.tick(start + Duration::from_millis(100));
+
+        assert_eq!(meter.fps(start + Duration::from_secs(10)), Some(1.0 / 9.9));
+    }
+
+    #[test]
+    fn freeze_needs_enough_unchanged_captures_over_time() {
+        let start = Instant::now();
+        let mut detector = FreezeDetector::default();
+        assert_eq!(detector.record(b"a".to_vec(), None, start), None);
+
+        // Many identical captures in quick succession aren't enough
+        for i in 1..=10 {
+            assert_eq!(detector.record(b"a".to_vec(), Some(0.0), start + Duration::from_secs(i)), None);
+        }
+        assert!(!detector.is_frozen());
+
+        assert_eq!(
+            detector.record(b"a".to_vec(), Some(0.0001), start + Duration::from_secs(31)),
+            Some(FreezeTransition::Froze)
+        );
+        assert!(detector.is_frozen());
+        assert_eq!(detector.record(b"a".to_vec(), Some(0.0), start + Duration::from_secs(40)), None);
+
+        assert_eq!(
+            detector.record(b"b".to_vec(), Some(0.05), start + Duration::from_secs(45)),
+            Some(FreezeTransition::Thawed)
+        );
+        assert_eq!(detector.unchanged_captures(), 0);
+        assert_eq!(detector.last_frame(), Some(&b"b"[..]));
+    }
+
+    #[test]
+    fn undecodable_capture_breaks_an_unchanged_run() {
+        let start = Instant::now();
+        let mut detector = FreezeDetector::default();
+        for i in 0..4 {
+            detector.record(b"a".to_vec(), Some(0.0), start + Duration::from_secs(i * 10));
+        }
+        detector.record(b"a".to_vec(), None, start + Duration::from_secs(40));
+        assert_eq!(detector.record(b"a".to_vec(), Some(0.0), start + Duration::from_secs(50)), None);
+        assert_eq!(detector.unchanged_captures(), 1);
+    }
+
+    #[test]
+    fn breaker_opens_after_threshold_and_recovers_with_one_trial() {
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::new(3, Duration::from_secs(30));
+
+        assert!(!breaker.record_failure(start));
+        assert!(!breaker.record_failure(start));
+        assert!(breaker.record_failure(start));
+        assert!(!breaker.allow(start + Duration::from_secs(10)));
+        assert_eq!(breaker.status(start + Duration::from_secs(10)).retry_in_secs, Some(20));
+
+        // Only one trial goes out after the cooldown
+        let after = start + Duration::from_secs(30);
+        assert_eq!(breaker.state(after), BreakerState::HalfOpen);
+        assert!(breaker.allow(after));
+        assert!(!breaker.allow(after));
+
+        // A failed trial starts a fresh cooldown
+        assert!(!breaker.record_failure(after));
+        assert!(!breaker.allow(after + Duration::from_secs(29)));
+
+        assert!(breaker.allow(after + Duration::from_secs(30)));
+        assert!(breaker.record_success());
+        assert_eq!(breaker.state(after + Duration::from_secs(30)), BreakerState::Closed);
+        assert!(breaker.allow(after + Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn breaker_success_resets_failure_count() {
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::new(2, Duration::from_secs(30));
+        breaker.record_failure(now);
+        assert!(!breaker.record_success());
+        assert!(!breaker.record_failure(now));
+        assert_eq!(breaker.state(now), BreakerState::Closed);
+    }
+
+    #[test]
+    fn empty_stats_have_no_rates() {
+        let report = CaptureStats::default().report("cam-1");
+        assert_eq!(report.total_captures, 0);
+        assert_eq!(report.avg_latency_ms, None);
+        assert_eq!(report.recent_success_rate, None);
+    }
+
+    #[test]
+    fn averages_latency_and_tracks_last_outcomes() {
+        let mut stats = CaptureStats::default();
+        stats.record(true, Duration::from_millis(100));
+        stats.record(false, Duration::from_millis(300));
+
+        let report = stats.report("cam-1");
+        assert_eq!((report.total_captures, report.successful_captures), (2, 1));
+        assert_eq!(report.avg_latency_ms, Some(200.0));
+        assert_eq!(report.recent_success_rate, Some(0.5));
+        assert!(report.last_success.is_some() && report.last_failure.is_some());
+    }
+
+    #[test]
+    fn success_rate_only_counts_recent_window() {
+        let mut stats = CaptureStats::default();
+        for _ in 0..CAPTURE_WINDOW {
+            stats.record(false, Duration::ZERO);
+        }
+        for _ in 0..CAPTURE_WINDOW {
+            stats.record(true, Duration::ZERO);
+        }
+
+        let report = stats.report("cam-1");
+        assert_eq!(report.recent_success_rate, Some(1.0));
+        assert_eq!(report.recent_window, CAPTURE_WINDOW);
+        assert_eq!(report.successful_captures, CAPTURE_WINDOW as u64);
+    }
+
+    #[test]
+    fn adaptive_schedule_speeds_up_on_activity_and_backs_off_when_idle() {
+        let mut schedule = AdaptiveSchedule::new(5, 1, 30, 2);
+        assert_eq!(schedule.current(), Duration::from_secs(5));
+
+        assert_eq!(schedule.next_interval(true), Duration::from_secs(1));
+        // Stays fast until enough idle cycles pass
+        assert_eq!(schedule.next_interval(false), Duration::from_secs(1));
+        assert_eq!(schedule.next_interval(false), Duration::from_secs(10));
+        assert_eq!(schedule.next_interval(false), Duration::from_secs(20));
+        assert_eq!(schedule.next_interval(false), Duration::from_secs(30));
+        assert_eq!(schedule.next_interval(false), Duration::from_secs(30));
+
+        assert_eq!(schedule.next_interval(true), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn heartbeat_backs_off_up_to_cap() {
+        let interval = Duration::from_secs(30);
+        assert_eq!(heartbeat_delay(interval, 0), interval);
+        assert_eq!(heartbeat_delay(interval, 1), Duration::from_secs(60));
+        assert_eq!(heartbeat_delay(interval, 3), Duration::from_secs(240));
+        assert_eq!(heartbeat_delay(interval, 10), MAX_HEARTBEAT_BACKOFF);
+        assert_eq!(heartbeat_delay(Duration::from_secs(600), 2), Duration::from_secs(600));
+    }
+
+    #[test]
+    fn groups_are_trimmed_and_matched_exactly() {
+        assert_eq!(normalize_group(Some("  Floor 2 ".to_string())), Some("Floor 2".to_string()));
+        assert_eq!(normalize_group(Some("   ".to_string())), None);
+
+        let groups = HashMap::from([
+            ("cam-b".to_string(), "Floor 2".to_string()),
+            ("cam-a".to_string(), "Floor 2".to_string()),
+            ("cam-c".to_string(), "Floor 1".to_string()),
+        ]);
+        assert_eq!(group_members(&groups, "Floor 2"), ["cam-a", "cam-b"]);
+        assert!(group_members(&groups, "floor 2").is_empty());
+    }
+
+    #[test]
+    fn camera_groups_survive_a_restart() {
+        let mut groups = CameraGroupMap::default();
+        groups.set("cam-a", Some(" Lobby ".to_string()));
+        groups.set("cam-b", Some("Lobby".to_string()));
+        groups.set("cam-b", None);
+        assert_eq!(groups.get("cam-a").as_deref(), Some("Lobby"));
+        assert_eq!(groups.members("Lobby"), ["cam-a"]);
+
+        let path = std::env::temp_dir()
+            .join(format!("camera-groups-{}", std::process::id()))
+            .join(CAMERA_GROUPS_FILE);
+        groups.save(&path).unwrap();
+        assert_eq!(CameraGroupMap::load(&path), groups);
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn batch_estimate_counts_sampled_frames() {
+        let estimate = estimate_batch(3600.0, 5.0, 0.002, Duration::from_millis(250)).unwrap();
+        assert_eq!(estimate.frame_count, 720);
+        assert!((estimate.estimated_cost - 1.44).abs() < 1e-9);
+        assert_eq!(estimate.estimated_duration, 180.0);
+
+        // A partial interval at the end still gets its first frame sampled
+        assert_eq!(estimate_batch(10.5, 5.0, 0.0, DEFAULT_BATCH_REQUEST_TIME).unwrap().frame_count, 3);
+        assert!(estimate_batch(60.0, 0.0, 0.0, DEFAULT_BATCH_REQUEST_TIME).is_err());
+    }
+
+    #[test]
+    fn rate_limit_pauses_one_camera_until_retry_after() {
+        let mut limits = RateLimits::default();
+        let now = Instant::now();
+
+        assert_eq!(limits.pause("cam-1", Some(Duration::from_secs(10)), now), Duration::from_secs(10));
+        assert_eq!(limits.remaining("cam-1", now + Duration::from_secs(4)), Some(Duration::from_secs(6)));
+        assert_eq!(limits.remaining("cam-2", now), None);
+        assert_eq!(limits.remaining("cam-1", now + Duration::from_secs(10)), None);
+
+        assert_eq!(limits.pause("cam-1", None, now), DEFAULT_RETRY_AFTER);
+        assert_eq!(limits.pause("cam-1", Some(Duration::from_secs(86_400)), now), MAX_RETRY_AFTER);
+    }
+
+    #[test]
+    fn live_view_never_outpaces_capture() {
+        assert_eq!(live_view_fps(2.0, true, Some(5.0)), 2.0);
+        assert_eq!(live_view_fps(30.0, true, Some(5.0)), 5.0);
+        assert_eq!(live_view_fps(30.0, true, None), MAX_LIVE_VIEW_FPS);
+        assert_eq!(live_view_fps(5.0, false, Some(25.0)), ONE_SHOT_LIVE_VIEW_FPS);
+        assert_eq!(live_view_fps(1e-300, false, None), MIN_LIVE_VIEW_FPS);
+        assert_eq!(live_view_fps(2.0, true, Some(0.0)), MIN_LIVE_VIEW_FPS);
+    }
+
+    #[test]
+    fn detection_history_buckets_counts_over_window() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(10_000);
+        let mut history = DetectionHistory::default();
+        history.record(now - Duration::from_secs(500), 9, 9); // before the window
+        history.record(now - Duration::from_secs(250), 2, 0);
+        history.record(now - Duration::from_secs(210), 3, 1);
+        history.record(now - Duration::from_secs(10), 1, 1);
+
+        let buckets = history.buckets(now, Duration::from_secs(300), Duration::from_secs(100));
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[0].start_ms, 9_700_000);
+        assert_eq!((buckets[0].results, buckets[0].detections, buckets[0].alerts), (2, 5, 1));
+        assert_eq!((buckets[1].results, buckets[1].detections), (0, 0));
+        assert_eq!((buckets[2].results, buckets[2].detections, buckets[2].alerts), (1, 1, 1));
+    }
+
+    #[test]
+    fn detection_history_is_bounded() {
+        let mut history = DetectionHistory::default();
+        let start = SystemTime::UNIX_EPOCH;
+        for i in 0..DETECTION_HISTORY_CAPACITY + 10 {
+            history.record(start + Duration::from_secs(i as u64), 1, 0);
+        }
+        assert_eq!(history.samples.len(), DETECTION_HISTORY_CAPACITY);
+        assert_eq!(history.samples.front().unwrap().at, start + Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,519 @@
+use std::io::Write as _;
+use std::path::Path;
+use std::time::Duration;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tauri::plugin::PermissionState;
+use crate::error::AgentError;
+use crate::webhook::{self, WebhookConfig, WebhookPayload};
+
+/// File in the app data directory holding the alert notifiers
+pub const NOTIFIERS_FILE: &str = "notifiers.json";
+
+/// Longest wait for the SMTP server to connect or answer one command
+const SMTP_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Somewhere alerts can be delivered
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Short label for logs and pipeline reports
+    fn name(&self) -> String;
+
+    async fn notify(&self, alert: &WebhookPayload) -> Result<(), AgentError>;
+}
+
+/// One-line description of an alert, shared by the human-readable channels
+pub fn summary(alert: &WebhookPayload) -> String {
+    let test = if alert.test { "[TEST] " } else { "" };
+    format!("{}{} detected in {} on {}", test, alert.alert_type, alert.zone_name, alert.camera_id)
+}
+
+/// How an SMTP connection is secured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with `STARTTLS`, usually port 587
+    #[default]
+    StartTls,
+    /// TLS from the start, usually port 465
+    Tls,
+    /// Unencrypted; only for relays on a trusted network
+    None,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    /// Login for `AUTH PLAIN` (or `LOGIN`); sent without authenticating when unset
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Kept in the keychain, never written to disk or sent back to the UI
+    #[serde(default, skip_serializing)]
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+/// A notification channel and its settings, as chosen with `set_notifiers`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotifierConfig {
+    /// Desktop notification; unavailable on headless installs
+    Os,
+    Webhook(WebhookConfig),
+    Email(SmtpConfig),
+    /// JSON line per alert, appended to `path`, or stdout when unset
+    Log { path: Option<String> },
+}
+
+impl NotifierConfig {
+    /// Whether the zone policy's webhook switch, rather than its
+    /// notification switch, governs this channel
+    pub fn is_webhook(&self) -> bool {
+        matches!(self, NotifierConfig::Webhook(_))
+    }
+
+    pub fn validate(&self) -> Result<(), AgentError> {
+        match self {
+            NotifierConfig::Email(config) if config.to.is_empty() => {
+                Err(AgentError::InvalidInput("Email notifier needs at least one recipient".to_string()))
+            }
+            NotifierConfig::Email(config) if config.host.trim().is_empty() => {
+                Err(AgentError::InvalidInput("Email notifier needs an SMTP host".to_string()))
+            }
+            NotifierConfig::Email(config) if config.security == SmtpSecurity::None && config.username.is_some() => {
+                Err(cleartext_login())
+            }
+            NotifierConfig::Webhook(config) if config.url.trim().is_empty() => {
+                Err(AgentError::InvalidInput("Webhook notifier needs a URL".to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn build(&self, app: &tauri::AppHandle) -> Box<dyn Notifier> {
+        match self {
+            NotifierConfig::Os => Box::new(OsNotifier { app: app.clone() }),
+            NotifierConfig::Webhook(config) => Box::new(WebhookNotifier { config: config.clone() }),
+            NotifierConfig::Email(config) => Box::new(EmailNotifier { config: config.clone() }),
+            NotifierConfig::Log { path } => Box::new(LogNotifier { path: path.clone() }),
+        }
+    }
+}
+
+/// Every channel alerts are delivered to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotifierSettings {
+    pub notifiers: Vec<NotifierConfig>,
+}
+
+impl Default for NotifierSettings {
+    /// Desktop notifications only, as before notifiers were configurable
+    fn default() -> Self {
+        Self { notifiers: vec![NotifierConfig::Os] }
+    }
+}
+
+impl NotifierSettings {
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AgentError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| AgentError::Internal(format!("Failed to encode notifiers: {}", e)))?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| AgentError::Internal(format!("Failed to create settings directory: {}", e)))?;
+        }
+        std::fs::write(path, json)
+            .map_err(|e| AgentError::Internal(format!("Failed to save notifiers: {}", e)))
+    }
+}
+
+pub struct OsNotifier {
+    app: tauri::AppHandle,
+}
+
+#[async_trait]
+impl Notifier for OsNotifier {
+    fn name(&self) -> String {
+        "os".to_string()
+    }
+
+    async fn notify(&self, alert: &WebhookPayload) -> Result<(), AgentError> {
+        show_os_notification(&self.app, "🚨 CivicSentinel Alert", &summary(alert))
+    }
+}
+
+/// Whether the OS allows desktop notifications; `None` when the
+/// notification plugin isn't running
+pub fn notification_permission(app: &tauri::AppHandle) -> Result<Option<PermissionState>, AgentError> {
+    use tauri::Manager;
+    use tauri_plugin_notification::NotificationExt;
+
+    if app.try_state::<tauri_plugin_notification::Notification<tauri::Wry>>().is_none() {
+        return Ok(None);
+    }
+    app.notification()
+        .permission_state()
+        .map(Some)
+        .map_err(|e| AgentError::Internal(format!("Failed to read notification permission: {}", e)))
+}
+
+/// Show a desktop notification, failing if it can't actually appear
+pub fn show_os_notification(app: &tauri::AppHandle, title: &str, body: &str) -> Result<(), AgentError> {
+    use tauri_plugin_notification::NotificationExt;
+
+    match notification_permission(app)? {
+        Some(PermissionState::Granted) => {}
+        Some(state) => return Err(AgentError::Auth(format!("Notifications are not permitted (permission {})", state))),
+        None => return Err(AgentError::Internal("Notification plugin is not running".to_string())),
+    }
+    app.notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show()
+        .map_err(|e| AgentError::Internal(format!("Failed to show notification: {}", e)))
+}
+
+pub struct WebhookNotifier {
+    config: WebhookConfig,
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> String {
+        format!("webhook {}", self.config.url)
+    }
+
+    async fn notify(&self, alert: &WebhookPayload) -> Result<(), AgentError> {
+        webhook::send_webhook(&self.config, alert).await
+    }
+}
+
+pub struct LogNotifier {
+    path: Option<String>,
+}
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    fn name(&self) -> String {
+        match &self.path {
+            Some(path) => format!("log {}", path),
+            None => "log".to_string(),
+        }
+    }
+
+    async fn notify(&self, alert: &WebhookPayload) -> Result<(), AgentError> {
+        let line = serde_json::to_string(alert)
+            .map_err(|e| AgentError::Internal(format!("Failed to encode alert: {}", e)))?;
+
+        let Some(path) = self.path.clone() else {
+            println!("[Alert] {}", line);
+            return Ok(());
+        };
+
+        tokio::task::spawn_blocking(move || {
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .map_err(|e| AgentError::Internal(format!("Failed to open alert log {}: {}", path, e)))?;
+            writeln!(file, "{}", line)
+                .map_err(|e| AgentError::Internal(format!("Failed to write alert log {}: {}", path, e)))
+        })
+        .await
+        .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))?
+    }
+}
+
+pub struct EmailNotifier {
+    config: SmtpConfig,
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    fn name(&self) -> String {
+        format!("email {}", self.config.to.join(", "))
+    }
+
+    async fn notify(&self, alert: &WebhookPayload) -> Result<(), AgentError> {
+        let subject = format!("CivicSentinel alert: {}", summary(alert));
+        let mut body = format!(
+            "{}\n\nCamera: {}\nZone: {}\nAlert: {}\nConfidence: {:.2}\nTime: {}\n",
+            summary(alert), alert.camera_id, alert.zone_name, alert.alert_type, alert.confidence, alert.timestamp,
+        );
+        if let Some(url) = &alert.image_url {
+            body.push_str(&format!("Evidence: {}\n", url));
+        }
+        send_email(&self.config, &subject, &body).await
+    }
+}
+
+/// Logging in over an unencrypted connection would send the password in the clear
+fn cleartext_login() -> AgentError {
+    AgentError::InvalidInput("SMTP login needs a TLS or STARTTLS connection; it would send the password in cleartext".to_string())
+}
+
+/// Drop CR and LF so values can't inject extra headers
+fn single_line(value: &str) -> String {
+    value.chars().filter(|c| *c != '\r' && *c != '\n').collect()
+}
+
+/// Map a lettre failure onto the agent's error kinds
+fn smtp_error(host: &str, e: lettre::transport::smtp::Error) -> AgentError {
+    let code = e.status().map(|code| code.to_string());
+    if e.is_timeout() {
+        AgentError::Timeout(format!("SMTP server {} did not answer", host))
+    } else if code.as_deref().is_some_and(|code| code.starts_with("53")) {
+        AgentError::Auth(format!("SMTP login rejected: {}", e))
+    } else if e.is_permanent() || e.is_transient() {
+        AgentError::Backend(format!("SMTP server {} refused the message: {}", host, e))
+    } else {
+        AgentError::Network(format!("Failed to send email through {}:{}", host, e))
+    }
+}
+
+/// Send a plain-text email through the configured SMTP server
+pub async fn send_email(config: &SmtpConfig, subject: &str, body: &str) -> Result<(), AgentError> {
+    use lettre::message::{header::ContentType, Mailbox};
+    use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+    use lettre::transport::smtp::extension::ClientId;
+    use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+    let mailbox = |address: &str| {
+        address.trim().parse::<Mailbox>()
+            .map_err(|e| AgentError::InvalidInput(format!("Invalid email address {:?}: {}", address, e)))
+    };
+    let mut message = Message::builder()
+        .from(mailbox(&config.from)?)
+        .subject(single_line(subject))
+        .header(ContentType::TEXT_PLAIN);
+    for recipient in &config.to {
+        message = message.to(mailbox(recipient)?);
+    }
+    let message = message.body(body.to_string())
+        .map_err(|e| AgentError::InvalidInput(format!("Failed to build email: {}", e)))?;
+
+    let builder = match config.security {
+        SmtpSecurity::None => {
+            if config.username.is_some() {
+                return Err(cleartext_login());
+            }
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+        }
+        SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)
+            .map_err(|e| AgentError::Internal(format!("Failed to set up TLS: {}", e)))?,
+        SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+            .map_err(|e| AgentError::Internal(format!("Failed to set up TLS: {}", e)))?,
+    };
+    let mut builder = builder
+        .port(config.port)
+        .timeout(Some(SMTP_TIMEOUT))
+        .hello_name(ClientId::Domain("civicsentinel-agent".to_string()));
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        builder = builder
+            .credentials(Credentials::new(username.clone(), password.clone()))
+            // LOGIN for servers that don't offer PLAIN
+            .authentication(vec![Mechanism::Plain, Mechanism::Login]);
+    }
+
+    builder.build()
+        .send(message)
+        .await
+        .map(|_| ())
+        .map_err(|e| smtp_error(&config.host, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    fn alert() -> WebhookPayload {
+        WebhookPayload {
+            camera_id: "cam-1".to_string(),
+            zone_name: "Gate".to_string(),
+            alert_type: "intrusion".to_string(),
+            confidence: 0.9,
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+            image_url: None,
+            test: false,
+        }
+    }
+
+    /// Minimal SMTP server that accepts one message and returns the transcript
+    async fn fake_smtp_server(listener: TcpListener) -> String {
+        let (socket, _) = listener.accept().await.unwrap();
+        let (read, mut write) = socket.into_split();
+        let mut read = BufReader::new(read);
+        let mut transcript = String::new();
+        write.write_all(b"220 fake ESMTP\r\n").await.unwrap();
+
+        let mut in_data = false;
+        loop {
+            let mut line = String::new();
+            if read.read_line(&mut line).await.unwrap() == 0 {
+                break;
+            }
+            transcript.push_str(&line);
+            let reply: &[u8] = if in_data {
+                if line != ".\r\n" {
+                    continue;
+                }
+                in_data = false;
+                b"250 queued\r\n"
+            } else if line.starts_with("EHLO") {
+                b"250-fake\r\n250 AUTH PLAIN LOGIN\r\n"
+            } else if line.starts_with("AUTH") {
+                b"235 ok\r\n"
+            } else if line.starts_with("DATA") {
+                in_data = true;
+                b"354 go ahead\r\n"
+            } else if line.starts_with("QUIT") {
+                write.write_all(b"221 bye\r\n").await.unwrap();
+                break;
+            } else {
+                b"250 ok\r\n"
+            };
+            write.write_all(reply).await.unwrap();
+        }
+        transcript
+    }
+
+    fn smtp_config(port: u16) -> SmtpConfig {
+        SmtpConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+            security: SmtpSecurity::None,
+            username: None,
+            password: None,
+            from: "agent@example.com".to_string(),
+            to: vec!["ops@example.com".to_string(), "guard@example.com".to_string()],
+        }
+    }
+
+    /// Send `alert` to a fake server and return what it received
+    async fn smtp_transcript(alert: &WebhookPayload) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(fake_smtp_server(listener));
+
+        EmailNotifier { config: smtp_config(port) }.notify(alert).await.unwrap();
+        server.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn email_notifier_speaks_smtp() {
+        let mut alert = alert();
+        alert.alert_type = ".intrusion".to_string();
+        alert.zone_name = "Gate\r\nBcc: evil@example.com".to_string();
+
+        let transcript = smtp_transcript(&alert).await;
+        assert!(transcript.contains("MAIL FROM:<agent@example.com>\r\n"));
+        assert!(transcript.contains("RCPT TO:<guard@example.com>\r\n"));
+        // Header values are flattened, so no extra header is injected
+        let headers = transcript.split("\r\n\r\n").next().unwrap();
+        assert!(!headers.lines().any(|line| line.starts_with("Bcc:")));
+        // Body lines starting with a dot are stuffed
+        assert!(transcript.contains("\r\n..intrusion detected in Gate\r\n"));
+        assert!(transcript.ends_with("\r\n.\r\nQUIT\r\n"));
+    }
+
+    #[tokio::test]
+    async fn non_ascii_subjects_are_encoded() {
+        let mut alert = alert();
+        alert.zone_name = "Südtor".to_string();
+
+        let transcript = smtp_transcript(&alert).await;
+        let subject = transcript.lines().find(|line| line.starts_with("Subject:")).unwrap();
+        assert!(subject.contains("=?utf-8?"), "{}", subject);
+        assert!(subject.is_ascii());
+    }
+
+    #[tokio::test]
+    async fn smtp_login_needs_encryption() {
+        let mut config = smtp_config(25);
+        config.username = Some("agent".to_string());
+        config.password = Some("secret".to_string());
+
+        assert!(NotifierConfig::Email(config.clone()).validate().is_err());
+        let sent = send_email(&config, "Alert", "body").await;
+        assert!(matches!(sent, Err(AgentError::InvalidInput(_))));
+
+        config.security = SmtpSecurity::StartTls;
+        assert!(NotifierConfig::Email(config).validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn log_notifier_appends_json_lines() {
+        let path = std::env::temp_dir().join(format!("civicsentinel-alerts-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let notifier = LogNotifier { path: Some(path.display().to_string()) };
+
+        notifier.notify(&alert()).await.unwrap();
+        notifier.notify(&alert()).await.unwrap();
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(log.lines().count(), 2);
+        assert_eq!(serde_json::from_str::<WebhookPayload>(log.lines().next().unwrap()).unwrap().zone_name, "Gate");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn email_needs_recipients() {
+        let config = SmtpConfig {
+            host: "smtp.example.com".to_string(),
+            port: 587,
+            security: SmtpSecurity::default(),
+            username: None,
+            password: None,
+            from: "agent@example.com".to_string(),
+            to: Vec::new(),
+        };
+        assert!(NotifierConfig::Email(config).validate().is_err());
+        assert!(NotifierConfig::Log { path: None }.validate().is_ok());
+    }
+
+    #[test]
+    fn saved_notifiers_leave_out_the_smtp_password() {
+        let settings = NotifierSettings {
+            notifiers: vec![
+                NotifierConfig::Os,
+                NotifierConfig::Email(SmtpConfig {
+                    host: "smtp.example.com".to_string(),
+                    port: 587,
+                    security: SmtpSecurity::default(),
+                    username: Some("agent".to_string()),
+                    password: Some("secret".to_string()),
+                    from: "agent@example.com".to_string(),
+                    to: vec!["ops@example.com".to_string()],
+                }),
+            ],
+        };
+        assert!(!serde_json::to_string(&settings).unwrap().contains("secret"));
+
+        let path = std::env::temp_dir()
+            .join(format!("notifiers-{}", std::process::id()))
+            .join(NOTIFIERS_FILE);
+        settings.save(&path).unwrap();
+        let loaded = NotifierSettings::load(&path);
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+
+        let [NotifierConfig::Os, NotifierConfig::Email(config)] = loaded.notifiers.as_slice() else {
+            panic!("unexpected notifiers: {:?}", loaded.notifiers)
+        };
+        assert_eq!(config.username.as_deref(), Some("agent"));
+        assert_eq!(config.password, None);
+    }
+}
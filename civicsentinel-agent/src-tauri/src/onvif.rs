@@ -0,0 +1,423 @@
+use base64::{Engine as _, engine::general_purpose};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use crate::camera::StreamProfile;
+use crate::error::AgentError;
+
+const DEVICE_NS: &str = "http://www.onvif.org/ver10/device/wsdl";
+const MEDIA_NS: &str = "http://www.onvif.org/ver10/media/wsdl";
+const SCHEMA_NS: &str = "http://www.onvif.org/ver10/schema";
+
+/// Login for a camera's ONVIF service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnvifCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// Credentials plus how far the device clock is ahead of ours
+///
+/// Cameras reject tokens whose `Created` time is too far from their own
+/// clock, so timestamps are shifted onto the device's time.
+struct UsernameToken<'a> {
+    credentials: &'a OnvifCredentials,
+    clock_offset: chrono::Duration,
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// WS-Security PasswordDigest: Base64(SHA1(nonce + created + password))
+fn password_digest(nonce: &[u8], created: &str, password: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(nonce);
+    hasher.update(created.as_bytes());
+    hasher.update(password.as_bytes());
+    general_purpose::STANDARD.encode(hasher.finalize())
+}
+
+/// UsernameToken header most cameras require for media requests
+fn security_header(token: &UsernameToken) -> String {
+    let mut nonce = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let created = (chrono::Utc::now() + token.clock_offset).format("%Y-%m-%dT%H:%M:%SZ").to_string();
+
+    format!(
+        concat!(
+            r#"<s:Header><Security s:mustUnderstand="1" xmlns="http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-wssecurity-secext-1.0.xsd">"#,
+            "<UsernameToken><Username>{}</Username>",
+            r#"<Password Type="http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-username-token-profile-1.0#PasswordDigest">{}</Password>"#,
+            r#"<Nonce EncodingType="http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-soap-message-security-1.0#Base64Binary">{}</Nonce>"#,
+            r#"<Created xmlns="http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-wssecurity-utility-1.0.xsd">{}</Created>"#,
+            "</UsernameToken></Security></s:Header>",
+        ),
+        xml_escape(&token.credentials.username),
+        password_digest(&nonce, &created, &token.credentials.password),
+        general_purpose::STANDARD.encode(nonce),
+        created,
+    )
+}
+
+/// POST a SOAP request and return the response body
+async fn soap_request(
+    client: &reqwest::Client,
+    url: &str,
+    body: &str,
+    token: Option<&UsernameToken<'_>>,
+) -> Result<String, AgentError> {
+    let header = token.map(security_header).unwrap_or_default();
+    let envelope = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope">{}<s:Body>{}</s:Body></s:Envelope>"#,
+        header, body
+    );
+
+    let response = client
+        .post(url)
+        .header("Content-Type", "application/soap+xml; charset=utf-8")
+        .body(envelope)
+        .send()
+        .await
+        .map_err(|e| AgentError::Network(format!("ONVIF request to {} failed: {}", url, e)))?;
+
+    let status = response.status();
+    let text = response.text().await.unwrap_or_default();
+
+    if status.is_success() {
+        return Ok(text);
+    }
+
+    // Cameras report bad credentials as a SOAP fault, often with a 400 or 500
+    if status.as_u16() == 401 || text.contains("NotAuthorized") {
+        return Err(AgentError::Auth(format!("ONVIF device rejected credentials ({})", status)));
+    }
+
+    Err(AgentError::Network(format!("ONVIF error {} from {}", status, url)))
+}
+
+/// Text of the first descendant element with this local name
+fn descendant_text<'a>(node: roxmltree::Node<'a, 'a>, name: &str) -> Option<&'a str> {
+    node.descendants()
+        .find(|n| n.is_element() && n.tag_name().name() == name)
+        .and_then(|n| n.text())
+        .map(str::trim)
+}
+
+fn parse_xml(body: &str) -> Result<roxmltree::Document<'_>, AgentError> {
+    roxmltree::Document::parse(body)
+        .map_err(|e| AgentError::Decode(format!("Invalid ONVIF response: {}", e)))
+}
+
+/// UTC time from a GetSystemDateAndTime response
+fn parse_device_time(body: &str) -> Result<Option<chrono::DateTime<chrono::Utc>>, AgentError> {
+    let doc = parse_xml(body)?;
+    let Some(utc) = doc.descendants().find(|n| n.is_element() && n.tag_name().name() == "UTCDateTime") else {
+        return Ok(None);
+    };
+
+    let field = |name: &str| descendant_text(utc, name).and_then(|v| v.parse::<u32>().ok());
+    let time = match (field("Year"), field("Month"), field("Day"), field("Hour"), field("Minute"), field("Second")) {
+        (Some(year), Some(month), Some(day), Some(hour), Some(minute), Some(second)) => {
+            chrono::NaiveDate::from_ymd_opt(year as i32, month, day)
+                .and_then(|date| date.and_hms_opt(hour, minute, second))
+                .map(|naive| naive.and_utc())
+        }
+        _ => None,
+    };
+
+    Ok(time)
+}
+
+/// How far the device clock is ahead of ours, if it reports its time
+///
+/// GetSystemDateAndTime must be answered without authentication, which is
+/// what makes it usable before the first signed request.
+async fn query_clock_offset(client: &reqwest::Client, device_url: &str) -> Result<Option<chrono::Duration>, AgentError> {
+    let body = format!(r#"<GetSystemDateAndTime xmlns="{}"/>"#, DEVICE_NS);
+    let response = soap_request(client, device_url, &body, None).await?;
+    Ok(parse_device_time(&response)?.map(|time| time - chrono::Utc::now()))
+}
+
+/// How far the device clock is ahead of ours; zero if it won't say
+async fn device_clock_offset(client: &reqwest::Client, device_url: &str) -> chrono::Duration {
+    match query_clock_offset(client, device_url).await {
+        Ok(Some(offset)) => {
+            if offset.num_seconds().abs() > 5 {
+                println!("[ONVIF] Device clock is {}s off; adjusting token timestamps", offset.num_seconds());
+            }
+            offset
+        }
+        Ok(None) => chrono::Duration::zero(),
+        Err(e) => {
+            println!("[ONVIF] Could not read device time: {}", e);
+            chrono::Duration::zero()
+        }
+    }
+}
+
+/// Device service at the standard ONVIF path on a stream's host
+pub fn default_device_url(stream_url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(stream_url).ok()?;
+    if !matches!(url.scheme(), "rtsp" | "rtsps" | "http" | "https") {
+        return None;
+    }
+    // host_str keeps the brackets around IPv6 literals
+    Some(format!("http://{}/onvif/device_service", url.host_str()?))
+}
+
+/// How far a camera's clock is ahead of ours, from an unauthenticated
+/// GetSystemDateAndTime; `None` if the device answers without a UTC time
+pub async fn clock_offset(device_url: &str) -> Result<Option<chrono::Duration>, AgentError> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(5))
+        .build()
+        .map_err(|e| AgentError::Internal(format!("Failed to build HTTP client: {}", e)))?;
+    query_clock_offset(&client, device_url).await
+}
+
+/// Media service address from a GetCapabilities response
+fn parse_media_xaddr(body: &str) -> Result<Option<String>, AgentError> {
+    let doc = parse_xml(body)?;
+    Ok(doc.descendants()
+        .find(|n| n.is_element() && n.tag_name().name() == "Media")
+        .and_then(|media| descendant_text(media, "XAddr"))
+        .map(str::to_string))
+}
+
+/// Profiles from a GetProfiles response, without stream URLs
+fn parse_profiles(body: &str) -> Result<Vec<StreamProfile>, AgentError> {
+    let doc = parse_xml(body)?;
+
+    let profiles = doc.descendants()
+        .filter(|n| n.is_element() && n.tag_name().name() == "Profiles")
+        .map(|profile| {
+            let encoder = profile.descendants()
+                .find(|n| n.is_element() && n.tag_name().name() == "VideoEncoderConfiguration");
+            let token = profile.attribute("token").map(str::to_string);
+
+            StreamProfile {
+                name: descendant_text(profile, "Name")
+                    .map(str::to_string)
+                    .or_else(|| token.clone())
+                    .unwrap_or_default(),
+                token,
+                stream_url: String::new(),
+                codec: encoder.and_then(|e| descendant_text(e, "Encoding")).map(str::to_string),
+                width: encoder.and_then(|e| descendant_text(e, "Width")).and_then(|w| w.parse().ok()),
+                height: encoder.and_then(|e| descendant_text(e, "Height")).and_then(|h| h.parse().ok()),
+            }
+        })
+        .collect();
+
+    Ok(profiles)
+}
+
+/// Query an ONVIF device's media profiles and the RTSP URL of each
+pub async fn get_stream_profiles(
+    device_url: &str,
+    credentials: Option<&OnvifCredentials>,
+) -> Result<Vec<StreamProfile>, AgentError> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .map_err(|e| AgentError::Internal(format!("Failed to build HTTP client: {}", e)))?;
+
+    let auth = match credentials {
+        Some(credentials) => Some(UsernameToken {
+            credentials,
+            clock_offset: device_clock_offset(&client, device_url).await,
+        }),
+        None => None,
+    };
+    let auth = auth.as_ref();
+
+    // Most devices host media on a separate service; fall back to the device URL
+    let capabilities = soap_request(
+        &client,
+        device_url,
+        &format!(r#"<GetCapabilities xmlns="{}"><Category>Media</Category></GetCapabilities>"#, DEVICE_NS),
+        auth,
+    ).await?;
+    let media_url = parse_media_xaddr(&capabilities)?.unwrap_or_else(|| device_url.to_string());
+    println!("[ONVIF] Media service at {}", media_url);
+
+    let profiles_body = soap_request(
+        &client,
+        &media_url,
+        &format!(r#"<GetProfiles xmlns="{}"/>"#, MEDIA_NS),
+        auth,
+    ).await?;
+    let mut profiles = parse_profiles(&profiles_body)?;
+
+    for profile in &mut profiles {
+        let Some(token) = &profile.token else { continue };
+        let body = format!(
+            concat!(
+                r#"<GetStreamUri xmlns="{media}"><StreamSetup>"#,
+                r#"<Stream xmlns="{schema}">RTP-Unicast</Stream>"#,
+                r#"<Transport xmlns="{schema}"><Protocol>RTSP</Protocol></Transport>"#,
+                "</StreamSetup><ProfileToken>{token}</ProfileToken></GetStreamUri>",
+            ),
+            media = MEDIA_NS,
+            schema = SCHEMA_NS,
+            token = xml_escape(token),
+        );
+
+        match soap_request(&client, &media_url, &body, auth).await {
+            Ok(response) => {
+                let doc = parse_xml(&response)?;
+                if let Some(uri) = descendant_text(doc.root(), "Uri") {
+                    profile.stream_url = uri.to_string();
+                }
+            }
+            Err(e) => println!("[ONVIF] No stream URI for profile {}: {}", token, e),
+        }
+    }
+
+    println!("[ONVIF] Found {} profiles", profiles.len());
+    Ok(profiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_string_contains, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const PROFILES: &str = r#"<?xml version="1.0"?>
+<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:trt="http://www.onvif.org/ver10/media/wsdl" xmlns:tt="http://www.onvif.org/ver10/schema">
+  <s:Body><trt:GetProfilesResponse>
+    <trt:Profiles token="main" fixed="true"><tt:Name>MainStream</tt:Name>
+      <tt:VideoEncoderConfiguration token="enc0"><tt:Name>enc0</tt:Name><tt:Encoding>H264</tt:Encoding>
+        <tt:Resolution><tt:Width>1920</tt:Width><tt:Height>1080</tt:Height></tt:Resolution>
+      </tt:VideoEncoderConfiguration>
+    </trt:Profiles>
+    <trt:Profiles token="sub" fixed="true"><tt:Name>SubStream</tt:Name>
+      <tt:VideoEncoderConfiguration token="enc1"><tt:Name>enc1</tt:Name><tt:Encoding>H264</tt:Encoding>
+        <tt:Resolution><tt:Width>640</tt:Width><tt:Height>360</tt:Height></tt:Resolution>
+      </tt:VideoEncoderConfiguration>
+    </trt:Profiles>
+  </trt:GetProfilesResponse></s:Body>
+</s:Envelope>"#;
+
+    fn stream_uri_response(uri: &str) -> String {
+        format!(
+            r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:trt="http://www.onvif.org/ver10/media/wsdl" xmlns:tt="http://www.onvif.org/ver10/schema"><s:Body><trt:GetStreamUriResponse><trt:MediaUri><tt:Uri>{}</tt:Uri></trt:MediaUri></trt:GetStreamUriResponse></s:Body></s:Envelope>"#,
+            uri
+        )
+    }
+
+    #[test]
+    fn parses_profile_encoder_settings() {
+        let profiles = parse_profiles(PROFILES).unwrap();
+
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles[0].name, "MainStream");
+        assert_eq!(profiles[0].token.as_deref(), Some("main"));
+        assert_eq!(profiles[0].codec.as_deref(), Some("H264"));
+        assert_eq!((profiles[1].width, profiles[1].height), (Some(640), Some(360)));
+    }
+
+    #[test]
+    fn password_digest_hashes_nonce_created_and_password() {
+        let digest = password_digest(b"nonce", "2024-01-01T00:00:00Z", "secret");
+        assert_eq!(digest, "kU35KuRv4ZlxdhDRm6st1m5t85U=");
+    }
+
+    #[test]
+    fn parses_device_utc_time() {
+        let body = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:tt="http://www.onvif.org/ver10/schema"><s:Body><GetSystemDateAndTimeResponse><SystemDateAndTime>
+            <tt:UTCDateTime><tt:Time><tt:Hour>13</tt:Hour><tt:Minute>5</tt:Minute><tt:Second>9</tt:Second></tt:Time>
+            <tt:Date><tt:Year>2024</tt:Year><tt:Month>2</tt:Month><tt:Day>29</tt:Day></tt:Date></tt:UTCDateTime>
+        </SystemDateAndTime></GetSystemDateAndTimeResponse></s:Body></s:Envelope>"#;
+
+        let time = parse_device_time(body).unwrap().unwrap();
+        assert_eq!(time.to_rfc3339(), "2024-02-29T13:05:09+00:00");
+    }
+
+    #[test]
+    fn device_url_is_derived_from_stream_host() {
+        assert_eq!(
+            default_device_url("rtsp://admin:pw@192.168.1.20:554/stream1").as_deref(),
+            Some("http://192.168.1.20/onvif/device_service"),
+        );
+        assert_eq!(
+            default_device_url("rtsp://[fe80::1]/live").as_deref(),
+            Some("http://[fe80::1]/onvif/device_service"),
+        );
+        assert_eq!(default_device_url("/videos/lobby.mp4"), None);
+    }
+
+    #[test]
+    fn token_created_time_follows_device_clock() {
+        let credentials = OnvifCredentials { username: "admin".to_string(), password: "secret".to_string() };
+        let token = UsernameToken { credentials: &credentials, clock_offset: chrono::Duration::days(365) };
+
+        let header = security_header(&token);
+        let next_year = (chrono::Utc::now() + chrono::Duration::days(365)).format("%Y-%m-%d").to_string();
+        assert!(header.contains(&format!(">{}T", next_year)));
+    }
+
+    #[tokio::test]
+    async fn resolves_media_service_and_stream_urls() {
+        let server = MockServer::start().await;
+        let media_url = format!("{}/onvif/media_service", server.uri());
+
+        Mock::given(method("POST"))
+            .and(path("/onvif/device_service"))
+            .and(body_string_contains("GetCapabilities"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:tt="http://www.onvif.org/ver10/schema"><s:Body><GetCapabilitiesResponse><Capabilities><tt:Media><tt:XAddr>{}</tt:XAddr></tt:Media></Capabilities></GetCapabilitiesResponse></s:Body></s:Envelope>"#,
+                media_url
+            )))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/onvif/media_service"))
+            .and(body_string_contains("GetProfiles"))
+            .and(body_string_contains("PasswordDigest"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(PROFILES))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/onvif/media_service"))
+            .and(body_string_contains("<ProfileToken>sub</ProfileToken>"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(stream_uri_response("rtsp://cam/sub")))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/onvif/media_service"))
+            .and(body_string_contains("<ProfileToken>main</ProfileToken>"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(stream_uri_response("rtsp://cam/main")))
+            .mount(&server)
+            .await;
+
+        let device_url = format!("{}/onvif/device_service", server.uri());
+        let credentials = OnvifCredentials { username: "admin".to_string(), password: "secret".to_string() };
+        let profiles = get_stream_profiles(&device_url, Some(&credentials)).await.unwrap();
+
+        assert_eq!(profiles[0].stream_url, "rtsp://cam/main");
+        assert_eq!(profiles[1].stream_url, "rtsp://cam/sub");
+    }
+
+    #[tokio::test]
+    async fn not_authorized_fault_maps_to_auth() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400).set_body_string(
+                "<s:Envelope><s:Body><s:Fault><s:Code><s:Subcode><s:Value>ter:NotAuthorized</s:Value></s:Subcode></s:Code></s:Fault></s:Body></s:Envelope>",
+            ))
+            .mount(&server)
+            .await;
+
+        let credentials = OnvifCredentials { username: "admin".to_string(), password: "wrong".to_string() };
+        let err = get_stream_profiles(&server.uri(), Some(&credentials)).await.unwrap_err();
+        assert!(matches!(err, AgentError::Auth(_)));
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::path::Path;
+use image::{Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use crate::api::Detection;
+use crate::error::AgentError;
+use crate::zones::point_in_polygon;
+
+/// File in the app data directory holding per-camera privacy masks
+pub const PRIVACY_MASKS_FILE: &str = "privacy_masks.json";
+
+/// How masked regions are hidden
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskStyle {
+    #[default]
+    Blur,
+    /// Solid black
+    Fill,
+}
+
+/// Regions of one camera's picture that must never leave the agent
+///
+/// Polygons are normalized (0-1) to the frame sent for detection, i.e.
+/// after any ROI crop, so they survive resizing for upload.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraPrivacy {
+    pub polygons: Vec<Vec<[f64; 2]>>,
+    pub style: MaskStyle,
+}
+
+impl CameraPrivacy {
+    pub fn validate(&self) -> Result<(), AgentError> {
+        for polygon in &self.polygons {
+            if polygon.len() < 3 {
+                return Err(AgentError::InvalidInput("A privacy mask needs at least 3 points".to_string()));
+            }
+            if let Some(point) = polygon.iter().find(|p| !p.iter().all(|v| (0.0..=1.0).contains(v))) {
+                return Err(AgentError::InvalidInput(format!(
+                    "Privacy mask points must be normalized (0-1), got {:?}", point
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn covers(&self, point: [f64; 2]) -> bool {
+        self.polygons.iter().any(|polygon| point_in_polygon(point, polygon))
+    }
+
+    /// Blur or fill the masked regions of a frame, returning it re-encoded
+    pub fn apply(&self, frame_bytes: &[u8]) -> Result<Vec<u8>, AgentError> {
+        let mut image = image::load_from_memory(frame_bytes)
+            .map_err(|e| AgentError::Decode(format!("Failed to decode frame: {}", e)))?
+            .to_rgb8();
+
+        self.mask_image(&mut image);
+
+        let mut encoded = Vec::new();
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut encoded, 90)
+            .encode_image(&image)
+            .map_err(|e| AgentError::Internal(format!("Failed to encode masked frame: {}", e)))?;
+        Ok(encoded)
+    }
+
+    fn mask_image(&self, image: &mut RgbImage) {
+        let (width, height) = image.dimensions();
+        // Strong enough that faces and text are unreadable at any resolution
+        let sigma = (width.max(height) as f32 / 40.0).max(8.0);
+
+        for polygon in &self.polygons {
+            // Pixel bounds of the polygon, so only that area is blurred and scanned
+            let (mut x_min, mut y_min, mut x_max, mut y_max) = (1.0f64, 1.0f64, 0.0f64, 0.0f64);
+            for [x, y] in polygon {
+                x_min = x_min.min(*x);
+                y_min = y_min.min(*y);
+                x_max = x_max.max(*x);
+                y_max = y_max.max(*y);
+            }
+            let left = (x_min * width as f64).floor() as u32;
+            let top = (y_min * height as f64).floor() as u32;
+            let right = ((x_max * width as f64).ceil() as u32).min(width);
+            let bottom = ((y_max * height as f64).ceil() as u32).min(height);
+            if left >= right || top >= bottom {
+                continue;
+            }
+
+            let blurred = match self.style {
+                MaskStyle::Blur => Some(image::imageops::blur(
+                    &image::imageops::crop_imm(image, left, top, right - left, bottom - top).to_image(),
+                    sigma,
+                )),
+                MaskStyle::Fill => None,
+            };
+
+            for y in top..bottom {
+                for x in left..right {
+                    let center = [(x as f64 + 0.5) / width as f64, (y as f64 + 0.5) / height as f64];
+                    if !point_in_polygon(center, polygon) {
+                        continue;
+                    }
+                    let pixel = match &blurred {
+                        Some(blurred) => *blurred.get_pixel(x - left, y - top),
+                        None => Rgb([0, 0, 0]),
+                    };
+                    image.put_pixel(x, y, pixel);
+                }
+            }
+        }
+    }
+
+    /// Drop detections centered in a masked region of a `width` x `height` frame
+    pub fn suppress(&self, detections: &mut Vec<Detection>, (width, height): (u32, u32)) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        detections.retain(|detection| {
+            let bbox = &detection.bbox;
+            let center = [
+                (bbox.x1 + bbox.x2) / 2.0 / width as f64,
+                (bbox.y1 + bbox.y2) / 2.0 / height as f64,
+            ];
+            !self.covers(center)
+        });
+    }
+}
+
+/// Privacy masks of every camera that has any
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PrivacyMasks(HashMap<String, CameraPrivacy>);
+
+impl PrivacyMasks {
+    /// A camera's masks, if it has any polygons
+    pub fn get(&self, camera_id: &str) -> Option<&CameraPrivacy> {
+        self.0.get(camera_id).filter(|privacy| !privacy.polygons.is_empty())
+    }
+
+    pub fn set(&mut self, camera_id: &str, privacy: CameraPrivacy) {
+        if privacy.polygons.is_empty() {
+            self.0.remove(camera_id);
+        } else {
+            self.0.insert(camera_id.to_string(), privacy);
+        }
+    }
+
+    /// Saved masks; a missing file is none, an unreadable one is an error so
+    /// frames are never uploaded unmasked by mistake
+    pub fn load(path: &Path) -> Result<Self, AgentError> {
+        let json = match std::fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(AgentError::Internal(format!("Failed to read {}: {}", path.display(), e))),
+        };
+        serde_json::from_str(&json)
+            .map_err(|e| AgentError::InvalidInput(format!("Invalid {}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AgentError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| AgentError::Internal(format!("Failed to encode privacy masks: {}", e)))?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| AgentError::Internal(format!("Failed to create settings directory: {}", e)))?;
+        }
+        std::fs::write(path, json)
+            .map_err(|e| AgentError::Internal(format!("Failed to save privacy masks: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::BoundingBox;
+
+    fn left_half(style: MaskStyle) -> CameraPrivacy {
+        CameraPrivacy {
+            polygons: vec![vec![[0.0, 0.0], [0.5, 0.0], [0.5, 1.0], [0.0, 1.0]]],
+            style,
+        }
+    }
+
+    #[test]
+    fn masked_region_is_hidden_and_the_rest_untouched() {
+        // Checkerboard, so a blur visibly changes every masked pixel
+        let mut image = RgbImage::from_fn(64, 32, |x, y| if (x + y) % 2 == 0 { Rgb([255, 255, 255]) } else { Rgb([0, 0, 0]) });
+        let original = image.clone();
+
+        left_half(MaskStyle::Fill).mask_image(&mut image);
+        assert_eq!(*image.get_pixel(5, 5), Rgb([0, 0, 0]));
+        assert_eq!(*image.get_pixel(4, 6), Rgb([0, 0, 0]));
+        assert_eq!(image.get_pixel(40, 6), original.get_pixel(40, 6));
+
+        let mut image = original.clone();
+        left_half(MaskStyle::Blur).mask_image(&mut image);
+        let gray = image.get_pixel(10, 10).0[0];
+        assert!((64..=192).contains(&gray), "pixel not blurred: {}", gray);
+        assert_eq!(image.get_pixel(50, 10), original.get_pixel(50, 10));
+    }
+
+    #[test]
+    fn detections_in_masked_regions_are_dropped() {
+        let detection = |x1: f64| Detection {
+            class_name: "person".to_string(),
+            confidence: 0.9,
+            bbox: BoundingBox { x1, y1: 10.0, x2: x1 + 20.0, y2: 50.0 },
+        };
+        let mut detections = vec![detection(10.0), detection(150.0)];
+
+        left_half(MaskStyle::Blur).suppress(&mut detections, (200, 100));
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].bbox.x1, 150.0);
+    }
+
+    #[test]
+    fn invalid_polygons_are_rejected() {
+        let privacy = CameraPrivacy { polygons: vec![vec![[0.0, 0.0], [1.0, 1.0]]], ..Default::default() };
+        assert!(privacy.validate().is_err());
+        let privacy = CameraPrivacy { polygons: vec![vec![[0.0, 0.0], [640.0, 0.0], [0.0, 480.0]]], ..Default::default() };
+        assert!(privacy.validate().is_err());
+        assert!(left_half(MaskStyle::Fill).validate().is_ok());
+    }
+}
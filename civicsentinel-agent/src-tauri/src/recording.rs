@@ -0,0 +1,628 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use crate::camera::{get_ffmpeg_path, redact_text, with_credentials, RtspTransport};
+use crate::error::AgentError;
+
+/// File in the app data directory listing where recordings were written
+pub const RECORDING_ROOTS_FILE: &str = "recording_roots.json";
+
+/// strftime pattern ffmpeg names each segment with (local time of its first frame)
+const SEGMENT_PATTERN: &str = "%Y%m%d-%H%M%S";
+/// Matroska survives ffmpeg being killed mid-segment, unlike MP4
+const SEGMENT_EXTENSION: &str = "mkv";
+/// How often expired segments are swept
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+/// How long ffmpeg gets to finish the current segment after being asked to quit
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+/// Wait before the first restart of a crashed ffmpeg; doubles per consecutive crash
+const FIRST_RESTART_DELAY: Duration = Duration::from_secs(2);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+/// A run this long counts as recovered and resets the restart backoff
+const HEALTHY_RUN: Duration = Duration::from_secs(60);
+/// A segment this small when ffmpeg dies holds no playable video
+const MIN_SEGMENT_BYTES: u64 = 1024;
+
+/// Lifecycle of a recording as reported by `get_recording_status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingState {
+    Running,
+    /// ffmpeg exited unexpectedly and is being restarted
+    Restarting,
+    Stopped,
+}
+
+/// Current state of a camera's recording
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingStatus {
+    pub state: RecordingState,
+    /// Segment ffmpeg is writing (the newest one in the directory)
+    pub current_file: Option<String>,
+    /// Size of all segments written since the recording started
+    pub bytes_written: u64,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+}
+
+impl RecordingStatus {
+    pub fn stopped() -> Self {
+        Self {
+            state: RecordingState::Stopped,
+            current_file: None,
+            bytes_written: 0,
+            restarts: 0,
+            last_error: None,
+        }
+    }
+}
+
+/// What the supervisor thread reports back
+#[derive(Debug)]
+struct Supervision {
+    state: RecordingState,
+    restarts: u32,
+    last_error: Option<String>,
+}
+
+/// One recorded segment file and the time span it covers
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingSegment {
+    pub path: String,
+    pub start: String,
+    pub end: String,
+    pub size_bytes: u64,
+}
+
+/// Continuous segmented recording of one camera (DVR mode)
+///
+/// A supervisor thread prunes expired segments and restarts ffmpeg into a
+/// fresh segment if it exits while the recording is meant to be running.
+pub struct Recording {
+    process: Arc<Mutex<Child>>,
+    pub output_dir: PathBuf,
+    started_at: DateTime<Local>,
+    is_running: Arc<AtomicBool>,
+    supervision: Arc<Mutex<Supervision>>,
+    supervisor: Option<JoinHandle<()>>,
+}
+
+impl Recording {
+    /// Start ffmpeg's segment muxer writing `<output_dir>/<camera_id>/<start time>.mkv`
+    ///
+    /// Streams are copied without re-encoding. Segments older than
+    /// `retention_hours` are deleted while the recording runs.
+    #[allow(clippy::too_many_arguments)]
+    pub fn start(
+        camera_id: &str,
+        source_url: &str,
+        username: Option<&str>,
+        password: Option<&str>,
+        rtsp_transport: RtspTransport,
+        segment_secs: u64,
+        retention_hours: u64,
+        output_dir: &Path,
+    ) -> Result<Self, AgentError> {
+        if segment_secs == 0 {
+            return Err(AgentError::InvalidInput("Segment length must be at least 1 second".to_string()));
+        }
+        if retention_hours == 0 {
+            return Err(AgentError::InvalidInput("Retention must be at least 1 hour".to_string()));
+        }
+
+        let dir = camera_dir(output_dir, camera_id);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| AgentError::Internal(format!("Failed to create recording directory: {}", e)))?;
+
+        let auth_url = with_credentials(source_url, username, password);
+
+        let mut args = vec!["-hide_banner".to_string(), "-loglevel".to_string(), "error".to_string()];
+        if source_url.starts_with("rtsp") {
+            args.extend(["-rtsp_transport".to_string(), rtsp_transport.ffmpeg_arg().to_string()]);
+        }
+        args.extend([
+            "-i".to_string(),
+            auth_url,
+            "-map".to_string(),
+            "0:v".to_string(),
+            "-c".to_string(),
+            "copy".to_string(),
+            "-f".to_string(),
+            "segment".to_string(),
+            "-segment_time".to_string(),
+            segment_secs.to_string(),
+            "-segment_format".to_string(),
+            "matroska".to_string(),
+            "-reset_timestamps".to_string(),
+            "1".to_string(),
+            "-strftime".to_string(),
+            "1".to_string(),
+            dir.join(format!("{}.{}", SEGMENT_PATTERN, SEGMENT_EXTENSION)).to_string_lossy().into_owned(),
+        ]);
+
+        println!("[Recording] Starting for camera {} into {:?} ({}s segments, {}h retention)",
+            camera_id, dir, segment_secs, retention_hours);
+
+        let started_at = Local::now();
+        let process = Arc::new(Mutex::new(spawn_ffmpeg(&args)?));
+        let is_running = Arc::new(AtomicBool::new(true));
+        let supervision = Arc::new(Mutex::new(Supervision {
+            state: RecordingState::Running,
+            restarts: 0,
+            last_error: None,
+        }));
+
+        let supervisor = {
+            let process = process.clone();
+            let is_running = is_running.clone();
+            let supervision = supervision.clone();
+            let dir = dir.clone();
+            let retention = Duration::from_secs(retention_hours * 3600);
+            std::thread::spawn(move || supervise(args, process, is_running, supervision, dir, retention))
+        };
+
+        Ok(Self {
+            process,
+            output_dir: dir,
+            started_at,
+            is_running,
+            supervision,
+            supervisor: Some(supervisor),
+        })
+    }
+
+    /// Whether the recording is active (including while ffmpeg is being restarted)
+    pub fn is_running(&self) -> bool {
+        self.is_running.load(Ordering::Relaxed)
+    }
+
+    pub fn status(&self) -> RecordingStatus {
+        let (state, restarts, last_error) = match self.supervision.lock() {
+            Ok(supervision) => (supervision.state, supervision.restarts, supervision.last_error.clone()),
+            Err(_) => (RecordingState::Stopped, 0, None),
+        };
+
+        let segments: Vec<_> = segment_files(&self.output_dir)
+            .into_iter()
+            .filter(|(_, start)| *start >= self.started_at - chrono::Duration::seconds(1))
+            .collect();
+        let bytes_written = segments.iter()
+            .filter_map(|(path, _)| std::fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum();
+        let current_file = (state != RecordingState::Stopped)
+            .then(|| segments.last().map(|(path, _)| path.to_string_lossy().into_owned()))
+            .flatten();
+
+        RecordingStatus { state, current_file, bytes_written, restarts, last_error }
+    }
+
+    /// Let ffmpeg close the current segment cleanly, killing it if it doesn't exit in time
+    pub fn stop(&mut self) -> Result<(), AgentError> {
+        self.is_running.store(false, Ordering::Relaxed);
+
+        // Once the supervisor is gone nothing can restart ffmpeg behind our back
+        if let Some(supervisor) = self.supervisor.take() {
+            let _ = supervisor.join();
+        }
+
+        let mut process = self.process.lock()
+            .map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))?;
+
+        if let Some(stdin) = process.stdin.as_mut() {
+            let _ = stdin.write_all(b"q");
+        }
+        drop(process.stdin.take());
+
+        let started = Instant::now();
+        loop {
+            match process.try_wait() {
+                Ok(Some(_)) => break,
+                Ok(None) if started.elapsed() < STOP_TIMEOUT => std::thread::sleep(Duration::from_millis(100)),
+                _ => {
+                    process.kill()
+                        .map_err(|e| AgentError::Ffmpeg(format!("Failed to kill recording: {}", e)))?;
+                    let _ = process.wait();
+                    break;
+                }
+            }
+        }
+
+        if let Ok(mut supervision) = self.supervision.lock() {
+            supervision.state = RecordingState::Stopped;
+        }
+
+        println!("[Recording] Stopped recording into {:?}", self.output_dir);
+        Ok(())
+    }
+}
+
+/// stdin stays open so stop() can ask ffmpeg to finish the current segment;
+/// stderr is logged with camera credentials masked
+fn spawn_ffmpeg(args: &[String]) -> Result<Child, AgentError> {
+    let mut child = Command::new(get_ffmpeg_path())
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| AgentError::Ffmpeg(format!("Failed to start recording: {}", e)))?;
+
+    if let Some(stderr) = child.stderr.take() {
+        std::thread::spawn(move || {
+            use std::io::BufRead;
+            for line in std::io::BufReader::new(stderr).lines().map_while(Result::ok) {
+                println!("[Recording] ffmpeg: {}", redact_text(&line));
+            }
+        });
+    }
+    Ok(child)
+}
+
+/// Delay before restart attempt number `failures + 1`
+fn restart_delay(failures: u32) -> Duration {
+    FIRST_RESTART_DELAY
+        .saturating_mul(2u32.saturating_pow(failures))
+        .min(MAX_RESTART_DELAY)
+}
+
+/// Sleep up to `duration`, returning early once the recording is stopped
+fn sleep_while_running(is_running: &AtomicBool, duration: Duration) {
+    let slept = Instant::now();
+    while is_running.load(Ordering::Relaxed) && slept.elapsed() < duration {
+        std::thread::sleep(Duration::from_millis(200));
+    }
+}
+
+/// Prune expired segments and restart ffmpeg whenever it exits on its own
+fn supervise(
+    args: Vec<String>,
+    process: Arc<Mutex<Child>>,
+    is_running: Arc<AtomicBool>,
+    supervision: Arc<Mutex<Supervision>>,
+    dir: PathBuf,
+    retention: Duration,
+) {
+    let set = |update: &dyn Fn(&mut Supervision)| {
+        if let Ok(mut supervision) = supervision.lock() {
+            update(&mut supervision);
+        }
+    };
+
+    let mut last_prune: Option<Instant> = None;
+    let mut run_started = Instant::now();
+    let mut failures = 0u32;
+
+    while is_running.load(Ordering::Relaxed) {
+        if last_prune.is_none_or(|at| at.elapsed() >= PRUNE_INTERVAL) {
+            let removed = prune_segments(&dir, retention, Local::now());
+            if removed > 0 {
+                println!("[Recording] Deleted {} expired segment(s) from {:?}", removed, dir);
+            }
+            last_prune = Some(Instant::now());
+        }
+
+        let exited = match process.lock() {
+            Ok(mut child) => child.try_wait().ok().flatten(),
+            Err(_) => break,
+        };
+
+        if let Some(status) = exited {
+            println!("[Recording] ffmpeg for {:?} exited unexpectedly ({})", dir, status);
+            set(&|s| {
+                s.state = RecordingState::Restarting;
+                s.last_error = Some(format!("ffmpeg exited: {}", status));
+            });
+
+            if let Some(partial) = remove_partial_segment(&dir) {
+                println!("[Recording] Removed partial segment {:?}", partial);
+            }
+
+            if run_started.elapsed() >= HEALTHY_RUN {
+                failures = 0;
+            }
+            let delay = restart_delay(failures);
+            failures = failures.saturating_add(1);
+            println!("[Recording] Restarting in {:?} (attempt {})", delay, failures);
+
+            sleep_while_running(&is_running, delay);
+            if !is_running.load(Ordering::Relaxed) {
+                break;
+            }
+
+            // strftime naming puts the restarted run in a new segment file
+            match spawn_ffmpeg(&args) {
+                Ok(child) => {
+                    if let Ok(mut process) = process.lock() {
+                        *process = child;
+                    }
+                    run_started = Instant::now();
+                    set(&|s| {
+                        s.state = RecordingState::Running;
+                        s.restarts += 1;
+                    });
+                }
+                Err(e) => set(&|s| s.last_error = Some(e.to_string())),
+            }
+            continue;
+        }
+
+        sleep_while_running(&is_running, Duration::from_secs(1));
+    }
+}
+
+/// Delete the newest segment if it is too small to hold any video, returning its path
+///
+/// Only called once ffmpeg has exited, so the newest segment is not being written.
+fn remove_partial_segment(dir: &Path) -> Option<PathBuf> {
+    let (path, _) = segment_files(dir).pop()?;
+    let size = std::fs::metadata(&path).ok()?.len();
+    if size >= MIN_SEGMENT_BYTES {
+        return None;
+    }
+    std::fs::remove_file(&path).ok()?;
+    Some(path)
+}
+
+/// Directory holding one camera's segments
+pub fn camera_dir(output_dir: &Path, camera_id: &str) -> PathBuf {
+    let safe_id: String = camera_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    output_dir.join(safe_id)
+}
+
+/// Every `output_dir` a recording was started with, so retention still
+/// finds the segments after the recording stops or the agent restarts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecordingRoots {
+    pub roots: Vec<PathBuf>,
+}
+
+impl RecordingRoots {
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AgentError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| AgentError::Internal(format!("Failed to encode recording roots: {}", e)))?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| AgentError::Internal(format!("Failed to create settings directory: {}", e)))?;
+        }
+        std::fs::write(path, json)
+            .map_err(|e| AgentError::Internal(format!("Failed to save recording roots: {}", e)))
+    }
+
+    /// Remember `root`; false if it was already known
+    pub fn insert(&mut self, root: &Path) -> bool {
+        if self.roots.iter().any(|known| known == root) {
+            return false;
+        }
+        self.roots.push(root.to_path_buf());
+        true
+    }
+
+    /// Camera directories under the known roots that hold segments; other
+    /// folders in a shared output directory are left out
+    pub fn camera_dirs(&self) -> Vec<PathBuf> {
+        self.roots.iter()
+            .filter_map(|root| std::fs::read_dir(root).ok())
+            .flat_map(|entries| entries.filter_map(|entry| entry.ok()))
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir() && !segment_files(path).is_empty())
+            .collect()
+    }
+}
+
+/// Start time encoded in a segment's file name
+fn segment_start(path: &Path) -> Option<DateTime<Local>> {
+    if path.extension()? != SEGMENT_EXTENSION {
+        return None;
+    }
+    let stem = path.file_stem()?.to_str()?;
+    let naive = NaiveDateTime::parse_from_str(stem, SEGMENT_PATTERN).ok()?;
+    Local.from_local_datetime(&naive).earliest()
+}
+
+/// Segment files in `dir` with their start times, oldest first
+fn segment_files(dir: &Path) -> Vec<(PathBuf, DateTime<Local>)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut segments: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter_map(|path| segment_start(&path).map(|start| (path, start)))
+        .collect();
+    segments.sort_by_key(|(_, start)| *start);
+    segments
+}
+
+/// Recorded segments in `dir`, oldest first
+///
+/// A segment ends where the next one starts; the newest segment (possibly
+/// still being written) ends at its last modification time.
+pub fn list_segments(dir: &Path) -> Vec<RecordingSegment> {
+    let segments = segment_files(dir);
+
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, (path, start))| {
+            let metadata = std::fs::metadata(path).ok();
+            let end = match segments.get(i + 1) {
+                Some((_, next_start)) => *next_start,
+                None => metadata
+                    .as_ref()
+                    .and_then(|m| m.modified().ok())
+                    .map(DateTime::<Local>::from)
+                    .map_or(*start, |modified| modified.max(*start)),
+            };
+
+            RecordingSegment {
+                path: path.to_string_lossy().into_owned(),
+                start: start.to_rfc3339(),
+                end: end.to_rfc3339(),
+                size_bytes: metadata.map_or(0, |m| m.len()),
+            }
+        })
+        .collect()
+}
+
+/// Delete segments that started before `now - retention`, returning how many were removed
+///
+/// The newest segment is always kept since ffmpeg may still be writing it.
+pub fn prune_segments(dir: &Path, retention: Duration, now: DateTime<Local>) -> usize {
+    let Ok(retention) = chrono::Duration::from_std(retention) else {
+        return 0;
+    };
+    let cutoff = now - retention;
+
+    let mut segments = segment_files(dir);
+    segments.pop();
+
+    segments
+        .into_iter()
+        .filter(|(_, start)| *start < cutoff)
+        .filter(|(path, _)| match std::fs::remove_file(path) {
+            Ok(()) => true,
+            Err(e) => {
+                println!("[Recording] Failed to delete {:?}: {}", path, e);
+                false
+            }
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("civicsentinel-recording-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn touch(dir: &Path, name: &str, bytes: usize) {
+        std::fs::write(dir.join(name), vec![0u8; bytes]).unwrap();
+    }
+
+    fn at(s: &str) -> DateTime<Local> {
+        let naive = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+        Local.from_local_datetime(&naive).earliest().unwrap()
+    }
+
+    #[test]
+    fn stopped_recordings_are_found_from_saved_roots() {
+        let root = scratch_dir("roots");
+        let camera = camera_dir(&root, "cam 1");
+        std::fs::create_dir_all(&camera).unwrap();
+        touch(&camera, "20240101-120000.mkv", 10);
+        std::fs::create_dir_all(root.join("holiday")).unwrap();
+        touch(&root.join("holiday"), "beach.mkv", 10);
+
+        // Only what was saved when the recording started; no Recording is running
+        let path = root.join(RECORDING_ROOTS_FILE);
+        let mut roots = RecordingRoots::default();
+        assert!(roots.insert(&root));
+        assert!(!roots.insert(&root));
+        roots.save(&path).unwrap();
+
+        assert_eq!(RecordingRoots::load(&path).camera_dirs(), vec![camera]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn lists_segments_in_order_with_time_ranges() {
+        let dir = scratch_dir("list");
+        touch(&dir, "20240101-120500.mkv", 20);
+        touch(&dir, "20240101-120000.mkv", 10);
+        touch(&dir, "notes.txt", 5);
+        touch(&dir, "garbage.mkv", 5);
+
+        let segments = list_segments(&dir);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].start, at("2024-01-01 12:00:00").to_rfc3339());
+        assert_eq!(segments[0].end, at("2024-01-01 12:05:00").to_rfc3339());
+        assert_eq!(segments[0].size_bytes, 10);
+        assert_eq!(segments[1].start, at("2024-01-01 12:05:00").to_rfc3339());
+        // Newest segment ends at its modification time, which is now
+        assert!(segments[1].end > segments[1].start);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_directory_has_no_segments() {
+        assert!(list_segments(Path::new("/nonexistent/civicsentinel")).is_empty());
+    }
+
+    #[test]
+    fn prunes_only_expired_segments_and_keeps_newest() {
+        let dir = scratch_dir("prune");
+        touch(&dir, "20240101-100000.mkv", 1);
+        touch(&dir, "20240101-110000.mkv", 1);
+        touch(&dir, "20240101-113000.mkv", 1);
+
+        let removed = prune_segments(&dir, Duration::from_secs(3600), at("2024-01-01 12:15:00"));
+        assert_eq!(removed, 2);
+        assert_eq!(list_segments(&dir).len(), 1);
+
+        // Even an expired segment survives if it is the one being written
+        let removed = prune_segments(&dir, Duration::from_secs(60), at("2024-02-01 00:00:00"));
+        assert_eq!(removed, 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn removes_only_a_tiny_newest_segment() {
+        let dir = scratch_dir("partial");
+        touch(&dir, "20240101-100000.mkv", 0);
+        touch(&dir, "20240101-110000.mkv", 4096);
+        assert_eq!(remove_partial_segment(&dir), None);
+
+        touch(&dir, "20240101-120000.mkv", 10);
+        assert_eq!(remove_partial_segment(&dir), Some(dir.join("20240101-120000.mkv")));
+        assert_eq!(list_segments(&dir).len(), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn restart_delay_backs_off_to_a_cap() {
+        assert_eq!(restart_delay(0), Duration::from_secs(2));
+        assert_eq!(restart_delay(2), Duration::from_secs(8));
+        assert_eq!(restart_delay(10), MAX_RESTART_DELAY);
+        assert_eq!(restart_delay(u32::MAX), MAX_RESTART_DELAY);
+    }
+
+    #[test]
+    fn camera_dir_keeps_ids_inside_output_dir() {
+        assert_eq!(camera_dir(Path::new("/rec"), "../cam 1"), PathBuf::from("/rec/___cam_1"));
+    }
+
+    #[test]
+    fn rejects_zero_segment_length() {
+        let err = Recording::start(
+            "cam", "rtsp://127.0.0.1/stream", None, None, RtspTransport::default(), 0, 24, Path::new("/tmp"),
+        ).err().unwrap();
+        assert!(matches!(err, AgentError::InvalidInput(_)));
+    }
+}
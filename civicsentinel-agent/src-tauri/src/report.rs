@@ -0,0 +1,402 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use image::{Rgb, RgbImage};
+use serde::Serialize;
+use crate::api::DetectionResponse;
+use crate::error::AgentError;
+use crate::labels::LabelMap;
+
+/// Directory in the app data dir where generated incident reports are kept
+pub const REPORTS_DIR: &str = "reports";
+
+/// Each glyph pixel is drawn as a SCALE x SCALE block
+const SCALE: u32 = 2;
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+pub(crate) const CHAR_ADVANCE: u32 = (GLYPH_WIDTH + 1) * SCALE;
+pub(crate) const TEXT_HEIGHT: u32 = GLYPH_HEIGHT * SCALE;
+const LINE_HEIGHT: u32 = (GLYPH_HEIGHT + 4) * SCALE;
+const PADDING: u32 = 8;
+const MIN_WIDTH: u32 = 480;
+
+const BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+const HEADER_BACKGROUND: Rgb<u8> = Rgb([17, 24, 39]);
+const HEADER_TEXT: Rgb<u8> = Rgb([255, 255, 255]);
+const TEXT: Rgb<u8> = Rgb([17, 24, 39]);
+const ALERT_TEXT: Rgb<u8> = Rgb([185, 28, 28]);
+
+/// 5x7 bitmap for the characters reports use; rows top to bottom, MSB left.
+/// Lowercase is drawn as uppercase and anything else as '?'.
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '%' => [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '#' => [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+/// Draw a line of text with its top-left corner at (x, y), clipped to the image
+pub(crate) fn draw_text(image: &mut RgbImage, x: u32, y: u32, text: &str, color: Rgb<u8>) {
+    let (width, height) = image.dimensions();
+
+    for (index, c) in text.chars().enumerate() {
+        let origin_x = x + index as u32 * CHAR_ADVANCE;
+        if origin_x + GLYPH_WIDTH * SCALE > width {
+            break;
+        }
+
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (0x10 >> col) == 0 {
+                    continue;
+                }
+                for dy in 0..SCALE {
+                    for dx in 0..SCALE {
+                        let px = origin_x + col * SCALE + dx;
+                        let py = y + row as u32 * SCALE + dy;
+                        if px < width && py < height {
+                            image.put_pixel(px, py, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn fill_rect(image: &mut RgbImage, y: u32, height: u32, color: Rgb<u8>) {
+    let width = image.width();
+    for py in y..(y + height).min(image.height()) {
+        for px in 0..width {
+            image.put_pixel(px, py, color);
+        }
+    }
+}
+
+/// Summary table rows: detections by class, then each alert
+fn summary_lines(response: &DetectionResponse, labels: &LabelMap) -> Vec<(String, Rgb<u8>)> {
+    let mut by_class: BTreeMap<String, (usize, f64)> = BTreeMap::new();
+    for detection in &response.detections {
+        let entry = by_class.entry(labels.display_name(&detection.class_name)).or_insert((0, 0.0));
+        entry.0 += 1;
+        entry.1 = entry.1.max(detection.confidence);
+    }
+
+    let mut lines = vec![(format!("DETECTIONS: {}", response.detections.len()), TEXT)];
+    for (class, (count, max_confidence)) in by_class {
+        lines.push((format!("  {:<14} {:>3}  MAX {:>3.0}%", class, count, max_confidence * 100.0), TEXT));
+    }
+
+    lines.push((format!("ALERTS: {}", response.alerts.len()), TEXT));
+    for alert in &response.alerts {
+        lines.push((
+            format!("  {:<14} {:<10} {:>3.0}%", alert.zone_name, alert.alert_type, alert.confidence * 100.0),
+            ALERT_TEXT,
+        ));
+    }
+
+    lines
+}
+
+/// Render a cached frame and its detections as a single PNG incident report
+///
+/// The frame is shown with detection boxes under a header naming the camera
+/// and capture time, followed by a table of detections and alerts.
+pub fn render_incident_report(
+    camera_id: &str,
+    frame_bytes: &[u8],
+    response: &DetectionResponse,
+    captured_at: chrono::DateTime<chrono::Local>,
+    labels: &LabelMap,
+) -> Result<Vec<u8>, AgentError> {
+    let mut frame = image::load_from_memory(frame_bytes)
+        .map_err(|e| AgentError::Decode(format!("Failed to decode frame: {}", e)))?
+        .to_rgb8();
+    crate::frame::draw_boxes(&mut frame, &response.detections, labels);
+
+    let lines = summary_lines(response, labels);
+    let header_height = LINE_HEIGHT + PADDING * 2;
+    let table_height = lines.len() as u32 * LINE_HEIGHT + PADDING * 2;
+
+    let width = frame.width().max(MIN_WIDTH);
+    let mut report = RgbImage::from_pixel(width, header_height + frame.height() + table_height, BACKGROUND);
+
+    fill_rect(&mut report, 0, header_height, HEADER_BACKGROUND);
+    let header = format!("CAMERA {}   {}", camera_id, captured_at.format("%Y-%m-%d %H:%M:%S %:z"));
+    draw_text(&mut report, PADDING, PADDING, &header, HEADER_TEXT);
+
+    image::imageops::replace(&mut report, &frame, 0, header_height as i64);
+
+    let table_top = header_height + frame.height() + PADDING;
+    for (index, (line, color)) in lines.iter().enumerate() {
+        draw_text(&mut report, PADDING, table_top + index as u32 * LINE_HEIGHT, line, *color);
+    }
+
+    let mut encoded = std::io::Cursor::new(Vec::new());
+    report.write_to(&mut encoded, image::ImageFormat::Png)
+        .map_err(|e| AgentError::Internal(format!("Failed to encode report: {}", e)))?;
+
+    Ok(encoded.into_inner())
+}
+
+/// One camera's cached frame and detections, for `snapshot_bundle`
+pub struct CameraSnapshot {
+    pub camera_id: String,
+    pub frame: Vec<u8>,
+    pub response: DetectionResponse,
+    pub captured_at: chrono::DateTime<chrono::Local>,
+}
+
+/// `manifest.json` at the root of a snapshot bundle
+#[derive(Debug, Serialize)]
+struct BundleManifest {
+    agent_version: &'static str,
+    exported_at: String,
+    cameras: Vec<BundleEntry>,
+}
+
+/// Paths are relative to the bundle root
+#[derive(Debug, Serialize)]
+struct BundleEntry {
+    camera_id: String,
+    captured_at: String,
+    frame: String,
+    annotated: Option<String>,
+    detections: String,
+    detection_count: usize,
+    alert_count: usize,
+}
+
+/// Package cameras' latest frames and detections as a ZIP
+///
+/// Each camera gets a directory holding `frame.jpg`, `detections.json` and,
+/// when the frame can be decoded, `annotated.jpg` with detection boxes drawn.
+pub fn snapshot_bundle(
+    cameras: &[CameraSnapshot],
+    exported_at: chrono::DateTime<chrono::Local>,
+    labels: &LabelMap,
+) -> Result<Vec<u8>, AgentError> {
+    let zip_error = |e: zip::result::ZipError| AgentError::Internal(format!("Failed to write bundle: {}", e));
+    let io_error = |e: std::io::Error| AgentError::Internal(format!("Failed to write bundle: {}", e));
+    let json_error = |e: serde_json::Error| AgentError::Internal(format!("Failed to encode bundle: {}", e));
+
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let mut entries = Vec::with_capacity(cameras.len());
+
+    for camera in cameras {
+        let dir = crate::sink::sanitize_component(&camera.camera_id);
+        let frame_path = format!("{}/frame.jpg", dir);
+        let detections_path = format!("{}/detections.json", dir);
+
+        let frame = match crate::frame::embed_metadata(&camera.frame, &camera.camera_id, camera.captured_at) {
+            Ok(stamped) => stamped,
+            Err(e) => {
+                println!("[Rust] Bundling {} frame without EXIF: {}", camera.camera_id, e);
+                camera.frame.clone()
+            }
+        };
+        zip.start_file(frame_path.as_str(), options).map_err(zip_error)?;
+        zip.write_all(&frame).map_err(io_error)?;
+
+        zip.start_file(detections_path.as_str(), options).map_err(zip_error)?;
+        zip.write_all(&serde_json::to_vec_pretty(&camera.response).map_err(json_error)?).map_err(io_error)?;
+
+        let annotated = match crate::frame::annotate(
+            &camera.frame,
+            &camera.response.detections,
+            labels,
+            &camera.camera_id,
+            camera.captured_at,
+        ) {
+            Ok(annotated) => {
+                let annotated_path = format!("{}/annotated.jpg", dir);
+                zip.start_file(annotated_path.as_str(), options).map_err(zip_error)?;
+                zip.write_all(&annotated).map_err(io_error)?;
+                Some(annotated_path)
+            }
+            Err(e) => {
+                println!("[Rust] Bundling {} without annotation: {}", camera.camera_id, e);
+                None
+            }
+        };
+
+        entries.push(BundleEntry {
+            camera_id: camera.camera_id.clone(),
+            captured_at: camera.captured_at.to_rfc3339(),
+            frame: frame_path,
+            annotated,
+            detections: detections_path,
+            detection_count: camera.response.detections.len(),
+            alert_count: camera.response.alerts.len(),
+        });
+    }
+
+    let manifest = BundleManifest {
+        agent_version: crate::version::AGENT_VERSION,
+        exported_at: exported_at.to_rfc3339(),
+        cameras: entries,
+    };
+    zip.start_file("manifest.json", options).map_err(zip_error)?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest).map_err(json_error)?).map_err(io_error)?;
+
+    Ok(zip.finish().map_err(zip_error)?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use crate::api::{BoundingBox, Detection, ZoneAlert};
+
+    fn test_frame(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbImage::from_pixel(width, height, Rgb([90, 90, 90]));
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut bytes, image::ImageFormat::Jpeg).unwrap();
+        bytes.into_inner()
+    }
+
+    fn response() -> DetectionResponse {
+        DetectionResponse {
+            camera_id: "front-door".to_string(),
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+            detections: vec![Detection {
+                class_name: "person".to_string(),
+                confidence: 0.92,
+                bbox: BoundingBox { x1: 40.0, y1: 30.0, x2: 160.0, y2: 200.0 },
+            }],
+            alerts: vec![ZoneAlert {
+                zone_id: 1,
+                zone_name: "Driveway".to_string(),
+                alert_type: "intrusion".to_string(),
+                confidence: 0.92,
+            }],
+            roi: None,
+            served_by: None,
+            evidence_url: None,
+            local_timestamp: None,
+            latency_ms: None,
+            inference_ms: None,
+        }
+    }
+
+    #[test]
+    fn report_stacks_header_frame_and_table() {
+        let labels = LabelMap::default();
+        let png = render_incident_report("front-door", &test_frame(640, 360), &response(), chrono::Local::now(), &labels)
+            .unwrap();
+
+        let report = image::load_from_memory(&png).unwrap();
+        let header_height = LINE_HEIGHT + PADDING * 2;
+        // Two summary rows plus one class row and one alert row
+        let table_height = 4 * LINE_HEIGHT + PADDING * 2;
+        assert_eq!(report.width(), 640);
+        assert_eq!(report.height(), header_height + 360 + table_height);
+    }
+
+    #[test]
+    fn summary_uses_display_names() {
+        let labels: LabelMap = serde_json::from_str(r#"{ "person": { "label": "Pedestrian" } }"#).unwrap();
+        let lines = summary_lines(&response(), &labels);
+        assert!(lines[1].0.trim_start().starts_with("Pedestrian"));
+    }
+
+    #[test]
+    fn narrow_frames_are_padded_to_fit_the_table() {
+        let labels = LabelMap::default();
+        let png = render_incident_report("cam", &test_frame(160, 120), &response(), chrono::Local::now(), &labels)
+            .unwrap();
+
+        assert_eq!(image::load_from_memory(&png).unwrap().width(), MIN_WIDTH);
+    }
+
+    #[test]
+    fn bundle_holds_frames_detections_and_manifest() {
+        let cameras = [
+            CameraSnapshot {
+                camera_id: "front/door".to_string(),
+                frame: test_frame(320, 240),
+                response: response(),
+                captured_at: chrono::Local::now(),
+            },
+            CameraSnapshot {
+                camera_id: "yard".to_string(),
+                frame: b"not-an-image".to_vec(),
+                response: response(),
+                captured_at: chrono::Local::now(),
+            },
+        ];
+
+        let bytes = snapshot_bundle(&cameras, chrono::Local::now(), &LabelMap::default()).unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let mut names: Vec<_> = archive.file_names().map(str::to_string).collect();
+        names.sort();
+        assert_eq!(names, [
+            "front_door/annotated.jpg",
+            "front_door/detections.json",
+            "front_door/frame.jpg",
+            "manifest.json",
+            "yard/detections.json",
+            "yard/frame.jpg",
+        ]);
+
+        let manifest: serde_json::Value = serde_json::from_reader(archive.by_name("manifest.json").unwrap()).unwrap();
+        assert_eq!(manifest["cameras"][0]["camera_id"], "front/door");
+        assert_eq!(manifest["cameras"][0]["detection_count"], 1);
+        assert_eq!(manifest["cameras"][1]["annotated"], serde_json::Value::Null);
+
+        for name in ["front_door/frame.jpg", "front_door/annotated.jpg"] {
+            let mut jpeg = Vec::new();
+            archive.by_name(name).unwrap().read_to_end(&mut jpeg).unwrap();
+            let exif = exif::Reader::new().read_from_container(&mut std::io::Cursor::new(jpeg)).unwrap();
+            assert!(exif.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY).is_some(), "{}", name);
+        }
+    }
+}
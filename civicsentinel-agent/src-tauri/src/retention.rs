@@ -0,0 +1,310 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use serde::{Deserialize, Serialize};
+use crate::error::AgentError;
+
+/// File in the app data directory holding the retention policy
+pub const RETENTION_FILE: &str = "retention.json";
+
+/// How often the retention policy is applied in the background
+pub const RETENTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Files changed this recently may still be open (e.g. the recording
+/// segment ffmpeg is writing) and are never deleted
+const IN_USE_GRACE: Duration = Duration::from_secs(60);
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Limits on the files the agent keeps on disk; no limit when unset
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    pub max_age_days: Option<u64>,
+    /// Total across all categories
+    pub max_total_bytes: Option<u64>,
+}
+
+impl RetentionPolicy {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_age_days.is_none() && self.max_total_bytes.is_none()
+    }
+
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AgentError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| AgentError::Internal(format!("Failed to encode retention policy: {}", e)))?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| AgentError::Internal(format!("Failed to create settings directory: {}", e)))?;
+        }
+        std::fs::write(path, json)
+            .map_err(|e| AgentError::Internal(format!("Failed to save retention policy: {}", e)))
+    }
+}
+
+/// A directory of agent-written files, e.g. `detections` or a recording dir
+#[derive(Debug, Clone)]
+pub struct DataDir {
+    pub category: String,
+    pub path: PathBuf,
+}
+
+/// Space used by one category of files
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CategoryUsage {
+    pub category: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Data directory that passed `check_storage`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StorageStatus {
+    pub path: String,
+    /// Bytes available to the agent; `None` where the platform doesn't report it
+    pub free_bytes: Option<u64>,
+}
+
+/// Make sure `dir` exists and the agent can write to it, by writing and
+/// deleting a scratch file
+///
+/// Read-only data directories (some sandboxes and containers) otherwise
+/// only show up later as failed saves, recordings and uploads.
+pub fn check_storage(dir: &Path) -> Result<StorageStatus, AgentError> {
+    let not_writable = |e: std::io::Error| AgentError::Internal(format!(
+        "Data directory {} is not writable: {}", dir.display(), e
+    ));
+
+    std::fs::create_dir_all(dir).map_err(not_writable)?;
+    let probe = dir.join(format!(".write-test-{}", std::process::id()));
+    std::fs::write(&probe, b"civicsentinel").map_err(not_writable)?;
+    std::fs::remove_file(&probe).map_err(not_writable)?;
+
+    Ok(StorageStatus {
+        path: dir.display().to_string(),
+        free_bytes: free_space(dir),
+    })
+}
+
+#[cfg(unix)]
+fn free_space(dir: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is NUL-terminated and `stats` is a valid out-pointer
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stats) } != 0 {
+        return None;
+    }
+    #[allow(clippy::unnecessary_cast)]
+    Some(stats.f_bavail as u64 * stats.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn free_space(_dir: &Path) -> Option<u64> {
+    None
+}
+
+/// What a retention pass deleted
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RetentionReport {
+    pub files_deleted: usize,
+    pub bytes_freed: u64,
+}
+
+struct DataFile {
+    path: PathBuf,
+    bytes: u64,
+    modified: SystemTime,
+}
+
+/// Every file under `dir`, recursively; unreadable entries are skipped
+fn files_under(dir: &Path) -> Vec<DataFile> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            files.extend(files_under(&entry.path()));
+        } else if metadata.is_file() {
+            files.push(DataFile {
+                path: entry.path(),
+                bytes: metadata.len(),
+                modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            });
+        }
+    }
+    files
+}
+
+/// Files and bytes in each category; a category listed twice is summed
+pub fn disk_usage(dirs: &[DataDir]) -> Vec<CategoryUsage> {
+    let mut usage: Vec<CategoryUsage> = Vec::new();
+    for dir in dirs {
+        let files = files_under(&dir.path);
+        let bytes = files.iter().map(|file| file.bytes).sum::<u64>();
+
+        match usage.iter_mut().find(|entry| entry.category == dir.category) {
+            Some(entry) => {
+                entry.files += files.len();
+                entry.bytes += bytes;
+            }
+            None => usage.push(CategoryUsage { category: dir.category.clone(), files: files.len(), bytes }),
+        }
+    }
+    usage
+}
+
+/// Delete files older than the age limit, then the oldest remaining files
+/// until the total fits the size limit
+///
+/// Files changed in the last minute are left alone even if that keeps the
+/// total over the limit. Directories are kept.
+pub fn apply_retention(dirs: &[DataDir], policy: &RetentionPolicy, now: SystemTime) -> RetentionReport {
+    let mut files: Vec<DataFile> = dirs.iter().flat_map(|dir| files_under(&dir.path)).collect();
+    files.sort_by_key(|file| file.modified);
+
+    let age_of = |file: &DataFile| now.duration_since(file.modified).unwrap_or_default();
+    let max_age = policy.max_age_days.map(|days| Duration::from_secs(days.saturating_mul(SECS_PER_DAY)));
+    let mut total = files.iter().map(|file| file.bytes).sum::<u64>();
+    let mut report = RetentionReport::default();
+
+    // Oldest first, so the size limit frees the least recent files
+    for file in files {
+        let age = age_of(&file);
+        if age < IN_USE_GRACE {
+            break;
+        }
+
+        let expired = max_age.is_some_and(|max_age| age > max_age);
+        let over_size = policy.max_total_bytes.is_some_and(|max_bytes| total > max_bytes);
+        if !expired && !over_size {
+            continue;
+        }
+
+        match std::fs::remove_file(&file.path) {
+            Ok(()) => {
+                total -= file.bytes;
+                report.files_deleted += 1;
+                report.bytes_freed += file.bytes;
+            }
+            Err(e) => println!("[Retention] Failed to delete {:?}: {}", file.path, e),
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("civicsentinel-retention-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Write a file last modified `age` before `now`
+    fn write_aged(path: &Path, bytes: usize, now: SystemTime, age: Duration) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, vec![0u8; bytes]).unwrap();
+        std::fs::File::options().write(true).open(path).unwrap()
+            .set_modified(now - age)
+            .unwrap();
+    }
+
+    fn data_dir(category: &str, path: &Path) -> DataDir {
+        DataDir { category: category.to_string(), path: path.to_path_buf() }
+    }
+
+    #[test]
+    fn storage_check_creates_dir_and_leaves_nothing_behind() {
+        let root = scratch_dir("storage");
+        let dir = root.join("data");
+
+        let status = check_storage(&dir).unwrap();
+        assert!(dir.is_dir());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        #[cfg(unix)]
+        assert!(status.free_bytes.is_some_and(|bytes| bytes > 0));
+
+        // A file where the directory should be can't be written into
+        let blocked = root.join("blocked");
+        std::fs::write(&blocked, b"").unwrap();
+        assert!(matches!(check_storage(&blocked), Err(AgentError::Internal(_))));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn usage_is_summed_per_category() {
+        let root = scratch_dir("usage");
+        let now = SystemTime::now();
+        write_aged(&root.join("detections/cam-1/a.jpg"), 100, now, HOUR);
+        write_aged(&root.join("detections/cam-2/b.jpg"), 50, now, HOUR);
+        write_aged(&root.join("alert_images/7"), 30, now, HOUR);
+
+        let usage = disk_usage(&[
+            data_dir("detections", &root.join("detections")),
+            data_dir("alert_images", &root.join("alert_images")),
+            data_dir("recordings", &root.join("missing")),
+        ]);
+
+        assert_eq!(usage, vec![
+            CategoryUsage { category: "detections".to_string(), files: 2, bytes: 150 },
+            CategoryUsage { category: "alert_images".to_string(), files: 1, bytes: 30 },
+            CategoryUsage { category: "recordings".to_string(), files: 0, bytes: 0 },
+        ]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn deletes_expired_then_oldest_over_size() {
+        let root = scratch_dir("apply");
+        let now = SystemTime::now();
+        let day = Duration::from_secs(SECS_PER_DAY);
+        write_aged(&root.join("old.jpg"), 100, now, day * 10);
+        write_aged(&root.join("older.jpg"), 100, now, day * 2);
+        write_aged(&root.join("newer.jpg"), 100, now, day);
+        write_aged(&root.join("writing.mp4"), 500, now, Duration::ZERO);
+
+        let policy = RetentionPolicy { max_age_days: Some(7), max_total_bytes: Some(600) };
+        let report = apply_retention(&[data_dir("detections", &root)], &policy, now);
+
+        // 10-day file expires; 2-day file goes to fit 600 bytes; the file
+        // still being written is never touched
+        assert_eq!(report, RetentionReport { files_deleted: 2, bytes_freed: 200 });
+        assert!(!root.join("old.jpg").exists());
+        assert!(!root.join("older.jpg").exists());
+        assert!(root.join("newer.jpg").exists());
+        assert!(root.join("writing.mp4").exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn unlimited_policy_deletes_nothing() {
+        let root = scratch_dir("unlimited");
+        let now = SystemTime::now();
+        write_aged(&root.join("a.jpg"), 100, now, Duration::from_secs(SECS_PER_DAY * 365));
+
+        let report = apply_retention(&[data_dir("detections", &root)], &RetentionPolicy::default(), now);
+        assert_eq!(report, RetentionReport::default());
+        assert!(root.join("a.jpg").exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
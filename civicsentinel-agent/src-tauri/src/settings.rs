@@ -0,0 +1,608 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use crate::error::AgentError;
+
+/// Default ceiling for a single uploaded frame
+pub const DEFAULT_MAX_FRAME_BYTES: usize = 2 * 1024 * 1024;
+
+/// Detection uploads allowed in flight at once by default
+pub const DEFAULT_MAX_CONCURRENT_UPLOADS: usize = 8;
+
+/// How long a frame waits for an upload slot before it is dropped
+pub const DEFAULT_UPLOAD_QUEUE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// File under the app data dir detection settings are saved to
+pub const DETECTION_SETTINGS_FILE: &str = "detection_settings.json";
+
+/// File in the app data directory holding upload byte counters
+pub const BANDWIDTH_FILE: &str = "bandwidth.json";
+
+/// File in the app data directory holding the monitoring schedule
+pub const SCHEDULE_FILE: &str = "schedule.json";
+
+/// Most frames a burst may capture for one detection cycle
+pub const MAX_BURST_FRAMES: u32 = 10;
+
+/// Default gap between the frames of a burst
+pub const DEFAULT_BURST_SPACING_MS: u64 = 200;
+
+/// Detection and alerting tunables, read and written as one unit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DetectionSettings {
+    /// Minimum time between webhook alerts for the same camera zone
+    pub alert_cooldown_secs: u64,
+    /// Consecutive detection cycles a zone alert must persist before it fires
+    pub alert_consecutive_frames: u32,
+    /// Boxes overlapping at least this much count as a repeat detection
+    pub dedup_iou_threshold: f64,
+    /// Frames captured and sent per detection cycle; more catch fast
+    /// passing objects at the cost of more uploads. 1 is a single frame.
+    pub burst_count: u32,
+    /// Gap between the frames of a burst
+    pub burst_spacing_ms: u64,
+    /// What one detection request costs, in the backend's billing
+    /// currency; used to estimate batch runs
+    pub cost_per_request: f64,
+}
+
+impl Default for DetectionSettings {
+    fn default() -> Self {
+        Self {
+            alert_cooldown_secs: crate::webhook::DEFAULT_ALERT_COOLDOWN_SECS,
+            alert_consecutive_frames: crate::webhook::DEFAULT_ALERT_CONSECUTIVE_FRAMES,
+            dedup_iou_threshold: crate::dedup::DEFAULT_IOU_THRESHOLD,
+            burst_count: 1,
+            burst_spacing_ms: DEFAULT_BURST_SPACING_MS,
+            cost_per_request: 0.0,
+        }
+    }
+}
+
+/// Partial update to `DetectionSettings`; omitted fields keep their value
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DetectionSettingsPatch {
+    pub alert_cooldown_secs: Option<u64>,
+    pub alert_consecutive_frames: Option<u32>,
+    pub dedup_iou_threshold: Option<f64>,
+    pub burst_count: Option<u32>,
+    pub burst_spacing_ms: Option<u64>,
+    pub cost_per_request: Option<f64>,
+}
+
+impl DetectionSettings {
+    /// Settings with `patch` applied, or an error if any field is invalid
+    ///
+    /// Nothing is applied unless the whole patch is valid.
+    pub fn patched(&self, patch: &DetectionSettingsPatch) -> Result<Self, AgentError> {
+        let updated = Self {
+            alert_cooldown_secs: patch.alert_cooldown_secs.unwrap_or(self.alert_cooldown_secs),
+            alert_consecutive_frames: patch.alert_consecutive_frames.unwrap_or(self.alert_consecutive_frames),
+            dedup_iou_threshold: patch.dedup_iou_threshold.unwrap_or(self.dedup_iou_threshold),
+            burst_count: patch.burst_count.unwrap_or(self.burst_count),
+            burst_spacing_ms: patch.burst_spacing_ms.unwrap_or(self.burst_spacing_ms),
+            cost_per_request: patch.cost_per_request.unwrap_or(self.cost_per_request),
+        };
+
+        if updated.alert_consecutive_frames == 0 {
+            return Err(AgentError::InvalidInput("alert_consecutive_frames must be at least 1".to_string()));
+        }
+        if !(0.0..=1.0).contains(&updated.dedup_iou_threshold) {
+            return Err(AgentError::InvalidInput(format!(
+                "dedup_iou_threshold must be between 0 and 1, got {}", updated.dedup_iou_threshold
+            )));
+        }
+        if !(1..=MAX_BURST_FRAMES).contains(&updated.burst_count) {
+            return Err(AgentError::InvalidInput(format!(
+                "burst_count must be between 1 and {}, got {}", MAX_BURST_FRAMES, updated.burst_count
+            )));
+        }
+
+        if !updated.cost_per_request.is_finite() || updated.cost_per_request < 0.0 {
+            return Err(AgentError::InvalidInput(format!(
+                "cost_per_request must be zero or more, got {}", updated.cost_per_request
+            )));
+        }
+
+        Ok(updated)
+    }
+
+    /// Saved settings, or the defaults if there are none or they can't be read
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AgentError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| AgentError::Internal(format!("Failed to encode detection settings: {}", e)))?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| AgentError::Internal(format!("Failed to create settings directory: {}", e)))?;
+        }
+        std::fs::write(path, json)
+            .map_err(|e| AgentError::Internal(format!("Failed to save detection settings: {}", e)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduleDay {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl ScheduleDay {
+    const ALL: [ScheduleDay; 7] = [
+        ScheduleDay::Mon, ScheduleDay::Tue, ScheduleDay::Wed, ScheduleDay::Thu,
+        ScheduleDay::Fri, ScheduleDay::Sat, ScheduleDay::Sun,
+    ];
+
+    fn of(date: chrono::NaiveDate) -> Self {
+        use chrono::Datelike;
+        Self::ALL[date.weekday().num_days_from_monday() as usize]
+    }
+}
+
+/// A window of the agent's local wall-clock time when monitoring runs
+///
+/// Times are `HH:MM`. An `end` at or before `start` runs past midnight into
+/// the next day, so `22:00`-`06:00` on Friday covers Friday night.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActiveWindow {
+    pub day: ScheduleDay,
+    pub start: String,
+    pub end: String,
+}
+
+/// Minutes since midnight of an `HH:MM` time
+fn parse_clock(time: &str) -> Result<u32, AgentError> {
+    use chrono::Timelike;
+    chrono::NaiveTime::parse_from_str(time, "%H:%M")
+        .map(|t| t.hour() * 60 + t.minute())
+        .map_err(|_| AgentError::InvalidInput(format!("Invalid time '{}', expected HH:MM", time)))
+}
+
+/// Agent-wide hours when capturing and uploading run; always on when disabled
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonitoringSchedule {
+    pub enabled: bool,
+    pub windows: Vec<ActiveWindow>,
+}
+
+impl MonitoringSchedule {
+    pub fn validate(&self) -> Result<(), AgentError> {
+        for window in &self.windows {
+            parse_clock(&window.start)?;
+            parse_clock(&window.end)?;
+        }
+        Ok(())
+    }
+
+    /// Whether monitoring should run at local time `now`
+    pub fn is_active(&self, now: chrono::NaiveDateTime) -> bool {
+        use chrono::Timelike;
+        if !self.enabled {
+            return true;
+        }
+
+        let minute = now.hour() * 60 + now.minute();
+        let today = ScheduleDay::of(now.date());
+        let yesterday = now.date().pred_opt().map(ScheduleDay::of);
+
+        self.windows.iter().any(|window| {
+            let (Ok(start), Ok(end)) = (parse_clock(&window.start), parse_clock(&window.end)) else {
+                return false;
+            };
+            if start < end {
+                window.day == today && (start..end).contains(&minute)
+            } else {
+                // Crosses midnight: the tail end belongs to the previous day's window
+                (window.day == today && minute >= start) || (Some(window.day) == yesterday && minute < end)
+            }
+        })
+    }
+
+    /// Saved schedule, or always-on if there is none or it can't be read
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AgentError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| AgentError::Internal(format!("Failed to encode schedule: {}", e)))?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| AgentError::Internal(format!("Failed to create settings directory: {}", e)))?;
+        }
+        std::fs::write(path, json)
+            .map_err(|e| AgentError::Internal(format!("Failed to save schedule: {}", e)))
+    }
+}
+
+/// Bytes uploaded to the detection backend, persisted across restarts
+///
+/// Day and month counters reset when the local date moves on. Once the
+/// month's uploads reach `monthly_cap_bytes`, frames stop being sent until
+/// the next month or until the cap is raised.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BandwidthUsage {
+    /// Day `day_bytes` covers, as YYYY-MM-DD
+    pub day: String,
+    pub day_bytes: u64,
+    /// Month `month_bytes` covers, as YYYY-MM
+    pub month: String,
+    pub month_bytes: u64,
+    pub total_bytes: u64,
+    pub monthly_cap_bytes: Option<u64>,
+}
+
+impl BandwidthUsage {
+    /// Start fresh day and month counters if `today` is past them
+    fn roll(&mut self, today: chrono::NaiveDate) {
+        let day = today.format("%Y-%m-%d").to_string();
+        let month = today.format("%Y-%m").to_string();
+        if self.day != day {
+            self.day = day;
+            self.day_bytes = 0;
+        }
+        if self.month != month {
+            self.month = month;
+            self.month_bytes = 0;
+        }
+    }
+
+    pub fn cap_reached(&mut self, today: chrono::NaiveDate) -> bool {
+        self.roll(today);
+        self.monthly_cap_bytes.is_some_and(|cap| self.month_bytes >= cap)
+    }
+
+    /// Count an upload; returns true if it used up the monthly cap
+    pub fn record(&mut self, bytes: u64, today: chrono::NaiveDate) -> bool {
+        let was_reached = self.cap_reached(today);
+        self.day_bytes += bytes;
+        self.month_bytes += bytes;
+        self.total_bytes += bytes;
+        !was_reached && self.cap_reached(today)
+    }
+
+    /// Counters as of `today`
+    pub fn current(&mut self, today: chrono::NaiveDate) -> Self {
+        self.roll(today);
+        self.clone()
+    }
+
+    /// Saved counters, or zeroes if there are none or they can't be read
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AgentError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| AgentError::Internal(format!("Failed to encode bandwidth usage: {}", e)))?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| AgentError::Internal(format!("Failed to create settings directory: {}", e)))?;
+        }
+        std::fs::write(path, json)
+            .map_err(|e| AgentError::Internal(format!("Failed to save bandwidth usage: {}", e)))
+    }
+}
+
+/// Settings applied to every frame before it leaves the agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UploadSettings {
+    /// Frames larger than this are re-encoded at lower quality before upload
+    pub max_frame_bytes: usize,
+    /// Encoding frames are sent to the detection backend in
+    pub upload_format: ImageFormat,
+    /// Request layout the detection backend expects
+    pub detection_api: DetectionApi,
+    /// How the detection backend's responses are read
+    pub response: crate::api::ResponseAdapter,
+    /// Send the camera's previously cached frame as `prev_image`, for
+    /// backends running temporal models
+    pub previous_frame: bool,
+}
+
+impl Default for UploadSettings {
+    fn default() -> Self {
+        Self {
+            max_frame_bytes: DEFAULT_MAX_FRAME_BYTES,
+            upload_format: ImageFormat::default(),
+            detection_api: DetectionApi::default(),
+            response: crate::api::ResponseAdapter::default(),
+            previous_frame: false,
+        }
+    }
+}
+
+/// Hardware decoder ffmpeg uses for camera streams
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HwAccel {
+    /// Software decoding
+    #[default]
+    None,
+    /// NVIDIA (including Jetson)
+    Cuda,
+    /// Intel Quick Sync Video
+    Qsv,
+    /// macOS
+    Videotoolbox,
+}
+
+impl HwAccel {
+    const ALL: [HwAccel; 3] = [HwAccel::Cuda, HwAccel::Qsv, HwAccel::Videotoolbox];
+
+    fn ffmpeg_name(self) -> Option<&'static str> {
+        match self {
+            HwAccel::None => None,
+            HwAccel::Cuda => Some("cuda"),
+            HwAccel::Qsv => Some("qsv"),
+            HwAccel::Videotoolbox => Some("videotoolbox"),
+        }
+    }
+
+    /// ffmpeg input options; must come before `-i`
+    pub fn input_args(self) -> &'static [&'static str] {
+        match self {
+            HwAccel::None => &[],
+            HwAccel::Cuda => &["-hwaccel", "cuda"],
+            HwAccel::Qsv => &["-hwaccel", "qsv"],
+            HwAccel::Videotoolbox => &["-hwaccel", "videotoolbox"],
+        }
+    }
+
+    /// Options offered by `ffmpeg -hwaccels` output, always including `None`
+    pub fn parse_available(output: &str) -> Vec<HwAccel> {
+        let listed: Vec<&str> = output.lines()
+            .skip_while(|line| !line.starts_with("Hardware acceleration methods"))
+            .skip(1)
+            .map(str::trim)
+            .collect();
+
+        std::iter::once(HwAccel::None)
+            .chain(HwAccel::ALL.into_iter().filter(|hwaccel| {
+                hwaccel.ffmpeg_name().is_some_and(|name| listed.contains(&name))
+            }))
+            .collect()
+    }
+}
+
+/// Which detect endpoint frames are posted to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DetectionApi {
+    /// `/api/v1/detect`: image part plus a `camera_id` text field
+    #[default]
+    V1,
+    /// `/api/v2/detect`: image part plus a JSON `metadata` part
+    V2,
+}
+
+/// Image encoding used for detection uploads
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    #[default]
+    Jpeg,
+    /// Lossless; larger uploads but no compression artifacts
+    Png,
+    /// Lossless WebP; smaller than PNG
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "frame.jpg",
+            ImageFormat::Png => "frame.png",
+            ImageFormat::Webp => "frame.webp",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Png => "image/png",
+            ImageFormat::Webp => "image/webp",
+        }
+    }
+}
+
+/// Bounds how many detection uploads run at once across all cameras
+///
+/// Frames wait for a free slot up to the queue timeout, then are dropped, so
+/// a slow backend can't pile up unbounded requests.
+#[derive(Debug)]
+pub struct UploadLimiter {
+    state: Mutex<LimiterState>,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    max: usize,
+    queue_timeout: Duration,
+    semaphore: Arc<Semaphore>,
+}
+
+impl Default for UploadLimiter {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(LimiterState {
+                max: DEFAULT_MAX_CONCURRENT_UPLOADS,
+                queue_timeout: DEFAULT_UPLOAD_QUEUE_TIMEOUT,
+                semaphore: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT_UPLOADS)),
+            }),
+        }
+    }
+}
+
+impl UploadLimiter {
+    /// Change the limits; uploads already holding a slot finish under the old limit
+    pub fn configure(&self, max: usize, queue_timeout: Duration) {
+        if let Ok(mut state) = self.state.lock() {
+            if state.max != max {
+                state.semaphore = Arc::new(Semaphore::new(max));
+                state.max = max;
+            }
+            state.queue_timeout = queue_timeout;
+        }
+    }
+
+    pub fn max(&self) -> usize {
+        self.state.lock().map(|state| state.max).unwrap_or(DEFAULT_MAX_CONCURRENT_UPLOADS)
+    }
+
+    /// Wait for an upload slot; None if none freed up within the queue timeout
+    pub async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        let (semaphore, queue_timeout) = {
+            let state = self.state.lock().ok()?;
+            (state.semaphore.clone(), state.queue_timeout)
+        };
+
+        tokio::time::timeout(queue_timeout, semaphore.acquire_owned())
+            .await
+            .ok()?
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule_windows_include_ones_crossing_midnight() {
+        // 2024-01-05 is a Friday
+        let at = |day: u32, time: &str| chrono::NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+            .and_time(chrono::NaiveTime::parse_from_str(time, "%H:%M").unwrap());
+        let window = |day, start: &str, end: &str| ActiveWindow { day, start: start.to_string(), end: end.to_string() };
+        let schedule = MonitoringSchedule {
+            enabled: true,
+            windows: vec![
+                window(ScheduleDay::Fri, "22:00", "06:00"),
+                window(ScheduleDay::Mon, "09:00", "17:00"),
+            ],
+        };
+
+        assert!(schedule.is_active(at(5, "23:30")));
+        assert!(schedule.is_active(at(6, "05:59")));
+        assert!(!schedule.is_active(at(6, "06:00")));
+        assert!(!schedule.is_active(at(5, "05:00")));
+        assert!(schedule.is_active(at(8, "09:00")));
+        assert!(!schedule.is_active(at(8, "17:00")));
+
+        assert!(MonitoringSchedule::default().is_active(at(6, "12:00")));
+        assert!(MonitoringSchedule { windows: vec![window(ScheduleDay::Mon, "9:60", "10:00")], ..schedule }
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn bandwidth_counters_roll_over_and_cap_uploads() {
+        let day = |d: u32, m: u32| chrono::NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        let mut usage = BandwidthUsage { monthly_cap_bytes: Some(1000), ..Default::default() };
+
+        assert!(!usage.record(600, day(30, 1)));
+        assert!(!usage.record(300, day(31, 1)));
+        assert_eq!(usage.current(day(31, 1)).day_bytes, 300);
+        assert!(usage.record(200, day(31, 1)));
+        assert!(usage.cap_reached(day(31, 1)));
+        // Already over the cap; only the upload that crossed it reports
+        assert!(!usage.record(10, day(31, 1)));
+
+        // A new month lifts the cap but keeps the running total
+        assert!(!usage.cap_reached(day(1, 2)));
+        let current = usage.current(day(1, 2));
+        assert_eq!((current.day_bytes, current.month_bytes, current.total_bytes), (0, 0, 1110));
+        assert_eq!(current.month, "2024-02");
+    }
+
+    #[test]
+    fn parses_available_hwaccels() {
+        let output = "Hardware acceleration methods:\nvdpau\ncuda\nvaapi\nqsv\n\n";
+        assert_eq!(HwAccel::parse_available(output), vec![HwAccel::None, HwAccel::Cuda, HwAccel::Qsv]);
+        assert_eq!(HwAccel::parse_available(""), vec![HwAccel::None]);
+    }
+
+    #[test]
+    fn detection_patch_changes_only_given_fields() {
+        let settings = DetectionSettings::default();
+        let patch = DetectionSettingsPatch { alert_cooldown_secs: Some(5), ..Default::default() };
+
+        let updated = settings.patched(&patch).unwrap();
+        assert_eq!(updated.alert_cooldown_secs, 5);
+        assert_eq!(updated.alert_consecutive_frames, settings.alert_consecutive_frames);
+    }
+
+    #[test]
+    fn invalid_detection_patch_is_rejected_whole() {
+        let patch = DetectionSettingsPatch {
+            alert_cooldown_secs: Some(5),
+            dedup_iou_threshold: Some(1.5),
+            ..Default::default()
+        };
+        assert!(matches!(DetectionSettings::default().patched(&patch), Err(AgentError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn detection_settings_survive_a_restart() {
+        let path = std::env::temp_dir()
+            .join(format!("civicsentinel-settings-{}", std::process::id()))
+            .join(DETECTION_SETTINGS_FILE);
+        assert_eq!(DetectionSettings::load(&path), DetectionSettings::default());
+
+        let settings = DetectionSettings { alert_consecutive_frames: 4, ..Default::default() };
+        settings.save(&path).unwrap();
+        assert_eq!(DetectionSettings::load(&path), settings);
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn upload_limiter_times_out_when_full() {
+        let limiter = UploadLimiter::default();
+        limiter.configure(1, Duration::from_millis(50));
+
+        let held = limiter.acquire().await.expect("first upload gets a slot");
+        assert!(limiter.acquire().await.is_none());
+
+        drop(held);
+        assert!(limiter.acquire().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn reconfiguring_resizes_the_limit() {
+        let limiter = UploadLimiter::default();
+        limiter.configure(2, Duration::from_millis(50));
+
+        let _first = limiter.acquire().await.unwrap();
+        let _second = limiter.acquire().await.unwrap();
+        assert!(limiter.acquire().await.is_none());
+        assert_eq!(limiter.max(), 2);
+    }
+}
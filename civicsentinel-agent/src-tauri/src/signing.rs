@@ -0,0 +1,149 @@
+use std::path::Path;
+use std::sync::{LazyLock, RwLock};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::error::AgentError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// File in the app data directory holding the signing toggle
+pub const SIGNING_FILE: &str = "signing.json";
+
+/// Header carrying the hex HMAC-SHA256 of a frame upload
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+
+/// Header carrying the Unix time, in seconds, the signature was made at
+pub const TIMESTAMP_HEADER: &str = "X-Signature-Timestamp";
+
+/// Secret frame uploads are signed with; None when signing is off
+static SECRET: LazyLock<RwLock<Option<Vec<u8>>>> = LazyLock::new(|| RwLock::new(None));
+
+/// Whether frame uploads are signed; the secret itself lives in the keychain
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SigningSettings {
+    pub enabled: bool,
+}
+
+impl SigningSettings {
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AgentError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| AgentError::Internal(format!("Failed to encode signing settings: {}", e)))?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| AgentError::Internal(format!("Failed to create settings directory: {}", e)))?;
+        }
+        std::fs::write(path, json)
+            .map_err(|e| AgentError::Internal(format!("Failed to save signing settings: {}", e)))
+    }
+}
+
+/// Sign frame uploads with `secret` from now on, or stop signing with None
+pub fn configure(secret: Option<&str>) -> Result<(), AgentError> {
+    *SECRET.write().map_err(|e| AgentError::Internal(format!("Lock error: {}", e)))? =
+        secret.map(|secret| secret.as_bytes().to_vec());
+    Ok(())
+}
+
+/// Hex HMAC-SHA256 over a `<name>=<hex SHA-256>` line for each uploaded
+/// part, in upload order, then the camera id and timestamp, each followed by
+/// a newline so their boundaries can't shift
+pub fn signature(secret: &[u8], parts: &[(&str, &[u8])], camera_id: &str, timestamp: i64) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts any key length");
+    for (name, bytes) in parts {
+        mac.update(name.as_bytes());
+        mac.update(b"=");
+        mac.update(hex::encode(Sha256::digest(bytes)).as_bytes());
+        mac.update(b"\n");
+    }
+    mac.update(camera_id.as_bytes());
+    mac.update(b"\n");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b"\n");
+    hex::encode(mac.finalize().into_bytes())
+}
+
+pub(crate) fn apply(
+    request: reqwest::RequestBuilder,
+    secret: Option<&[u8]>,
+    camera_id: &str,
+    parts: &[(&str, &[u8])],
+    timestamp: i64,
+) -> reqwest::RequestBuilder {
+    match secret {
+        Some(secret) => request
+            .header(SIGNATURE_HEADER, signature(secret, parts, camera_id, timestamp))
+            .header(TIMESTAMP_HEADER, timestamp.to_string()),
+        None => request,
+    }
+}
+
+/// Add signature headers to a frame upload when signing is configured;
+/// `parts` are the named multipart parts the upload carries
+pub fn sign(request: reqwest::RequestBuilder, camera_id: &str, parts: &[(&str, &[u8])]) -> reqwest::RequestBuilder {
+    let secret = match SECRET.read() {
+        Ok(secret) => secret.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    };
+    apply(request, secret.as_deref(), camera_id, parts, chrono::Utc::now().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn signature_covers_parts_camera_and_time() {
+        let frame: &[(&str, &[u8])] = &[("image", b"jpeg-bytes")];
+        let signed = signature(b"secret", frame, "cam-1", 1_700_000_000);
+        assert_eq!(signed.len(), 64);
+        assert_eq!(signed, signature(b"secret", frame, "cam-1", 1_700_000_000));
+
+        assert_ne!(signed, signature(b"other", frame, "cam-1", 1_700_000_000));
+        assert_ne!(signed, signature(b"secret", &[("image", b"jpeg-byteS")], "cam-1", 1_700_000_000));
+        assert_ne!(signed, signature(b"secret", &[("prev_image", b"jpeg-bytes")], "cam-1", 1_700_000_000));
+        assert_ne!(signed, signature(b"secret", frame, "cam-2", 1_700_000_000));
+        assert_ne!(signed, signature(b"secret", frame, "cam-1", 1_700_000_001));
+        assert_ne!(
+            signed,
+            signature(b"secret", &[("image", b"jpeg-bytes"), ("prev_image", b"older")], "cam-1", 1_700_000_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn signed_requests_carry_signature_and_timestamp() {
+        let server = MockServer::start().await;
+        let parts: &[(&str, &[u8])] = &[("image", b"jpeg-bytes")];
+        let expected = signature(b"secret", parts, "cam-1", 1_700_000_000);
+        Mock::given(method("POST"))
+            .and(header(SIGNATURE_HEADER, expected.as_str()))
+            .and(header(TIMESTAMP_HEADER, "1700000000"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let request = reqwest::Client::new().post(server.uri());
+        let response = apply(request, Some(b"secret"), "cam-1", parts, 1_700_000_000)
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        let unsigned = apply(reqwest::Client::new().post(server.uri()), None, "cam-1", parts, 0)
+            .build()
+            .unwrap();
+        assert!(unsigned.headers().get(SIGNATURE_HEADER).is_none());
+    }
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use crate::api::DetectionResponse;
+use crate::error::AgentError;
+
+/// Where captured frames are sent for detection
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", content = "url")]
+pub enum DetectionSink {
+    /// Upload to the configured cloud backend
+    #[default]
+    Cloud,
+    /// Upload to a LAN inference server speaking the same detect API,
+    /// without the cloud API key, and keep a copy of every frame and result on disk
+    LocalHttp(String),
+    /// Never upload; frames are only written to disk
+    FileOnly,
+}
+
+impl DetectionSink {
+    /// Whether frames and results should be written to disk
+    pub fn writes_files(&self) -> bool {
+        !matches!(self, DetectionSink::Cloud)
+    }
+}
+
+/// Response recorded for frames that are not sent anywhere
+pub fn empty_response(camera_id: &str) -> DetectionResponse {
+    DetectionResponse {
+        camera_id: camera_id.to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        detections: Vec::new(),
+        alerts: Vec::new(),
+        roi: None,
+        served_by: None,
+        evidence_url: None,
+        local_timestamp: None,
+        latency_ms: None,
+        inference_ms: None,
+    }
+}
+
+/// Write a frame and its JSON sidecar under `root/YYYY-MM-DD/<camera_id>/`,
+/// named and EXIF-stamped by the time the frame was captured
+///
+/// Returns the path of the written JPEG.
+pub fn write_frame(
+    root: &Path,
+    camera_id: &str,
+    frame_bytes: &[u8],
+    response: &DetectionResponse,
+    captured_at: chrono::DateTime<chrono::Local>,
+) -> Result<PathBuf, AgentError> {
+    let dir = root
+        .join(captured_at.format("%Y-%m-%d").to_string())
+        .join(sanitize_component(camera_id));
+
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| AgentError::Internal(format!("Failed to create {}: {}", dir.display(), e)))?;
+
+    let stem = captured_at.format("%H%M%S%.3f").to_string();
+    let frame_path = dir.join(format!("{}.jpg", stem));
+    let sidecar_path = dir.join(format!("{}.json", stem));
+
+    // Stamp time and camera into the JPEG for chain-of-custody
+    let stamped = crate::frame::embed_metadata(frame_bytes, camera_id, captured_at)?;
+    std::fs::write(&frame_path, stamped)
+        .map_err(|e| AgentError::Internal(format!("Failed to write {}: {}", frame_path.display(), e)))?;
+
+    let sidecar = serde_json::to_vec_pretty(response)
+        .map_err(|e| AgentError::Internal(format!("Failed to serialize detections: {}", e)))?;
+    std::fs::write(&sidecar_path, sidecar)
+        .map_err(|e| AgentError::Internal(format!("Failed to write {}: {}", sidecar_path.display(), e)))?;
+
+    Ok(frame_path)
+}
+
+/// Keep camera ids from escaping the sink directory
+pub(crate) fn sanitize_component(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
@@ -0,0 +1,180 @@
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::error::AgentError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// S3-compatible bucket that alert evidence frames are uploaded to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3Config {
+    /// e.g. `https://s3.eu-west-1.amazonaws.com` or a MinIO/R2 endpoint
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+/// Percent-encode a path segment the way SigV4 expects
+fn uri_encode(segment: &str) -> String {
+    segment.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Derive the SigV4 signing key for one day, region and service
+fn signing_key(secret_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let k_date = hmac(format!("AWS4{}", secret_key).as_bytes(), date);
+    let k_region = hmac(&k_date, region);
+    let k_service = hmac(&k_region, service);
+    hmac(&k_service, "aws4_request")
+}
+
+/// Upload a JPEG with a SigV4-signed PUT and return its path-style object URL
+pub async fn upload_frame(config: &S3Config, key: &str, jpeg_bytes: Vec<u8>) -> Result<String, AgentError> {
+    let endpoint = reqwest::Url::parse(&config.endpoint)
+        .map_err(|e| AgentError::InvalidInput(format!("Invalid S3 endpoint: {}", e)))?;
+
+    let host = match (endpoint.host_str(), endpoint.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => return Err(AgentError::InvalidInput(format!("S3 endpoint has no host: {}", config.endpoint))),
+    };
+
+    let object_path = std::iter::once(config.bucket.as_str())
+        .chain(key.split('/'))
+        .map(uri_encode)
+        .collect::<Vec<_>>()
+        .join("/");
+    let canonical_uri = format!("{}/{}", endpoint.path().trim_end_matches('/'), object_path);
+    let object_url = format!("{}://{}{}", endpoint.scheme(), host, canonical_uri);
+
+    let now = chrono::Utc::now();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let payload_hash = hex::encode(Sha256::digest(&jpeg_bytes));
+
+    let signed_headers = "content-type;host;x-amz-content-sha256;x-amz-date";
+    let canonical_request = format!(
+        "PUT\n{}\n\ncontent-type:image/jpeg\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+        canonical_uri, host, payload_hash, amz_date, signed_headers, payload_hash
+    );
+
+    let scope = format!("{}/{}/s3/aws4_request", date, config.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date, scope, hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signature = hex::encode(hmac(
+        &signing_key(&config.secret_key, &date, &config.region, "s3"),
+        &string_to_sign,
+    ));
+
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        config.access_key, scope, signed_headers, signature
+    );
+
+    let client = crate::proxy::http_client();
+
+    let response = client
+        .put(&object_url)
+        .header("Authorization", authorization)
+        .header("Content-Type", "image/jpeg")
+        .header("x-amz-content-sha256", payload_hash)
+        .header("x-amz-date", amz_date)
+        .body(jpeg_bytes)
+        .send()
+        .await
+        .map_err(|e| AgentError::Network(format!("S3 upload failed: {}", e)))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(match status.as_u16() {
+            401 | 403 => AgentError::Auth(format!("S3 error {}: {}", status, text)),
+            _ => AgentError::Backend(format!("S3 error {}: {}", status, text)),
+        });
+    }
+
+    Ok(object_url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, header_regex, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn signing_key_matches_aws_example() {
+        // From the AWS "Examples of how to derive a signing key" docs
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam");
+        assert_eq!(hex::encode(key), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+    }
+
+    #[test]
+    fn uri_encode_escapes_reserved_characters() {
+        assert_eq!(uri_encode("front door:1.jpg"), "front%20door%3A1.jpg");
+    }
+
+    #[tokio::test]
+    async fn upload_frame_puts_signed_object() {
+        let server = MockServer::start().await;
+        let body = b"jpeg-bytes".to_vec();
+
+        Mock::given(method("PUT"))
+            .and(path("/evidence/cam-1/frame.jpg"))
+            .and(header("Content-Type", "image/jpeg"))
+            .and(header("x-amz-content-sha256", hex::encode(Sha256::digest(&body)).as_str()))
+            .and(header_regex(
+                "Authorization",
+                r"^AWS4-HMAC-SHA256 Credential=AKID/\d{8}/us-east-1/s3/aws4_request, SignedHeaders=content-type;host;x-amz-content-sha256;x-amz-date, Signature=[0-9a-f]{64}$",
+            ))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let config = S3Config {
+            endpoint: server.uri(),
+            bucket: "evidence".to_string(),
+            region: "us-east-1".to_string(),
+            access_key: "AKID".to_string(),
+            secret_key: "secret".to_string(),
+        };
+
+        let url = upload_frame(&config, "cam-1/frame.jpg", body).await.unwrap();
+        assert_eq!(url, format!("{}/evidence/cam-1/frame.jpg", server.uri()));
+    }
+
+    #[tokio::test]
+    async fn upload_frame_maps_forbidden_to_auth() {
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(403).set_body_string("SignatureDoesNotMatch"))
+            .mount(&server)
+            .await;
+
+        let config = S3Config {
+            endpoint: server.uri(),
+            bucket: "evidence".to_string(),
+            region: "us-east-1".to_string(),
+            access_key: "AKID".to_string(),
+            secret_key: "wrong".to_string(),
+        };
+
+        let err = upload_frame(&config, "cam-1/frame.jpg", b"jpeg".to_vec()).await.unwrap_err();
+        assert!(matches!(err, AgentError::Auth(_)));
+    }
+}
@@ -0,0 +1,150 @@
+//! Local RTSP source for tests that need a real camera stream.
+//!
+//! `TestRtspServer::start()` launches mediamtx on a free port and publishes an
+//! ffmpeg test pattern to it; both processes are killed on drop. It returns
+//! `None` when mediamtx or ffmpeg are not installed. Tests using it are
+//! `#[ignore]`d; run them with `cargo test -- --ignored` where both exist.
+//! Set `MEDIAMTX_PATH` if mediamtx is not on `PATH`.
+
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const STREAM_PATH: &str = "test";
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct TestRtspServer {
+    server: Child,
+    publisher: Option<Child>,
+    port: u16,
+}
+
+impl TestRtspServer {
+    /// Start mediamtx and a publisher, waiting until the stream is readable
+    pub fn start() -> Option<Self> {
+        let mediamtx = std::env::var("MEDIAMTX_PATH").unwrap_or_else(|_| "mediamtx".to_string());
+        let port = free_port()?;
+
+        // Only RTSP is needed; turn the other protocols off so their fixed
+        // ports can't collide between parallel tests
+        let server = Command::new(&mediamtx)
+            .env("MTX_RTSPADDRESS", format!("127.0.0.1:{}", port))
+            .env("MTX_RTSPTRANSPORTS", "tcp")
+            .env("MTX_RTMP", "no")
+            .env("MTX_HLS", "no")
+            .env("MTX_WEBRTC", "no")
+            .env("MTX_SRT", "no")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        let server = match server {
+            Ok(server) => server,
+            Err(e) => {
+                eprintln!("[TestRtspServer] mediamtx unavailable ({})", e);
+                return None;
+            }
+        };
+
+        let mut harness = Self { server, publisher: None, port };
+
+        if !wait_for(|| TcpStream::connect(("127.0.0.1", port)).is_ok()) {
+            eprintln!("[TestRtspServer] mediamtx did not start listening");
+            return None;
+        }
+
+        if !harness.start_publisher() {
+            eprintln!("[TestRtspServer] ffmpeg publisher did not come up");
+            return None;
+        }
+
+        Some(harness)
+    }
+
+    pub fn url(&self) -> String {
+        format!("rtsp://127.0.0.1:{}/{}", self.port, STREAM_PATH)
+    }
+
+    /// Publish a looping test pattern; returns once it can be read back
+    pub fn start_publisher(&mut self) -> bool {
+        self.stop_publisher();
+
+        let publisher = Command::new(crate::camera::get_ffmpeg_path())
+            .args([
+                "-re",
+                "-f", "lavfi",
+                "-i", "testsrc=size=320x240:rate=10",
+                "-c:v", "mpeg4",
+                "-f", "rtsp",
+                "-rtsp_transport", "tcp",
+                &self.url(),
+            ])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+
+        match publisher {
+            Ok(publisher) => self.publisher = Some(publisher),
+            Err(e) => {
+                eprintln!("[TestRtspServer] ffmpeg unavailable: {}", e);
+                return false;
+            }
+        }
+
+        let url = self.url();
+        wait_for(|| stream_readable(&url))
+    }
+
+    /// Stop publishing, simulating a camera that dropped off the network
+    pub fn stop_publisher(&mut self) {
+        if let Some(mut publisher) = self.publisher.take() {
+            let _ = publisher.kill();
+            let _ = publisher.wait();
+        }
+    }
+}
+
+impl Drop for TestRtspServer {
+    fn drop(&mut self) {
+        self.stop_publisher();
+        let _ = self.server.kill();
+        let _ = self.server.wait();
+    }
+}
+
+/// Ask the OS for a port nothing is listening on
+fn free_port() -> Option<u16> {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .ok()
+}
+
+fn stream_readable(url: &str) -> bool {
+    Command::new(crate::camera::get_ffmpeg_path())
+        .args([
+            "-rtsp_transport", "tcp",
+            "-timeout", "2000000",
+            "-i", url,
+            "-vframes", "1",
+            "-f", "null",
+            "-loglevel", "error",
+            "-",
+        ])
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+/// Poll `ready` until it returns true or the startup timeout passes
+fn wait_for(mut ready: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    while Instant::now() < deadline {
+        if ready() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+    false
+}
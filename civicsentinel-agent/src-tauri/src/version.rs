@@ -0,0 +1,113 @@
+use serde::Serialize;
+
+/// This agent's release, from Cargo.toml
+pub const AGENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+type Version = (u64, u64, u64);
+
+/// Backend versions each agent release line works with:
+/// (agent major.minor, oldest supported backend, newest tested backend)
+const COMPATIBILITY: &[((u64, u64), Version, Version)] = &[
+    ((0, 1), (0, 1, 0), (0, 1, u64::MAX)),
+];
+
+/// Whether this agent and backend are expected to work together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compatibility {
+    Ok,
+    /// Probably works, but the combination is untested or unknown
+    Warn,
+    Incompatible,
+}
+
+/// Everything support needs to spot version skew, from `version_info`
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionInfo {
+    pub agent_version: String,
+    pub ffmpeg_version: Option<String>,
+    pub backend_version: Option<String>,
+    pub backend_status: Option<String>,
+    pub compatibility: Compatibility,
+    /// Why the verdict isn't `Ok`, or why part of the information is missing
+    pub notes: Vec<String>,
+}
+
+/// Leading `major.minor.patch` of a version string; `v` prefix and suffixes
+/// such as `-rc1` are ignored
+pub fn parse_version(version: &str) -> Option<Version> {
+    let core = version.trim().trim_start_matches('v');
+    let mut parts = core.split(['.', '-', '+']).map(|part| part.parse::<u64>());
+
+    let major = parts.next()?.ok()?;
+    let minor = parts.next()?.ok()?;
+    let patch = parts.next().and_then(|part| part.ok()).unwrap_or(0);
+    Some((major, minor, patch))
+}
+
+/// Version number from the first line of `ffmpeg -version`
+pub fn parse_ffmpeg_version(output: &str) -> Option<String> {
+    output.lines()
+        .next()?
+        .strip_prefix("ffmpeg version ")?
+        .split_whitespace()
+        .next()
+        .map(str::to_string)
+}
+
+/// Look the pair up in the compatibility matrix
+pub fn check_compatibility(agent_version: &str, backend_version: &str) -> (Compatibility, Option<String>) {
+    let (Some(agent), Some(backend)) = (parse_version(agent_version), parse_version(backend_version)) else {
+        return (Compatibility::Warn, Some(format!(
+            "Cannot compare agent {} with backend {}", agent_version, backend_version
+        )));
+    };
+
+    let Some((_, oldest, newest)) = COMPATIBILITY.iter().find(|(line, _, _)| *line == (agent.0, agent.1)) else {
+        return (Compatibility::Warn, Some(format!(
+            "Agent {} is not in the compatibility matrix", agent_version
+        )));
+    };
+
+    if backend < *oldest {
+        (Compatibility::Incompatible, Some(format!(
+            "Backend {} is older than this agent supports ({}.{}.{}); update the backend",
+            backend_version, oldest.0, oldest.1, oldest.2
+        )))
+    } else if backend > *newest {
+        (Compatibility::Warn, Some(format!(
+            "Backend {} is newer than this agent was tested with; update the agent", backend_version
+        )))
+    } else {
+        (Compatibility::Ok, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_versions_with_prefixes_and_suffixes() {
+        assert_eq!(parse_version("0.1.0"), Some((0, 1, 0)));
+        assert_eq!(parse_version("v1.2"), Some((1, 2, 0)));
+        assert_eq!(parse_version("2.0.1-rc1"), Some((2, 0, 1)));
+        assert_eq!(parse_version("unknown"), None);
+    }
+
+    #[test]
+    fn reads_ffmpeg_version_line() {
+        let output = "ffmpeg version 6.1.1 Copyright (c) 2000-2023 the FFmpeg developers\nbuilt with gcc";
+        assert_eq!(parse_ffmpeg_version(output).as_deref(), Some("6.1.1"));
+        assert_eq!(parse_ffmpeg_version(""), None);
+    }
+
+    #[test]
+    fn compatibility_follows_matrix() {
+        assert_eq!(check_compatibility("0.1.0", "0.1.7").0, Compatibility::Ok);
+        assert_eq!(check_compatibility("0.1.0", "0.0.9").0, Compatibility::Incompatible);
+        assert_eq!(check_compatibility("0.1.0", "0.2.0").0, Compatibility::Warn);
+        assert_eq!(check_compatibility("9.9.0", "0.1.0").0, Compatibility::Warn);
+        assert_eq!(check_compatibility("0.1.0", "dev").0, Compatibility::Warn);
+    }
+}
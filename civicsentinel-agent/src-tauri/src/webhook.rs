@@ -0,0 +1,291 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use crate::api::ZoneAlert;
+use crate::error::AgentError;
+
+/// Minimum time between two alerts for the same camera zone
+pub const DEFAULT_ALERT_COOLDOWN_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// JSON body with `{{field}}` placeholders; None sends the payload as-is
+    pub template: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    pub camera_id: String,
+    pub zone_name: String,
+    pub alert_type: String,
+    pub confidence: f64,
+    pub timestamp: String,
+    pub image_url: Option<String>,
+    /// Set on synthetic alerts from the test commands so receivers can ignore them
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub test: bool,
+}
+
+impl WebhookPayload {
+    pub fn from_alert(camera_id: &str, timestamp: &str, alert: &ZoneAlert, image_url: Option<String>) -> Self {
+        Self {
+            camera_id: camera_id.to_string(),
+            zone_name: alert.zone_name.clone(),
+            alert_type: alert.alert_type.clone(),
+            confidence: alert.confidence,
+            timestamp: timestamp.to_string(),
+            image_url,
+            test: false,
+        }
+    }
+
+    /// Payload sent by `test_webhook`
+    pub fn sample() -> Self {
+        Self {
+            camera_id: "test-camera".to_string(),
+            zone_name: "Test Zone".to_string(),
+            alert_type: "intrusion".to_string(),
+            confidence: 0.99,
+            timestamp: unix_timestamp_now(),
+            image_url: None,
+            test: true,
+        }
+    }
+}
+
+/// How one stage of the alert path fared in `test_alert_pipeline`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", content = "detail", rename_all = "snake_case")]
+pub enum StageOutcome {
+    /// Delivered; carries e.g. the uploaded evidence URL
+    Delivered(Option<String>),
+    /// Not attempted, and why (not configured, on cooldown)
+    Skipped(String),
+    Failed(String),
+}
+
+/// Result of sending a synthetic alert down every configured path
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertPipelineReport {
+    pub evidence: StageOutcome,
+    pub webhook: StageOutcome,
+    /// OS notifier's outcome, also listed in `notifiers`
+    pub notification: StageOutcome,
+    pub notifiers: Vec<NotifierOutcome>,
+}
+
+/// How one configured notifier fared in `test_alert_pipeline`
+#[derive(Debug, Clone, Serialize)]
+pub struct NotifierOutcome {
+    pub notifier: String,
+    pub outcome: StageOutcome,
+}
+
+/// Current UTC time as seconds since the Unix epoch, formatted as a string
+fn unix_timestamp_now() -> String {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs().to_string())
+        .unwrap_or_default()
+}
+
+/// Tracks when each camera zone last alerted so repeats can be suppressed
+#[derive(Debug)]
+pub struct AlertCooldown {
+    cooldown: Duration,
+    last_fired: HashMap<(String, i64), Instant>,
+}
+
+impl Default for AlertCooldown {
+    fn default() -> Self {
+        Self {
+            cooldown: Duration::from_secs(DEFAULT_ALERT_COOLDOWN_SECS),
+            last_fired: HashMap::new(),
+        }
+    }
+}
+
+impl AlertCooldown {
+    pub fn set_cooldown(&mut self, cooldown: Duration) {
+        self.cooldown = cooldown;
+    }
+
+    /// Returns true (and records the alert) if the zone is outside its cooldown
+    pub fn should_fire(&mut self, camera_id: &str, zone_id: i64) -> bool {
+        let now = Instant::now();
+        let key = (camera_id.to_string(), zone_id);
+
+        match self.last_fired.get(&key) {
+            Some(last) if now.duration_since(*last) < self.cooldown => false,
+            _ => {
+                self.last_fired.insert(key, now);
+                true
+            }
+        }
+    }
+}
+
+/// Consecutive detection cycles a zone alert must persist before it fires
+pub const DEFAULT_ALERT_CONSECUTIVE_FRAMES: u32 = 2;
+
+/// Suppresses zone alerts until they have been seen in enough consecutive cycles
+#[derive(Debug)]
+pub struct AlertDebounce {
+    required: u32,
+    hits: HashMap<(String, i64, String), u32>,
+}
+
+impl Default for AlertDebounce {
+    fn default() -> Self {
+        Self {
+            required: DEFAULT_ALERT_CONSECUTIVE_FRAMES,
+            hits: HashMap::new(),
+        }
+    }
+}
+
+impl AlertDebounce {
+    pub fn set_required(&mut self, consecutive_frames: u32) {
+        self.required = consecutive_frames.max(1);
+    }
+
+    /// Record one cycle's alerts for a camera and keep only those that have
+    /// now been present for the required number of consecutive cycles
+    pub fn filter(&mut self, camera_id: &str, alerts: Vec<ZoneAlert>) -> Vec<ZoneAlert> {
+        let seen: HashSet<(i64, String)> = alerts.iter()
+            .map(|alert| (alert.zone_id, alert.alert_type.clone()))
+            .collect();
+
+        // Absent this cycle means the streak starts over
+        self.hits.retain(|(camera, zone_id, alert_type), _| {
+            camera != camera_id || seen.contains(&(*zone_id, alert_type.clone()))
+        });
+
+        for (zone_id, alert_type) in seen {
+            *self.hits.entry((camera_id.to_string(), zone_id, alert_type)).or_default() += 1;
+        }
+
+        alerts.into_iter()
+            .filter(|alert| {
+                let key = (camera_id.to_string(), alert.zone_id, alert.alert_type.clone());
+                self.hits.get(&key).copied().unwrap_or(0) >= self.required
+            })
+            .collect()
+    }
+}
+
+/// Substitute `{{field}}` placeholders with JSON-escaped payload values
+fn render_template(template: &str, payload: &WebhookPayload) -> Result<String, AgentError> {
+    let escape = |value: &str| {
+        let quoted = serde_json::to_string(value).unwrap_or_default();
+        quoted[1..quoted.len() - 1].to_string()
+    };
+
+    let body = template
+        .replace("{{camera_id}}", &escape(&payload.camera_id))
+        .replace("{{zone_name}}", &escape(&payload.zone_name))
+        .replace("{{alert_type}}", &escape(&payload.alert_type))
+        .replace("{{confidence}}", &format!("{:.2}", payload.confidence))
+        .replace("{{timestamp}}", &escape(&payload.timestamp))
+        .replace("{{image_url}}", &escape(payload.image_url.as_deref().unwrap_or("")))
+        .replace("{{test}}", if payload.test { "true" } else { "false" });
+
+    // Reject templates that don't render to valid JSON before we send them
+    serde_json::from_str::<serde_json::Value>(&body)
+        .map_err(|e| AgentError::InvalidInput(format!("Webhook template is not valid JSON: {}", e)))?;
+
+    Ok(body)
+}
+
+/// POST an alert payload to the configured webhook
+pub async fn send_webhook(config: &WebhookConfig, payload: &WebhookPayload) -> Result<(), AgentError> {
+    let body = match &config.template {
+        Some(template) => render_template(template, payload)?,
+        None => serde_json::to_string(payload)
+            .map_err(|e| AgentError::Internal(format!("Failed to serialize webhook payload: {}", e)))?,
+    };
+
+    let client = crate::proxy::http_client();
+
+    let response = client
+        .post(&config.url)
+        .header("Content-Type", "application/json")
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| AgentError::Network(format!("Webhook request failed: {}", e)))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(AgentError::Backend(format!("Webhook error {}: {}", status, text)));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(zone_id: i64, alert_type: &str) -> ZoneAlert {
+        ZoneAlert {
+            zone_id,
+            zone_name: format!("Zone {}", zone_id),
+            alert_type: alert_type.to_string(),
+            confidence: 0.9,
+        }
+    }
+
+    #[test]
+    fn debounce_fires_after_consecutive_cycles() {
+        let mut debounce = AlertDebounce::default();
+
+        assert!(debounce.filter("cam-1", vec![alert(1, "intrusion")]).is_empty());
+        assert_eq!(debounce.filter("cam-1", vec![alert(1, "intrusion")]).len(), 1);
+        assert_eq!(debounce.filter("cam-1", vec![alert(1, "intrusion")]).len(), 1);
+    }
+
+    #[test]
+    fn debounce_resets_when_alert_is_absent() {
+        let mut debounce = AlertDebounce::default();
+
+        debounce.filter("cam-1", vec![alert(1, "intrusion")]);
+        debounce.filter("cam-1", Vec::new());
+
+        assert!(debounce.filter("cam-1", vec![alert(1, "intrusion")]).is_empty());
+    }
+
+    #[test]
+    fn debounce_tracks_cameras_and_alert_types_separately() {
+        let mut debounce = AlertDebounce::default();
+
+        debounce.filter("cam-1", vec![alert(1, "intrusion")]);
+        // Another camera's cycle must not reset cam-1's streak
+        debounce.filter("cam-2", Vec::new());
+        assert!(debounce.filter("cam-1", vec![alert(1, "loitering")]).is_empty());
+        assert!(debounce.filter("cam-1", vec![alert(1, "intrusion")]).is_empty());
+    }
+
+    #[test]
+    fn debounce_of_one_passes_everything() {
+        let mut debounce = AlertDebounce::default();
+        debounce.set_required(1);
+
+        assert_eq!(debounce.filter("cam-1", vec![alert(1, "intrusion")]).len(), 1);
+    }
+
+    #[test]
+    fn test_marker_only_appears_on_synthetic_alerts() {
+        let real = WebhookPayload::from_alert("cam-1", "0", &alert(1, "intrusion"), None);
+        let real_json = serde_json::to_value(&real).unwrap();
+        assert!(real_json.get("test").is_none());
+
+        let sample_json = serde_json::to_value(WebhookPayload::sample()).unwrap();
+        assert_eq!(sample_json["test"], true);
+
+        let body = render_template(r#"{"zone": "{{zone_name}}", "test": {{test}}}"#, &real).unwrap();
+        assert_eq!(body, r#"{"zone": "Zone 1", "test": false}"#);
+    }
+}
@@ -0,0 +1,467 @@
+use std::collections::HashMap;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::api::{self, AuthScheme, Detection, ZoneResponse};
+use crate::error::AgentError;
+
+/// File in the app data directory holding per-zone notification policies
+pub const ZONE_NOTIFY_FILE: &str = "zone_notify.json";
+
+/// Where a zone's alerts are delivered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyPolicy {
+    /// Log only
+    None,
+    /// Desktop notification
+    Notification,
+    /// Alert webhook, if one is configured
+    Webhook,
+    #[default]
+    Both,
+}
+
+impl NotifyPolicy {
+    pub fn notification(self) -> bool {
+        matches!(self, NotifyPolicy::Notification | NotifyPolicy::Both)
+    }
+
+    pub fn webhook(self) -> bool {
+        matches!(self, NotifyPolicy::Webhook | NotifyPolicy::Both)
+    }
+}
+
+/// Notification policy of each camera zone; zones without one use `Both`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ZoneNotifyPolicies(HashMap<String, HashMap<i64, NotifyPolicy>>);
+
+impl ZoneNotifyPolicies {
+    pub fn get(&self, camera_id: &str, zone_id: i64) -> NotifyPolicy {
+        self.0.get(camera_id)
+            .and_then(|zones| zones.get(&zone_id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn set(&mut self, camera_id: &str, zone_id: i64, policy: NotifyPolicy) {
+        self.0.entry(camera_id.to_string()).or_default().insert(zone_id, policy);
+    }
+
+    pub fn remove(&mut self, camera_id: &str, zone_id: i64) {
+        if let Some(zones) = self.0.get_mut(camera_id) {
+            zones.remove(&zone_id);
+            if zones.is_empty() {
+                self.0.remove(camera_id);
+            }
+        }
+    }
+
+    /// Fill in each zone's policy
+    pub fn annotate(&self, zones: &mut [ZoneResponse]) {
+        for zone in zones {
+            zone.notify = self.get(&zone.camera_id, zone.id);
+        }
+    }
+
+    /// Saved policies, or none if there are none or they can't be read
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AgentError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| AgentError::Internal(format!("Failed to encode zone notify policies: {}", e)))?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| AgentError::Internal(format!("Failed to create settings directory: {}", e)))?;
+        }
+        std::fs::write(path, json)
+            .map_err(|e| AgentError::Internal(format!("Failed to save zone notify policies: {}", e)))
+    }
+}
+
+/// Result of checking a zone polygon that is usable
+#[derive(Debug, Clone, Serialize)]
+pub struct ZoneValidation {
+    /// Enclosed area in the same units as the coordinates
+    pub area: f64,
+    /// Problems that don't block creation but likely make the zone useless
+    pub warnings: Vec<String>,
+}
+
+/// Shoelace area of a closed polygon
+fn polygon_area(points: &[[f64; 2]]) -> f64 {
+    let twice_area: f64 = points.iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| a[0] * b[1] - b[0] * a[1])
+        .sum();
+    twice_area.abs() / 2.0
+}
+
+/// Sign of the turn a -> b -> c: positive is counter-clockwise, 0 is collinear
+fn orientation(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> f64 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+/// Whether every point lies on one line (or they are all the same point)
+fn all_collinear(points: &[[f64; 2]]) -> bool {
+    let first = points[0];
+    match points.iter().find(|p| **p != first) {
+        Some(&second) => points.iter().all(|p| orientation(first, second, *p) == 0.0),
+        None => true,
+    }
+}
+
+/// Whether `p` lies within the bounding box of segment a-b (used for collinear cases)
+fn within_bounds(a: [f64; 2], b: [f64; 2], p: [f64; 2]) -> bool {
+    p[0] >= a[0].min(b[0]) && p[0] <= a[0].max(b[0])
+        && p[1] >= a[1].min(b[1]) && p[1] <= a[1].max(b[1])
+}
+
+/// Whether segments p1-p2 and q1-q2 touch or cross
+fn segments_intersect(p1: [f64; 2], p2: [f64; 2], q1: [f64; 2], q2: [f64; 2]) -> bool {
+    let d1 = orientation(q1, q2, p1);
+    let d2 = orientation(q1, q2, p2);
+    let d3 = orientation(p1, p2, q1);
+    let d4 = orientation(p1, p2, q2);
+
+    if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+        return true;
+    }
+
+    (d1 == 0.0 && within_bounds(q1, q2, p1))
+        || (d2 == 0.0 && within_bounds(q1, q2, p2))
+        || (d3 == 0.0 && within_bounds(p1, p2, q1))
+        || (d4 == 0.0 && within_bounds(p1, p2, q2))
+}
+
+/// Whether `point` lies inside `polygon` (even-odd ray casting)
+pub fn point_in_polygon(point: [f64; 2], polygon: &[[f64; 2]]) -> bool {
+    let [x, y] = point;
+    let mut inside = false;
+
+    for (a, b) in polygon.iter().zip(polygon.iter().cycle().skip(1)) {
+        if (a[1] > y) != (b[1] > y) {
+            let crossing_x = a[0] + (y - a[1]) * (b[0] - a[0]) / (b[1] - a[1]);
+            if x < crossing_x {
+                inside = !inside;
+            }
+        }
+    }
+
+    inside
+}
+
+/// Which detections a zone would alert on, from `test_zone`
+#[derive(Debug, Clone, Serialize)]
+pub struct ZoneTestResult {
+    /// Detections whose box center falls inside the zone
+    pub hits: Vec<Detection>,
+    pub total_detections: usize,
+}
+
+/// Run the backend's zone rule locally: a detection triggers when the center
+/// of its bounding box is inside the polygon
+pub fn test_zone(coordinates: &[[f64; 2]], detections: &[Detection]) -> ZoneTestResult {
+    let hits = detections.iter()
+        .filter(|detection| {
+            let bbox = &detection.bbox;
+            point_in_polygon([(bbox.x1 + bbox.x2) / 2.0, (bbox.y1 + bbox.y2) / 2.0], coordinates)
+        })
+        .cloned()
+        .collect();
+
+    ZoneTestResult { hits, total_detections: detections.len() }
+}
+
+/// Check a zone polygon before it is sent to the backend
+///
+/// Rejects polygons with fewer than 3 vertices or crossing edges, and warns
+/// when the enclosed area is zero (e.g. all points collinear).
+pub fn validate_polygon(coordinates: &[[f64; 2]]) -> Result<ZoneValidation, AgentError> {
+    if coordinates.len() < 3 {
+        return Err(AgentError::InvalidInput(format!(
+            "Zone needs at least 3 points, got {}", coordinates.len()
+        )));
+    }
+
+    if let Some(point) = coordinates.iter().find(|p| !p[0].is_finite() || !p[1].is_finite()) {
+        return Err(AgentError::InvalidInput(format!("Zone point is not a number: {:?}", point)));
+    }
+
+    // A flat polygon folds back over itself, which would otherwise be
+    // reported as crossing edges; flag it as the more useful warning instead
+    let area = polygon_area(coordinates);
+    if all_collinear(coordinates) {
+        return Ok(ZoneValidation {
+            area: 0.0,
+            warnings: vec!["Zone has zero area; its points are collinear and it will never trigger".to_string()],
+        });
+    }
+
+    let n = coordinates.len();
+    let edge = |i: usize| (coordinates[i], coordinates[(i + 1) % n]);
+
+    // Adjacent edges share a vertex, so only compare edges at least two apart
+    for i in 0..n {
+        for j in (i + 2)..n {
+            if i == 0 && j == n - 1 {
+                continue;
+            }
+            let (p1, p2) = edge(i);
+            let (q1, q2) = edge(j);
+            if segments_intersect(p1, p2, q1, q2) {
+                return Err(AgentError::InvalidInput(format!(
+                    "Zone edges {} and {} intersect; the polygon must not cross itself", i + 1, j + 1
+                )));
+            }
+        }
+    }
+
+    Ok(ZoneValidation { area, warnings: Vec::new() })
+}
+
+/// A zone to create on every camera a template is applied to
+#[derive(Debug, Clone, Deserialize)]
+pub struct ZoneTemplate {
+    pub name: String,
+    pub coordinates: Vec<[f64; 2]>,
+    pub alert_type: String,
+    #[serde(default)]
+    pub notify: NotifyPolicy,
+}
+
+/// What applying a template did to one camera
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateResult {
+    /// Zones created before any failure
+    pub created: Vec<ZoneResponse>,
+    /// Why the remaining zones were not created
+    pub error: Option<String>,
+}
+
+/// Create every template zone on each camera, cameras in parallel
+///
+/// All polygons are validated up front so a bad template fails before any
+/// zone exists. Within a camera zones are created in order and the first
+/// failure skips the rest.
+pub async fn apply_template(
+    backend_url: &str,
+    api_key: &str,
+    auth: &AuthScheme,
+    camera_ids: Vec<String>,
+    templates: Vec<ZoneTemplate>,
+) -> Result<HashMap<String, TemplateResult>, AgentError> {
+    if templates.is_empty() {
+        return Err(AgentError::InvalidInput("Zone template has no zones".to_string()));
+    }
+    for template in &templates {
+        validate_polygon(&template.coordinates)
+            .map_err(|e| AgentError::InvalidInput(format!("Zone '{}': {}", template.name, e.message())))?;
+    }
+
+    let templates = std::sync::Arc::new(templates);
+    let mut tasks = tokio::task::JoinSet::new();
+    for camera_id in camera_ids {
+        let templates = templates.clone();
+        let backend_url = backend_url.to_string();
+        let api_key = api_key.to_string();
+        let auth = auth.clone();
+
+        tasks.spawn(async move {
+            let mut result = TemplateResult { created: Vec::new(), error: None };
+            for template in templates.iter() {
+                let zone = api::create_zone(
+                    &backend_url,
+                    &camera_id,
+                    &template.name,
+                    &template.coordinates,
+                    &template.alert_type,
+                    &api_key,
+                    &auth,
+                ).await;
+
+                match zone {
+                    Ok(mut zone) => {
+                        zone.notify = template.notify;
+                        result.created.push(zone);
+                    }
+                    Err(e) => {
+                        result.error = Some(format!("Zone '{}': {}", template.name, e.message()));
+                        break;
+                    }
+                }
+            }
+            (camera_id, result)
+        });
+    }
+
+    let mut results = HashMap::new();
+    while let Some(joined) = tasks.join_next().await {
+        let (camera_id, result) = joined
+            .map_err(|e| AgentError::Internal(format!("Task join error: {}", e)))?;
+        results.insert(camera_id, result);
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zone_notify_policies_default_to_both_and_round_trip() {
+        let mut policies = ZoneNotifyPolicies::default();
+        assert_eq!(policies.get("cam-1", 1), NotifyPolicy::Both);
+
+        policies.set("cam-1", 1, NotifyPolicy::None);
+        policies.set("cam-1", 2, NotifyPolicy::Webhook);
+        assert_eq!(policies.get("cam-1", 1), NotifyPolicy::None);
+        assert_eq!(policies.get("cam-2", 1), NotifyPolicy::Both);
+        assert!(!NotifyPolicy::Webhook.notification() && NotifyPolicy::Webhook.webhook());
+
+        let path = std::env::temp_dir()
+            .join(format!("zone-notify-{}", std::process::id()))
+            .join(ZONE_NOTIFY_FILE);
+        policies.save(&path).unwrap();
+        assert_eq!(ZoneNotifyPolicies::load(&path), policies);
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+
+        policies.remove("cam-1", 1);
+        assert_eq!(policies.get("cam-1", 1), NotifyPolicy::Both);
+    }
+
+    #[test]
+    fn accepts_simple_square() {
+        let result = validate_polygon(&[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]).unwrap();
+        assert_eq!(result.area, 1.0);
+        assert!(result.warnings.is_empty());
+    }
+
+    #[test]
+    fn accepts_concave_polygon() {
+        let arrow = [[0.0, 0.0], [2.0, 1.0], [0.0, 2.0], [1.0, 1.0]];
+        assert!(validate_polygon(&arrow).is_ok());
+    }
+
+    #[test]
+    fn rejects_fewer_than_three_points() {
+        let err = validate_polygon(&[[0.0, 0.0], [1.0, 1.0]]).unwrap_err();
+        assert!(matches!(err, AgentError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn rejects_bow_tie() {
+        let bow_tie = [[0.0, 0.0], [1.0, 1.0], [1.0, 0.0], [0.0, 1.0]];
+        let err = validate_polygon(&bow_tie).unwrap_err();
+        assert!(err.message().contains("intersect"));
+    }
+
+    #[test]
+    fn warns_on_collinear_points() {
+        let result = validate_polygon(&[[0.0, 0.0], [1.0, 1.0], [2.0, 2.0], [3.0, 3.0]]).unwrap();
+        assert_eq!(result.area, 0.0);
+        assert_eq!(result.warnings.len(), 1);
+    }
+
+    #[test]
+    fn point_in_concave_polygon() {
+        // U shape open at the top between x=1 and x=2
+        let u = [[0.0, 0.0], [3.0, 0.0], [3.0, 3.0], [2.0, 3.0], [2.0, 1.0], [1.0, 1.0], [1.0, 3.0], [0.0, 3.0]];
+        assert!(point_in_polygon([0.5, 2.0], &u));
+        assert!(point_in_polygon([1.5, 0.5], &u));
+        assert!(!point_in_polygon([1.5, 2.0], &u));
+        assert!(!point_in_polygon([4.0, 1.0], &u));
+    }
+
+    #[test]
+    fn zone_test_hits_detections_centered_inside() {
+        let detection = |x1: f64| Detection {
+            class_name: "person".to_string(),
+            confidence: 0.9,
+            bbox: api::BoundingBox { x1, y1: 0.0, x2: x1 + 100.0, y2: 100.0 },
+        };
+        let zone = [[0.0, 0.0], [100.0, 0.0], [100.0, 100.0], [0.0, 100.0]];
+
+        // Centers at x=50 (inside) and x=130 (outside, though the box overlaps)
+        let result = test_zone(&zone, &[detection(0.0), detection(80.0)]);
+        assert_eq!(result.total_detections, 2);
+        assert_eq!(result.hits.len(), 1);
+        assert_eq!(result.hits[0].bbox.x1, 0.0);
+    }
+
+    fn template(name: &str) -> ZoneTemplate {
+        ZoneTemplate {
+            name: name.to_string(),
+            coordinates: vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]],
+            alert_type: "intrusion".to_string(),
+            notify: NotifyPolicy::default(),
+        }
+    }
+
+    fn zone_body(camera_id: &str, id: i64) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "camera_id": camera_id,
+            "name": "Gate",
+            "coordinates": [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]],
+            "alert_type": "intrusion",
+            "active": true,
+            "active_hours": null,
+            "created_at": "2024-01-01T00:00:00Z"
+        })
+    }
+
+    #[tokio::test]
+    async fn apply_template_reports_each_camera() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        use wiremock::matchers::{method, path};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/cameras/cam-1/zones"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(zone_body("cam-1", 1)))
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/cameras/cam-2/zones"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let results = apply_template(
+            &server.uri(),
+            "test-key",
+            &AuthScheme::Bearer,
+            vec!["cam-1".to_string(), "cam-2".to_string()],
+            vec![template("Gate"), template("Door")],
+        ).await.unwrap();
+
+        assert_eq!(results["cam-1"].created.len(), 2);
+        assert!(results["cam-1"].error.is_none());
+        assert!(results["cam-2"].created.is_empty());
+        assert!(results["cam-2"].error.as_ref().unwrap().contains("Gate"));
+    }
+
+    #[tokio::test]
+    async fn apply_template_rejects_invalid_zone_before_creating_any() {
+        let mut bow_tie = template("Bow tie");
+        bow_tie.coordinates = vec![[0.0, 0.0], [1.0, 1.0], [1.0, 0.0], [0.0, 1.0]];
+
+        let err = apply_template(
+            "http://127.0.0.1:9",
+            "test-key",
+            &AuthScheme::Bearer,
+            vec!["cam-1".to_string()],
+            vec![template("Gate"), bow_tie],
+        ).await.unwrap_err();
+
+        assert!(err.message().contains("Bow tie"));
+    }
+}
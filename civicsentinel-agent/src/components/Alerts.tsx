@@ -1,8 +1,9 @@
 import React, { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { AlertTriangle, Camera, Clock, ChevronLeft, ChevronRight } from 'lucide-react';
+import { AlertTriangle, Camera, Clock, ChevronLeft, ChevronRight, Image } from 'lucide-react';
 import { useSettingsStore } from '../stores/settingsStore';
 import { useCameraStore } from '../stores/cameraStore';
+import { errorMessage } from '../utils/errors';
 
 interface Alert {
   id: number;
@@ -37,6 +38,7 @@ export function Alerts() {
   const [totalPages, setTotalPages] = useState(1);
   const [total, setTotal] = useState(0);
   const [selectedCamera, setSelectedCamera] = useState<string | null>(null);
+  const [images, setImages] = useState<Record<number, string>>({});
   const pageSize = 20;
 
   const fetchAlerts = async () => {
@@ -62,7 +64,7 @@ export function Alerts() {
       setTotalPages(Math.ceil(response.total / pageSize));
     } catch (error) {
       console.error('Failed to fetch alerts:', error);
-      setError('Failed to load alerts: ' + error);
+      setError('Failed to load alerts: ' + errorMessage(error));
     } finally {
       setLoading(false);
     }
@@ -72,6 +74,18 @@ export function Alerts() {
     fetchAlerts();
   }, [page, selectedCamera, apiKey, backendUrl]);
 
+  // Downloaded and cached by the agent so the API key never reaches the
+  // webview; a fresh URL is requested so expired presigned links still load
+  const loadImage = async (alertId: number) => {
+    try {
+      const image: string = await invoke('stream_alert_image', { alertId, apiKey, backendUrl });
+      setImages((current) => ({ ...current, [alertId]: image }));
+    } catch (error) {
+      console.error('Failed to fetch alert image:', error);
+      setError('Failed to load alert image: ' + errorMessage(error));
+    }
+  };
+
   const getCameraName = (cameraId: string) => {
     const camera = cameras.find((c) => c.id === cameraId);
     return camera ? camera.name : cameraId;
@@ -175,6 +189,9 @@ export function Alerts() {
                     <th className="px-6 py-3 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">
                       Zone ID
                     </th>
+                    <th className="px-6 py-3 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">
+                      Image
+                    </th>
                   </tr>
                 </thead>
                 <tbody className="bg-white divide-y divide-gray-200">
@@ -211,6 +228,25 @@ export function Alerts() {
                       <td className="px-6 py-4 whitespace-nowrap text-sm text-gray-500">
                         Zone #{alert.zone_id}
                       </td>
+                      <td className="px-6 py-4 whitespace-nowrap text-sm text-gray-500">
+                        {images[alert.id] ? (
+                          <img
+                            src={`data:image/jpeg;base64,${images[alert.id]}`}
+                            alt={`Alert ${alert.id}`}
+                            className="h-16 rounded"
+                          />
+                        ) : alert.image_url ? (
+                          <button
+                            onClick={() => loadImage(alert.id)}
+                            className="flex items-center gap-1 text-purple-600 hover:text-purple-800"
+                          >
+                            <Image size={16} />
+                            View
+                          </button>
+                        ) : (
+                          '—'
+                        )}
+                      </td>
                     </tr>
                   ))}
                 </tbody>
@@ -1,9 +1,16 @@
-import React, { useState } from 'react';
+import React, { useEffect, useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { open } from '@tauri-apps/plugin-dialog';
 import { Search, Wifi, Check, X, Video, Radio } from 'lucide-react';
 import { DiscoveredCamera } from '../lib/types';
 import { useCameraStore } from '../stores/cameraStore';
+import { errorMessage } from '../utils/errors';
+
+interface NetworkInterface {
+  name: string;
+  ipv4: string;
+  subnet: string;
+}
 
 interface CameraDiscoveryProps {
   onComplete: () => void;
@@ -19,22 +26,48 @@ export function CameraDiscovery({ onComplete }: CameraDiscoveryProps) {
   const [manualName, setManualName] = useState('');
   const [manualUsername, setManualUsername] = useState('');
   const [manualPassword, setManualPassword] = useState('');
+  const [interfaces, setInterfaces] = useState<NetworkInterface[]>([]);
+  const [subnet, setSubnet] = useState('');
+  const [storageError, setStorageError] = useState<string | null>(null);
   const { addCamera } = useCameraStore();
 
+  // Settings, recordings and logs all fail later if the data dir is read-only
+  useEffect(() => {
+    invoke('check_storage').catch((error) => setStorageError(errorMessage(error)));
+  }, []);
+
+  // Offer each interface's subnet; empty selection scans the default one
+  useEffect(() => {
+    invoke<NetworkInterface[]>('list_network_interfaces')
+      .then(setInterfaces)
+      .catch((error) => console.error('Failed to list network interfaces:', error));
+  }, []);
+
   const handleScan = async () => {
     setIsScanning(true);
     try {
-      const cameras: DiscoveredCamera[] = await invoke('scan_network');
+      const cameras: DiscoveredCamera[] = await invoke('scan_network', {
+        options: subnet ? { subnet } : null,
+      });
       setDiscoveredCameras(cameras);
       console.log('Discovered cameras:', cameras);
     } catch (error) {
       console.error('Failed to scan network:', error);
-      alert('Failed to scan network: ' + error);
+      alert('Failed to scan network: ' + errorMessage(error));
     } finally {
       setIsScanning(false);
     }
   };
 
+  // The pending scan_network call resolves with the partial results
+  const handleCancelScan = async () => {
+    try {
+      await invoke('cancel_scan');
+    } catch (error) {
+      console.error('Failed to cancel scan:', error);
+    }
+  };
+
   const toggleCamera = (ip: string) => {
     const newSelected = new Set(selectedCameras);
     if (newSelected.has(ip)) {
@@ -72,22 +105,23 @@ export function CameraDiscovery({ onComplete }: CameraDiscoveryProps) {
       }
     } catch (error) {
       console.error('Failed to select video file:', error);
-      alert('Failed to select video file: ' + error);
+      alert('Failed to select video file: ' + errorMessage(error));
     }
   };
 
-  const handleAddManualCamera = () => {
+  const handleAddManualCamera = async () => {
     if (!manualUrl || !manualName) {
       alert('Please enter both camera name and stream URL');
       return;
     }
 
-    if (!manualUrl.startsWith('rtsp://') && !manualUrl.startsWith('http://') && !manualUrl.startsWith('https://')) {
-      alert('Stream URL must start with rtsp://, http://, or https://');
+    const schemes = ['rtsp://', 'http://', 'https://', 'screen://', 'sdi://'];
+    if (!schemes.some((scheme) => manualUrl.startsWith(scheme))) {
+      alert('Stream URL must start with rtsp://, http://, https://, screen://, or sdi://');
       return;
     }
 
-    const id = `cam_${manualName.replace(/[^a-zA-Z0-9]/g, '_')}`;
+    const id = await invoke<string>('derive_camera_id', { rtspUrl: manualUrl });
 
     addCamera({
       id,
@@ -109,10 +143,10 @@ export function CameraDiscovery({ onComplete }: CameraDiscoveryProps) {
 
   const handleContinue = async () => {
     // Add selected cameras to store
-    selectedCameras.forEach((ip) => {
+    for (const ip of selectedCameras) {
       const camera = discoveredCameras.find((c) => c.ip === ip);
       if (camera) {
-        const id = `cam_${ip.replace(/\./g, '_')}`;
+        const id = await invoke<string>('derive_camera_id', { rtspUrl: camera.rtsp_url });
         addCamera({
           id,
           name: cameraNames[ip] || `Camera ${ip}`,
@@ -121,7 +155,7 @@ export function CameraDiscovery({ onComplete }: CameraDiscoveryProps) {
           isMonitoring: false,
         });
       }
-    });
+    }
 
     onComplete();
   };
@@ -143,9 +177,32 @@ export function CameraDiscovery({ onComplete }: CameraDiscoveryProps) {
             </p>
           </div>
 
+          {storageError && (
+            <div className="mb-6 p-4 bg-red-50 border border-red-200 rounded-lg text-sm text-red-700">
+              Settings and recordings can't be saved: {storageError}
+            </div>
+          )}
+
           {/* Scan Button */}
           {discoveredCameras.length === 0 && (
             <div className="text-center">
+              {interfaces.length > 1 && (
+                <div className="mb-4">
+                  <select
+                    value={subnet}
+                    onChange={(e) => setSubnet(e.target.value)}
+                    disabled={isScanning}
+                    className="px-3 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-purple-500"
+                  >
+                    <option value="">Default network</option>
+                    {interfaces.map((iface) => (
+                      <option key={`${iface.name}-${iface.ipv4}`} value={iface.subnet}>
+                        {iface.name} ({iface.subnet})
+                      </option>
+                    ))}
+                  </select>
+                </div>
+              )}
               <div className="grid grid-cols-1 md:grid-cols-3 gap-4 max-w-4xl mx-auto">
                 <button
                   onClick={handleScan}
@@ -181,6 +238,12 @@ export function CameraDiscovery({ onComplete }: CameraDiscoveryProps) {
                   <p className="text-gray-600 mt-4">
                     This may take 10-30 seconds...
                   </p>
+                  <button
+                    onClick={handleCancelScan}
+                    className="mt-4 text-sm text-gray-600 hover:text-gray-800 underline"
+                  >
+                    Stop and show results so far
+                  </button>
                 </div>
               )}
             </div>
@@ -303,7 +366,7 @@ export function CameraDiscovery({ onComplete }: CameraDiscoveryProps) {
                   className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono text-sm"
                 />
                 <p className="mt-1 text-xs text-gray-500">
-                  RTSP: rtsp://host:port/path or HTTP: http://host:port/path
+                  RTSP: rtsp://host:port/path or HTTP: http://host:port/path; screen://0 or sdi://0 for local capture
                 </p>
               </div>
 
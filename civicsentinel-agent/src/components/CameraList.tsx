@@ -1,11 +1,22 @@
 import React, { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { open } from '@tauri-apps/plugin-dialog';
-import { Camera, Video, Play, Pause, Settings as SettingsIcon, Edit, Plus, FileVideo, Eye, Radio } from 'lucide-react';
+import { Camera, Video, Play, Pause, Settings as SettingsIcon, Edit, Plus, FileVideo, Eye, Radio, Monitor } from 'lucide-react';
 import { useCameraStore } from '../stores/cameraStore';
 import { useSettingsStore } from '../stores/settingsStore';
 import { ZoneEditor } from './ZoneEditor';
 import { LiveCameraView } from './LiveCameraView';
+import { errorMessage } from '../utils/errors';
+import type { FrameSnapshot } from '../utils/frames';
+
+interface StreamProfile {
+  name: string;
+  token: string | null;
+  stream_url: string;
+  codec: string | null;
+  width: number | null;
+  height: number | null;
+}
 
 export function CameraList() {
   const { cameras, updateCamera, toggleMonitoring, addCamera } = useCameraStore();
@@ -18,9 +29,14 @@ export function CameraList() {
   const [rtspName, setRtspName] = useState('');
   const [username, setUsername] = useState('');
   const [password, setPassword] = useState('');
+  const [streamProfiles, setStreamProfiles] = useState<StreamProfile[]>([]);
+  const [probing, setProbing] = useState(false);
 
   // Helper function to detect source type
-  const getSourceType = (url: string): 'rtsp' | 'http' | 'video' => {
+  const getSourceType = (url: string): 'rtsp' | 'http' | 'device' | 'video' => {
+    if (url.startsWith('screen://') || url.startsWith('sdi://')) {
+      return 'device';
+    }
     if (url.startsWith('rtsp://') || url.startsWith('rtmp://')) {
       return 'rtsp';
     }
@@ -36,27 +52,54 @@ export function CameraList() {
     setRtspName('');
     setUsername('');
     setPassword('');
+    setStreamProfiles([]);
+  };
+
+  // Accepts an ONVIF device URL (lists every profile) or an RTSP URL
+  const handleProbeStreams = async () => {
+    setProbing(true);
+    try {
+      const profiles: StreamProfile[] = await invoke('probe_streams', {
+        url: rtspUrl,
+        username: username || null,
+        password: password || null,
+      });
+      setStreamProfiles(profiles);
+    } catch (error) {
+      alert('Failed to probe streams: ' + errorMessage(error));
+    } finally {
+      setProbing(false);
+    }
   };
 
-  const handleSaveRtspCamera = () => {
+  const handleSaveRtspCamera = async () => {
     if (!rtspUrl || !rtspName) {
       alert('Please enter both camera name and stream URL');
       return;
     }
 
-    if (!rtspUrl.startsWith('rtsp://') && !rtspUrl.startsWith('http://') && !rtspUrl.startsWith('https://')) {
-      alert('Stream URL must start with rtsp://, http://, or https://');
+    const schemes = ['rtsp://', 'http://', 'https://', 'screen://', 'sdi://'];
+    if (!schemes.some((scheme) => rtspUrl.startsWith(scheme))) {
+      alert('Stream URL must start with rtsp://, http://, https://, screen://, or sdi://');
       return;
     }
 
-    const id = `cam_${rtspName.replace(/[^a-zA-Z0-9]/g, '_')}`;
+    const id = await invoke<string>('derive_camera_id', { rtspUrl });
+
+    // Keep the login in the OS keychain rather than the persisted camera list
+    if (username && password) {
+      try {
+        await invoke('set_camera_credentials', { cameraId: id, username, password });
+      } catch (error) {
+        alert('Failed to save camera credentials: ' + errorMessage(error));
+        return;
+      }
+    }
 
     addCamera({
       id,
       name: rtspName,
       rtspUrl,
-      username: username || undefined,
-      password: password || undefined,
       status: 'disconnected',
       isMonitoring: false,
     });
@@ -110,7 +153,7 @@ export function CameraList() {
       }
     } catch (error) {
       console.error('Failed to select video file:', error);
-      alert('Failed to select video file: ' + error);
+      alert('Failed to select video file: ' + errorMessage(error));
     }
   };
 
@@ -121,7 +164,7 @@ export function CameraList() {
         if (cam.isMonitoring && cam.status === 'connected') {
           try {
             // Get frame from Rust
-            const frameBase64: string = await invoke('get_frame', {
+            const { frame: frameBase64 } = await invoke<FrameSnapshot>('get_frame', {
               cameraId: cam.id,
             });
 
@@ -139,17 +182,7 @@ export function CameraList() {
 
             // Update camera frame
             updateCamera(cam.id, { lastFrame: frameBase64 });
-
-            // If alerts, show notification
-            if ((result as any).alerts?.length > 0) {
-              console.log('Showing notification for alert:', (result as any).alerts[0]);
-              await invoke('show_notification', {
-                title: '🚨 CivicSentinel Alert',
-                body: `${(result as any).alerts[0].alert_type} detected at ${cam.name}`,
-              });
-            } else {
-              console.log('No alerts to show notification for');
-            }
+            // Alert notifications are sent by the agent according to each zone's notify policy
           } catch (error) {
             console.error('Failed to process frame:', error);
           }
@@ -186,13 +219,13 @@ export function CameraList() {
     // If no frame available, capture one first
     if (!camera.lastFrame) {
       try {
-        const frameBase64: string = await invoke('get_frame', {
+        const { frame: frameBase64 } = await invoke<FrameSnapshot>('get_frame', {
           cameraId,
         });
         updateCamera(cameraId, { lastFrame: frameBase64 });
       } catch (error) {
         console.error('Failed to capture frame:', error);
-        alert('Failed to capture frame: ' + error);
+        alert('Failed to capture frame: ' + errorMessage(error));
         return;
       }
     }
@@ -303,6 +336,11 @@ export function CameraList() {
                         <Radio size={12} />
                         HTTP
                       </span>
+                    ) : getSourceType(camera.rtspUrl) === 'device' ? (
+                      <span className="px-2 py-0.5 bg-amber-100 text-amber-700 text-xs font-medium rounded flex items-center gap-1">
+                        <Monitor size={12} />
+                        DEVICE
+                      </span>
                     ) : (
                       <span className="px-2 py-0.5 bg-purple-100 text-purple-700 text-xs font-medium rounded flex items-center gap-1">
                         <FileVideo size={12} />
@@ -426,8 +464,38 @@ export function CameraList() {
                   className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-blue-500 font-mono text-sm"
                 />
                 <p className="mt-1 text-xs text-gray-500">
-                  RTSP: rtsp://host:port/path or HTTP: http://host:port/path
+                  RTSP: rtsp://host:port/path or HTTP: http://host:port/path; screen://0 or sdi://0 for local capture
                 </p>
+                <button
+                  onClick={handleProbeStreams}
+                  disabled={!rtspUrl || probing}
+                  className="mt-2 text-sm text-blue-600 hover:text-blue-800 disabled:opacity-50"
+                >
+                  {probing ? 'Probing...' : 'Find streams (RTSP or ONVIF device URL)'}
+                </button>
+                {streamProfiles.length > 0 && (
+                  <div className="mt-2 space-y-1">
+                    {streamProfiles.map((profile) => (
+                      <button
+                        key={profile.token ?? profile.stream_url}
+                        onClick={() => setRtspUrl(profile.stream_url)}
+                        disabled={!profile.stream_url}
+                        className={`w-full text-left px-3 py-2 border rounded-lg text-sm hover:bg-gray-50 disabled:opacity-50 ${
+                          profile.stream_url === rtspUrl ? 'border-blue-500' : 'border-gray-300'
+                        }`}
+                      >
+                        <span className="font-medium">{profile.name}</span>
+                        <span className="ml-2 text-gray-500">
+                          {profile.width && profile.height ? `${profile.width}x${profile.height}` : 'unknown size'}
+                          {profile.codec ? ` · ${profile.codec}` : ''}
+                        </span>
+                      </button>
+                    ))}
+                    <p className="text-xs text-gray-500">
+                      Lower resolution substreams are cheaper for detection
+                    </p>
+                  </div>
+                )}
               </div>
 
               <div>
@@ -1,7 +1,9 @@
 import React, { useEffect, useRef, useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import { sendNotification } from '@tauri-apps/plugin-notification';
 import { useSettingsStore } from '../stores/settingsStore';
+import type { FrameSnapshot } from '../utils/frames';
 
 interface Detection {
   bbox: {
@@ -26,6 +28,14 @@ interface DetectionResponse {
   timestamp: string;
   detections: Detection[];
   alerts: Alert[];
+  served_by?: string;
+  local_timestamp?: string;
+  latency_ms?: number;
+}
+
+interface ResolvedLabel {
+  label: string;
+  color: [number, number, number];
 }
 
 interface Zone {
@@ -36,6 +46,18 @@ interface Zone {
   alert_type: string;
 }
 
+interface CaptureStats {
+  camera_id: string;
+  total_captures: number;
+  successful_captures: number;
+  avg_latency_ms: number | null;
+  last_success: string | null;
+  last_failure: string | null;
+  recent_success_rate: number | null;
+  recent_window: number;
+  stream_reopens: number;
+}
+
 interface LiveCameraViewProps {
   cameraId: string;
   cameraName: string;
@@ -66,6 +88,21 @@ export const LiveCameraView: React.FC<LiveCameraViewProps> = ({
     alerts: 0,
     fps: 0,
   });
+  const [captureStats, setCaptureStats] = useState<CaptureStats | null>(null);
+  // Display name and color per class, from labels.json via get_labels
+  const labelStylesRef = useRef<Record<string, ResolvedLabel>>({});
+
+  // Camera link health, separate from detection results
+  useEffect(() => {
+    const refresh = () => {
+      invoke<CaptureStats>('get_capture_stats', { cameraId })
+        .then(setCaptureStats)
+        .catch(() => setCaptureStats(null));
+    };
+    refresh();
+    const interval = setInterval(refresh, 5000);
+    return () => clearInterval(interval);
+  }, [cameraId]);
 
   // Helper: Check if point is inside polygon (for zone detection)
   const isPointInPolygon = (point: { x: number; y: number }, polygon: Array<{ x: number; y: number }>) => {
@@ -91,19 +128,21 @@ export const LiveCameraView: React.FC<LiveCameraViewProps> = ({
       const { x1, y1, x2, y2 } = det.bbox;
       const width = x2 - x1;
       const height = y2 - y1;
+      const style = labelStylesRef.current[det.class_name];
+      const color = style ? `rgb(${style.color.join(', ')})` : '#00ff00';
 
       // Draw box
-      ctx.strokeStyle = '#00ff00';
+      ctx.strokeStyle = color;
       ctx.lineWidth = 3;
       ctx.strokeRect(x1, y1, width, height);
 
       // Draw label background
-      const label = `${det.class_name}: ${(det.confidence * 100).toFixed(0)}%`;
+      const label = `${style?.label ?? det.class_name}: ${(det.confidence * 100).toFixed(0)}%`;
       ctx.font = 'bold 14px Arial';
       const metrics = ctx.measureText(label);
       const labelHeight = 22;
 
-      ctx.fillStyle = '#00ff00';
+      ctx.fillStyle = color;
       ctx.fillRect(x1, y1 - labelHeight, metrics.width + 12, labelHeight);
 
       // Draw label text
@@ -200,56 +239,48 @@ export const LiveCameraView: React.FC<LiveCameraViewProps> = ({
     }, 2000);
 
     // ==========================================
-    // LOOP 1: FAST CAPTURE (2 FPS - OPTIMIZED)
+    // LOOP 1: LIVE FRAMES (2 FPS, PUSHED BY THE AGENT)
     // ==========================================
-    const captureLoop = setInterval(async () => {
+    // The agent falls back to the last cached frame so the view doesn't go blank
+    const unlistenFrames = listen<FrameSnapshot>(`live-frame:${cameraId}`, ({ payload }) => {
       if (!mounted) return;
+      const { frame: frameBase64, stale } = payload;
 
-      try {
-        console.log('[LiveView] Capturing frame...');
-
-        // Capture frame - NO API call, NO blocking
-        const frameBase64 = await invoke<string>('get_frame', {
-          cameraId,
-        });
+      // Cache frame for display and detection loops
+      latestFrame = frameBase64;
+      frameCount++;
 
-        if (!mounted) return;
-
-        // Cache frame for display and detection loops
-        latestFrame = frameBase64;
-        frameCount++;
+      // Add to detection queue (max 3 frames to prevent overload);
+      // stale frames were already analysed when they were live
+      if (!stale && detectionQueue.length < 3) {
+        detectionQueue.push(frameBase64);
+      }
 
-        // Add to detection queue (max 3 frames to prevent overload)
-        if (detectionQueue.length < 3) {
-          detectionQueue.push(frameBase64);
-        }
+      console.log('[LiveView] Frame received, size:', frameBase64.length);
 
-        console.log('[LiveView] Frame captured, size:', frameBase64.length);
+      // Hide loading on FIRST successful frame + cancel timeout
+      if (frameCount === 1) {
+        clearTimeout(loadingTimeout); // Cancel loading timeout
+        setIsLoading(false);
+        setError(null);
+        console.log('[LiveView] First frame received!');
+      }
 
-        // Hide loading on FIRST successful frame + cancel timeout
-        if (frameCount === 1) {
-          clearTimeout(loadingTimeout); // Cancel loading timeout
-          setIsLoading(false);
-          setError(null);
-          console.log('[LiveView] First frame received!');
-        }
+      // Update FPS counter
+      const now = Date.now();
+      if (now - lastFpsTime >= 1000) {
+        setStats((prev) => ({ ...prev, fps: frameCount }));
+        frameCount = 0;
+        lastFpsTime = now;
+      }
+    });
 
-        // Update FPS counter
-        const now = Date.now();
-        if (now - lastFpsTime >= 1000) {
-          setStats((prev) => ({ ...prev, fps: frameCount }));
-          frameCount = 0;
-          lastFpsTime = now;
-        }
-      } catch (err) {
-        if (mounted) {
-          console.error('[LiveView] Capture error:', err);
-          const errorMsg = err instanceof Error ? err.message : 'Failed to capture frame';
-          setError(errorMsg);
-          // Don't set loading on error - keep showing last frame
-        }
+    invoke('start_live_view', { cameraId, fps: 2 }).catch((err) => {
+      if (mounted) {
+        console.error('[LiveView] Failed to start live view:', err);
+        setError(err instanceof Error ? err.message : String(err));
       }
-    }, 500); // FASTER: Every 0.5 seconds (2 FPS)
+    });
 
     // ==========================================
     // LOOP 2: FAST AI DETECTION (2s interval + queue processing)
@@ -288,6 +319,17 @@ export const LiveCameraView: React.FC<LiveCameraViewProps> = ({
         // Cache detection results for display loop
         latestDetections = detectionData;
 
+        // Look up labels for classes not seen yet
+        const unknownClasses = [...new Set(detectionData.detections.map((det) => det.class_name))]
+          .filter((className) => !labelStylesRef.current[className]);
+        if (unknownClasses.length > 0) {
+          invoke<Record<string, ResolvedLabel>>('get_labels', { classNames: unknownClasses })
+            .then((styles) => {
+              labelStylesRef.current = { ...labelStylesRef.current, ...styles };
+            })
+            .catch((err) => console.warn('[LiveView] Failed to load labels:', err));
+        }
+
         // Update state
         setDetections(detectionData.detections);
         setAlerts(detectionData.alerts);
@@ -371,7 +413,10 @@ export const LiveCameraView: React.FC<LiveCameraViewProps> = ({
       console.log('[LiveView] Component unmounting, cleaning up loops');
       mounted = false;
       clearTimeout(loadingTimeout);
-      clearInterval(captureLoop);
+      unlistenFrames.then((unlisten) => unlisten());
+      invoke('stop_live_view', { cameraId }).catch((err) => {
+        console.error('[LiveView] Failed to stop live view:', err);
+      });
       clearInterval(detectionLoop);
       clearInterval(displayLoop);
     };
@@ -472,6 +517,15 @@ export const LiveCameraView: React.FC<LiveCameraViewProps> = ({
                 Zones: {zones.length}
               </span>
             </div>
+            {captureStats?.recent_success_rate != null && (
+              <div className="flex items-center gap-2" title={`Last ${captureStats.recent_window} captures`}>
+                <span className="text-gray-400 text-sm">
+                  Link: {(captureStats.recent_success_rate * 100).toFixed(0)}%
+                  {captureStats.avg_latency_ms != null && ` · ${captureStats.avg_latency_ms.toFixed(0)}ms`}
+                  {captureStats.stream_reopens > 0 && ` · ${captureStats.stream_reopens} reopens`}
+                </span>
+              </div>
+            )}
           </div>
 
           <div className="flex gap-2">
@@ -3,6 +3,7 @@ import { invoke } from '@tauri-apps/api/core';
 import { Camera as LucideCamera, Save, X, Edit2, Trash2, Plus } from 'lucide-react';
 import { Zone } from '../lib/types';
 import { useSettingsStore } from '../stores/settingsStore';
+import { errorMessage } from '../utils/errors';
 
 interface Point {
   x: number;
@@ -27,6 +28,7 @@ export function ZoneEditor({ cameraId, frameBase64, onClose }: ZoneEditorProps)
   const [selectedZone, setSelectedZone] = useState<Zone | null>(null);
   const [draggingPointIndex, setDraggingPointIndex] = useState<number | null>(null);
   const [isEditMode, setIsEditMode] = useState(false);
+  const [zoneIssue, setZoneIssue] = useState('');
 
   const { backendUrl, apiKey } = useSettingsStore();
 
@@ -35,6 +37,24 @@ export function ZoneEditor({ cameraId, frameBase64, onClose }: ZoneEditorProps)
     loadZones();
   }, [cameraId]);
 
+  // Validate the polygon as it is drawn so problems show before saving
+  useEffect(() => {
+    if (points.length < 3) {
+      setZoneIssue('');
+      return;
+    }
+
+    let cancelled = false;
+    const coordinates: [number, number][] = points.map((p) => [p.x, p.y]);
+    invoke<{ area: number; warnings: string[] }>('validate_zone', { coordinates })
+      .then((result) => !cancelled && setZoneIssue(result.warnings.join(' ')))
+      .catch((err) => !cancelled && setZoneIssue(errorMessage(err)));
+
+    return () => {
+      cancelled = true;
+    };
+  }, [points]);
+
   // ESC key to close
   useEffect(() => {
     const handleEscape = (e: KeyboardEvent) => {
@@ -269,6 +289,9 @@ export function ZoneEditor({ cameraId, frameBase64, onClose }: ZoneEditorProps)
     try {
       const coordinates: [number, number][] = points.map((p) => [p.x, p.y]);
 
+      // Reject a bad polygon before the old zone is deleted
+      await invoke('validate_zone', { coordinates });
+
       // If editing, delete the old zone first
       if (isEditMode && selectedZone) {
         await invoke('delete_zone', {
@@ -299,7 +322,7 @@ export function ZoneEditor({ cameraId, frameBase64, onClose }: ZoneEditorProps)
       setSelectedZone(null);
       await loadZones();
     } catch (err) {
-      setError(err as string);
+      setError(errorMessage(err));
       console.error(isEditMode ? 'Failed to update zone:' : 'Failed to create zone:', err);
     } finally {
       setLoading(false);
@@ -370,7 +393,7 @@ export function ZoneEditor({ cameraId, frameBase64, onClose }: ZoneEditorProps)
         handleReset();
       }
     } catch (err) {
-      setError(err as string);
+      setError(errorMessage(err));
       console.error('Failed to delete zone:', err);
     } finally {
       setLoading(false);
@@ -458,6 +481,12 @@ export function ZoneEditor({ cameraId, frameBase64, onClose }: ZoneEditorProps)
               </div>
             )}
 
+            {zoneIssue && (
+              <div className="bg-yellow-50 border border-yellow-200 text-yellow-800 px-3 py-2 rounded-lg text-sm">
+                {zoneIssue}
+              </div>
+            )}
+
             {/* Zone Configuration */}
             {!isDrawing && points.length >= 3 && (
               <div className="space-y-4">
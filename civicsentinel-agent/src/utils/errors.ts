@@ -0,0 +1,24 @@
+// Rust commands reject with a tagged error: `{ kind, message }`
+export interface AgentError {
+  kind:
+    | 'NotFound'
+    | 'Network'
+    | 'Auth'
+    | 'Ffmpeg'
+    | 'Timeout'
+    | 'Decode'
+    | 'InvalidInput'
+    | 'Backend'
+    | 'RateLimited'
+    | 'Paused'
+    | 'Internal';
+  message: string;
+}
+
+export function isAgentError(err: unknown): err is AgentError {
+  return typeof err === 'object' && err !== null && 'kind' in err && 'message' in err;
+}
+
+export function errorMessage(err: unknown): string {
+  return isAgentError(err) ? err.message : String(err);
+}
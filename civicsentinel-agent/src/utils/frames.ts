@@ -0,0 +1,6 @@
+// Frame returned by the `get_frame` command and `live-frame:{cameraId}` events
+export interface FrameSnapshot {
+  frame: string; // base64 encoded JPEG
+  stale: boolean; // live capture failed; this is the last cached frame
+  age_secs: number; // age of a stale frame, 0 for live frames
+}
@@ -61,6 +61,7 @@ async def test_health_endpoint():
     data = response.json()
 
     assert "status" in data
+    assert "version" in data
     assert "model_loaded" in data
     assert "database_connected" in data
     assert "timestamp" in data